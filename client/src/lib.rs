@@ -0,0 +1,263 @@
+//! Client for the daemon of md2htm, so Rust services don't need to implement
+//! the framing of the socket protocol themselves.
+//!
+//! ```no_run
+//! use md2htm_client::{Client, Options};
+//! use std::time::Duration;
+//!
+//! let mut client = Client::connect("/run/mdserv/mdserv.sock")?.timeout(Duration::from_secs(5));
+//! let html = client.convert(b"# Hello\n", &Options::new().flag("semantic", true))?;
+//! # Ok::<(), md2htm_client::Error>(())
+//! ```
+
+use std::{
+    fmt,
+    io::{self, ErrorKind, Read, Write},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    thread::sleep,
+    time::Duration,
+};
+
+/// Size of the length prefixes, which are big endian `usize`
+const PS: usize = size_of::<usize>();
+/// Bit of the first length telling that options come before the markdown
+const OPTIONS: usize = 1 << (usize::BITS - 1);
+/// Wait before the first retry, doubled for every next one
+const RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Why a conversion didn't give any HTML
+#[derive(Debug)]
+pub enum Error {
+    /// Connecting, writing or reading failed. Timeouts come as `TimedOut`
+    /// or `WouldBlock`.
+    Io(io::Error),
+    /// The daemon closed the connection without an answer. It does that when
+    /// the options are broken or the document goes over its limits.
+    Dropped,
+    /// The daemon had as many connections as it takes and didn't read the
+    /// request. Trying again later may work.
+    Busy,
+    /// The markdown looked like a binary file, which the daemon converts
+    /// only with the option `force-binary`.
+    Binary,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::Dropped => write!(f, "the daemon closed the connection without an answer"),
+            Self::Busy => write!(f, "the daemon is busy"),
+            Self::Binary => write!(f, "the markdown looks like a binary file"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Dropped | Self::Busy | Self::Binary => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            // The daemon hangs up right away on requests over its limits
+            ErrorKind::UnexpectedEof | ErrorKind::BrokenPipe | ErrorKind::ConnectionReset => {
+                Self::Dropped
+            }
+            _ => Self::Io(e),
+        }
+    }
+}
+
+/// Options of a request overriding the ones the daemon was started with.
+/// They are sent as the `key = value` lines of the config files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    lines: String,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Option without a value, like `semantic`
+    pub fn flag(mut self, key: &str, on: bool) -> Self {
+        self.lines.push_str(&format!("{key} = {on}\n"));
+        self
+    }
+
+    /// Option with a value, like `dialect` or `base-url`
+    pub fn value(mut self, key: &str, value: &str) -> Self {
+        self.lines.push_str(&format!("{key} = {}\n", quoted(value)));
+        self
+    }
+
+    /// Option taking a list, like `enable` or `deny`
+    pub fn list(mut self, key: &str, values: &[&str]) -> Self {
+        let values: Vec<String> = values.iter().map(|value| quoted(value)).collect();
+        self.lines
+            .push_str(&format!("{key} = [{}]\n", values.join(", ")));
+        self
+    }
+
+    fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+/// String in the quotes of the config files
+fn quoted(value: &str) -> String {
+    let mut quoted: String = String::from("\"");
+
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+/// Connection to the daemon. The daemon answers one document per
+/// connection, so every conversion after the first connects again.
+#[derive(Debug)]
+pub struct Client {
+    path: PathBuf,
+    /// Connection made but not used yet
+    stream: Option<UnixStream>,
+    timeout: Option<Duration>,
+    retries: u32,
+}
+
+impl Client {
+    /// Connects to the socket of the daemon
+    pub fn connect<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path: PathBuf = path.as_ref().to_path_buf();
+
+        Ok(Self {
+            stream: Some(UnixStream::connect(&path)?),
+            path,
+            timeout: None,
+            retries: 3,
+        })
+    }
+
+    /// Gives up on reads and writes that take longer than this
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// How many times connecting is tried again before giving up, for
+    /// example while the daemon is restarting. Defaults to 3.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Converts the markdown into HTML
+    pub fn convert(&mut self, markdown: &[u8], options: &Options) -> Result<Vec<u8>, Error> {
+        let mut stream: UnixStream = self.request(markdown, options)?;
+        read_frame(&mut stream)
+    }
+
+    /// Converts the markdown, handing the HTML to `write` in chunks as the
+    /// daemon sends them, so the start of a large document can be used
+    /// before the rest is converted
+    pub fn convert_chunked(
+        &mut self,
+        markdown: &[u8],
+        options: &Options,
+        mut write: impl FnMut(&[u8]),
+    ) -> Result<(), Error> {
+        let options: Options = options.clone().flag("chunked", true);
+        let mut stream: UnixStream = self.request(markdown, &options)?;
+
+        loop {
+            let chunk: Vec<u8> = read_frame(&mut stream)?;
+
+            // An empty chunk ends the answer
+            if chunk.is_empty() {
+                return Ok(());
+            }
+
+            write(&chunk);
+        }
+    }
+
+    /// Sends the request on a connection of its own
+    fn request(&mut self, markdown: &[u8], options: &Options) -> Result<UnixStream, Error> {
+        let mut stream: UnixStream = match self.stream.take() {
+            Some(stream) => stream,
+            None => self.reconnect()?,
+        };
+
+        stream.set_read_timeout(self.timeout)?;
+        stream.set_write_timeout(self.timeout)?;
+
+        let mut request: Vec<u8> =
+            Vec::with_capacity(options.lines.len() + markdown.len() + 2 * PS);
+
+        if !options.is_empty() {
+            request.extend_from_slice(&(options.lines.len() | OPTIONS).to_be_bytes());
+            request.extend_from_slice(options.lines.as_bytes());
+        }
+
+        request.extend_from_slice(&markdown.len().to_be_bytes());
+        request.extend_from_slice(markdown);
+
+        match stream.write_all(&request) {
+            Ok(_) => Ok(stream),
+            // A busy daemon answers before reading the request and hangs up
+            Err(e) => match read_frame(&mut stream) {
+                Err(Error::Busy) => Err(Error::Busy),
+                _ => Err(e.into()),
+            },
+        }
+    }
+
+    fn reconnect(&self) -> io::Result<UnixStream> {
+        let mut delay: Duration = RETRY_DELAY;
+        let mut retries: u32 = self.retries;
+
+        loop {
+            match UnixStream::connect(&self.path) {
+                Ok(stream) => return Ok(stream),
+                Err(e) if retries == 0 => return Err(e),
+                Err(_) => {
+                    sleep(delay);
+                    delay *= 2;
+                    retries -= 1;
+                }
+            }
+        }
+    }
+}
+
+/// Reads a length prefixed message. The highest bit of the length marks
+/// the answer of a busy daemon, or the refusal of binary markdown.
+fn read_frame(stream: &mut UnixStream) -> Result<Vec<u8>, Error> {
+    let mut len: [u8; PS] = [0; PS];
+    stream.read_exact(&mut len)?;
+    let len: usize = usize::from_be_bytes(len);
+    let mut bytes: Vec<u8> = vec![0; len & !OPTIONS];
+    stream.read_exact(&mut bytes)?;
+
+    match len & OPTIONS {
+        0 => Ok(bytes),
+        _ if bytes == b"binary" => Err(Error::Binary),
+        _ => Err(Error::Busy),
+    }
+}
@@ -0,0 +1,48 @@
+/* C interface of md2htm. Build the library with
+ * `cargo rustc --lib --release --features ffi --crate-type cdylib`
+ * and link against target/release/libmd2htm.so. */
+
+#ifndef MD2HTM_H
+#define MD2HTM_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define MD2HTM_OK 0
+#define MD2HTM_NULL_POINTER -1
+
+/* Zeroed options are not the defaults, so pass NULL for those. */
+typedef struct md2htm_options {
+    /* Escape raw HTML and drop urls with other schemes than http, https,
+     * mailto and tel */
+    bool safe;
+    /* Use <em> and <strong> instead of <i> and <b> */
+    bool semantic;
+    /* Underline _text_ with <u> */
+    bool underline;
+} md2htm_options;
+
+/* The HTML is not null terminated. Release it with md2htm_result_free. */
+typedef struct md2htm_result {
+    uint8_t *data;
+    size_t len;
+} md2htm_result;
+
+/* Converts len bytes of markdown into HTML stored in result.
+ * options may be NULL. Returns MD2HTM_OK on success. */
+int32_t md2htm_convert(const uint8_t *input, size_t len,
+                       const md2htm_options *options, md2htm_result *result);
+
+/* Releases the HTML of a result filled by md2htm_convert. */
+void md2htm_result_free(md2htm_result *result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
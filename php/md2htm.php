@@ -1,16 +1,65 @@
 <?php
 
-function parse($markdown) {
-    $sock = "unix:///run/mdserv/mdserv.sock";
-    // Assumes big endianness
-    $len = pack("J", strlen($markdown));
-    $handle = fsockopen($sock);
-    fwrite($handle, $len.$markdown);
+// Options override the ones of the daemon for this request, like
+// ["dialect" => "gfm", "standalone" => true]
+function parse($markdown, $options = []) {
+    $handle = request($markdown, $options);
     $len = fread($handle, 8);
     $len = unpack("J", $len);
+    busy($handle, $len[1]);
     $html = fread($handle, $len[1]);
     fclose($handle);
     return $html;
 }
 
+// Hands the HTML to $write as the daemon sends it, like
+// parse_chunked($markdown, function ($html) { echo $html; flush(); });
+function parse_chunked($markdown, $write, $options = []) {
+    $options["chunked"] = true;
+    $handle = request($markdown, $options);
+
+    // An empty chunk ends the answer
+    while (($len = unpack("J", fread($handle, 8))[1]) != 0) {
+        busy($handle, $len);
+        $write(stream_get_contents($handle, $len));
+    }
+
+    fclose($handle);
+}
+
+// A busy daemon sets the highest bit of the length, which makes it negative.
+// So does the refusal of markdown that looks binary.
+function busy($handle, $len) {
+    if ($len < 0) {
+        $reason = fread($handle, $len & PHP_INT_MAX);
+        fclose($handle);
+
+        if ($reason == "binary") {
+            throw new RuntimeException("the markdown looks like a binary file");
+        }
+
+        throw new RuntimeException("the daemon is busy");
+    }
+}
+
+function request($markdown, $options) {
+    $sock = "unix:///run/mdserv/mdserv.sock";
+    $request = "";
+
+    if ($options) {
+        $lines = "";
+        foreach ($options as $key => $value) {
+            $lines .= $key." = ".json_encode($value, JSON_UNESCAPED_SLASHES)."\n";
+        }
+        // The highest bit of the length tells that options come first
+        $request = pack("J", strlen($lines) | PHP_INT_MIN).$lines;
+    }
+
+    // Assumes big endianness
+    $len = pack("J", strlen($markdown));
+    $handle = fsockopen($sock);
+    fwrite($handle, $request.$len.$markdown);
+    return $handle;
+}
+
 ?>
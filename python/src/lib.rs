@@ -0,0 +1,62 @@
+//! Python module wrapping the parser. Build and install it into the current
+//! virtual environment with `maturin develop` in this directory.
+//!
+//! ```python
+//! import md2htm
+//!
+//! md2htm.convert("# Hello", safe=True, emphasis="semantic")
+//! ```
+
+use md2htm::{Em, Ext, ParserBuilder, MDS};
+use pyo3::{exceptions::PyTypeError, prelude::*, types::PyDict};
+
+/// Converts markdown to HTML.
+///
+/// Keyword options: `safe` (bool), `emphasis` ("semantic" or
+/// "presentational") and `underline` (bool).
+#[pyfunction]
+#[pyo3(signature = (text, **options))]
+fn convert(text: &str, options: Option<&Bound<'_, PyDict>>) -> PyResult<String> {
+    let mut builder: ParserBuilder = ParserBuilder::new();
+
+    if let Some(options) = options {
+        for (key, value) in options.iter() {
+            match key.extract::<String>()?.as_str() {
+                "safe" => builder = builder.safe(value.extract()?),
+
+                "emphasis" => match value.extract::<String>()?.as_str() {
+                    "semantic" => builder = builder.emphasis_style(Em::Semantic),
+                    "presentational" => builder = builder.emphasis_style(Em::Presentational),
+                    other => {
+                        return Err(PyTypeError::new_err(format!(
+                            "unknown emphasis style '{other}'"
+                        )))
+                    }
+                },
+
+                "underline" => {
+                    builder = if value.extract()? {
+                        builder.extension(Ext::Underline)
+                    } else {
+                        builder.disable(Ext::Underline)
+                    }
+                }
+
+                other => {
+                    return Err(PyTypeError::new_err(format!(
+                        "convert() got an unexpected keyword argument '{other}'"
+                    )))
+                }
+            }
+        }
+    }
+
+    let html: Vec<u8> = MDS::parse_with(text.as_bytes().to_vec(), &builder.build());
+    Ok(String::from_utf8_lossy(&html).into_owned())
+}
+
+#[pymodule]
+#[pyo3(name = "md2htm")]
+fn md2htm_python(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(convert, m)?)
+}
@@ -0,0 +1,207 @@
+//! Access log of the daemon with a line for every request, so operators can
+//! audit the use of a shared converter. The file is rotated when it would
+//! grow over its size limit.
+
+use crate::daemon::Log;
+use md2htm::Error;
+use std::{
+    fs::{rename, File, OpenOptions},
+    io::{Result, Write},
+    os::unix::net::SocketAddr,
+    sync::{Mutex, OnceLock, PoisonError},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Rotated files kept as `.1`, `.2` and so on after the path, `.1` being the newest
+const KEEP: usize = 5;
+
+/// The log of the daemon, if it was asked for
+static ACCESS_LOG: OnceLock<AccessLog> = OnceLock::new();
+
+#[derive(Debug)]
+struct AccessLog {
+    path: String,
+    max_size: u64,
+    /// The file being written and its size
+    file: Mutex<(File, u64)>,
+}
+
+/// Starts logging the requests into the file
+pub fn open(path: &str, max_size: u64) -> Result<()> {
+    let file: File = append(path)?;
+    let size: u64 = file.metadata()?.len();
+
+    let _ = ACCESS_LOG.set(AccessLog {
+        path: path.to_string(),
+        max_size,
+        file: Mutex::new((file, size)),
+    });
+
+    Ok(())
+}
+
+fn append(path: &str) -> Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+impl AccessLog {
+    fn write(&self, line: &str) -> Result<()> {
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if file.1 > 0 && file.1 + line.len() as u64 > self.max_size {
+            self.rotate()?;
+            *file = (append(&self.path)?, 0);
+        }
+
+        file.0.write_all(line.as_bytes())?;
+        file.1 += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&self) -> Result<()> {
+        for n in (1..KEEP).rev() {
+            let _ = rename(
+                format!("{}.{n}", self.path),
+                format!("{}.{}", self.path, n + 1),
+            );
+        }
+
+        rename(&self.path, format!("{}.1", self.path))
+    }
+}
+
+/// How a request ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    /// The request was broken or over the size limit
+    Dropped,
+    /// The client left or the timeout passed
+    Cancelled,
+    /// The HTML grew over the output limit
+    TooLarge,
+    /// The answer couldn't be sent
+    Failed,
+    /// The daemon had too many connections and answered `busy`
+    Busy,
+}
+
+impl Status {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Dropped => "dropped",
+            Self::Cancelled => "cancelled",
+            Self::TooLarge => "too-large",
+            Self::Failed => "failed",
+            Self::Busy => "busy",
+        }
+    }
+}
+
+impl From<Error> for Status {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Cancelled => Self::Cancelled,
+            Error::OutputLimit { .. } => Self::TooLarge,
+        }
+    }
+}
+
+/// Line of the access log, written when dropped
+#[derive(Debug)]
+pub struct Entry {
+    started: Instant,
+    peer: String,
+    pub bytes_in: usize,
+    pub bytes_out: usize,
+    pub status: Status,
+    log: Log,
+}
+
+impl Entry {
+    pub fn new(peer: Option<SocketAddr>, log: Log) -> Self {
+        Self {
+            started: Instant::now(),
+            peer: peer.as_ref().map_or_else(|| String::from("-"), name),
+            bytes_in: 0,
+            bytes_out: 0,
+            status: Status::Dropped,
+            log,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+impl Drop for Entry {
+    fn drop(&mut self) {
+        let Some(access_log) = ACCESS_LOG.get() else {
+            return;
+        };
+
+        let line: String = format!(
+            "{} {} in={} out={} duration={:.3}ms status={}\n",
+            timestamp(SystemTime::now()),
+            self.peer,
+            self.bytes_in,
+            self.bytes_out,
+            self.elapsed().as_secs_f64() * 1000.0,
+            self.status.name()
+        );
+
+        if let Err(e) = access_log.write(&line) {
+            if self.log >= Log::Error {
+                eprintln!("Couldn't write the access log: {e}");
+            }
+        }
+    }
+}
+
+/// Path of the socket of the peer, `@name` for abstract sockets and `-`
+/// for the unnamed ones most clients connect with
+fn name(addr: &SocketAddr) -> String {
+    if let Some(path) = addr.as_pathname() {
+        return path.display().to_string().replace(' ', "%20");
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::linux::net::SocketAddrExt;
+
+        if let Some(name) = addr.as_abstract_name() {
+            return format!("@{}", String::from_utf8_lossy(name)).replace(' ', "%20");
+        }
+    }
+
+    String::from("-")
+}
+
+/// The time in UTC as `2026-01-31T12:00:00.000Z`
+fn timestamp(time: SystemTime) -> String {
+    let since: u128 = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis());
+    let (days, millis) = ((since / 86_400_000) as i64, since % 86_400_000);
+
+    // Days to the civil date, counting years from March so leap days come last
+    let z: i64 = days + 719_468;
+    let era: i64 = z.div_euclid(146_097);
+    let doe: i64 = z - era * 146_097;
+    let yoe: i64 = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy: i64 = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp: i64 = (5 * doy + 2) / 153;
+    let day: i64 = doy - (153 * mp + 2) / 5 + 1;
+    let month: i64 = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year: i64 = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
@@ -0,0 +1,412 @@
+//! Tree of the document with its blocks and their inline content, for
+//! tools that want the structure of the page instead of its bytes: tables
+//! of contents, link checkers and other renderers. The tree is read from
+//! the rendered HTML through [`crate::HtmlEvents`], so it is a view of the
+//! output of the state machine, in the order the machine wrote it, and
+//! [`Document::render_html`] writes the same HTML. The parser doesn't build
+//! it as it goes, and it doesn't change how the markdown is parsed.
+//!
+//! ```
+//! use md2htm::ast::Block;
+//! use md2htm::MDS;
+//!
+//! let document = MDS::parse_document(b"# Title\n\nSee [docs](docs.html)\n".to_vec(), &Default::default());
+//! assert!(matches!(document.blocks[0], Block::Heading { level: 1, .. }));
+//! assert_eq!(document.links()[0].attribute("href"), Some("docs.html"));
+//! assert_eq!(document.render_html(), MDS::parse(b"# Title\n\nSee [docs](docs.html)\n".to_vec()));
+//! ```
+
+use crate::events::{push_html, Event, HtmlEvents, Kind, Tag};
+use alloc::{string::String, vec, vec::Vec};
+
+/// Elements holding blocks instead of inline content
+const CONTAINERS: &[&str] = &[
+    "article",
+    "aside",
+    "blockquote",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "figure",
+    "footer",
+    "header",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "section",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "ul",
+];
+
+/// The whole document
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Document {
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Block {
+    Heading {
+        tag: Tag,
+        level: u8,
+        content: Vec<Inline>,
+    },
+    Paragraph {
+        tag: Tag,
+        content: Vec<Inline>,
+    },
+    /// `<pre>` with the `<code>` in it
+    CodeBlock {
+        tag: Tag,
+        content: Vec<Inline>,
+    },
+    Rule(Tag),
+    /// Lists, items, quotes, tables, divs and other elements of blocks
+    Container {
+        tag: Tag,
+        blocks: Vec<Block>,
+    },
+    /// Inline content outside of paragraphs, like the text of the items of
+    /// tight lists and the line breaks between blocks
+    Inlines(Vec<Inline>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Inline {
+    /// Text as written in the HTML, its character references kept
+    Text(String),
+    /// Raw fences and comments, and the tags of elements that were never
+    /// closed
+    Html(String),
+    /// Emphasis, links, code spans and the other elements of text
+    Element { tag: Tag, content: Vec<Inline> },
+    /// `<br>`, `<img>` and other elements without content
+    Void(Tag),
+}
+
+/// Element or piece of text while the tree is built
+#[derive(Debug)]
+enum Node {
+    Element(Tag, Vec<Node>),
+    Void(Tag),
+    Text(String),
+    Html(String),
+}
+
+impl Document {
+    /// Builds the tree from the events of the rendered HTML
+    pub fn from_events(events: HtmlEvents<'_>) -> Self {
+        // The children of the open elements, the document at the bottom
+        let mut stack: Vec<(Option<Tag>, Vec<Node>)> = vec![(None, Vec::new())];
+
+        for event in events {
+            match event {
+                Event::Start(tag) if tag.is_void() || tag.self_closing => {
+                    push(&mut stack, Node::Void(tag));
+                }
+                Event::Start(tag) => stack.push((Some(tag), Vec::new())),
+                // The end of a void element was handled with its start
+                Event::End(tag) if tag.is_void() || tag.self_closing => {}
+                Event::End(tag) => {
+                    let open: Option<usize> = stack.iter().rposition(|(open, _)| {
+                        open.as_ref()
+                            .is_some_and(|open| open.name.eq_ignore_ascii_case(&tag.name))
+                    });
+
+                    match open {
+                        Some(i) => {
+                            while stack.len() > i + 1 {
+                                unclosed(&mut stack);
+                            }
+
+                            if let Some((Some(tag), children)) = stack.pop() {
+                                push(&mut stack, Node::Element(tag, children));
+                            }
+                        }
+                        // An end tag of the raw HTML without a start
+                        None => push(&mut stack, Node::Html(end_tag(&tag))),
+                    }
+                }
+                Event::Text(text) => push(&mut stack, Node::Text(lossy(text))),
+                Event::Html(html) => push(&mut stack, Node::Html(lossy(html))),
+            }
+        }
+
+        while stack.len() > 1 {
+            unclosed(&mut stack);
+        }
+
+        let (_, nodes) = stack.pop().unwrap_or_default();
+        Self {
+            blocks: to_blocks(nodes),
+        }
+    }
+
+    /// Writes the tree as HTML
+    pub fn render_html(&self) -> Vec<u8> {
+        let mut events: Vec<Event<'_>> = Vec::new();
+
+        for block in &self.blocks {
+            block.events(&mut events);
+        }
+
+        let mut html: Vec<u8> = Vec::new();
+        push_html(&mut html, events);
+        html
+    }
+
+    /// The headings with their level and text, for tables of contents
+    pub fn headings(&self) -> Vec<(u8, &Tag, String)> {
+        let mut headings: Vec<(u8, &Tag, String)> = Vec::new();
+        each_block(&self.blocks, &mut |block| {
+            if let Block::Heading {
+                tag,
+                level,
+                content,
+            } = block
+            {
+                headings.push((*level, tag, text(content)));
+            }
+        });
+        headings
+    }
+
+    /// The `<a>` tags of the document
+    pub fn links(&self) -> Vec<&Tag> {
+        let mut links: Vec<&Tag> = Vec::new();
+        each_block(&self.blocks, &mut |block| {
+            let content: &[Inline] = match block {
+                Block::Heading { content, .. }
+                | Block::Paragraph { content, .. }
+                | Block::CodeBlock { content, .. }
+                | Block::Inlines(content) => content,
+                Block::Rule(_) | Block::Container { .. } => &[],
+            };
+            each_inline(content, &mut |inline| {
+                if let Inline::Element { tag, .. } = inline {
+                    if tag.kind() == Kind::Link {
+                        links.push(tag);
+                    }
+                }
+            });
+        });
+        links
+    }
+}
+
+impl Block {
+    fn events<'a>(&'a self, events: &mut Vec<Event<'a>>) {
+        let (tag, content): (&Tag, &[Inline]) = match self {
+            Self::Heading { tag, content, .. }
+            | Self::Paragraph { tag, content }
+            | Self::CodeBlock { tag, content } => (tag, content),
+            Self::Rule(tag) => {
+                events.push(Event::Start(tag.clone()));
+                events.push(Event::End(tag.clone()));
+                return;
+            }
+            Self::Container { tag, blocks } => {
+                events.push(Event::Start(tag.clone()));
+
+                for block in blocks {
+                    block.events(events);
+                }
+
+                events.push(Event::End(tag.clone()));
+                return;
+            }
+            Self::Inlines(content) => {
+                for inline in content {
+                    inline.events(events);
+                }
+
+                return;
+            }
+        };
+
+        events.push(Event::Start(tag.clone()));
+
+        for inline in content {
+            inline.events(events);
+        }
+
+        events.push(Event::End(tag.clone()));
+    }
+}
+
+impl Inline {
+    fn events<'a>(&'a self, events: &mut Vec<Event<'a>>) {
+        match self {
+            Self::Text(text) => events.push(Event::Text(text.as_bytes())),
+            Self::Html(html) => events.push(Event::Html(html.as_bytes())),
+            Self::Element { tag, content } => {
+                events.push(Event::Start(tag.clone()));
+
+                for inline in content {
+                    inline.events(events);
+                }
+
+                events.push(Event::End(tag.clone()));
+            }
+            Self::Void(tag) => {
+                events.push(Event::Start(tag.clone()));
+                events.push(Event::End(tag.clone()));
+            }
+        }
+    }
+}
+
+fn push(stack: &mut [(Option<Tag>, Vec<Node>)], node: Node) {
+    if let Some((_, children)) = stack.last_mut() {
+        children.push(node);
+    }
+}
+
+/// Moves the children of the element left open by the raw HTML to its
+/// parent after the start tag, which stays as it was written
+fn unclosed(stack: &mut Vec<(Option<Tag>, Vec<Node>)>) {
+    let Some((tag, children)) = stack.pop() else {
+        return;
+    };
+
+    if let Some(tag) = tag {
+        let mut start: Vec<u8> = Vec::new();
+        push_html(&mut start, [Event::Start(tag)]);
+        push(stack, Node::Html(lossy(&start)));
+    }
+
+    for child in children {
+        push(stack, child);
+    }
+}
+
+fn end_tag(tag: &Tag) -> String {
+    let mut end: Vec<u8> = Vec::new();
+    push_html(&mut end, [Event::End(tag.clone())]);
+    lossy(&end)
+}
+
+fn lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Blocks of the nodes, runs of inline nodes between them going together
+fn to_blocks(nodes: Vec<Node>) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut inlines: Vec<Inline> = Vec::new();
+
+    for node in nodes {
+        let block: Block = match node {
+            Node::Element(tag, children) => match tag.kind() {
+                Kind::Heading(level) => Block::Heading {
+                    tag,
+                    level,
+                    content: children.into_iter().map(inline).collect(),
+                },
+                // Paragraphs the parser writes around lists hold blocks
+                Kind::Paragraph if children.iter().any(container) => Block::Container {
+                    tag,
+                    blocks: to_blocks(children),
+                },
+                Kind::Paragraph => Block::Paragraph {
+                    tag,
+                    content: children.into_iter().map(inline).collect(),
+                },
+                Kind::CodeBlock => Block::CodeBlock {
+                    tag,
+                    content: children.into_iter().map(inline).collect(),
+                },
+                _ if is_container(&tag) => Block::Container {
+                    tag,
+                    blocks: to_blocks(children),
+                },
+                _ => {
+                    inlines.push(inline(Node::Element(tag, children)));
+                    continue;
+                }
+            },
+            Node::Void(tag) if tag.kind() == Kind::Rule => Block::Rule(tag),
+            node => {
+                inlines.push(inline(node));
+                continue;
+            }
+        };
+
+        if !inlines.is_empty() {
+            blocks.push(Block::Inlines(core::mem::take(&mut inlines)));
+        }
+
+        blocks.push(block);
+    }
+
+    if !inlines.is_empty() {
+        blocks.push(Block::Inlines(inlines));
+    }
+
+    blocks
+}
+
+fn is_container(tag: &Tag) -> bool {
+    CONTAINERS
+        .iter()
+        .any(|name| tag.name.eq_ignore_ascii_case(name))
+}
+
+fn container(node: &Node) -> bool {
+    matches!(node, Node::Element(tag, _) if is_container(tag))
+}
+
+fn inline(node: Node) -> Inline {
+    match node {
+        Node::Element(tag, children) => Inline::Element {
+            tag,
+            content: children.into_iter().map(inline).collect(),
+        },
+        Node::Void(tag) => Inline::Void(tag),
+        Node::Text(text) => Inline::Text(text),
+        Node::Html(html) => Inline::Html(html),
+    }
+}
+
+fn each_block<'a>(blocks: &'a [Block], f: &mut impl FnMut(&'a Block)) {
+    for block in blocks {
+        f(block);
+
+        if let Block::Container { blocks, .. } = block {
+            each_block(blocks, f);
+        }
+    }
+}
+
+fn each_inline<'a>(content: &'a [Inline], f: &mut impl FnMut(&'a Inline)) {
+    for inline in content {
+        f(inline);
+
+        if let Inline::Element { content, .. } = inline {
+            each_inline(content, f);
+        }
+    }
+}
+
+/// Text of the inline content without the tags
+fn text(content: &[Inline]) -> String {
+    let mut text: String = String::new();
+    each_inline(content, &mut |inline| {
+        if let Inline::Text(part) = inline {
+            text.push_str(part);
+        }
+    });
+    crate::text::unescape(text.as_bytes())
+}
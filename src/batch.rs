@@ -0,0 +1,150 @@
+//! Batch conversion for data pipelines. Every line of the standard input is
+//! a JSON object with an `id` and the `markdown`, and every line written to
+//! the standard output has the same `id` with the `html` and the
+//! `diagnostics`, in the same order. Thousands of snippets go through one
+//! process without the socket of the daemon.
+
+use crate::config::Setting;
+use crate::i18n::{self, tr};
+use crate::json::{self, Value};
+use crate::pragma;
+use md2htm::{Diagnostic, Limits, Output, Parser, ParserBuilder, Severity};
+use std::{
+    fmt::Write as _,
+    io::{self, BufRead, BufWriter, Result, Write},
+};
+
+/// Converts the lines of the standard input until it ends. Returns false if
+/// some line couldn't be converted.
+pub fn run(
+    builder: &ParserBuilder,
+    settings: &[Setting],
+    max_output: Option<usize>,
+) -> Result<bool> {
+    let mut input = io::stdin().lock();
+    let mut output = BufWriter::new(io::stdout().lock());
+    let parser: Parser = Parser::new(builder.clone().build());
+    let limits: Limits = Limits {
+        max_output,
+        ..Limits::default()
+    };
+    let mut ok: bool = true;
+    let mut line: String = String::new();
+
+    while input.read_line(&mut line)? > 0 {
+        if !line.trim().is_empty() {
+            let mut answer: String = String::new();
+            ok &= convert(
+                &mut answer,
+                line.trim(),
+                &parser,
+                builder,
+                settings,
+                &limits,
+            );
+            answer.push('\n');
+            output.write_all(answer.as_bytes())?;
+        }
+
+        line.clear();
+
+        // Answer right away when the rest of the input isn't there yet
+        if input.fill_buf()?.is_empty() {
+            output.flush()?;
+        }
+    }
+
+    output.flush()?;
+    Ok(ok)
+}
+
+/// Writes the answer to the line, or the error with the id if there is one
+fn convert(
+    answer: &mut String,
+    line: &str,
+    parser: &Parser,
+    builder: &ParserBuilder,
+    settings: &[Setting],
+    limits: &Limits,
+) -> bool {
+    let request: Option<Value> = json::parse(line);
+    let id: &Value = request
+        .as_ref()
+        .and_then(|request| request.get("id"))
+        .unwrap_or(&Value::Null);
+
+    answer.push_str("{\"id\":");
+    id.write(answer);
+
+    let Some(markdown) = request
+        .as_ref()
+        .and_then(|request| request.get("markdown"))
+        .and_then(Value::as_str)
+    else {
+        answer.push_str(",\"error\":");
+        json::string(answer, tr!("Expected a JSON object with the markdown."));
+        answer.push('}');
+        return false;
+    };
+
+    // Documents with a pragma get a parser of their own
+    let (options, markdown) = pragma(settings, builder, markdown.as_bytes());
+    let own: Parser;
+    let parser: &Parser = match options.clone().build() == *parser.options() {
+        true => parser,
+        false => {
+            own = Parser::new(options.build());
+            &own
+        }
+    };
+
+    let output: Output = match parser.parse_limited(markdown, limits) {
+        Ok(output) => output,
+        Err(e) => {
+            answer.push_str(",\"error\":");
+            json::string(answer, &e.to_string());
+            answer.push('}');
+            return false;
+        }
+    };
+
+    answer.push_str(",\"html\":");
+    json::string(answer, &String::from_utf8_lossy(&output.html));
+
+    if let Some(stats) = output.stats {
+        let _ = write!(
+            answer,
+            ",\"stats\":{{\"words\":{},\"reading_minutes\":{}}}",
+            stats.words, stats.reading_minutes
+        );
+    }
+
+    answer.push_str(",\"diagnostics\":[");
+
+    for (i, diagnostic) in output.diagnostics.iter().enumerate() {
+        if i != 0 {
+            answer.push(',');
+        }
+
+        write_diagnostic(answer, diagnostic);
+    }
+
+    answer.push_str("]}");
+    true
+}
+
+fn write_diagnostic(json: &mut String, diagnostic: &Diagnostic) {
+    let _ = write!(
+        json,
+        "{{\"line\":{},\"column\":{},\"severity\":\"{}\",\"code\":\"{}\",\"message\":",
+        diagnostic.position.line,
+        diagnostic.position.column,
+        match diagnostic.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        },
+        diagnostic.code.name()
+    );
+    json::string(json, &i18n::message(diagnostic));
+    json.push('}');
+}
@@ -0,0 +1,356 @@
+//! Build mode that converts a directory of markdown files into a directory
+//! of HTML pages. Images with paths relative to the pages are copied under
+//! `assets` in the output directory, and the pages are rewritten to use the
+//! copies. A search index of the pages can be written too. Front matter
+//! between `---` lines at the start of the files is left out, and the files
+//! with `draft: true` in it are skipped. The files are always processed in
+//! the same order, so building the same sources again gives identical output.
+
+use crate::config::Setting;
+use crate::head::{front_matter, front_value};
+use crate::i18n::tr;
+use crate::{has_errors, json, pragma, print_diagnostic};
+use md2htm::{validate, Output, Parser, ParserBuilder};
+use std::{
+    collections::HashSet,
+    fs,
+    io::Result,
+    path::{Component, Path, PathBuf},
+};
+
+/// Directory in the output that the images are copied to
+const ASSETS: &str = "assets";
+/// File in the output that the search index is written to
+const SEARCH_INDEX: &str = "search-index.json";
+
+/// Build settings that aren't parser options
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Write the plain text of every page into a search index for
+    /// client-side search libraries like lunr or fuse
+    pub search_index: bool,
+    /// Build the pages marked with `draft: true` in their front matter too
+    pub drafts: bool,
+    /// Build everything a second time and check that the files are identical
+    pub check_reproducible: bool,
+    /// Check that the HTML of every page is well-formed
+    pub validate: bool,
+    /// Layers of options the pragmas of the pages are applied on top of
+    pub(crate) settings: Vec<Setting>,
+}
+
+/// What a build produced
+#[derive(Debug)]
+struct Built {
+    pages: usize,
+    assets: usize,
+    drafts: usize,
+    /// Pages with diagnostics denied with --deny
+    errors: usize,
+    /// Every file written, relative to the output directory
+    files: Vec<PathBuf>,
+}
+
+pub fn run(src: &Path, dst: &Path, builder: ParserBuilder, config: Config) -> Result<()> {
+    let built: Built = build(src, dst, &builder, &config, true)?;
+
+    println!(
+        "{}",
+        tr!(
+            "Built {} pages and copied {} assets into {}.",
+            built.pages,
+            built.assets,
+            dst.display()
+        )
+    );
+
+    if built.drafts != 0 {
+        println!(
+            "{}",
+            tr!(
+                "Skipped {} drafts. Build them too with --drafts.",
+                built.drafts
+            )
+        );
+    }
+
+    if built.errors != 0 {
+        eprintln!("{}", tr!("{} pages have errors.", built.errors));
+        std::process::exit(1);
+    }
+
+    if config.check_reproducible {
+        let again: PathBuf =
+            std::env::temp_dir().join(format!("md2htm-check-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&again);
+        let rebuilt: Built = build(src, &again, &builder, &config, false)?;
+
+        let differences: Vec<PathBuf> = compare(dst, &built.files, &again, &rebuilt.files);
+        let _ = fs::remove_dir_all(&again);
+
+        if !differences.is_empty() {
+            for file in &differences {
+                eprintln!("{}", tr!("{} differs between builds.", file.display()));
+            }
+
+            std::process::exit(1);
+        }
+
+        println!("{}", tr!("A second build produced identical files."));
+    }
+
+    Ok(())
+}
+
+/// Builds the pages into the output directory. The problems are printed only
+/// if `report` is set.
+fn build(
+    src: &Path,
+    dst: &Path,
+    builder: &ParserBuilder,
+    config: &Config,
+    report: bool,
+) -> Result<Built> {
+    let mut sources: Vec<PathBuf> = Vec::new();
+    find_markdown(src, &mut sources)?;
+    sources.sort();
+
+    let mut copied: HashSet<PathBuf> = HashSet::new();
+    let mut pages: usize = 0;
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut drafts: usize = 0;
+    let mut errors: usize = 0;
+    let mut index: String = String::from("[");
+    // The search index links to the sections of the pages
+    let builder: ParserBuilder = builder.clone().heading_map(config.search_index);
+
+    for source in &sources {
+        let Ok(relative) = source.strip_prefix(src) else {
+            continue;
+        };
+
+        let dir: &Path = relative.parent().unwrap_or(Path::new(""));
+        let page: PathBuf = dst.join(relative).with_extension("html");
+
+        // The assets mirror the source directories, so a page only needs to
+        // step back to the root of the output.
+        let mut prefix: String = "../".repeat(dir.components().count());
+        prefix.push_str(ASSETS);
+        prefix.push('/');
+        for component in dir.components() {
+            prefix.push_str(&component.as_os_str().to_string_lossy());
+            prefix.push('/');
+        }
+
+        let markdown: Vec<u8> = fs::read(source)?;
+        let (front, body) = front_matter(&markdown);
+
+        if !config.drafts && is_draft(front) {
+            drafts += 1;
+            continue;
+        }
+
+        let (page_builder, body) = pragma(&config.settings, &builder, body);
+        let parser: Parser = Parser::new(
+            page_builder
+                .heading_map(config.search_index)
+                .asset_prefix(&prefix)
+                .build(),
+        );
+        let output: Output = parser.parse(body);
+        pages += 1;
+        errors += usize::from(has_errors(&output.diagnostics));
+
+        for diagnostic in output.diagnostics.iter().filter(|_| report) {
+            eprint!("{}: ", source.display());
+            print_diagnostic(diagnostic);
+        }
+
+        if config.validate && report {
+            if let Err(e) = validate(&output.html) {
+                eprintln!(
+                    "{}",
+                    tr!("{}: The HTML isn't well-formed at {}", source.display(), e)
+                );
+            }
+        }
+
+        if let Some(parent) = page.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&page, &output.html)?;
+        files.push(page.strip_prefix(dst).unwrap_or(&page).to_path_buf());
+
+        if config.search_index {
+            if index.len() > 1 {
+                index.push(',');
+            }
+
+            index_page(&mut index, relative, &output);
+        }
+
+        for asset in &output.assets {
+            let Some(asset) = normalize(&dir.join(asset)) else {
+                if !report {
+                    continue;
+                }
+
+                eprintln!(
+                    "{}",
+                    tr!(
+                        "{}: Not copying {}, because it is outside the source directory.",
+                        source.display(),
+                        asset
+                    )
+                );
+                continue;
+            };
+
+            if !copied.insert(asset.clone()) {
+                continue;
+            }
+
+            let to: PathBuf = dst.join(ASSETS).join(&asset);
+
+            if let Some(parent) = to.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            if let Err(e) = fs::copy(src.join(&asset), &to) {
+                if report {
+                    eprintln!(
+                        "{}",
+                        tr!(
+                            "{}: Couldn't copy {}: {}",
+                            source.display(),
+                            asset.display(),
+                            e
+                        )
+                    );
+                }
+
+                copied.remove(&asset);
+            }
+        }
+    }
+
+    if config.search_index {
+        index.push_str(if index.len() > 1 { "\n]\n" } else { "]\n" });
+        fs::create_dir_all(dst)?;
+        fs::write(dst.join(SEARCH_INDEX), index)?;
+        files.push(PathBuf::from(SEARCH_INDEX));
+    }
+
+    for asset in &copied {
+        files.push(Path::new(ASSETS).join(asset));
+    }
+
+    Ok(Built {
+        pages,
+        assets: copied.len(),
+        drafts,
+        errors,
+        files,
+    })
+}
+
+/// Paths of the files that differ between the builds, or that only one of
+/// them wrote
+fn compare(a: &Path, a_files: &[PathBuf], b: &Path, b_files: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = a_files.to_vec();
+
+    for file in b_files {
+        if !files.contains(file) {
+            files.push(file.clone());
+        }
+    }
+
+    files.sort();
+    files.retain(|file| {
+        if !a_files.contains(file) || !b_files.contains(file) {
+            return true;
+        }
+
+        match (fs::read(a.join(file)), fs::read(b.join(file))) {
+            (Ok(first), Ok(second)) => first != second,
+            _ => true,
+        }
+    });
+    files
+}
+
+/// Checks if the front matter has `draft: true` in it
+fn is_draft(front: &[u8]) -> bool {
+    front_value(front, "draft").is_some_and(|value| matches!(value.as_str(), "true" | "yes"))
+}
+
+/// Adds the page to the search index. The title is the first top level
+/// heading, or the file name without one.
+fn index_page(index: &mut String, relative: &Path, output: &Output) {
+    let url: String = relative
+        .with_extension("html")
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let title: String = output
+        .headings
+        .iter()
+        .find(|heading| heading.level == 1)
+        .map(|heading| heading.text.clone())
+        .unwrap_or_else(|| {
+            relative
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned()
+        });
+
+    index.push_str("\n{\"url\": ");
+    json::string(index, &url);
+    index.push_str(", \"title\": ");
+    json::string(index, &title);
+    index.push_str(", \"headings\": ");
+    json::headings(index, &output.headings);
+    index.push_str(", \"body\": ");
+    json::string(index, &output.text());
+    index.push('}');
+}
+
+/// Collects the markdown files in the directory and its subdirectories
+pub(crate) fn find_markdown(dir: &Path, found: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path: PathBuf = entry?.path();
+
+        if path.is_dir() {
+            find_markdown(&path, found)?;
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            found.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolves the `.` and `..` parts of a relative path without touching the
+/// file system. Paths leading outside are rejected.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normal: PathBuf = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => normal.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normal.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+
+    Some(normal)
+}
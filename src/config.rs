@@ -0,0 +1,541 @@
+//! Layered configuration. The options can be set in the system config, the
+//! user config, environment variables, on the command line and in a pragma
+//! on the first line of a document, each layer overriding the ones before.
+//! The config files hold the long names of the options without the dashes:
+//!
+//! ```toml
+//! # ~/.config/md2htm/config.toml
+//! dialect = "gfm"
+//! heading-ids = true
+//! enable = ["kbd", "comments"]
+//! ```
+//!
+//! The environment variables are the names in capitals with the dashes
+//! replaced by underscores, like `MD2HTM_HEADING_IDS=1`, and the pragma is an
+//! HTML comment like `<!-- md2htm: toc = true, placement = "marker" -->`.
+//!
+//! Every layer is turned into command line flags that are handled like the
+//! ones given on the command line.
+
+use crate::help;
+use crate::i18n::tr;
+use md2htm::detect_dialect;
+use std::{env, fs, io::ErrorKind, path::PathBuf};
+
+/// Config file shared by all users
+pub(crate) const SYSTEM: &str = "/etc/md2htm/config.toml";
+/// Prefix of the environment variables
+const ENV_PREFIX: &str = "MD2HTM_";
+/// Start of the pragma comment
+const PRAGMA: &[u8] = b"<!-- md2htm:";
+/// Options taking a comma separated list. Every layer adds to these instead
+/// of replacing them, so the layers can enable and disable extensions.
+const LISTS: &[&str] = &["enable", "disable", "allow", "warn", "deny"];
+/// Options a document can't set for itself, because they loosen the limits
+/// the converter was started with, write files or are about the whole run
+const NOT_IN_PRAGMA: &[&str] = &[
+    "safe",
+    "csp",
+    "max-output",
+    "max-link-length",
+    "timeout",
+    "trace-parser",
+    "inject-head",
+    "inject-body",
+    "heading-map",
+    "source-map",
+    "epub",
+    "excerpt",
+    "standalone",
+    "theme",
+    "inline-theme",
+    "force-binary",
+    "eol",
+    "chunked",
+    "threads",
+    "file",
+    "clients",
+    "duration",
+    "status",
+    "stats",
+    "validate",
+    "check-reproducible",
+    "drafts",
+    "search-index",
+    "reference",
+];
+
+/// Options a client of the daemon can't set for its request
+const NOT_IN_REQUEST: &[&str] = &[
+    "socket",
+    "datagram",
+    "access-log",
+    "access-log-size",
+    "max-size",
+    "workers",
+    "max-connections",
+    "idle-timeout",
+    "threads",
+    "file",
+    "clients",
+    "duration",
+    "log",
+    "eol",
+    "max-output",
+    "max-link-length",
+    "timeout",
+    "trace-parser",
+    "inject-head",
+    "inject-body",
+    "heading-map",
+    "source-map",
+    "epub",
+    "validate",
+    "check-reproducible",
+    "drafts",
+    "search-index",
+    "reference",
+];
+
+/// Where a setting came from, from the lowest precedence to the highest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Source {
+    System,
+    User,
+    Env,
+    Cli,
+    /// Options sent with a request to the daemon
+    Request,
+    Pragma,
+}
+
+impl Source {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Source::System => tr!("system config"),
+            Source::User => tr!("user config"),
+            Source::Env => tr!("environment"),
+            Source::Cli => tr!("command line"),
+            Source::Request => tr!("request"),
+            Source::Pragma => tr!("pragma"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Value {
+    Bool(bool),
+    /// Strings and numbers, which are given to the flags as is
+    Text(String),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Setting {
+    /// Name of the option without the dashes
+    pub(crate) key: String,
+    pub(crate) value: Value,
+    pub(crate) source: Source,
+}
+
+/// Path of the user config, under `XDG_CONFIG_HOME` or `~/.config`
+pub(crate) fn user_path() -> Option<PathBuf> {
+    let base: PathBuf = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => dir.into(),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(base.join("md2htm").join("config.toml"))
+}
+
+/// Reads the config files and the environment. Prints what is wrong with
+/// them and returns None if anything is.
+pub(crate) fn load() -> Option<Vec<Setting>> {
+    let mut settings: Vec<Setting> = Vec::new();
+    let files = [
+        (Some(PathBuf::from(SYSTEM)), Source::System),
+        (user_path(), Source::User),
+    ];
+
+    for (path, source) in files {
+        let Some(path) = path else {
+            continue;
+        };
+
+        let text: String = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => {
+                eprintln!("{}", tr!("Couldn't read {}: {}", path.display(), e));
+                return None;
+            }
+        };
+
+        match parse(&text, source) {
+            Ok(parsed) => settings.extend(parsed),
+            Err((line, message)) => {
+                eprintln!("{}:{line}: {message}", path.display());
+                return None;
+            }
+        }
+    }
+
+    for (name, takes_value) in help::flags() {
+        let var: String = format!("{ENV_PREFIX}{}", name.to_uppercase().replace('-', "_"));
+        let Ok(text) = env::var(&var) else {
+            continue;
+        };
+
+        let value: Value = if takes_value {
+            Value::Text(text)
+        } else {
+            match text.as_str() {
+                "1" | "true" => Value::Bool(true),
+                "0" | "false" | "" => Value::Bool(false),
+                _ => {
+                    eprintln!("{}", tr!("{} expects 1, 0, true or false.", var));
+                    return None;
+                }
+            }
+        };
+
+        settings.push(Setting {
+            key: name.to_string(),
+            value,
+            source: Source::Env,
+        });
+    }
+
+    Some(settings)
+}
+
+/// Reads the settings from the flags of the command line
+pub(crate) fn from_args(args: &[String]) -> Vec<Setting> {
+    let mut settings: Vec<Setting> = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let Some(name) = arg.strip_prefix("--") else {
+            continue;
+        };
+
+        let Some(takes_value) = help::flag(name) else {
+            continue;
+        };
+
+        let value: Value = match takes_value {
+            true => match args.next() {
+                Some(value) => Value::Text(value.clone()),
+                None => continue,
+            },
+            false => Value::Bool(true),
+        };
+
+        settings.push(Setting {
+            key: name.to_string(),
+            value,
+            source: Source::Cli,
+        });
+    }
+
+    settings
+}
+
+/// Leaves out the settings overridden by a later layer. The list options are
+/// all kept in order.
+pub(crate) fn effective(settings: &[Setting]) -> Vec<&Setting> {
+    let mut effective: Vec<&Setting> = Vec::new();
+
+    for setting in settings {
+        if !LISTS.contains(&setting.key.as_str()) {
+            effective.retain(|earlier| earlier.key != setting.key);
+        }
+
+        effective.push(setting);
+    }
+
+    effective
+}
+
+/// Checks if the dialect in effect is `auto`, to be detected from each document
+pub(crate) fn auto_dialect(settings: &[Setting]) -> bool {
+    settings
+        .iter()
+        .rfind(|setting| setting.key == "dialect")
+        .is_some_and(|setting| setting.value == Value::Text(String::from("auto")))
+}
+
+/// Replaces the dialect `auto` with the one detected from the markdown
+pub(crate) fn resolve_dialect(settings: &mut [Setting], markdown: &[u8]) {
+    for setting in settings.iter_mut() {
+        if setting.key == "dialect" && setting.value == Value::Text(String::from("auto")) {
+            setting.value = Value::Text(detect_dialect(markdown).name().to_string());
+        }
+    }
+}
+
+/// Turns the settings into command line flags
+pub(crate) fn to_args(settings: &[&Setting]) -> Vec<String> {
+    let mut args: Vec<String> = Vec::new();
+
+    for setting in settings {
+        match &setting.value {
+            Value::Bool(true) => args.push(format!("--{}", setting.key)),
+            Value::Bool(false) => {}
+            Value::Text(text) => {
+                args.push(format!("--{}", setting.key));
+                args.push(text.clone());
+            }
+        }
+    }
+
+    args
+}
+
+/// Reads the pragma on the first line of the markdown. Returns the settings,
+/// or what is wrong with them, and the markdown after the pragma.
+pub(crate) fn pragma(markdown: &[u8]) -> (Result<Vec<Setting>, String>, &[u8]) {
+    // Markers like `<!-- md2htm:off -->` of the comments extension aren't pragmas
+    let Some(rest) = markdown
+        .strip_prefix(PRAGMA)
+        .filter(|rest| rest.first().is_some_and(u8::is_ascii_whitespace))
+    else {
+        return (Ok(Vec::new()), markdown);
+    };
+
+    let Some(end) = rest.windows(3).position(|window| window == b"-->") else {
+        return (Ok(Vec::new()), markdown);
+    };
+
+    let mut body: &[u8] = &rest[end + 3..];
+    body = body.strip_prefix(b"\r").unwrap_or(body);
+    body = body.strip_prefix(b"\n").unwrap_or(body);
+
+    let settings = match parse(&String::from_utf8_lossy(&rest[..end]), Source::Pragma) {
+        Ok(settings) => match settings
+            .iter()
+            .find(|setting| NOT_IN_PRAGMA.contains(&setting.key.as_str()))
+        {
+            Some(setting) => Err(tr!("The pragma can't set {}.", setting.key)),
+            None => Ok(settings),
+        },
+        Err((_, message)) => Err(message),
+    };
+
+    (settings, body)
+}
+
+/// Reads the options a client sent with its request to the daemon. Options
+/// that loosen the limits of the daemon, write files or are about the daemon
+/// itself are refused, and safe mode and --csp can only be turned on.
+pub(crate) fn request(options: &[u8]) -> Result<Vec<Setting>, String> {
+    let settings: Vec<Setting> =
+        parse(&String::from_utf8_lossy(options), Source::Request).map_err(|(_, e)| e)?;
+
+    match settings.iter().find(|setting| {
+        NOT_IN_REQUEST.contains(&setting.key.as_str())
+            || (matches!(setting.key.as_str(), "safe" | "csp")
+                && setting.value == Value::Bool(false))
+    }) {
+        Some(setting) => Err(tr!("A request can't set {}.", setting.key)),
+        None => Ok(settings),
+    }
+}
+
+/// Reads `key = value` pairs separated by newlines or commas. The values are
+/// TOML booleans, strings, numbers or arrays of strings. Returns the line
+/// and the message of the first problem.
+fn parse(text: &str, source: Source) -> Result<Vec<Setting>, (usize, String)> {
+    let mut settings: Vec<Setting> = Vec::new();
+    let mut reader: Reader = Reader {
+        text: text.as_bytes(),
+        at: 0,
+        line: 1,
+    };
+
+    loop {
+        reader.skip_blank();
+
+        let Some(&byte) = reader.peek() else {
+            return Ok(settings);
+        };
+
+        if byte == b'[' {
+            return Err(reader.error(tr!("Tables aren't supported.")));
+        }
+
+        let key: String = reader.key();
+
+        if key.is_empty() {
+            return Err(reader.error(tr!("Expected the name of an option.")));
+        }
+
+        let Some(takes_value) = help::flag(&key) else {
+            return Err(reader.error(tr!("Unknown option {}.", key)));
+        };
+
+        reader.skip_spaces();
+
+        if reader.peek() != Some(&b'=') {
+            return Err(reader.error(tr!("Expected = after {}.", key)));
+        }
+
+        reader.at += 1;
+        reader.skip_spaces();
+
+        let value: Value = match (reader.value(), takes_value) {
+            (Ok(Raw::Bool(value)), false) => Value::Bool(value),
+            (Ok(Raw::Text(text)), true) => Value::Text(text),
+            (Ok(Raw::List(items)), true) if LISTS.contains(&key.as_str()) => {
+                Value::Text(items.join(","))
+            }
+            (Ok(_), false) => return Err(reader.error(tr!("{} expects true or false.", key))),
+            (Ok(_), true) => return Err(reader.error(tr!("{} expects a string.", key))),
+            (Err(message), _) => return Err(reader.error(message)),
+        };
+
+        settings.push(Setting { key, value, source });
+
+        reader.skip_spaces();
+
+        match reader.peek() {
+            None | Some(b'\n' | b'\r' | b',' | b'#') => {}
+            Some(_) => return Err(reader.error(tr!("Expected a new line after the value."))),
+        }
+    }
+}
+
+/// Value as it was written
+enum Raw {
+    Bool(bool),
+    Text(String),
+    List(Vec<String>),
+}
+
+struct Reader<'a> {
+    text: &'a [u8],
+    at: usize,
+    line: usize,
+}
+
+impl Reader<'_> {
+    fn peek(&self) -> Option<&u8> {
+        self.text.get(self.at)
+    }
+
+    fn error(&self, message: impl Into<String>) -> (usize, String) {
+        (self.line, message.into())
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t')) {
+            self.at += 1;
+        }
+    }
+
+    /// Skips whitespace, separators and comments
+    fn skip_blank(&mut self) {
+        while let Some(&byte) = self.peek() {
+            match byte {
+                b'\n' => self.line += 1,
+                b' ' | b'\t' | b'\r' | b',' => {}
+                b'#' => {
+                    while self.peek().is_some_and(|&byte| byte != b'\n') {
+                        self.at += 1;
+                    }
+                    continue;
+                }
+                _ => return,
+            }
+
+            self.at += 1;
+        }
+    }
+
+    fn key(&mut self) -> String {
+        let start: usize = self.at;
+
+        while self
+            .peek()
+            .is_some_and(|&byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+        {
+            self.at += 1;
+        }
+
+        String::from_utf8_lossy(&self.text[start..self.at]).into_owned()
+    }
+
+    fn value(&mut self) -> Result<Raw, String> {
+        match self.peek() {
+            Some(b'"' | b'\'') => self.string().map(Raw::Text),
+            Some(b'[') => {
+                self.at += 1;
+                let mut items: Vec<String> = Vec::new();
+
+                loop {
+                    self.skip_blank();
+
+                    match self.peek() {
+                        Some(b']') => {
+                            self.at += 1;
+                            return Ok(Raw::List(items));
+                        }
+                        Some(b'"' | b'\'') => items.push(self.string()?),
+                        _ => return Err(tr!("Arrays can only hold strings.").into()),
+                    }
+                }
+            }
+            _ => {
+                // Booleans and numbers
+                let start: usize = self.at;
+
+                while self.peek().is_some_and(|&byte| {
+                    byte.is_ascii_alphanumeric() || matches!(byte, b'.' | b'+' | b'-' | b'_')
+                }) {
+                    self.at += 1;
+                }
+
+                match &self.text[start..self.at] {
+                    b"true" => Ok(Raw::Bool(true)),
+                    b"false" => Ok(Raw::Bool(false)),
+                    number if !number.is_empty() && number[0] != b'_' => {
+                        Ok(Raw::Text(String::from_utf8_lossy(number).replace('_', "")))
+                    }
+                    _ => Err(tr!("Expected a value.").into()),
+                }
+            }
+        }
+    }
+
+    /// Reads a basic string with escapes or a literal string without them
+    fn string(&mut self) -> Result<String, String> {
+        let quote: u8 = self.text[self.at];
+        let mut string: Vec<u8> = Vec::new();
+        self.at += 1;
+
+        loop {
+            let Some(&byte) = self.peek() else {
+                return Err(tr!("String isn't closed.").into());
+            };
+
+            self.at += 1;
+
+            match byte {
+                b'\n' => return Err(tr!("String isn't closed.").into()),
+                _ if byte == quote => return Ok(String::from_utf8_lossy(&string).into_owned()),
+                b'\\' if quote == b'"' => {
+                    let escaped: u8 = match self.peek() {
+                        Some(b'"') => b'"',
+                        Some(b'\\') => b'\\',
+                        Some(b'n') => b'\n',
+                        Some(b't') => b'\t',
+                        _ => return Err(tr!("Unknown escape in a string.").into()),
+                    };
+
+                    string.push(escaped);
+                    self.at += 1;
+                }
+                _ => string.push(byte),
+            }
+        }
+    }
+}
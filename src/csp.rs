@@ -0,0 +1,266 @@
+//! Removing what a strict Content-Security-Policy would block from the HTML,
+//! so pages don't need `'unsafe-inline'`. The parser never writes inline
+//! styles or scripts itself, so only the HTML written in the markdown loses
+//! its `style` and event handler attributes, `javascript:` urls and
+//! `<script>` and `<style>` elements.
+
+use crate::events::Html;
+use crate::sourcemap::SourceMap;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Elements removed with everything in them
+const ELEMENTS: &[&[u8]] = &[b"script", b"style"];
+
+/// Removes the inline styles and scripts from the HTML
+pub(crate) fn strip(html: &mut Html, source_map: &mut SourceMap) {
+    let mut removed: Vec<Range<usize>> = Vec::new();
+    let mut at: usize = 0;
+
+    while let Some(start) = html[at..].iter().position(|&b| b == b'<').map(|i| at + i) {
+        if html[start..].starts_with(b"<!--") {
+            at = find(html, start, b"-->").map_or(html.len(), |end| end + 3);
+            continue;
+        }
+
+        let closing: bool = html.get(start + 1) == Some(&b'/');
+        let name_start: usize = start + 1 + usize::from(closing);
+        let name_end: usize = name_start
+            + html[name_start..]
+                .iter()
+                .take_while(|b| b.is_ascii_alphanumeric())
+                .count();
+
+        // Not a tag, like `a < b`
+        if name_end == name_start {
+            at = start + 1;
+            continue;
+        }
+
+        let Some(end) = tag_end(html, name_end) else {
+            break;
+        };
+        let name: &[u8] = &html[name_start..name_end];
+
+        if ELEMENTS
+            .iter()
+            .any(|element| name.eq_ignore_ascii_case(element))
+        {
+            let mut close: Vec<u8> = Vec::with_capacity(name.len() + 2);
+            close.extend_from_slice(b"</");
+            close.extend_from_slice(name);
+
+            // A stray closing tag goes alone, an element with its content
+            let element_end: usize = match closing {
+                true => end,
+                false => find_ignore_case(html, end, &close)
+                    .and_then(|close| tag_end(html, close + 2))
+                    .unwrap_or(html.len()),
+            };
+
+            removed.push(start..element_end);
+            at = element_end;
+            continue;
+        }
+
+        attributes(html, name_end, end - 1, &mut removed);
+        at = end;
+    }
+
+    for range in removed.iter().rev() {
+        source_map.removed(range.start, range.len());
+    }
+
+    html.edit(
+        removed
+            .into_iter()
+            .map(|range| (range, Html::default()))
+            .collect(),
+    );
+}
+
+/// Adds the attributes between `from` and the `>` or `/>` at `to` that would
+/// be blocked, with the whitespace before them
+fn attributes(html: &[u8], from: usize, to: usize, removed: &mut Vec<Range<usize>>) {
+    let mut at: usize = from;
+
+    while at < to {
+        let start: usize = at;
+
+        while at < to && html[at].is_ascii_whitespace() {
+            at += 1;
+        }
+
+        let name_start: usize = at;
+
+        while at < to && !matches!(html[at], b'=' | b'/' | b'>') && !html[at].is_ascii_whitespace()
+        {
+            at += 1;
+        }
+
+        let name: &[u8] = &html[name_start..at];
+        let mut value: &[u8] = b"";
+        let equals: usize = at
+            + html[at..to]
+                .iter()
+                .take_while(|b| b.is_ascii_whitespace())
+                .count();
+
+        // The value goes with the name, also with whitespace around the `=`
+        if equals < to && html[equals] == b'=' {
+            at = equals + 1;
+
+            while at < to && html[at].is_ascii_whitespace() {
+                at += 1;
+            }
+
+            match html[at] {
+                quote @ (b'"' | b'\'') => {
+                    let end: usize = html[at + 1..to]
+                        .iter()
+                        .position(|&b| b == quote)
+                        .map_or(to, |i| at + 1 + i);
+                    value = &html[at + 1..end];
+                    at = (end + 1).min(to);
+                }
+                _ => {
+                    let end: usize = at
+                        + html[at..to]
+                            .iter()
+                            .take_while(|b| !b.is_ascii_whitespace())
+                            .count();
+                    value = &html[at..end];
+                    at = end;
+                }
+            }
+        }
+
+        if name.is_empty() {
+            at += 1;
+            continue;
+        }
+
+        if blocked(name, value) {
+            removed.push(start..at);
+        }
+    }
+}
+
+/// Checks if the attribute is an inline style or script
+fn blocked(name: &[u8], value: &[u8]) -> bool {
+    let url: Vec<u8> = decode(value)
+        .iter()
+        .filter(|b| !b.is_ascii_whitespace() && !b.is_ascii_control())
+        .map(u8::to_ascii_lowercase)
+        .take(11)
+        .collect();
+
+    name.eq_ignore_ascii_case(b"style")
+        || name.len() > 2 && name[..2].eq_ignore_ascii_case(b"on")
+        || url == b"javascript:"
+}
+
+/// The value of the attribute as the browser reads it, with its character
+/// references decoded
+fn decode(value: &[u8]) -> Vec<u8> {
+    let mut decoded: Vec<u8> = Vec::with_capacity(value.len());
+    let mut at: usize = 0;
+
+    while at < value.len() {
+        match reference(&value[at..]) {
+            Some((byte, len)) => {
+                decoded.push(byte);
+                at += len;
+            }
+            None => {
+                decoded.push(value[at]);
+                at += 1;
+            }
+        }
+    }
+
+    decoded
+}
+
+/// The character a reference at the start of the bytes stands for, and the
+/// length of the reference. Characters outside ASCII are given as 0x80,
+/// since only the ASCII ones can spell a scheme.
+fn reference(bytes: &[u8]) -> Option<(u8, usize)> {
+    let rest: &[u8] = bytes.strip_prefix(b"&")?;
+
+    let Some(number) = rest.strip_prefix(b"#") else {
+        return NAMED
+            .iter()
+            .find(|(name, _)| rest.starts_with(name))
+            .map(|&(name, byte)| (byte, name.len() + 1));
+    };
+
+    let hex: bool = matches!(number.first(), Some(b'x' | b'X'));
+    let radix: u32 = if hex { 16 } else { 10 };
+    let digits: &[u8] = &number[usize::from(hex)..];
+    let count: usize = digits
+        .iter()
+        .take_while(|&&b| char::from(b).is_digit(radix))
+        .count();
+
+    if count == 0 {
+        return None;
+    }
+
+    // Browsers take the reference also without the `;`
+    let code: u32 = digits[..count].iter().fold(0, |code: u32, &b| {
+        code.saturating_mul(radix)
+            .saturating_add(char::from(b).to_digit(radix).unwrap_or(0))
+    });
+    let len: usize = 2 + usize::from(hex) + count + usize::from(digits.get(count) == Some(&b';'));
+
+    Some((
+        u8::try_from(code)
+            .ok()
+            .filter(|b| b.is_ascii() && *b != 0)
+            .unwrap_or(0x80),
+        len,
+    ))
+}
+
+/// Named references to the characters that matter in a scheme
+const NAMED: &[(&[u8], u8)] = &[
+    (b"colon;", b':'),
+    (b"Tab;", b'\t'),
+    (b"NewLine;", b'\n'),
+    (b"amp;", b'&'),
+    (b"quot;", b'"'),
+    (b"apos;", b'\''),
+    (b"lt;", b'<'),
+    (b"gt;", b'>'),
+];
+
+/// Index after the `>` ending the tag, skipping the ones in quoted values
+pub(crate) fn tag_end(html: &[u8], from: usize) -> Option<usize> {
+    let mut quote: Option<u8> = None;
+
+    for (i, &b) in html[from..].iter().enumerate() {
+        match (quote, b) {
+            (None, b'"' | b'\'') => quote = Some(b),
+            (Some(q), _) if b == q => quote = None,
+            (None, b'>') => return Some(from + i + 1),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+pub(crate) fn find(html: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    html[from..]
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|i| from + i)
+}
+
+pub(crate) fn find_ignore_case(html: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    html[from..]
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle))
+        .map(|i| from + i)
+}
@@ -0,0 +1,72 @@
+//! Position tracking of the parser in the markdown.
+
+/// Position of a byte in the markdown. Lines and columns start from 1 and
+/// columns count characters, with tabs advancing to the next tab stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    /// Byte offset from the start of the markdown
+    pub offset: usize,
+}
+
+impl Position {
+    /// Position of the first byte
+    pub const START: Position = Position {
+        line: 1,
+        column: 1,
+        offset: 0,
+    };
+}
+
+/// Tab stops are every this many columns
+pub const TAB_SIZE: usize = 4;
+
+/// Keeps track of the position while the bytes are fed to the parser one by one.
+#[derive(Debug, Clone)]
+pub(crate) struct Cursor {
+    position: Position,
+    /// `\r\n` is a single line break, so the `\n` after `\r` doesn't start a new line.
+    after_cr: bool,
+}
+
+impl Cursor {
+    /// Cursor starting from the position, which is in the middle of the
+    /// markdown when only a part of it is parsed
+    pub(crate) fn at(position: Position) -> Self {
+        Self {
+            position,
+            after_cr: false,
+        }
+    }
+
+    /// Position of the next byte
+    pub(crate) fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Moves over the byte
+    pub(crate) fn advance(&mut self, byte: u8) {
+        let p: &mut Position = &mut self.position;
+        p.offset += 1;
+
+        match byte {
+            b'\n' if self.after_cr => {}
+
+            b'\r' | b'\n' => {
+                p.line += 1;
+                p.column = 1;
+            }
+
+            b'\t' => p.column += TAB_SIZE - (p.column - 1) % TAB_SIZE,
+
+            // UTF-8 continuation bytes belong to the character before them
+            0x80..=0xBF => {}
+
+            _ => p.column += 1,
+        }
+
+        self.after_cr = byte == b'\r';
+    }
+}
@@ -0,0 +1,1033 @@
+//! Daemon mode that parses markdown sent through a unix domain socket. Each
+//! message is the length of the markdown as big endian `usize` followed by
+//! the markdown, and the answer is the HTML framed the same way. A request
+//! can start with options that override the ones of the daemon: the length
+//! of the options with the highest bit set, and the options as `key = value`
+//! lines like in the config files, such as `dialect = "gfm"`. With
+//! `chunked = true` the HTML comes in length-prefixed chunks ending with an
+//! empty one, so the start of a large document arrives before the rest is
+//! converted. Small requests can also be sent as datagrams to the socket of
+//! `--datagram`, framed the same way, to skip setting up a connection. Over
+//! `--max-connections` the connections are answered with `busy` framed with
+//! the highest bit of the length set, and closed. Markdown that looks like a
+//! binary file is answered with `binary` framed the same way, unless the
+//! request or the daemon has `force-binary = true`. A request with the option
+//! `status = true` is answered with `name value` lines telling the state of
+//! the daemon, like its connections, open descriptors and memory.
+
+use crate::access::{self, Entry, Status};
+use crate::config::{self, Setting, Value};
+use crate::head::{self, standalone, Head};
+use crate::i18n::tr;
+#[cfg(feature = "systemd")]
+use crate::systemd::{self, accept};
+use crate::{looks_binary, report, split_front, Mode, Options, PS};
+use md2htm::{Cancel, Error, Ext, Limits, Parser, Stats};
+#[cfg(feature = "systemd")]
+use std::os::fd::AsRawFd;
+use std::{
+    borrow::Cow,
+    env,
+    fs::remove_file,
+    io::{ErrorKind, Read, Result, Write},
+    os::{
+        fd::{FromRawFd, RawFd},
+        unix::net::{UnixDatagram, UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{sync_channel, Receiver, TrySendError},
+        Arc, Mutex, OnceLock, PoisonError,
+    },
+    thread::{scope, sleep, spawn},
+    time::{Duration, Instant},
+};
+
+/// Bit of the first length telling that options come before the markdown
+const OPTIONS: usize = 1 << (usize::BITS - 1);
+/// Requests with more options than this are dropped
+const MAX_OPTIONS: usize = 64 * 1024;
+/// Size of the access log rotated when not given
+const ACCESS_LOG_SIZE: u64 = 10 * 1024 * 1024;
+/// Datagrams larger than this are dropped
+const MAX_DATAGRAM: usize = 64 * 1024;
+/// Markdown parsed between the chunks of chunked answers
+const PIECE: usize = 64 * 1024;
+/// Most of a message allocated before its bytes arrive, when there's no
+/// `--max-size`. Longer messages grow the buffer as they come, so a client
+/// claiming a huge length can't make the daemon allocate it.
+const PREALLOC: usize = 16 * 1024 * 1024;
+/// Answer of the connections over the limit
+const BUSY: &[u8] = b"busy";
+/// Answer of the requests whose markdown looks like a binary file
+const BINARY: &[u8] = b"binary";
+/// Variable telling the upgraded daemon the fd of the listener it inherited
+pub(crate) const LISTEN_FD: &str = "MD2HTM_LISTEN_FD";
+/// First fd passed by systemd socket activation
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// Set when the listener was handed over to an upgraded daemon
+pub(crate) static HANDED_OVER: AtomicBool = AtomicBool::new(false);
+/// Connections being answered
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+/// Connections answered with `busy`
+static REFUSED: AtomicUsize = AtomicUsize::new(0);
+/// Requests answered with HTML
+static ANSWERED: AtomicUsize = AtomicUsize::new(0);
+/// When the daemon started serving, for the status
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Prints the message to stderr if the log level of the config lets it through
+macro_rules! log {
+    ($config:expr, $level:expr, $($arg:tt)*) => {
+        if $config.log >= $level {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Daemon settings that aren't parser options
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Socket to listen when the command line doesn't give one
+    pub socket: Option<String>,
+    /// Datagram socket answering small requests without connections
+    pub datagram: Option<String>,
+    /// Conversions still running this long after the client connected are cancelled
+    pub timeout: Option<Duration>,
+    /// Connections sending nothing for this long while the request is read
+    /// are closed, so clients that never hang up don't use up the descriptors
+    pub idle_timeout: Option<Duration>,
+    /// Conversions producing more HTML than this are aborted
+    pub max_output: Option<usize>,
+    /// Messages with more markdown than this are dropped without reading them
+    pub max_size: Option<usize>,
+    /// Number of threads serving the connections. Without it every
+    /// connection gets a thread of its own.
+    pub workers: Option<usize>,
+    /// Connections answered or waiting for a worker at most, the ones over
+    /// it are answered with `busy`
+    pub max_connections: Option<usize>,
+    pub log: Log,
+    /// File with a line for every request
+    pub access_log: Option<String>,
+    /// The access log is rotated when it would grow larger than this
+    pub access_log_size: Option<u64>,
+    /// Answers are sent in chunks as they are converted
+    pub chunked: bool,
+    /// Markdown that looks binary is converted instead of answered with `binary`
+    pub force_binary: bool,
+    /// Layers of options the options of the requests are applied on top of
+    pub(crate) settings: Vec<Setting>,
+}
+
+/// How much the daemon writes to stderr
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Log {
+    Off,
+    /// Failed connections
+    #[default]
+    Error,
+    /// Every conversion too
+    Info,
+}
+
+impl Log {
+    pub const ALL: [Log; 3] = [Log::Off, Log::Error, Log::Info];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Log::Off => "off",
+            Log::Error => "error",
+            Log::Info => "info",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Log> {
+        Log::ALL.into_iter().find(|log| log.name() == name)
+    }
+}
+
+/// Suggested systemd unit running the daemon with the arguments. With the
+/// `systemd` feature the daemon tells when it is ready and pings the watchdog.
+pub fn unit(exe: &Path, args: &[String]) -> String {
+    let notify: bool = cfg!(feature = "systemd");
+    let mut exec: String = format!("{} daemon", exe.display());
+
+    for arg in args {
+        exec.push(' ');
+
+        // systemd splits the command at spaces and expands % specifiers
+        let arg: String = arg.replace('%', "%%");
+
+        if arg.is_empty() || arg.contains([' ', '"', '\\', '\'']) {
+            exec.push('"');
+            exec.push_str(&arg.replace('\\', "\\\\").replace('"', "\\\""));
+            exec.push('"');
+        } else {
+            exec.push_str(&arg);
+        }
+    }
+
+    format!(
+        "[Unit]\n\
+         Description=Markdown to HTML parser daemon\n\
+         \n\
+         [Service]\n\
+         Type={}\n\
+         ExecStart={exec}\n\
+         {}\
+         Restart=on-failure\n\
+         User=your_webserver_user_here\n\
+         RuntimeDirectory=mdserv\n\
+         RuntimeDirectoryMode=0775\n\
+         \n\
+         [Install]\n\
+         WantedBy=multi-user.target\n",
+        if notify { "notify" } else { "simple" },
+        if notify {
+            // Reloading upgrades the daemon to the executable on disk
+            "WatchdogSec=30\nExecReload=/bin/kill -HUP $MAINPID\n"
+        } else {
+            ""
+        },
+    )
+}
+
+pub fn run(sock: &str, parser: Parser, config: Config) -> Result<()> {
+    let _ = STARTED.set(Instant::now());
+
+    if let Some(path) = &config.access_log {
+        access::open(path, config.access_log_size.unwrap_or(ACCESS_LOG_SIZE))?;
+    }
+
+    // Bound first, so it is ready once clients can connect to the stream socket
+    let datagram: Option<UnixDatagram> = match &config.datagram {
+        Some(path) => {
+            let _ = remove_file(path);
+            Some(UnixDatagram::bind(path)?)
+        }
+        None => None,
+    };
+
+    // Whether the socket file is left for systemd when stopping
+    let (listener, activated) = match inherited() {
+        Some(inherited) => inherited,
+        None => {
+            // Try to remove the socket file but don't really care about the outcome,
+            // because the binding won't succeed if there is no privileges to write.
+            let _ = remove_file(sock);
+            (UnixListener::bind(sock)?, false)
+        }
+    };
+
+    // The daemon handing the listener over may have left it non-blocking
+    listener.set_nonblocking(cfg!(feature = "systemd"))?;
+    log!(config, Log::Info, "Listening {sock}.");
+
+    // The signals are blocked before any thread is spawned to inherit them
+    #[cfg(feature = "systemd")]
+    systemd::on_signals(listener.as_raw_fd());
+
+    if let (Some(socket), Some(path)) = (datagram, &config.datagram) {
+        // Datagrams are answered one at a time by each of the threads
+        for _ in 0..config.workers.unwrap_or(1) {
+            let socket: UnixDatagram = socket.try_clone()?;
+            let parser: Parser = parser.clone();
+            let config: Config = config.clone();
+            spawn(move || datagram_handler(socket, &parser, &config));
+        }
+
+        log!(config, Log::Info, "Listening datagrams in {path}.");
+    }
+
+    #[cfg(feature = "systemd")]
+    {
+        systemd::notify("READY=1");
+        systemd::watchdog();
+    }
+
+    match config.workers {
+        None => {
+            let parser: Arc<Parser> = Arc::new(parser);
+
+            while let Some(stream) = accept(&listener) {
+                match stream {
+                    Ok(stream) if saturated(&config) => refuse(stream, &config),
+                    Ok(stream) => {
+                        let active: Active = Active::new();
+                        let parser: Arc<Parser> = Arc::clone(&parser);
+                        let config: Config = config.clone();
+                        spawn(move || {
+                            stream_handler(stream, &parser, &config);
+                            drop(active);
+                        });
+                    }
+
+                    Err(e) => log!(config, Log::Error, "Failed to catch the stream: {e}"),
+                }
+            }
+        }
+
+        // The workers take turns accepting the connections, so at most that many
+        // documents are converted at once and the rest wait in the backlog
+        Some(workers) if config.max_connections.is_none() => scope(|s| {
+            for _ in 0..workers {
+                s.spawn(|| {
+                    while let Some(stream) = accept(&listener) {
+                        match stream {
+                            Ok(stream) => stream_handler(stream, &parser, &config),
+                            Err(e) => log!(config, Log::Error, "Failed to catch the stream: {e}"),
+                        }
+                    }
+                });
+            }
+        }),
+
+        // The connections wait for a free worker in a queue, and the ones that
+        // don't fit in it are answered right away
+        Some(workers) => {
+            let max: usize = config.max_connections.unwrap_or(workers);
+            let (sender, receiver) =
+                sync_channel::<(UnixStream, Active)>(max.saturating_sub(workers));
+            let receiver: Mutex<Receiver<(UnixStream, Active)>> = Mutex::new(receiver);
+
+            scope(|s| {
+                for _ in 0..workers {
+                    s.spawn(|| loop {
+                        let next = receiver
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .recv();
+
+                        match next {
+                            Ok((stream, active)) => {
+                                stream_handler(stream, &parser, &config);
+                                drop(active);
+                            }
+                            // The listener stopped and the queue is empty
+                            Err(_) => return,
+                        }
+                    });
+                }
+
+                while let Some(stream) = accept(&listener) {
+                    match stream {
+                        Ok(stream) => match sender.try_send((stream, Active::new())) {
+                            Ok(_) => {}
+                            Err(TrySendError::Full((stream, active))) => {
+                                drop(active);
+                                refuse(stream, &config);
+                            }
+                            Err(TrySendError::Disconnected(_)) => break,
+                        },
+
+                        Err(e) => log!(config, Log::Error, "Failed to catch the stream: {e}"),
+                    }
+                }
+
+                drop(sender);
+            });
+        }
+    }
+
+    // The connections accepted before stopping are still answered
+    while ACTIVE.load(Ordering::Acquire) > 0 {
+        sleep(Duration::from_millis(10));
+    }
+
+    // The socket belongs to systemd or to the upgraded daemon
+    if !activated && !HANDED_OVER.load(Ordering::Acquire) {
+        let _ = remove_file(sock);
+    }
+
+    // The upgraded daemon has bound the path again
+    if let Some(path) = config
+        .datagram
+        .filter(|_| !HANDED_OVER.load(Ordering::Acquire))
+    {
+        let _ = remove_file(path);
+    }
+
+    Ok(())
+}
+
+/// Listener inherited from the daemon this one upgraded, or passed by systemd
+/// socket activation. Tells which of the two it was.
+fn inherited() -> Option<(UnixListener, bool)> {
+    let (fd, activated): (RawFd, bool) =
+        match env::var(LISTEN_FD).ok().and_then(|fd| fd.parse().ok()) {
+            Some(fd) => (fd, false),
+            None if env::var("LISTEN_PID").is_ok_and(|pid| pid == process::id().to_string())
+                && env::var("LISTEN_FDS").is_ok_and(|fds| fds != "0") =>
+            {
+                (SD_LISTEN_FDS_START, true)
+            }
+            None => return None,
+        };
+
+    // SAFETY: the fd was passed for this process to listen, and nothing else
+    // in it owns the fd
+    Some((unsafe { UnixListener::from_raw_fd(fd) }, activated))
+}
+
+/// Waits for the next connection
+#[cfg(not(feature = "systemd"))]
+fn accept(listener: &UnixListener) -> Option<Result<UnixStream>> {
+    Some(listener.accept().map(|(stream, _)| stream))
+}
+
+/// Checks if as many connections are being answered as the config allows
+fn saturated(config: &Config) -> bool {
+    config
+        .max_connections
+        .is_some_and(|max| ACTIVE.load(Ordering::Acquire) >= max)
+}
+
+/// Answers the connection with `busy` without reading the request
+fn refuse(mut stream: UnixStream, config: &Config) {
+    let mut entry: Entry = Entry::new(stream.peer_addr().ok(), config.log);
+    entry.status = Status::Busy;
+
+    let refused: usize = REFUSED.fetch_add(1, Ordering::Relaxed) + 1;
+    log!(
+        config,
+        Log::Info,
+        "Refused a connection with {} being answered, {refused} refused so far.",
+        ACTIVE.load(Ordering::Acquire)
+    );
+
+    // The client may not wait for the answer, so it isn't an error if it's gone
+    let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+    let _ = stream.write_all(&refusal(BUSY));
+}
+
+/// The reason framed with the highest bit of the length set
+fn refusal(reason: &[u8]) -> Vec<u8> {
+    let mut answer: Vec<u8> = (reason.len() | OPTIONS).to_be_bytes().to_vec();
+    answer.extend_from_slice(reason);
+    answer
+}
+
+/// Checks if the markdown looks binary and neither the request nor the
+/// daemon forces converting it
+fn refuses_binary(mode: &Mode, config: &Config, markdown: &[u8]) -> bool {
+    !mode.force_binary && !config.force_binary && looks_binary(markdown)
+}
+
+/// Counts a connection as active until dropped
+struct Active;
+
+impl Active {
+    fn new() -> Self {
+        ACTIVE.fetch_add(1, Ordering::AcqRel);
+        Active
+    }
+}
+
+impl Drop for Active {
+    fn drop(&mut self) {
+        ACTIVE.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+fn stream_handler(mut stream: UnixStream, parser: &Parser, config: &Config) {
+    let started: Instant = Instant::now();
+    let deadline: Option<Instant> = config.timeout.map(|timeout| started + timeout);
+
+    let mut entry: Entry = Entry::new(stream.peer_addr().ok(), config.log);
+
+    if let Err(e) = stream.set_read_timeout(config.idle_timeout) {
+        log!(config, Log::Error, "Couldn't set the idle timeout: {e}");
+    }
+
+    let Some((options, mdbuf)) = read_request(&mut stream, config) else {
+        return;
+    };
+
+    entry.bytes_in = options.as_ref().map_or(0, Vec::len) + mdbuf.len();
+
+    if options.as_deref().is_some_and(asks_status) {
+        return answer_status(&mut stream, config, &mut entry);
+    }
+
+    let Some((parser, mode, chunked)) = request(options, parser, config, &mdbuf) else {
+        return;
+    };
+
+    if refuses_binary(&mode, config, &mdbuf) {
+        entry.status = Status::Dropped;
+        log!(config, Log::Error, "Refusing a request that looks binary.");
+        let _ = stream.write_all(&refusal(BINARY));
+        return;
+    }
+
+    // Excerpts and the root tags need the whole HTML, so they come in one chunk
+    if chunked && !mode.excerpt && !mode.standalone {
+        return convert_chunked(&stream, &parser, &mdbuf, deadline, config, &mut entry);
+    }
+
+    match convert(Some(&stream), &parser, &mode, &mdbuf, deadline, config) {
+        Ok(mut parsed) => {
+            let len: usize = parsed.len();
+            let plen: [u8; PS] = len.to_be_bytes();
+            entry.status = Status::Failed;
+
+            // The empty chunk ending the answer
+            if chunked && !parsed.is_empty() {
+                parsed.extend_from_slice(&[0; PS]);
+            }
+
+            // A single `write` may accept only a part of the buffer, which
+            // truncates large documents, so everything goes through `write_all`.
+            match stream.write_all(&plen) {
+                Ok(_) => match stream.write_all(&parsed) {
+                    Ok(_) => match stream.flush() {
+                        Ok(_) => {
+                            entry.status = Status::Ok;
+                            entry.bytes_out = len;
+                            ANSWERED.fetch_add(1, Ordering::Relaxed);
+                            log!(
+                                config,
+                                Log::Info,
+                                "Converted {} bytes into {len} bytes of HTML in {:?}.",
+                                mdbuf.len(),
+                                started.elapsed()
+                            )
+                        }
+                        Err(e) => log!(config, Log::Error, "Flushing wasn't successful: {e}"),
+                    },
+
+                    Err(e) => log!(config, Log::Error, "Couldn't write the parsed data: {e}"),
+                },
+
+                Err(e) => log!(config, Log::Error, "Couldn't write the length bytes: {e}"),
+            }
+        }
+
+        Err(e) => {
+            entry.status = Status::from(e);
+            log!(config, Log::Error, "Dropping the connection: {e}");
+        }
+    }
+}
+
+/// Answers the requests sent as datagrams, which are framed like on the
+/// stream socket. The answer goes back to the sender in one datagram, or an
+/// empty one when the request is dropped.
+fn datagram_handler(socket: UnixDatagram, parser: &Parser, config: &Config) {
+    // The lengths in a datagram can't claim more than it holds
+    let config: Config = Config {
+        max_size: Some(config.max_size.unwrap_or(MAX_DATAGRAM).min(MAX_DATAGRAM)),
+        ..config.clone()
+    };
+    // The extra byte tells apart the datagrams that didn't fit
+    let mut buf: Vec<u8> = vec![0; MAX_DATAGRAM + 1];
+
+    loop {
+        let (len, sender) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => {
+                log!(config, Log::Error, "Failed to receive a datagram: {e}");
+                return;
+            }
+        };
+
+        let mut entry: Entry = Entry::new(Some(sender.clone()), config.log);
+        entry.bytes_in = len;
+
+        if sender.is_unnamed() {
+            log!(
+                config,
+                Log::Error,
+                "Dropping a datagram from a socket without an address to answer."
+            );
+            continue;
+        }
+
+        let answer: Vec<u8> =
+            datagram_answer(&buf[..len], parser, &config, &mut entry).unwrap_or_default();
+
+        if let Err(e) = socket.send_to_addr(&answer, &sender) {
+            entry.status = Status::Failed;
+            log!(
+                config,
+                Log::Error,
+                "Couldn't send the answer of {} bytes: {e}",
+                answer.len()
+            );
+        }
+    }
+}
+
+/// The framed HTML answering the datagram. Problems are logged and give None.
+fn datagram_answer(
+    mut datagram: &[u8],
+    parser: &Parser,
+    config: &Config,
+    entry: &mut Entry,
+) -> Option<Vec<u8>> {
+    let started: Instant = Instant::now();
+    let deadline: Option<Instant> = config.timeout.map(|timeout| started + timeout);
+
+    if datagram.len() > MAX_DATAGRAM {
+        log!(
+            config,
+            Log::Error,
+            "Dropping a datagram over the limit of {MAX_DATAGRAM} bytes."
+        );
+        return None;
+    }
+
+    let (options, markdown) = read_request(&mut datagram, config)?;
+    let (parser, mode, _) = request(options, parser, config, &markdown)?;
+
+    if refuses_binary(&mode, config, &markdown) {
+        entry.status = Status::Dropped;
+        log!(config, Log::Error, "Refusing a datagram that looks binary.");
+        return Some(refusal(BINARY));
+    }
+
+    match convert(None, &parser, &mode, &markdown, deadline, config) {
+        Ok(html) => {
+            entry.status = Status::Ok;
+            entry.bytes_out = html.len();
+            log!(
+                config,
+                Log::Info,
+                "Converted {} bytes from a datagram into {} bytes of HTML in {:?}.",
+                markdown.len(),
+                html.len(),
+                started.elapsed()
+            );
+
+            let mut answer: Vec<u8> = html.len().to_be_bytes().to_vec();
+            answer.extend_from_slice(&html);
+            Some(answer)
+        }
+
+        Err(e) => {
+            entry.status = Status::from(e);
+            log!(config, Log::Error, "Dropping the datagram: {e}");
+            None
+        }
+    }
+}
+
+/// Reads the markdown of the request, and the options if the client sent
+/// any. Problems are logged and give None.
+fn read_request(stream: &mut impl Read, config: &Config) -> Option<(Option<Vec<u8>>, Vec<u8>)> {
+    let mut len: usize = read_len(stream, config)?;
+    let mut options: Option<Vec<u8>> = None;
+
+    if len & OPTIONS != 0 {
+        len &= !OPTIONS;
+
+        if len > MAX_OPTIONS {
+            log!(
+                config,
+                Log::Error,
+                "Dropping a request with {len} bytes of options, which is over the limit."
+            );
+            return None;
+        }
+
+        options = Some(read_bytes(stream, len, config)?);
+        len = read_len(stream, config)?;
+    }
+
+    if config.max_size.is_some_and(|max_size| len > max_size) {
+        log!(
+            config,
+            Log::Error,
+            "Dropping a message of {len} bytes, which is over the limit."
+        );
+        return None;
+    }
+
+    Some((options, read_bytes(stream, len, config)?))
+}
+
+fn read_len(stream: &mut impl Read, config: &Config) -> Option<usize> {
+    let mut lbuf: [u8; PS] = [0; PS];
+
+    match stream.read_exact(&mut lbuf) {
+        Ok(_) => Some(usize::from_be_bytes(lbuf)),
+        Err(e) if idle(&e) => {
+            log!(config, Log::Info, "Closing a connection that went idle.");
+            None
+        }
+        Err(e) => {
+            log!(
+                config,
+                Log::Error,
+                "Failed to read the length of the message: {e}"
+            );
+            None
+        }
+    }
+}
+
+/// Reads the message into a buffer sized once by its length, which is
+/// within `--max-size` by now
+fn read_bytes(stream: &mut impl Read, len: usize, config: &Config) -> Option<Vec<u8>> {
+    let mut buf: Vec<u8> = Vec::with_capacity(len.min(config.max_size.unwrap_or(PREALLOC)));
+
+    match stream.take(len as u64).read_to_end(&mut buf) {
+        Ok(read) if read == len => Some(buf),
+        Ok(read) => {
+            log!(
+                config,
+                Log::Error,
+                "Failed to read the {len} message bytes: the message ended after {read}"
+            );
+            None
+        }
+        Err(e) if idle(&e) => {
+            log!(
+                config,
+                Log::Info,
+                "Closing a connection that went idle in the middle of {len} message bytes."
+            );
+            None
+        }
+        Err(e) => {
+            log!(
+                config,
+                Log::Error,
+                "Failed to read the {len} message bytes: {e}"
+            );
+            None
+        }
+    }
+}
+
+/// Checks if the read failed on the idle timeout, which gives `WouldBlock`
+/// on some platforms and `TimedOut` on others
+fn idle(e: &std::io::Error) -> bool {
+    matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+/// Parser, output mode and chunking of the request, with the options it
+/// came with applied. Broken options are logged and give None.
+fn request<'a>(
+    options: Option<Vec<u8>>,
+    parser: &'a Parser,
+    config: &Config,
+    markdown: &[u8],
+) -> Option<(Cow<'a, Parser>, Mode, bool)> {
+    // The dialect `auto` of the daemon is detected like the one of a request
+    let options: Option<Vec<u8>> = match options {
+        None if config::auto_dialect(&config.settings) => Some(Vec::new()),
+        options => options,
+    };
+
+    match options
+        .map(|options| request_options(&options, parser, config, markdown))
+        .transpose()
+    {
+        Ok(Some((parser, mode, chunked))) => Some((Cow::Owned(parser), mode, chunked)),
+        Ok(None) => Some((Cow::Borrowed(parser), Mode::default(), config.chunked)),
+        Err(e) => {
+            log!(
+                config,
+                Log::Error,
+                "Dropping a request with broken options: {e}"
+            );
+            None
+        }
+    }
+}
+
+/// Checks if the options ask for the status of the daemon instead of HTML
+fn asks_status(options: &[u8]) -> bool {
+    config::request(options).is_ok_and(|settings| {
+        settings
+            .iter()
+            .any(|setting| setting.key == "status" && setting.value == Value::Bool(true))
+    })
+}
+
+/// State of the daemon and the process as `name value` lines. The numbers
+/// the platform doesn't tell are left out.
+fn status() -> String {
+    let mut lines: String = String::new();
+    let mut line = |name: &str, value: usize| lines.push_str(&format!("{name} {value}\n"));
+
+    line(
+        "uptime-seconds",
+        STARTED
+            .get()
+            .map_or(0, |started| started.elapsed().as_secs()) as usize,
+    );
+    line("active-connections", ACTIVE.load(Ordering::Acquire));
+    line("refused-connections", REFUSED.load(Ordering::Relaxed));
+    line("answered-requests", ANSWERED.load(Ordering::Relaxed));
+
+    if let Ok(fds) = std::fs::read_dir("/proc/self/fd") {
+        line("open-fds", fds.count());
+    }
+
+    // The resident set is given in kilobytes
+    if let Some(rss) = std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix("VmRSS:"))
+                .and_then(|kb| {
+                    kb.trim()
+                        .trim_end_matches("kB")
+                        .trim()
+                        .parse::<usize>()
+                        .ok()
+                })
+        })
+    {
+        line("rss-bytes", rss * 1024);
+    }
+
+    lines
+}
+
+fn answer_status(stream: &mut UnixStream, config: &Config, entry: &mut Entry) {
+    let status: String = status();
+    let mut answer: Vec<u8> = status.len().to_be_bytes().to_vec();
+    answer.extend_from_slice(status.as_bytes());
+    entry.status = Status::Failed;
+
+    match stream.write_all(&answer) {
+        Ok(_) => {
+            entry.status = Status::Ok;
+            entry.bytes_out = status.len();
+        }
+        Err(e) => log!(config, Log::Error, "Couldn't write the status: {e}"),
+    }
+}
+
+/// Asks the daemon listening the socket for its status and prints it.
+/// Tells if the daemon answered.
+pub fn print_status(sock: &Path) -> Result<bool> {
+    let options: &[u8] = b"status = true";
+    let mut request: Vec<u8> = (options.len() | OPTIONS).to_be_bytes().to_vec();
+    request.extend_from_slice(options);
+    request.extend_from_slice(&0usize.to_be_bytes());
+
+    let mut stream: UnixStream = match UnixStream::connect(sock) {
+        Ok(stream) => stream,
+        Err(e) => {
+            eprintln!("{}", tr!("Couldn't connect to {}: {}", sock.display(), e));
+            return Ok(false);
+        }
+    };
+    stream.write_all(&request)?;
+
+    let mut len: [u8; PS] = [0; PS];
+    stream.read_exact(&mut len)?;
+
+    match usize::from_be_bytes(len) {
+        len if len & OPTIONS != 0 => {
+            eprintln!("{}", tr!("The daemon is busy."));
+            Ok(false)
+        }
+        len => {
+            let mut status: Vec<u8> = vec![0; len];
+            stream.read_exact(&mut status)?;
+            std::io::stdout().write_all(&status)?;
+            Ok(true)
+        }
+    }
+}
+
+/// Parser and output mode of a request with options, which override the
+/// options of the daemon
+fn request_options(
+    options: &[u8],
+    parser: &Parser,
+    config: &Config,
+    markdown: &[u8],
+) -> std::result::Result<(Parser, Mode, bool), String> {
+    let mut settings: Vec<Setting> = config.settings.clone();
+    settings.extend(
+        config::request(options)?
+            .into_iter()
+            .filter(|setting| setting.key != "status"),
+    );
+    config::resolve_dialect(&mut settings, markdown);
+
+    let options: Options = crate::options(config::to_args(&config::effective(&settings)))
+        .ok_or_else(|| String::from("an option has an invalid value"))?;
+    let built: md2htm::Options = options.builder.build();
+
+    // Raw fences would let the client write the HTML safe mode escapes
+    if built.is_safe() && built.has(Ext::RawFence) && !parser.options().has(Ext::RawFence) {
+        return Err(tr!("A request can't enable raw-fence in safe mode.").into());
+    }
+
+    Ok((Parser::new(built), options.mode, options.daemon.chunked))
+}
+
+/// Parses the message, giving up if the client leaves, the deadline passes or
+/// the HTML grows too large. Datagrams have no stream to watch.
+fn convert(
+    stream: Option<&UnixStream>,
+    parser: &Parser,
+    mode: &Mode,
+    markdown: &[u8],
+    deadline: Option<Instant>,
+    config: &Config,
+) -> std::result::Result<Vec<u8>, Error> {
+    // The client isn't supposed to send anything while waiting for the answer,
+    // so a non-blocking read tells if the other end is still there.
+    let stream: Option<&UnixStream> = stream.filter(|stream| stream.set_nonblocking(true).is_ok());
+    let watch: Watch = Watch { stream, deadline };
+    let (front, markdown) = split_front(mode, markdown);
+    let limits: Limits = Limits {
+        cancel: Some(&watch),
+        max_output: config.max_output,
+    };
+
+    let parsed = match mode.excerpt {
+        true => parser.excerpt_limited(markdown, &limits),
+        false => parser.parse_limited(markdown, &limits),
+    };
+
+    if let Some(stream) = stream {
+        let _ = stream.set_nonblocking(false);
+    }
+
+    parsed.map(|output| {
+        let stats: Option<Stats> = output.stats;
+        let html: Vec<u8> = report(output);
+
+        match mode.standalone {
+            true => {
+                let snippet = |path: &Option<PathBuf>| {
+                    head::snippet(path.as_deref(), mode.nonce.as_deref()).unwrap_or_else(|e| {
+                        log!(config, Log::Error, "Couldn't read the snippet to add: {e}");
+                        None
+                    })
+                };
+                // There's no directory to copy the stylesheet to
+                let theme = mode.theme.map(|theme| {
+                    theme.inline(parser.options().class_prefix(), mode.nonce.as_deref())
+                });
+                let head: Head = Head {
+                    stats,
+                    theme,
+                    inject_head: snippet(&mode.inject_head),
+                    inject_body: snippet(&mode.inject_body),
+                    ..Head::new(parser, front, markdown, &html)
+                };
+                standalone(html, &head)
+            }
+            false => html,
+        }
+    })
+}
+
+/// Parses the message like `convert`, but sends the HTML of every finished
+/// piece as a chunk right away and ends the answer with an empty chunk
+fn convert_chunked(
+    stream: &UnixStream,
+    parser: &Parser,
+    markdown: &[u8],
+    deadline: Option<Instant>,
+    config: &Config,
+    entry: &mut Entry,
+) {
+    let watching: bool = stream.set_nonblocking(true).is_ok();
+    let watch: Watch = Watch {
+        stream: Some(stream).filter(|_| watching),
+        deadline,
+    };
+    let limits: Limits = Limits {
+        cancel: Some(&watch),
+        max_output: config.max_output,
+    };
+
+    let mut sent: usize = 0;
+    let mut failed: Option<std::io::Error> = None;
+    let send = |chunk: &[u8]| -> Result<()> {
+        // The watch reads without blocking, but the chunks must be written whole
+        stream.set_nonblocking(false)?;
+        let mut writer: &UnixStream = stream;
+        writer.write_all(&chunk.len().to_be_bytes())?;
+        writer.write_all(chunk)?;
+        writer.flush()?;
+        stream.set_nonblocking(watching)
+    };
+
+    let parsed = parser.parse_chunked(markdown, PIECE, &limits, &mut |chunk: &[u8]| match send(
+        chunk,
+    ) {
+        Ok(_) => {
+            sent += chunk.len();
+            true
+        }
+        Err(e) => {
+            failed = Some(e);
+            false
+        }
+    });
+
+    if watching {
+        let _ = stream.set_nonblocking(false);
+    }
+
+    entry.bytes_out = sent;
+    entry.status = Status::Failed;
+
+    match (parsed, failed) {
+        (_, Some(e)) => log!(config, Log::Error, "Couldn't write a chunk: {e}"),
+        (Err(e), None) => {
+            entry.status = Status::from(e);
+            log!(config, Log::Error, "Dropping the connection: {e}");
+        }
+        (Ok(_), None) => match send(&[]) {
+            Ok(_) => {
+                entry.status = Status::Ok;
+                log!(
+                    config,
+                    Log::Info,
+                    "Converted {} bytes into {sent} bytes of HTML in chunks in {:?}.",
+                    markdown.len(),
+                    entry.elapsed()
+                )
+            }
+            Err(e) => log!(config, Log::Error, "Couldn't write the last chunk: {e}"),
+        },
+    }
+}
+
+/// Cancels the parsing when the client hangs up or the deadline passes
+struct Watch<'a> {
+    stream: Option<&'a UnixStream>,
+    deadline: Option<Instant>,
+}
+
+impl Cancel for Watch<'_> {
+    fn is_cancelled(&self) -> bool {
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return true;
+        }
+
+        // Anything read here would be garbage anyway, the request has been read already.
+        let Some(mut stream) = self.stream else {
+            return false;
+        };
+
+        match stream.read(&mut [0; 1]) {
+            // End of the stream, the client has hung up
+            Ok(0) => true,
+            Ok(_) => false,
+            Err(e) => e.kind() != ErrorKind::WouldBlock,
+        }
+    }
+}
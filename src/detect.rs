@@ -0,0 +1,75 @@
+//! Guessing the dialect a document was written for, so archives of documents
+//! from many sources can be converted with `--dialect auto`.
+
+use crate::options::Dialect;
+
+/// Guesses the dialect from the syntax only some of them have. Pipe tables,
+/// `~~strikethrough~~` and task lists are GitHub's, and setext headings
+/// CommonMark's. Documents with none of them are legacy. Code blocks are
+/// skipped, as anything goes in them.
+pub fn detect_dialect(markdown: &[u8]) -> Dialect {
+    let mut setext: bool = false;
+    let mut fence: bool = false;
+    let mut previous: &[u8] = b"";
+
+    for line in markdown.split(|&byte| byte == b'\n') {
+        let line: &[u8] = line.strip_suffix(b"\r").unwrap_or(line).trim_ascii();
+
+        if line.starts_with(b"```") {
+            fence = !fence;
+        }
+
+        if fence || line.starts_with(b"```") {
+            previous = b"";
+            continue;
+        }
+
+        if task(line) || strikethrough(line) || (previous.contains(&b'|') && delimiter_row(line)) {
+            return Dialect::Gfm;
+        }
+
+        setext |= underline(line) && paragraph(previous);
+        previous = line;
+    }
+
+    match setext {
+        true => Dialect::CommonMark,
+        false => Dialect::Legacy,
+    }
+}
+
+/// `- [ ] ` or `- [x] ` starting a list item
+fn task(line: &[u8]) -> bool {
+    matches!(line.first(), Some(b'-' | b'*' | b'+'))
+        && matches!(line.get(1..6), Some(b" [ ] " | b" [x] " | b" [X] "))
+}
+
+/// `~~` before text closed by another `~~` on the same line
+fn strikethrough(line: &[u8]) -> bool {
+    line.windows(3)
+        .position(|w| w[..2] == *b"~~" && !w[2].is_ascii_whitespace() && w[2] != b'~')
+        .is_some_and(|at| line[at + 3..].windows(2).any(|w| w == b"~~"))
+}
+
+/// `| --- | :-: |` under the header row of a table
+fn delimiter_row(line: &[u8]) -> bool {
+    line.contains(&b'-')
+        && line.contains(&b'|')
+        && line
+            .iter()
+            .all(|byte| matches!(byte, b'|' | b'-' | b':' | b' ' | b'\t'))
+}
+
+/// `===` or `---` that makes the line above a heading
+fn underline(line: &[u8]) -> bool {
+    line.len() >= 2 && (line.iter().all(|&b| b == b'=') || line.iter().all(|&b| b == b'-'))
+}
+
+/// Line of text that isn't another block
+fn paragraph(line: &[u8]) -> bool {
+    !line.is_empty()
+        && !matches!(
+            line[0],
+            b'#' | b'-' | b'*' | b'+' | b'>' | b'|' | b'<' | b'='
+        )
+}
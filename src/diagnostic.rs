@@ -0,0 +1,183 @@
+//! Problems in the markdown that the parser noticed but worked around.
+
+use crate::cursor::Position;
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+/// Stable codes of the diagnostics, so they can be allowed or denied one by
+/// one like compiler lints. Each variant must have its own bit index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Code {
+    /// `#` without any text after it
+    EmptyHeader = 0,
+    /// More than six `#`
+    HeaderLevel = 1,
+    /// Backticks the code block handling didn't expect
+    CodeBlockState = 2,
+    /// Unexpected state of a link. A bug in the parser.
+    LinkState = 3,
+    /// Line break in the middle of inline code
+    CodeNewline = 4,
+    /// Two backticks where three would start a code block
+    FenceLength = 5,
+    /// Line break in the middle of a link or an image
+    LinkNewline = 6,
+    /// Single `*` inside bold text
+    LiteralStar = 7,
+    /// List key without a space after it
+    ListSpace = 8,
+    /// Heading level skipped, like h2 followed by h4
+    HeadingLevelSkip = 9,
+    /// Heading without text, even if it had a `#` and a space
+    EmptyHeading = 10,
+    /// Image without alt text
+    MissingAlt = 11,
+    /// Link text like "here" that doesn't tell where the link goes
+    VagueLink = 12,
+    /// Heading id that was already taken
+    DuplicateId = 13,
+    /// Link to a `#fragment` that doesn't match any heading id
+    BrokenFragment = 14,
+    /// No `[TOC]` line though the table of contents was asked to go there
+    MissingTocMarker = 15,
+    /// Bytes that aren't UTF-8, replaced or rejected as the options say
+    InvalidUtf8 = 16,
+    /// Text or url of a link or an image longer than the options allow
+    LinkTruncated = 17,
+}
+
+impl Code {
+    /// Every code, in bit order
+    pub const ALL: &'static [Code] = &[
+        Code::EmptyHeader,
+        Code::HeaderLevel,
+        Code::CodeBlockState,
+        Code::LinkState,
+        Code::CodeNewline,
+        Code::FenceLength,
+        Code::LinkNewline,
+        Code::LiteralStar,
+        Code::ListSpace,
+        Code::HeadingLevelSkip,
+        Code::EmptyHeading,
+        Code::MissingAlt,
+        Code::VagueLink,
+        Code::DuplicateId,
+        Code::BrokenFragment,
+        Code::MissingTocMarker,
+        Code::InvalidUtf8,
+        Code::LinkTruncated,
+    ];
+
+    /// Name of the code used on the command line and in the messages
+    pub fn name(self) -> &'static str {
+        match self {
+            Code::EmptyHeader => "empty-header",
+            Code::HeaderLevel => "header-level",
+            Code::CodeBlockState => "code-block-state",
+            Code::LinkState => "link-state",
+            Code::CodeNewline => "code-newline",
+            Code::FenceLength => "fence-length",
+            Code::LinkNewline => "link-newline",
+            Code::LiteralStar => "literal-star",
+            Code::ListSpace => "list-space",
+            Code::HeadingLevelSkip => "heading-level-skip",
+            Code::EmptyHeading => "empty-heading",
+            Code::MissingAlt => "missing-alt",
+            Code::VagueLink => "vague-link",
+            Code::DuplicateId => "duplicate-id",
+            Code::BrokenFragment => "broken-fragment",
+            Code::MissingTocMarker => "missing-toc-marker",
+            Code::InvalidUtf8 => "invalid-utf8",
+            Code::LinkTruncated => "link-truncated",
+        }
+    }
+
+    /// Finds the code by its name
+    pub fn from_name(name: &str) -> Option<Code> {
+        Self::ALL.iter().copied().find(|code| code.name() == name)
+    }
+}
+
+/// What is done with the diagnostics of a code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// Leave them out
+    Allow,
+    /// Report them as warnings, which is the default
+    Warn,
+    /// Report them as errors
+    Deny,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem in the markdown at the given position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    pub position: Position,
+    pub code: Code,
+    pub severity: Severity,
+    pub message: String,
+    /// The values filled into the message, so it can be translated
+    pub args: Vec<String>,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(position: Position, code: Code, message: &str) -> Self {
+        Self {
+            position,
+            code,
+            severity: Severity::Warning,
+            message: message.into(),
+            args: Vec::new(),
+        }
+    }
+
+    /// Fills the `{}` placeholders of the template with the args in order
+    pub(crate) fn with_args(
+        position: Position,
+        code: Code,
+        template: &str,
+        args: Vec<String>,
+    ) -> Self {
+        let mut message: String = String::with_capacity(template.len());
+        let mut values = args.iter();
+        let mut parts = template.split("{}");
+
+        message.push_str(parts.next().unwrap_or_default());
+
+        for part in parts {
+            message.push_str(values.next().map_or("", String::as_str));
+            message.push_str(part);
+        }
+
+        Self {
+            position,
+            code,
+            severity: Severity::Warning,
+            message,
+            args,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {} column {}: {} [{}]",
+            self.position.line,
+            self.position.column,
+            self.message,
+            self.code.name()
+        )
+    }
+}
@@ -0,0 +1,53 @@
+//! Line endings of the written HTML. The parser copies the line breaks of the
+//! markdown and writes `\n` after the tags of its own, so documents written on
+//! Windows come out with both. `--eol` makes them all the same.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum Eol {
+    /// The line breaks are written as the parser wrote them
+    #[default]
+    Preserve,
+    Lf,
+    Crlf,
+}
+
+impl Eol {
+    pub(crate) const ALL: [Eol; 3] = [Eol::Preserve, Eol::Lf, Eol::Crlf];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Preserve => "preserve",
+            Self::Lf => "lf",
+            Self::Crlf => "crlf",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Eol> {
+        Self::ALL.into_iter().find(|eol| eol.name() == name)
+    }
+
+    /// Rewrites every `\r\n`, `\r` and `\n` of the HTML as the line ending
+    pub(crate) fn apply(self, html: Vec<u8>) -> Vec<u8> {
+        let eol: &[u8] = match self {
+            Self::Preserve => return html,
+            Self::Lf => b"\n",
+            Self::Crlf => b"\r\n",
+        };
+
+        let mut written: Vec<u8> = Vec::with_capacity(html.len() + html.len() / 32);
+        let mut bytes = html.iter().copied().peekable();
+
+        while let Some(byte) = bytes.next() {
+            match byte {
+                b'\r' => {
+                    bytes.next_if_eq(&b'\n');
+                    written.extend_from_slice(eol);
+                }
+                b'\n' => written.extend_from_slice(eol),
+                byte => written.push(byte),
+            }
+        }
+
+        written
+    }
+}
@@ -0,0 +1,64 @@
+//! Navigation of EPUB chapters. Every chapter converted with `--epub` gets a
+//! fragment with the `toc` and `landmarks` navs of EPUB 3 next to it, which
+//! the packer merges into the navigation document of the book.
+
+use crate::head::escape;
+use md2htm::HeadingAnchor;
+
+const NAMESPACES: &str =
+    "xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\"";
+
+/// The navs of the chapter in the file, the headings nested by their level.
+/// A chapter without headings is listed by its title.
+pub(crate) fn nav(headings: &[HeadingAnchor], chapter: &str, title: &str) -> String {
+    let chapter: String = escape(chapter);
+    let mut nav: String = format!("<nav {NAMESPACES} epub:type=\"toc\">\n<ol>\n");
+
+    // Levels of the items left open
+    let mut open: Vec<u8> = Vec::new();
+
+    for heading in headings {
+        match open.last() {
+            None => open.push(heading.level),
+            Some(&last) if heading.level > last => {
+                nav.push_str("\n<ol>\n");
+                open.push(heading.level);
+            }
+            Some(_) => {
+                nav.push_str("</li>\n");
+
+                while open.len() > 1 && open[open.len() - 2] >= heading.level {
+                    open.pop();
+                    nav.push_str("</ol>\n</li>\n");
+                }
+
+                *open.last_mut().unwrap() = heading.level;
+            }
+        }
+
+        nav.push_str(&format!(
+            "<li><a href=\"{chapter}#{}\">{}</a>",
+            escape(&heading.id),
+            escape(&heading.text)
+        ));
+    }
+
+    match open.len() {
+        0 => nav.push_str(&format!(
+            "<li><a href=\"{chapter}\">{}</a></li>\n",
+            escape(title)
+        )),
+        depth => {
+            nav.push_str("</li>\n");
+            nav.push_str(&"</ol>\n</li>\n".repeat(depth - 1));
+        }
+    }
+
+    nav.push_str("</ol>\n</nav>\n");
+    nav.push_str(&format!(
+        "<nav {NAMESPACES} epub:type=\"landmarks\" hidden=\"hidden\">\n<ol>\n\
+         <li><a epub:type=\"bodymatter\" href=\"{chapter}\">{}</a></li>\n</ol>\n</nav>\n",
+        escape(title)
+    ));
+    nav
+}
@@ -0,0 +1,26 @@
+//! Reasons why parsing stopped before the end of the markdown.
+
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Error {
+    /// The [`Cancel`](crate::limits::Cancel) of the limits asked to stop.
+    Cancelled,
+    /// The HTML would have grown over the `max_output` of the limits.
+    OutputLimit { limit: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cancelled => write!(f, "parsing was cancelled"),
+            Self::OutputLimit { limit } => {
+                write!(f, "the HTML would exceed the limit of {limit} bytes")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
@@ -0,0 +1,58 @@
+//! Guessing the size of the HTML before parsing, so the output buffer is
+//! allocated once for most documents.
+
+/// Bytes of HTML a run of the same byte of markdown is expected to add on
+/// top of itself. Runs count once, as ` ``` ` opens one code block and `**`
+/// one bold text. Links and code are written with much longer tags than the
+/// bytes marking them, so they weigh the most.
+const EXTRA: [u8; 256] = {
+    let mut extra: [u8; 256] = [0; 256];
+    // `<a href="">` and `</a>` for the four bytes of `[]()`
+    extra[b'[' as usize] = 12;
+    // `<img src="" alt="">` for `![]()`
+    extra[b'!' as usize] = 6;
+    // The tags of code for its two runs of backticks
+    extra[b'`' as usize] = 26;
+    // `<i>` and `</i>` for the two stars
+    extra[b'*' as usize] = 3;
+    extra[b'_' as usize] = 3;
+    // `<h1>` and `</h1>` for `# `
+    extra[b'#' as usize] = 4;
+    // `<li>` and `</li>` for `- `
+    extra[b'-' as usize] = 4;
+    // The paragraph tags of the lines
+    extra[b'\n' as usize] = 7;
+    // Character references when escaped
+    extra[b'<' as usize] = 3;
+    extra[b'>' as usize] = 3;
+    extra[b'&' as usize] = 4;
+    extra[b'"' as usize] = 5;
+    extra
+};
+
+/// Expected size of the HTML of the markdown in bytes. The parser allocates
+/// its output with this, and callers keeping buffers around can use it to
+/// pick one large enough.
+///
+/// ```
+/// use md2htm::{estimate_output, Parser};
+///
+/// let markdown: &[u8] = b"See [the docs](https://example.com/docs) and `cargo doc`.\n";
+/// let html = Parser::default().parse(markdown).html;
+/// assert!(estimate_output(markdown) >= html.len());
+/// ```
+pub fn estimate_output(markdown: &[u8]) -> usize {
+    let mut size: usize = markdown.len();
+    let mut previous: Option<u8> = None;
+
+    for &b in markdown {
+        if previous != Some(b) {
+            size += EXTRA[b as usize] as usize;
+        }
+
+        previous = Some(b);
+    }
+
+    // An eighth more, as a little too much is cheaper than growing the buffer
+    size + size / 8
+}
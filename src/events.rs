@@ -0,0 +1,620 @@
+//! The HTML of a converted document as events, so the output can be walked
+//! and rewritten without searching the bytes for tags: links rewritten,
+//! headings collected, elements dropped. This is a view of the rendered
+//! output, not a pull parser. The document is converted in full first, the
+//! tags are read back from where [`Html`], the writer of the HTML, marked
+//! them, and the text is the escaped text of the HTML. The marks only tell
+//! which bytes are tags, so the markdown that looks like HTML stays text.
+//! [`push_html`] writes the events back into the same HTML.
+//!
+//! ```
+//! use md2htm::{push_html, Event, Kind, Parser};
+//!
+//! let output = Parser::default().parse(b"# Title\n\nSee [docs](docs.md)\n");
+//! let events = output.html_events().map(|mut event| {
+//!     if let Event::Start(tag) = &mut event {
+//!         if tag.kind() == Kind::Link {
+//!             let href = tag.attribute("href").unwrap_or_default().replace(".md", ".html");
+//!             tag.set_attribute("href", &href);
+//!         }
+//!     }
+//!     event
+//! });
+//!
+//! let mut html = Vec::new();
+//! push_html(&mut html, events);
+//! assert!(html.windows(9).any(|w| w == b"docs.html"));
+//! ```
+
+use crate::csp::tag_end;
+use crate::writeto::WtiteTo;
+use alloc::{string::String, vec::Vec};
+use core::ops::{Deref, Range};
+
+/// Elements void in HTML, which have no end tag
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Piece of the rendered HTML. Every `Start` is followed by an `End` with
+/// the same tag, right away for void elements like `<br>` and `<img>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<'a> {
+    Start(Tag),
+    End(Tag),
+    /// Text as written in the HTML, its character references kept. Without
+    /// safe mode the HTML written in the markdown is text too.
+    Text(&'a [u8]),
+    /// Comments and the blocks of raw HTML
+    Html(&'a [u8]),
+}
+
+/// Element of the HTML with its attributes in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tag {
+    /// Name as written
+    pub name: String,
+    /// Names and the values as written, `None` for attributes without one
+    pub attributes: Vec<(String, Option<String>)>,
+    /// Written like `<br />`
+    pub self_closing: bool,
+}
+
+/// What the element is, going by its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Kind {
+    Paragraph,
+    Heading(u8),
+    BlockQuote,
+    CodeBlock,
+    List,
+    OrderedList,
+    Item,
+    Emphasis,
+    Strong,
+    Strikethrough,
+    Code,
+    Link,
+    Image,
+    LineBreak,
+    Rule,
+    Table,
+    TableRow,
+    TableCell,
+    Other,
+}
+
+impl Tag {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: String::from(name),
+            attributes: Vec::new(),
+            self_closing: false,
+        }
+    }
+
+    pub fn kind(&self) -> Kind {
+        match self.name.to_ascii_lowercase().as_str() {
+            "p" => Kind::Paragraph,
+            "h1" => Kind::Heading(1),
+            "h2" => Kind::Heading(2),
+            "h3" => Kind::Heading(3),
+            "h4" => Kind::Heading(4),
+            "h5" => Kind::Heading(5),
+            "h6" => Kind::Heading(6),
+            "blockquote" => Kind::BlockQuote,
+            "pre" => Kind::CodeBlock,
+            "ul" => Kind::List,
+            "ol" => Kind::OrderedList,
+            "li" => Kind::Item,
+            "em" | "i" => Kind::Emphasis,
+            "strong" | "b" => Kind::Strong,
+            "del" | "s" => Kind::Strikethrough,
+            "code" => Kind::Code,
+            "a" => Kind::Link,
+            "img" => Kind::Image,
+            "br" => Kind::LineBreak,
+            "hr" => Kind::Rule,
+            "table" => Kind::Table,
+            "tr" => Kind::TableRow,
+            "td" | "th" => Kind::TableCell,
+            _ => Kind::Other,
+        }
+    }
+
+    /// Value of the attribute as written, empty for one without a value
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_deref().unwrap_or_default())
+    }
+
+    /// Sets the value written for the attribute, adding it after the others
+    /// if the tag doesn't have it. The value is written as it is, so it must
+    /// be escaped already.
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        match self
+            .attributes
+            .iter_mut()
+            .find(|(attribute, _)| attribute.eq_ignore_ascii_case(name))
+        {
+            Some((_, old)) => *old = Some(String::from(value)),
+            None => self
+                .attributes
+                .push((String::from(name), Some(String::from(value)))),
+        }
+    }
+
+    pub fn remove_attribute(&mut self, name: &str) {
+        self.attributes
+            .retain(|(attribute, _)| !attribute.eq_ignore_ascii_case(name));
+    }
+
+    /// Reads back the start or end tag the parser wrote
+    pub(crate) fn parse(bytes: &[u8]) -> Self {
+        let end: bool = bytes.starts_with(b"</");
+        let name_start: usize = 1 + usize::from(end);
+        let name_end: usize = name_start
+            + bytes[name_start..]
+                .iter()
+                .take_while(|b| b.is_ascii_alphanumeric())
+                .count();
+        let mut tag: Tag = Tag::new(&String::from_utf8_lossy(&bytes[name_start..name_end]));
+
+        if !end {
+            tag.self_closing = bytes.ends_with(b"/>");
+            let attributes_end: usize = bytes.len() - 1 - usize::from(tag.self_closing);
+            tag.attributes = attributes(&bytes[name_end..attributes_end.max(name_end)]);
+        }
+
+        tag
+    }
+
+    pub(crate) fn is_void(&self) -> bool {
+        VOID.iter().any(|void| self.name.eq_ignore_ascii_case(void))
+    }
+}
+
+/// What the parser wrote at a range of the HTML. The text is what's
+/// between them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Piece {
+    Start,
+    End,
+    Html,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Mark {
+    pub(crate) piece: Piece,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+/// The writer of the HTML. The parser writes the tags into it as elements
+/// start and end, and the text between them, and the writer keeps where
+/// each of the tags went for [`HtmlEvents`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Html {
+    pub(crate) bytes: Vec<u8>,
+    pub(crate) marks: Vec<Mark>,
+}
+
+impl Html {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Self {
+            bytes: Vec::with_capacity(capacity),
+            marks: Vec::new(),
+        }
+    }
+
+    /// Text without tags
+    pub(crate) fn text(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            marks: Vec::new(),
+        }
+    }
+
+    /// Tags and the text between them, written in one go
+    pub(crate) fn of_tags(bytes: Vec<u8>) -> Self {
+        let mut html: Html = Self::text(bytes);
+        html.tagged(0);
+        html
+    }
+
+    /// Writes the tags, and the line breaks between them
+    pub(crate) fn tags(&mut self, tags: &[u8]) {
+        let from: usize = self.bytes.len();
+        self.bytes.extend_from_slice(tags);
+        self.tagged(from);
+    }
+
+    /// Keeps where the tags written since `from` are, for the tags written
+    /// a piece at a time
+    pub(crate) fn tagged(&mut self, from: usize) {
+        let mut at: usize = from;
+
+        while let Some(start) = self.bytes[at..]
+            .iter()
+            .position(|&b| b == b'<')
+            .map(|i| at + i)
+        {
+            let end: usize = tag_end(&self.bytes, start + 1).unwrap_or(self.bytes.len());
+            let piece: Piece = match self.bytes.get(start + 1) {
+                Some(b'/') => Piece::End,
+                _ => Piece::Start,
+            };
+
+            self.marks.push(Mark { piece, start, end });
+            at = end;
+        }
+    }
+
+    /// Writes raw HTML, like comments and the blocks of raw fences
+    pub(crate) fn raw(&mut self, html: &[u8]) {
+        if html.is_empty() {
+            return;
+        }
+
+        let start: usize = self.bytes.len();
+        self.bytes.extend_from_slice(html);
+        self.marks.push(Mark {
+            piece: Piece::Html,
+            start,
+            end: self.bytes.len(),
+        });
+    }
+
+    pub(crate) fn push(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+
+    /// Writes the HTML written on its own, like the content of a quote
+    pub(crate) fn append(&mut self, html: &Html) {
+        let at: usize = self.bytes.len();
+        self.bytes.extend_from_slice(&html.bytes);
+        self.marks.extend(html.marks.iter().map(|mark| Mark {
+            start: mark.start + at,
+            end: mark.end + at,
+            ..*mark
+        }));
+    }
+
+    /// Keeps the HTML in the range only
+    pub(crate) fn crop(&mut self, range: Range<usize>) {
+        self.bytes.truncate(range.end);
+        self.bytes.drain(..range.start);
+        crop(&mut self.marks, range);
+    }
+
+    pub(crate) fn splice(&mut self, range: Range<usize>, html: Html) {
+        self.edit(Vec::from([(range, html)]));
+    }
+
+    /// Replaces the ranges of the HTML, which are in order and don't
+    /// overlap. Tags replaced from their start are gone, and tags edited
+    /// inside stay tags.
+    pub(crate) fn edit(&mut self, edits: Vec<(Range<usize>, Html)>) {
+        if edits.is_empty() {
+            return;
+        }
+
+        // Where the HTML of each edit starts in the new bytes
+        let mut bytes: Vec<u8> = Vec::with_capacity(self.bytes.len());
+        let mut starts: Vec<usize> = Vec::with_capacity(edits.len());
+        let mut copied: usize = 0;
+
+        for (range, html) in &edits {
+            bytes.extend_from_slice(&self.bytes[copied..range.start]);
+            starts.push(bytes.len());
+            bytes.extend_from_slice(&html.bytes);
+            copied = range.end;
+        }
+
+        bytes.extend_from_slice(&self.bytes[copied..]);
+
+        // New index of the old one after the first `before` edits
+        let moved = |at: usize, before: usize| match before {
+            0 => at,
+            _ => {
+                let (range, html) = &edits[before - 1];
+                starts[before - 1] + html.bytes.len() + at.saturating_sub(range.end)
+            }
+        };
+
+        let mut marks: Vec<Mark> = Vec::with_capacity(self.marks.len());
+
+        for mark in &self.marks {
+            let before: usize = edits.partition_point(|(range, _)| range.end <= mark.start);
+
+            // Replaced from its start
+            if edits
+                .get(before)
+                .is_some_and(|(range, _)| range.start <= mark.start)
+            {
+                continue;
+            }
+
+            // The edits inside the tag, not the ones right after it
+            let mut inside: usize = edits.partition_point(|(range, _)| range.end <= mark.end);
+            while inside > before && edits[inside - 1].0.start >= mark.end {
+                inside -= 1;
+            }
+
+            marks.push(Mark {
+                start: moved(mark.start, before),
+                end: moved(mark.end, inside).min(bytes.len()),
+                ..*mark
+            });
+        }
+
+        for ((range, html), start) in edits.iter().zip(starts) {
+            let at: usize = self.marks.partition_point(|mark| mark.start < range.start);
+
+            // Tags written inside a tag aren't tags of their own
+            if at > 0 && range.start < self.marks[at - 1].end {
+                continue;
+            }
+
+            marks.extend(html.marks.iter().map(|mark| Mark {
+                start: mark.start + start,
+                end: mark.end + start,
+                ..*mark
+            }));
+        }
+
+        marks.sort_by_key(|mark| mark.start);
+        self.bytes = bytes;
+        self.marks = marks;
+    }
+}
+
+impl Deref for Html {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// Text written a byte at a time
+impl Extend<u8> for Html {
+    fn extend<T: IntoIterator<Item = u8>>(&mut self, bytes: T) {
+        self.bytes.extend(bytes);
+    }
+}
+
+impl WtiteTo for Html {
+    fn write(&mut self, bytes: &[u8]) {
+        self.bytes.write(bytes);
+    }
+
+    fn escape(&mut self, bytes: &[u8], escape: bool) {
+        self.bytes.escape(bytes, escape);
+    }
+
+    fn write_number(&mut self, n: usize) {
+        self.bytes.write_number(n);
+    }
+
+    fn encode(&mut self, bytes: &[u8]) {
+        self.bytes.encode(bytes);
+    }
+}
+
+/// Adds the marks of the HTML in the range to the marks of the HTML it's
+/// copied to at `at`
+pub(crate) fn copy(marks: &mut Vec<Mark>, from: &[Mark], range: Range<usize>, at: usize) {
+    marks.extend(
+        from.iter()
+            .filter(|mark| mark.start >= range.start && mark.end <= range.end)
+            .map(|mark| Mark {
+                start: mark.start - range.start + at,
+                end: mark.end - range.start + at,
+                ..*mark
+            }),
+    );
+}
+
+/// Keeps the marks of the HTML in the range, relative to its start
+pub(crate) fn crop(marks: &mut Vec<Mark>, range: Range<usize>) {
+    marks.retain_mut(|mark| {
+        let inside: bool = mark.start >= range.start && mark.end <= range.end;
+        mark.start = mark.start.saturating_sub(range.start);
+        mark.end = mark.end.saturating_sub(range.start);
+        inside
+    });
+}
+
+/// Iterator over the events of the rendered HTML
+#[derive(Debug, Clone)]
+pub struct HtmlEvents<'a> {
+    html: &'a [u8],
+    /// Marks of the tags not yet reached
+    marks: &'a [Mark],
+    at: usize,
+    /// Elements started and not yet ended
+    open: Vec<Tag>,
+    /// End of the void element just started
+    pending: Option<Tag>,
+}
+
+impl<'a> HtmlEvents<'a> {
+    pub(crate) fn new(html: &'a [u8], marks: &'a [Mark]) -> Self {
+        // The marks don't fit HTML changed after parsing
+        let marks: &[Mark] = match marks.last() {
+            Some(mark) if mark.end > html.len() => &[],
+            _ => marks,
+        };
+
+        Self {
+            html,
+            marks,
+            at: 0,
+            open: Vec::new(),
+            pending: None,
+        }
+    }
+}
+
+impl<'a> Iterator for HtmlEvents<'a> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Event<'a>> {
+        if let Some(tag) = self.pending.take() {
+            return Some(Event::End(tag));
+        }
+
+        let html: &'a [u8] = self.html;
+
+        let Some((mark, rest)) = self.marks.split_first() else {
+            let text: &'a [u8] = &html[self.at..];
+            self.at = html.len();
+            return (!text.is_empty()).then_some(Event::Text(text));
+        };
+
+        if mark.start > self.at {
+            let text: &'a [u8] = &html[self.at..mark.start];
+            self.at = mark.start;
+            return Some(Event::Text(text));
+        }
+
+        let bytes: &'a [u8] = &html[mark.start..mark.end];
+        self.marks = rest;
+        self.at = mark.end;
+
+        match mark.piece {
+            Piece::Start => {
+                let tag: Tag = Tag::parse(bytes);
+
+                match tag.is_void() || tag.self_closing {
+                    true => self.pending = Some(tag.clone()),
+                    false => self.open.push(tag.clone()),
+                }
+
+                Some(Event::Start(tag))
+            }
+
+            Piece::End => {
+                let tag: Tag = Tag::parse(bytes);
+
+                match self
+                    .open
+                    .iter()
+                    .rposition(|open| open.name.eq_ignore_ascii_case(&tag.name))
+                {
+                    Some(i) => Some(Event::End(self.open.remove(i))),
+                    None => Some(Event::End(tag)),
+                }
+            }
+
+            Piece::Html => Some(Event::Html(bytes)),
+        }
+    }
+}
+
+/// Writes the events as HTML. The events of [`crate::Output::html_events`]
+/// give back the same HTML.
+pub fn push_html<'a>(html: &mut Vec<u8>, events: impl IntoIterator<Item = Event<'a>>) {
+    for event in events {
+        match event {
+            Event::Start(tag) => {
+                html.push(b'<');
+                html.extend_from_slice(tag.name.as_bytes());
+
+                for (name, value) in &tag.attributes {
+                    html.push(b' ');
+                    html.extend_from_slice(name.as_bytes());
+
+                    if let Some(value) = value {
+                        let quote: u8 = match value.contains('"') {
+                            true => b'\'',
+                            false => b'"',
+                        };
+
+                        html.push(b'=');
+                        html.push(quote);
+                        html.extend_from_slice(value.as_bytes());
+                        html.push(quote);
+                    }
+                }
+
+                match tag.self_closing {
+                    true => html.extend_from_slice(b" />"),
+                    false => html.push(b'>'),
+                }
+            }
+            Event::End(tag) => {
+                if !tag.is_void() && !tag.self_closing {
+                    html.extend_from_slice(b"</");
+                    html.extend_from_slice(tag.name.as_bytes());
+                    html.push(b'>');
+                }
+            }
+            Event::Text(text) | Event::Html(text) => html.extend_from_slice(text),
+        }
+    }
+}
+
+/// Names and values of the attributes of a tag
+fn attributes(tag: &[u8]) -> Vec<(String, Option<String>)> {
+    let mut attributes: Vec<(String, Option<String>)> = Vec::new();
+    let mut at: usize = 0;
+
+    while at < tag.len() {
+        while at < tag.len() && tag[at].is_ascii_whitespace() {
+            at += 1;
+        }
+
+        let name_start: usize = at;
+
+        while at < tag.len() && tag[at] != b'=' && !tag[at].is_ascii_whitespace() {
+            at += 1;
+        }
+
+        if at == name_start {
+            at += 1;
+            continue;
+        }
+
+        let name: String = String::from_utf8_lossy(&tag[name_start..at]).into_owned();
+
+        if tag.get(at) != Some(&b'=') {
+            attributes.push((name, None));
+            continue;
+        }
+
+        at += 1;
+
+        let value: &[u8] = match tag.get(at) {
+            Some(&quote @ (b'"' | b'\'')) => {
+                let end: usize = tag[at + 1..]
+                    .iter()
+                    .position(|&b| b == quote)
+                    .map_or(tag.len(), |i| at + 1 + i);
+                let value: &[u8] = &tag[at + 1..end];
+                at = end + 1;
+                value
+            }
+            _ => {
+                let end: usize = at
+                    + tag[at..]
+                        .iter()
+                        .take_while(|b| !b.is_ascii_whitespace())
+                        .count();
+                let value: &[u8] = &tag[at..end];
+                at = end;
+                value
+            }
+        };
+
+        attributes.push((name, Some(String::from_utf8_lossy(value).into_owned())));
+    }
+
+    attributes
+}
@@ -0,0 +1,42 @@
+//! Finding the excerpt of a document for listing pages.
+
+use core::ops::Range;
+
+/// Line that ends the excerpt when it's in the markdown
+const MORE: &[u8] = b"<!-- more -->";
+
+/// Index of the line containing only the `<!-- more -->` marker
+pub(crate) fn more_marker(markdown: &[u8]) -> Option<usize> {
+    let mut start: usize = 0;
+
+    for line in markdown.split(|&b| b == b'\n') {
+        if line.trim_ascii() == MORE {
+            return Some(start);
+        }
+
+        start += line.len() + 1;
+    }
+
+    None
+}
+
+/// Range of the first paragraph element in the HTML, tags included
+pub(crate) fn first_paragraph(html: &[u8]) -> Option<Range<usize>> {
+    let is_open = |at: usize| html[at..].starts_with(b"<p>") || html[at..].starts_with(b"<p ");
+    let start: usize = (0..html.len()).find(|&at| is_open(at))?;
+    let mut depth: usize = 0;
+
+    for at in start..html.len() {
+        if is_open(at) {
+            depth += 1;
+        } else if html[at..].starts_with(b"</p>") {
+            depth -= 1;
+
+            if depth == 0 {
+                return Some(start..at + 4);
+            }
+        }
+    }
+
+    Some(start..html.len())
+}
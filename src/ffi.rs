@@ -0,0 +1,106 @@
+//! C interface for using the parser from other languages without the daemon.
+//! The matching declarations are in `include/md2htm.h`.
+
+#![allow(non_camel_case_types)]
+
+use crate::{Em, Ext, Options, ParserBuilder, MDS};
+use std::{ptr, slice};
+
+/// Everything went fine
+pub const MD2HTM_OK: i32 = 0;
+/// A required pointer was null
+pub const MD2HTM_NULL_POINTER: i32 = -1;
+
+/// Options understood by [`md2htm_convert`]. Zeroed options are not the
+/// defaults, so pass a null pointer for those.
+#[repr(C)]
+pub struct md2htm_options {
+    pub safe: bool,
+    pub semantic: bool,
+    pub underline: bool,
+}
+
+/// Converted HTML that must be released with [`md2htm_result_free`]
+#[repr(C)]
+pub struct md2htm_result {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl md2htm_options {
+    fn build(&self) -> Options {
+        let builder: ParserBuilder =
+            ParserBuilder::new()
+                .safe(self.safe)
+                .emphasis_style(if self.semantic {
+                    Em::Semantic
+                } else {
+                    Em::Presentational
+                });
+
+        if self.underline {
+            builder.extension(Ext::Underline).build()
+        } else {
+            builder.disable(Ext::Underline).build()
+        }
+    }
+}
+
+/// Converts `len` bytes of markdown from `input` into HTML stored in `result`.
+/// `options` may be null to use the defaults. Returns `MD2HTM_OK` on success.
+///
+/// # Safety
+///
+/// `input` must point to `len` readable bytes, `options` must be null or point
+/// to valid options and `result` must point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn md2htm_convert(
+    input: *const u8,
+    len: usize,
+    options: *const md2htm_options,
+    result: *mut md2htm_result,
+) -> i32 {
+    if result.is_null() || (input.is_null() && len != 0) {
+        return MD2HTM_NULL_POINTER;
+    }
+
+    let markdown: Vec<u8> = if len == 0 {
+        Vec::new()
+    } else {
+        slice::from_raw_parts(input, len).to_vec()
+    };
+
+    let options: Options = match options.as_ref() {
+        Some(options) => options.build(),
+        None => Options::default(),
+    };
+
+    let html: Box<[u8]> = MDS::parse_with(markdown, &options).into_boxed_slice();
+    let len: usize = html.len();
+
+    (*result).data = Box::into_raw(html) as *mut u8;
+    (*result).len = len;
+
+    MD2HTM_OK
+}
+
+/// Releases the HTML of a result and resets it. Freeing a reset or null
+/// result does nothing.
+///
+/// # Safety
+///
+/// `result` must be null or point to a result filled by [`md2htm_convert`].
+#[no_mangle]
+pub unsafe extern "C" fn md2htm_result_free(result: *mut md2htm_result) {
+    if let Some(result) = result.as_mut() {
+        if !result.data.is_null() {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+                result.data,
+                result.len,
+            )));
+        }
+
+        result.data = ptr::null_mut();
+        result.len = 0;
+    }
+}
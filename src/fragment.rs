@@ -0,0 +1,50 @@
+//! Parsing a part of a document on its own, so editors can render again
+//! only the block that was edited.
+
+use crate::cursor::Position;
+use crate::mdstate::Boundary;
+
+/// Where a fragment of a document starts. The parser is back at its start
+/// state between blocks, so a fragment starting there parses the same as
+/// in the whole document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockContext {
+    /// Position of the first byte of the fragment in the document
+    pub position: Position,
+    /// Where the HTML of the fragment starts in the HTML of the document
+    pub html: usize,
+}
+
+impl BlockContext {
+    /// The start of the document
+    pub const START: BlockContext = BlockContext {
+        position: Position::START,
+        html: 0,
+    };
+
+    /// Context of a fragment starting at the position, when the HTML
+    /// before it isn't known
+    pub fn at(position: Position) -> Self {
+        Self { position, html: 0 }
+    }
+}
+
+impl Default for BlockContext {
+    fn default() -> Self {
+        Self::START
+    }
+}
+
+impl From<&Boundary> for BlockContext {
+    fn from(boundary: &Boundary) -> Self {
+        Self {
+            position: Position {
+                line: boundary.line,
+                column: 1,
+                offset: boundary.source,
+            },
+            html: boundary.html,
+        }
+    }
+}
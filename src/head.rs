@@ -0,0 +1,239 @@
+//! Standalone documents and what goes in their head: the title, the
+//! description and the OpenGraph and Twitter tags that make links to the
+//! page unfurl when they are shared. The values come from the front matter
+//! between `---` lines at the start of the markdown, or else from the page.
+
+use md2htm::{to_xhtml, Output, Parser, Stats};
+use std::{fs, io::Result, path::Path};
+
+/// Longest description taken from the excerpt, in characters
+const DESCRIPTION: usize = 160;
+
+/// What the head of a standalone document tells about the page
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Head {
+    pub(crate) title: Option<String>,
+    pub(crate) description: Option<String>,
+    /// Picture shown in the previews of shared links
+    pub(crate) image: Option<String>,
+    /// Canonical address of the page
+    pub(crate) url: Option<String>,
+    pub(crate) stats: Option<Stats>,
+    /// `<style>` or `<link>` of the `--theme`
+    pub(crate) theme: Option<String>,
+    /// Snippets from `--inject-head` and `--inject-body`, like the loader of
+    /// a highlighter or analytics
+    pub(crate) inject_head: Option<String>,
+    pub(crate) inject_body: Option<String>,
+    /// The document is written as XHTML like the HTML of `--xhtml`
+    pub(crate) xhtml: bool,
+    /// The sections of `--slides` are put in the containers of reveal.js
+    pub(crate) slides: bool,
+}
+
+impl Head {
+    /// Takes the values from the front matter, the title from the first top
+    /// level heading of the HTML and the description from the excerpt of
+    /// the markdown when the front matter doesn't give them
+    pub(crate) fn new(parser: &Parser, front: &[u8], markdown: &[u8], html: &[u8]) -> Self {
+        Self {
+            title: front_value(front, "title").or_else(|| title(html)),
+            description: front_value(front, "description").or_else(|| {
+                let excerpt: String = parser.excerpt(markdown).text();
+                let excerpt: String = excerpt.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+                match excerpt.chars().count() > DESCRIPTION {
+                    true => Some(format!(
+                        "{}…",
+                        excerpt.chars().take(DESCRIPTION - 1).collect::<String>()
+                    )),
+                    false => Some(excerpt).filter(|excerpt| !excerpt.is_empty()),
+                }
+            }),
+            image: front_value(front, "image"),
+            url: front_value(front, "url"),
+            stats: None,
+            theme: None,
+            inject_head: None,
+            inject_body: None,
+            xhtml: parser.options().is_xhtml(),
+            slides: parser.options().is_slides(),
+        }
+    }
+}
+
+/// Wraps the HTML into a whole document with the head
+pub(crate) fn standalone(html: Vec<u8>, head: &Head) -> Vec<u8> {
+    let mut document: Vec<u8> = Vec::with_capacity(html.len() + 1024);
+
+    match head.xhtml {
+        true => document.extend_from_slice(
+            b"<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE html>\n\
+              <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n",
+        ),
+        false => document.extend_from_slice(b"<!DOCTYPE html>\n<html>\n"),
+    }
+
+    document.extend_from_slice(b"<head>\n<meta charset=\"utf-8\">\n");
+
+    let mut meta = |attribute: &str, name: &str, value: &Option<String>| {
+        if let Some(value) = value {
+            document.extend_from_slice(
+                format!(
+                    "<meta {attribute}=\"{name}\" content=\"{}\">\n",
+                    escape(value)
+                )
+                .as_bytes(),
+            );
+        }
+    };
+
+    if let Some(stats) = head.stats {
+        meta("name", "word-count", &Some(stats.words.to_string()));
+        meta(
+            "name",
+            "reading-time",
+            &Some(format!("{} min", stats.reading_minutes)),
+        );
+    }
+
+    meta("name", "description", &head.description);
+
+    // The cards of shared links need at least a title
+    if head.title.is_some() {
+        let card: &str = match head.image {
+            Some(_) => "summary_large_image",
+            None => "summary",
+        };
+
+        meta("property", "og:type", &Some(String::from("article")));
+        meta("property", "og:title", &head.title);
+        meta("property", "og:description", &head.description);
+        meta("property", "og:image", &head.image);
+        meta("property", "og:url", &head.url);
+        meta("name", "twitter:card", &Some(String::from(card)));
+        meta("name", "twitter:title", &head.title);
+        meta("name", "twitter:description", &head.description);
+        meta("name", "twitter:image", &head.image);
+    }
+
+    if let Some(title) = &head.title {
+        document.extend_from_slice(format!("<title>{}</title>\n", escape(title)).as_bytes());
+    }
+
+    if let Some(theme) = &head.theme {
+        document.extend_from_slice(theme.as_bytes());
+    }
+
+    // After the theme, so the snippet can override it
+    if let Some(snippet) = &head.inject_head {
+        document.extend_from_slice(snippet.as_bytes());
+    }
+
+    document.extend_from_slice(b"</head>\n<body>\n");
+
+    if head.slides {
+        document.extend_from_slice(b"<div class=\"reveal\">\n<div class=\"slides\">\n");
+    }
+
+    document.extend_from_slice(&html);
+
+    if head.slides {
+        document.extend_from_slice(b"</div>\n</div>\n");
+    }
+
+    if let Some(snippet) = &head.inject_body {
+        document.extend_from_slice(snippet.as_bytes());
+    }
+
+    document.extend_from_slice(b"</body>\n</html>\n");
+
+    // The head and the snippets are HTML until now
+    match head.xhtml {
+        true => to_xhtml(&document),
+        false => document,
+    }
+}
+
+/// Reads the snippet to add to the page, giving its scripts and styles the
+/// nonce, so a Content-Security-Policy allowing the nonce runs them
+pub(crate) fn snippet(path: Option<&Path>, nonce: Option<&str>) -> Result<Option<String>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+
+    let mut snippet: String = fs::read_to_string(path)?;
+
+    if let Some(nonce) = nonce {
+        let attribute: String = format!(" nonce=\"{}\"", escape(nonce));
+
+        for tag in ["<script", "<style"] {
+            snippet = snippet.replace(tag, &format!("{tag}{attribute}"));
+        }
+    }
+
+    if !snippet.ends_with('\n') {
+        snippet.push('\n');
+    }
+
+    Ok(Some(snippet))
+}
+
+/// Text of the first `<h1>` of the HTML
+fn title(html: &[u8]) -> Option<String> {
+    let start: usize = html.windows(3).position(|w| w == b"<h1")?;
+    let end: usize = start + html[start..].windows(5).position(|w| w == b"</h1>")? + 5;
+
+    let mut heading: Output = Output::default();
+    heading.html = html[start..end].to_vec();
+    let title: String = heading.text().trim().to_string();
+    Some(title).filter(|title| !title.is_empty())
+}
+
+/// Escapes the text for an attribute value or the title
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Splits the front matter between `---` lines off the start of the markdown.
+/// Returns the front matter without the lines, and the rest of the markdown.
+pub(crate) fn front_matter(markdown: &[u8]) -> (&[u8], &[u8]) {
+    let Some(rest) = markdown
+        .strip_prefix(b"---\n")
+        .or_else(|| markdown.strip_prefix(b"---\r\n"))
+    else {
+        return (&[], markdown);
+    };
+
+    let mut start: usize = 0;
+
+    for line in rest.split_inclusive(|&b| b == b'\n') {
+        if line.trim_ascii() == b"---" {
+            return (&rest[..start], &rest[start + line.len()..]);
+        }
+
+        start += line.len();
+    }
+
+    // Without the closing line it's just markdown
+    (&[], markdown)
+}
+
+/// Value of the `key: value` line of the front matter, without the quotes
+/// around it
+pub(crate) fn front_value(front: &[u8], key: &str) -> Option<String> {
+    front.split(|&b| b == b'\n').find_map(|line| {
+        let (name, value) = line.split_at(line.iter().position(|&b| b == b':')?);
+        let value: &[u8] = value[1..].trim_ascii();
+        let value: &[u8] = match value {
+            [b'"', inner @ .., b'"'] | [b'\'', inner @ .., b'\''] => inner,
+            value => value,
+        };
+
+        (name.trim_ascii() == key.as_bytes() && !value.is_empty())
+            .then(|| String::from_utf8_lossy(value).into_owned())
+    })
+}
@@ -0,0 +1,417 @@
+//! Heading numbering, the ids generated from the heading texts and the table
+//! of contents linking to them.
+
+use crate::cursor::Position;
+use crate::diagnostic::{Code, Diagnostic};
+use crate::events::Html;
+use crate::options::{Options, Permalink, Slug};
+use crate::text::{strip_tags, unescape};
+use crate::writeto::*;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::{format, string::String, vec, vec::Vec};
+use core::ops::Range;
+
+/// Keeps count of the headings while parsing. The parser tells when a heading
+/// tag has been opened and when it is closed.
+#[derive(Debug)]
+pub(crate) struct Headings {
+    /// Headings on each level since the previous heading of a higher level
+    counters: [usize; 6],
+    /// Hierarchical number of the open heading, like `1.2`
+    number: Vec<u8>,
+    /// Index of the `>` ending the open heading tag, and where the heading text starts
+    open: Option<(usize, usize)>,
+    /// Level of the previous heading
+    level: u8,
+    /// Where the previous heading started
+    position: Position,
+    /// Diagnostics about renamed duplicate ids
+    duplicates: Vec<Diagnostic>,
+    /// Index right after the closing tag of the first heading
+    first_end: Option<usize>,
+    /// Headings with ids for the table of contents
+    list: Vec<Heading>,
+    /// Ids given to the headings
+    ids: BTreeSet<String>,
+    /// Number to try next for the ids taken more than once
+    next: BTreeMap<String, usize>,
+}
+
+impl Headings {
+    pub(crate) fn new() -> Self {
+        Self {
+            counters: [0; 6],
+            number: Vec::new(),
+            open: None,
+            level: 0,
+            position: Position::START,
+            duplicates: Vec::new(),
+            first_end: None,
+            list: Vec::new(),
+            ids: BTreeSet::new(),
+            next: BTreeMap::new(),
+        }
+    }
+
+    /// Counts the heading. Must be called right after its opening tag has been written.
+    pub(crate) fn open(
+        &mut self,
+        output: &mut Html,
+        level: u8,
+        position: Position,
+        options: &Options,
+    ) {
+        self.level = level;
+        self.position = position;
+        let level: usize = (level as usize).clamp(1, 6);
+        self.counters[level - 1] += 1;
+        self.counters[level..].fill(0);
+
+        // Levels above the first heading used in the document aren't numbered,
+        // so a document starting from `##` gets numbers like 1, 1.1 and 2.
+        let first: usize = self.counters[..level]
+            .iter()
+            .position(|&n| n != 0)
+            .unwrap_or(level - 1);
+
+        self.number.clear();
+        for (i, n) in self.counters[first..level].iter().enumerate() {
+            if i != 0 {
+                self.number.push(b'.');
+            }
+
+            self.number.write_number(*n);
+        }
+
+        let tag_end: usize = output.len() - 1;
+
+        if options.number_headings {
+            output.write(&self.number);
+            if level - first == 1 {
+                output.push(b'.');
+            }
+            output.push(b' ');
+        }
+
+        self.open = Some((tag_end, output.len()));
+    }
+
+    /// Adds the id to the open heading and writes its closing tag. Returns
+    /// false if the heading had no text.
+    pub(crate) fn close(&mut self, output: &mut Html, level: u8, options: &Options) -> bool {
+        let mut has_text: bool = true;
+
+        if let Some((tag_end, start)) = self.open.take() {
+            let mut text: Vec<u8> = Vec::with_capacity(output.len() - start);
+            strip_tags(&output[start..], &mut text);
+            has_text = !text.trim_ascii().is_empty();
+
+            if options.has_heading_ids() {
+                let mut id: String = options.slug_prefix.clone();
+
+                if options.number_headings {
+                    for &b in &self.number {
+                        id.push(if b == b'.' { '-' } else { b as char });
+                    }
+                    id.push('-');
+                }
+
+                slug(
+                    &String::from_utf8_lossy(&output[start..]),
+                    options.slug,
+                    &mut id,
+                );
+                let id: String = self.unique(id, options);
+
+                let mut text: Vec<u8> = Vec::with_capacity(output.len() - tag_end);
+                strip_tags(&output[tag_end + 1..], &mut text);
+
+                let mut attribute: Vec<u8> = Vec::with_capacity(id.len() + 6);
+                attribute.write(b" id=\"");
+                attribute.escape(id.as_bytes(), true);
+                attribute.push(b'"');
+                let mut edits: Vec<(Range<usize>, Html)> =
+                    vec![(tag_end..tag_end, Html::text(attribute))];
+
+                if options.permalink == Permalink::Wrap {
+                    let mut link: Vec<u8> = Vec::with_capacity(id.len() + 11);
+                    link.write(b"<a href=\"#");
+                    link.escape(id.as_bytes(), true);
+                    link.write(b"\">");
+                    edits.push((tag_end + 1..tag_end + 1, Html::of_tags(link)));
+                }
+
+                output.edit(edits);
+
+                match options.permalink {
+                    Permalink::None => {}
+
+                    Permalink::Anchor => {
+                        let from: usize = output.len();
+                        output.write(b" <a class=\"");
+                        output.write(options.class_prefix.as_bytes());
+                        output.write(b"anchor\" href=\"#");
+                        output.escape(id.as_bytes(), true);
+                        output.write(b"\">#</a>");
+                        output.tagged(from);
+                    }
+
+                    Permalink::Wrap => output.tags(b"</a>"),
+                }
+
+                self.list.push(Heading {
+                    level,
+                    id,
+                    text,
+                    line: self.position.line,
+                });
+            }
+        }
+
+        let from: usize = output.len();
+        output.write(b"</h");
+        output.push(level + 48);
+        output.push(b'>');
+        output.tagged(from);
+
+        if self.first_end.is_none() {
+            self.first_end = Some(output.len());
+        }
+
+        has_text
+    }
+
+    /// Level of the previous heading, or 0 before the first one
+    pub(crate) fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// Adds the suffix to the id, and a number before it if the id is already
+    /// taken: `setup`, `setup-1`, `setup-2`.
+    fn unique(&mut self, id: String, options: &Options) -> String {
+        let mut unique: String = id.clone() + &options.slug_suffix;
+        let taken: bool = self.ids.contains(&unique);
+
+        if taken {
+            let n: &mut usize = self.next.entry(id.clone()).or_insert(1);
+
+            loop {
+                unique = format!("{id}-{n}{}", options.slug_suffix);
+                *n += 1;
+
+                if !self.ids.contains(&unique) {
+                    break;
+                }
+            }
+        }
+
+        self.ids.insert(unique.clone());
+
+        if taken && options.warn_duplicate_ids {
+            let original: String = id + &options.slug_suffix;
+            self.duplicates.push(Diagnostic::with_args(
+                self.position,
+                Code::DuplicateId,
+                "Heading id {} is already taken. Using {} instead.",
+                vec![original, unique.clone()],
+            ));
+        }
+
+        unique
+    }
+
+    /// Checks if a heading has the id
+    pub(crate) fn has_id(&self, id: &[u8]) -> bool {
+        core::str::from_utf8(id).is_ok_and(|id| self.ids.contains(id))
+    }
+
+    /// Diagnostics about the heading ids that had to be renamed
+    pub(crate) fn take_duplicates(&mut self) -> Vec<Diagnostic> {
+        core::mem::take(&mut self.duplicates)
+    }
+
+    /// Index right after the first heading
+    pub(crate) fn first_end(&self) -> Option<usize> {
+        self.first_end
+    }
+
+    /// Checks if any headings have been collected for the table of contents
+    pub(crate) fn has_any(&self) -> bool {
+        !self.list.is_empty()
+    }
+
+    /// The headings that got ids, in document order
+    pub(crate) fn anchors(&self) -> Vec<HeadingAnchor> {
+        self.list
+            .iter()
+            .map(|heading| HeadingAnchor {
+                level: heading.level,
+                text: unescape(&heading.text),
+                id: heading.id.clone(),
+                line: heading.line,
+            })
+            .collect()
+    }
+
+    /// Writes the table of contents as nested lists at the index, or at the
+    /// end of the output without one. Nothing is written without headings.
+    pub(crate) fn write_toc(&self, output: &mut Html, at: Option<usize>, options: &Options) {
+        if self.list.is_empty() {
+            return;
+        }
+
+        let mut toc: Vec<u8> = Vec::with_capacity(self.list.len() * 64);
+        let mut levels: Vec<u8> = Vec::with_capacity(6);
+        let at: usize = at.unwrap_or(output.len());
+
+        // Keep a blank line between the table of contents and the blocks before it
+        if output[..at].ends_with(b"\n") {
+            if at == output.len() && !output.ends_with(b"\n\n") {
+                toc.push(b'\n');
+            }
+        } else if at != 0 {
+            toc.write(b"\n\n");
+        }
+
+        toc.write(b"<nav class=\"");
+        toc.write(options.class_prefix.as_bytes());
+        toc.write(b"toc\">");
+
+        for heading in &self.list {
+            while levels.len() > 1 && levels.last().is_some_and(|&l| l > heading.level) {
+                toc.write(b"</li>\n</ul>");
+                levels.pop();
+            }
+
+            match levels.last() {
+                Some(&l) if l >= heading.level => toc.write(b"</li>\n<li>"),
+                _ => {
+                    toc.write(b"\n<ul>\n<li>");
+                    levels.push(heading.level);
+                }
+            }
+
+            toc.write(b"<a href=\"#");
+            toc.escape(heading.id.as_bytes(), true);
+            toc.write(b"\">");
+            toc.write(&heading.text);
+            toc.write(b"</a>");
+        }
+
+        for _ in levels {
+            toc.write(b"</li>\n</ul>");
+        }
+
+        toc.write(b"\n</nav>");
+
+        if at == output.len() {
+            toc.push(b'\n');
+        }
+
+        output.splice(at..at, Html::of_tags(toc));
+    }
+}
+
+/// A heading collected for the table of contents
+#[derive(Debug)]
+struct Heading {
+    level: u8,
+    id: String,
+    /// The heading HTML without the tags
+    text: Vec<u8>,
+    /// Line of the markdown the heading is on
+    line: usize,
+}
+
+/// Where a heading can be linked to, for search indexers and "edit this
+/// section" links
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeadingAnchor {
+    pub level: u8,
+    /// Plain text of the heading
+    pub text: String,
+    pub id: String,
+    /// Line of the markdown the heading is on, starting from 1
+    pub line: usize,
+}
+
+/// Turns the heading HTML into an id. Tags are dropped and character
+/// references are skipped, then the text is slugified with the chosen style.
+pub(crate) fn slug(html: &str, style: Slug, id: &mut String) {
+    let mut in_tag: bool = false;
+    let mut chars = html.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if in_tag => {}
+
+            '&' => {
+                if let Some(len) = reference_len(&html[i..]) {
+                    chars.nth(len - 2);
+                }
+            }
+
+            ' ' => id.push('-'),
+            '-' | '_' => id.push(c),
+            _ if c.is_ascii_alphanumeric() => id.push(c.to_ascii_lowercase()),
+
+            _ => match style {
+                Slug::GitHub if c.is_alphanumeric() => id.extend(c.to_lowercase()),
+                Slug::Ascii => {
+                    if let Some(ascii) = transliterate(c) {
+                        id.push_str(ascii);
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+}
+
+/// Length of the character reference like `&amp;` or `&#35;` at the start of the text
+fn reference_len(text: &str) -> Option<usize> {
+    let end: usize = text.find(';')?;
+    let name: &str = &text[1..end];
+
+    if !name.is_empty()
+        && name
+            .strip_prefix('#')
+            .unwrap_or(name)
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric())
+    {
+        Some(end + 1)
+    } else {
+        None
+    }
+}
+
+/// ASCII replacements for the accented latin letters
+fn transliterate(c: char) -> Option<&'static str> {
+    let ascii: &str = match c {
+        'À'..='Å' | 'à'..='å' | 'Ā' | 'ā' | 'Ą' | 'ą' => "a",
+        'Æ' | 'æ' => "ae",
+        'Ç' | 'ç' | 'Č' | 'č' | 'Ć' | 'ć' => "c",
+        'Ð' | 'ð' | 'Đ' | 'đ' | 'Ď' | 'ď' => "d",
+        'È'..='Ë' | 'è'..='ë' | 'Ē' | 'ē' | 'Ę' | 'ę' | 'Ě' | 'ě' => "e",
+        'Ì'..='Ï' | 'ì'..='ï' | 'Ī' | 'ī' => "i",
+        'Ł' | 'ł' => "l",
+        'Ñ' | 'ñ' | 'Ń' | 'ń' | 'Ň' | 'ň' => "n",
+        'Ò'..='Ö' | 'Ø' | 'ò'..='ö' | 'ø' | 'Ō' | 'ō' | 'Ő' | 'ő' => "o",
+        'Œ' | 'œ' => "oe",
+        'Ř' | 'ř' => "r",
+        'Š' | 'š' | 'Ś' | 'ś' => "s",
+        'ß' => "ss",
+        'Ť' | 'ť' => "t",
+        'Þ' | 'þ' => "th",
+        'Ù'..='Ü' | 'ù'..='ü' | 'Ū' | 'ū' | 'Ů' | 'ů' | 'Ű' | 'ű' => "u",
+        'Ý' | 'ý' | 'ÿ' | 'Ÿ' => "y",
+        'Ž' | 'ž' | 'Ź' | 'ź' | 'Ż' | 'ż' => "z",
+        _ => return None,
+    };
+
+    Some(ascii)
+}
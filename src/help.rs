@@ -0,0 +1,796 @@
+//! Help generated from the definitions of the commands and the options they
+//! take, so `md2htm build --help` only shows what matters for building.
+
+use crate::i18n::{tr, translate};
+use crate::{code_names, config, extension_names, SOCK};
+
+/// Column the descriptions start from
+const INDENT: usize = 32;
+/// Width the descriptions are wrapped to
+const WIDTH: usize = 96;
+
+/// Name of the default command, which converts a single file
+pub(crate) const CONVERT: &str = "convert";
+
+pub(crate) struct Command {
+    pub(crate) name: &'static str,
+    pub(crate) aliases: &'static [&'static str],
+    /// Arguments after the options
+    usage: &'static str,
+    /// Line shown in the list of commands
+    summary: &'static str,
+    about: &'static str,
+}
+
+struct Flag {
+    name: &'static str,
+    value: Option<&'static str>,
+    about: &'static str,
+    /// Commands that take the option
+    commands: &'static [&'static str],
+}
+
+/// Commands that parse markdown, which is all of them
+const ALL: &[&str] = &[
+    CONVERT, "a11y", "check", "build", "preview", "daemon", "xtest", "batch",
+];
+
+pub(crate) const COMMANDS: &[Command] = &[
+    Command {
+        name: CONVERT,
+        aliases: &[],
+        usage: "[source file] [[output file]]",
+        summary: "Convert the source file into HTML. This is what runs without a command.",
+        about: "Parses Markdown to HTML without adding any of the root tags, unless --standalone is given. The source file doesn't need the extension .md or any other. If the output file is omitted, the path of the source file is used with its extension replaced with .html, or .html appended.",
+    },
+    Command {
+        name: "a11y",
+        aliases: &[],
+        usage: "[source file]",
+        summary: "Report accessibility problems in the source file.",
+        about: "Report accessibility problems in the source file: skipped heading levels, empty headings, images without alt text and links with texts like \"here\". Exits with status 1 if any are found.",
+    },
+    Command {
+        name: "check",
+        aliases: &[],
+        usage: "[source file]",
+        summary: "Report problems in the source file.",
+        about: "Report problems in the source file, like links to #fragments that don't match any heading id. Exits with status 1 if any are found.",
+    },
+    Command {
+        name: "build",
+        aliases: &[],
+        usage: "[source] [output]",
+        summary: "Convert a directory of markdown files into a directory of HTML files.",
+        about: "Convert every .md file in the source directory and its subdirectories into an .html file in the output directory. Images with relative paths are copied under assets in the output directory and the pages are rewritten to use them. Front matter between --- lines at the start of the files is left out.",
+    },
+    Command {
+        name: "preview",
+        aliases: &[],
+        usage: "",
+        summary: "Start a preview server for editors.",
+        about: "Start a preview server for editors. It speaks JSON-RPC over stdio like a language server, takes the documents with didOpen and didChange and answers with md2htm/preview notifications holding the HTML and with publishDiagnostics.",
+    },
+    Command {
+        name: "daemon",
+        aliases: &["--daemon", "d", "-d"],
+        usage: "[[socket path]]",
+        summary: "Convert markdown sent through a unix domain socket.",
+        about: "Start the program in daemon mode that listens a socket in {sock}. An alternative socket path may be given as the only argument. The options can also be given as environment variables, like MD2HTM_SOCKET, MD2HTM_MAX_SIZE, MD2HTM_WORKERS and MD2HTM_LOG. A request can override the options of the daemon by starting with the length of its options with the highest bit set, followed by the options as key = value lines like in the config files. md2htm daemon --print-unit prints a systemd unit running the daemon with the options given. A socket passed by systemd socket activation is listened instead of the path. With the systemd feature SIGHUP starts the executable again with the socket, and the old daemon stops after answering the connections it accepted, so upgrades don't drop any clients. If the program doesn't have sufficient privileges to remove the socket file, it can be removed manually with: sudo rm {sock}",
+    },
+    Command {
+        name: "config",
+        aliases: &[],
+        usage: "show [[source file]]",
+        summary: "Show the options in effect and where they were set.",
+        about: "Show the options in effect and where they were set. The options are read from {system}, then from {user}, then from environment variables like MD2HTM_HEADING_IDS=1, then from the command line and last from a pragma like <!-- md2htm: toc = true --> on the first line of the source file, each overriding the ones before. The config files hold the names of the options without the dashes, like heading-ids = true. The extensions and diagnostic codes of every layer are applied in order instead of replacing each other.",
+    },
+    Command {
+        name: "xtest",
+        aliases: &[],
+        usage: "[corpus directory]",
+        summary: "Compare the HTML of a corpus of markdown files with another implementation.",
+        about: "Convert every markdown file in the directory and its subdirectories with md2htm and with a reference command reading the markdown from stdin, and compare the two as sequences of tags and text. Prints the files that differ with the tokens around the first difference, and which tags the differences start from the most, to guide compatibility work. Exits with status 1 if any file differs.",
+    },
+    Command {
+        name: "selftest",
+        aliases: &[],
+        usage: "",
+        summary: "Check that the build converts and serves markdown correctly.",
+        about: "Convert a set of documents built into the program and run requests against a daemon of this executable listening a temporary socket, printing how each check went. Exits with status 1 if any of them failed. Handy for checking a build on a new platform.",
+    },
+    Command {
+        name: "loadtest",
+        aliases: &[],
+        usage: "",
+        summary: "Measure how many requests a running daemon answers per second.",
+        about: "Send the document given with --file to the daemon listening the socket given with --socket, or {sock}, from many clients at once for a while, each request on a connection of its own. Prints how many requests were answered per second and the percentiles of the latencies, so deployments can be sized and regressions noticed. Exits with status 1 if none of the requests was answered.",
+    },
+    Command {
+        name: "batch",
+        aliases: &[],
+        usage: "",
+        summary: "Convert JSON lines from the standard input.",
+        about: "Read a JSON object with an id and the markdown from every line of the standard input, and write a line with the same id, the html and the diagnostics for each, in the same order. Lines that can't be converted get an error instead of the html and make the exit status 1. Pragmas and the options of the command apply as in conversion.",
+    },
+    Command {
+        name: "status",
+        aliases: &[],
+        usage: "",
+        summary: "Show the state of a running daemon.",
+        about: "Ask the daemon listening the socket given with --socket, or {sock}, for its state and print it as name value lines: uptime, connections being answered, refused and answered so far, open file descriptors and resident memory. The descriptors and memory are left out on platforms without /proc. Clients can ask the same by sending status = true as the options of a request. Exits with status 1 if the daemon didn't answer.",
+    },
+    Command {
+        name: "help",
+        aliases: &["--help", "h", "-h", "?"],
+        usage: "[[command]]",
+        summary: "Show this help, or the help of the command, and exit.",
+        about: "Show the help of the command and exit. md2htm [command] --help does the same.",
+    },
+];
+
+const FLAGS: &[Flag] = &[
+    Flag {
+        name: "--safe",
+        value: None,
+        about: "Escape raw HTML and drop link and image urls that use other schemes than http, https, mailto or tel. Use this for untrusted input.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--csp",
+        value: None,
+        about: "Leave out inline styles and scripts, so the HTML can be served under a Content-Security-Policy without unsafe-inline. The parser writes none, so this removes the style and event handler attributes, javascript: urls and script and style elements of raw HTML.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--print",
+        value: None,
+        about: "Rewrite the HTML for printed handouts: pages break before the h1 and h2 headings after the first, the addresses of the links are listed at the end and numbered after the links like footnotes, and details elements are opened. The page-break, link-note and link-notes classes are for the stylesheet.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--slides",
+        value: None,
+        about: "Split the output into <section class=\"slide\"> elements at the --- rules and ## headings of the top level, turning an outline into slides for reveal.js. The rules are left out, and standalone documents put the sections in the reveal and slides containers reveal.js looks for.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--no-paragraphs",
+        value: None,
+        about: "Leave out the <p> tags around the paragraphs, for HTML that goes into list items, table cells or other blocks of a template. The paragraphs run together, so this suits single paragraphs best.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--xhtml",
+        value: None,
+        about: "Serialize the output as XHTML: void elements like br close themselves, attribute values are quoted, named character references XML doesn't know become numeric ones and stray & and < are escaped. Standalone documents get the XML declaration and namespaces.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--semantic",
+        value: None,
+        about: "Use <em> and <strong> instead of <i> and <b> for emphasis.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--sourcepos",
+        value: None,
+        about: "Add data-sourcepos=\"line:column\" attributes to the block elements telling where in the markdown they started.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--max-output",
+        value: Some("[bytes]"),
+        about: "Give up if the HTML would grow larger than this. In daemon mode the connection is closed without an answer.",
+        commands: &[CONVERT, "daemon", "batch"],
+    },
+    Flag {
+        name: "--timeout",
+        value: Some("[seconds]"),
+        about: "Cancel conversions that are still running this long after the client connected. Conversions are also cancelled when the client hangs up before the answer is ready.",
+        commands: &["daemon"],
+    },
+    Flag {
+        name: "--status",
+        value: None,
+        about: "Sent as the option of a request, answers the state of the daemon instead of HTML, like md2htm status prints it.",
+        commands: &["daemon"],
+    },
+    Flag {
+        name: "--idle-timeout",
+        value: Some("[seconds]"),
+        about: "Close connections that send nothing for this long before the whole request has come, so clients that never hang up don't run the daemon out of file descriptors.",
+        commands: &["daemon"],
+    },
+    Flag {
+        name: "--socket",
+        value: Some("[path]"),
+        about: "Socket to listen when it isn't given as the argument, or of the daemon to send the requests of a load test or of the status to. Defaults to {sock}.",
+        commands: &["daemon", "loadtest", "status"],
+    },
+    Flag {
+        name: "--datagram",
+        value: Some("[path]"),
+        about: "Also answer requests sent as datagrams to this socket, one datagram each way, so small snippets don't need a connection. The datagrams are framed like on the stream socket and can hold at most 64 KiB. Dropped requests are answered with an empty datagram.",
+        commands: &["daemon"],
+    },
+    Flag {
+        name: "--max-size",
+        value: Some("[bytes]"),
+        about: "Drop the messages with more markdown than this without reading them.",
+        commands: &["daemon"],
+    },
+    Flag {
+        name: "--workers",
+        value: Some("[threads]"),
+        about: "Serve the connections with this many threads, converting at most that many documents at once. Without it every connection gets a thread of its own.",
+        commands: &["daemon"],
+    },
+    Flag {
+        name: "--max-connections",
+        value: Some("[connections]"),
+        about: "Answer busy to the connections over this many being answered or waiting for a worker, instead of queueing them.",
+        commands: &["daemon"],
+    },
+    Flag {
+        name: "--log",
+        value: Some("[level]"),
+        about: "What is logged to stderr: off, error for failed connections or info for every conversion too. Defaults to error.",
+        commands: &["daemon"],
+    },
+    Flag {
+        name: "--access-log",
+        value: Some("[path]"),
+        about: "Write a line for every request into this file: the time, the socket of the client, the bytes in and out, the duration and how the request ended.",
+        commands: &["daemon"],
+    },
+    Flag {
+        name: "--access-log-size",
+        value: Some("[bytes]"),
+        about: "Rotate the access log when it would grow larger than this, keeping five old files as .1 to .5 after its path. Defaults to 10 MiB.",
+        commands: &["daemon"],
+    },
+    Flag {
+        name: "--chunked",
+        value: None,
+        about: "Answer with the HTML in chunks, each prefixed with its length, and an empty chunk after the last one. The start of a large document is sent while the rest is still converted. Excerpts and standalone documents come in one chunk.",
+        commands: &["daemon"],
+    },
+    Flag {
+        name: "--file",
+        value: Some("[path]"),
+        about: "Markdown file the load test sends.",
+        commands: &["loadtest"],
+    },
+    Flag {
+        name: "--clients",
+        value: Some("[clients]"),
+        about: "How many clients send requests at once. Defaults to 4.",
+        commands: &["loadtest"],
+    },
+    Flag {
+        name: "--duration",
+        value: Some("[seconds]"),
+        about: "How long the load test runs. Defaults to 10 seconds.",
+        commands: &["loadtest"],
+    },
+    Flag {
+        name: "--reference",
+        value: Some("[command]"),
+        about: "Command converting the markdown of its stdin for comparison, with its arguments. Defaults to cmark.",
+        commands: &["xtest"],
+    },
+    Flag {
+        name: "--excerpt",
+        value: None,
+        about: "Only convert the part before a <!-- more --> line, or the first paragraph without one.",
+        commands: &[CONVERT],
+    },
+    Flag {
+        name: "--standalone",
+        value: None,
+        about: "Add the root tags making the output a whole HTML document. The title, description, image and url of the front matter between --- lines at the start go into the head as the title and the OpenGraph and Twitter tags, the title defaulting to the first top level heading and the description to the excerpt.",
+        commands: &[CONVERT],
+    },
+    Flag {
+        name: "--theme",
+        value: Some("[theme]"),
+        about: "Style standalone documents with the built-in light, dark or print theme. The stylesheet is written next to the output as md2htm-[theme].css and linked, and the daemon inlines it.",
+        commands: &[CONVERT],
+    },
+    Flag {
+        name: "--inline-theme",
+        value: None,
+        about: "Put the stylesheet of the theme into the head of the document instead of a file next to it.",
+        commands: &[CONVERT],
+    },
+    Flag {
+        name: "--eol",
+        value: Some("[eol]"),
+        about: "Write the line breaks of the HTML as lf or crlf, whatever the markdown had. Defaults to preserve, which keeps the line breaks of the markdown and writes lf after the tags.",
+        commands: &[CONVERT],
+    },
+    Flag {
+        name: "--force-binary",
+        value: None,
+        about: "Convert input that looks like a binary file: NUL bytes, or a start that is over a tenth control characters or invalid UTF-8. Without this the converter refuses it with an error and the daemon answers binary.",
+        commands: &[CONVERT, "daemon"],
+    },
+    Flag {
+        name: "--inject-head",
+        value: Some("[file]"),
+        about: "Add the contents of the file to the end of the head of standalone documents, like the loader of a highlighter. The preview server sends it with every preview as head.",
+        commands: &[CONVERT, "preview", "daemon"],
+    },
+    Flag {
+        name: "--inject-body",
+        value: Some("[file]"),
+        about: "Add the contents of the file to the end of the body of standalone documents, like analytics. The preview server sends it with every preview as body.",
+        commands: &[CONVERT, "preview", "daemon"],
+    },
+    Flag {
+        name: "--nonce",
+        value: Some("[nonce]"),
+        about: "Give the script and style tags of the injected files and the inlined theme the nonce, so a Content-Security-Policy allowing it runs them.",
+        commands: &[CONVERT, "preview", "daemon"],
+    },
+    Flag {
+        name: "--threads",
+        value: Some("[threads]"),
+        about: "Split documents of hundreds of kilobytes and more between top-level blocks and parse the parts on this many threads. The HTML is the same as without it.",
+        commands: &[CONVERT],
+    },
+    Flag {
+        name: "--stats",
+        value: None,
+        about: "Print the word count and the reading time. With --standalone they are also added to the document as <meta> tags.",
+        commands: &[CONVERT],
+    },
+    Flag {
+        name: "--heading-ids",
+        value: None,
+        about: "Give the headings ids generated from their text.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--epub",
+        value: None,
+        about: "Write an EPUB chapter: the output is XHTML with ids on the headings and defaults to the .xhtml extension, and a .nav.xhtml file next to it has the toc nav of the headings and the landmarks nav of the chapter for the EPUB packer.",
+        commands: &[CONVERT],
+    },
+    Flag {
+        name: "--heading-map",
+        value: None,
+        about: "Write the text, id, line and level of every heading into a .headings.json file next to the output file.",
+        commands: &[CONVERT],
+    },
+    Flag {
+        name: "--source-map",
+        value: None,
+        about: "Write the byte ranges of every markdown line and the HTML written for it into a .map.json file next to the output file. The ranges don't include the root tags of --standalone.",
+        commands: &[CONVERT],
+    },
+    Flag {
+        name: "--trace-parser",
+        value: Some("[file]"),
+        about: "Log every state transition of the parser with the byte and position causing it into the file as JSON lines. Setting MD2HTM_TRACE=1 does the same into a .trace.jsonl file next to the output file.",
+        commands: &[CONVERT],
+    },
+    Flag {
+        name: "--number-headings",
+        value: None,
+        about: "Prefix the headings with hierarchical numbers like 1., 1.1 and 1.1.1. The numbers are included in the heading ids.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--dialect",
+        value: Some("[name]"),
+        about: "Start from the extensions, emphasis tags, code blocks and url encoding of a dialect: commonmark, gfm or legacy. With auto the dialect is guessed for each document from pipe tables, strikethrough and task lists, which are gfm, and setext headings, which are commonmark. The other options adjust the dialect wherever they are given. Defaults to legacy.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--enable",
+        value: Some("[extensions]"),
+        about: "Enable the comma separated syntax extensions.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--disable",
+        value: Some("[extensions]"),
+        about: "Disable the comma separated syntax extensions. Known extensions: {extensions}. Enabled by default: underline.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--allow",
+        value: Some("[codes]"),
+        about: "Leave out the diagnostics with the comma separated codes.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--warn",
+        value: Some("[codes]"),
+        about: "Report the diagnostics with the codes as warnings, which is the default.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--deny",
+        value: Some("[codes]"),
+        about: "Report the diagnostics with the codes as errors and exit with status 1 after writing the output. Known codes: {codes}.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--indented",
+        value: Some("[what]"),
+        about: "What lines starting with spaces become: div wraps them in <div class=\"intend\">, code makes code blocks of lines indented 4 columns or more and join continues the previous paragraph. Indented lines right after a paragraph continue it with code too. Defaults to div.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--invalid-utf8",
+        value: Some("[what]"),
+        about: "What is done with markdown that isn't valid UTF-8: keep writes the bytes as they are, replace writes U+FFFD in place of them with an invalid-utf8 warning and reject writes nothing but an invalid-utf8 error. Defaults to keep.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--max-link-length",
+        value: Some("[bytes]"),
+        about: "Cut the text and the address of links and images short at this many bytes with a link-truncated warning. Defaults to 65536.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--url-encoding",
+        value: None,
+        about: "Percent-encode the characters that can't be in the addresses of links and images, like spaces and non-ASCII letters, unless they are already. On by default with the commonmark and gfm dialects.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--no-url-encoding",
+        value: None,
+        about: "Write the addresses of links and images as they were written, which the legacy dialect does by default.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--punycode",
+        value: None,
+        about: "Write the hosts of addresses with letters outside ASCII in Punycode, like xn--bcher-kva.example for bücher.example, as browsers send them.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--table-align",
+        value: Some("[how]"),
+        about: "How the alignment of table columns is written on the cells: attribute writes align=\"left\", style writes style=\"text-align: left\" and class writes class=\"align-left\" with the class prefix. Defaults to attribute.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--list-numbering",
+        value: Some("[how]"),
+        about: "How the items of ordered lists are numbered: normalize counts up from the number of the first item, respect keeps the numbers written in the markdown with value attributes on the items that don't follow the one before. Defaults to normalize.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--code-blocks",
+        value: Some("[what]"),
+        about: "What code blocks become: div writes <div class=\"code\"> blocks and pre writes <pre><code class=\"language-x\"> blocks with escaped code. Defaults to div, or pre with the commonmark and gfm dialects.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--dedent-code",
+        value: None,
+        about: "Leave out the indentation common to all lines of fenced code blocks.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--expand-tabs",
+        value: Some("[width]"),
+        about: "Replace the tabs in fenced code blocks with spaces up to the next multiple of the width.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--missing-alt",
+        value: Some("[what]"),
+        about: "What to do with images without alt text: ignore them, warn about them or make the alt text from the filename. Defaults to ignore.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--warn-duplicate-ids",
+        value: None,
+        about: "Warn about headings whose ids were already taken. Those get a number added either way, like setup-1.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--toc",
+        value: None,
+        about: "Generate a table of contents linking to the headings.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--placement",
+        value: Some("[where]"),
+        about: "Where the table of contents is written: end, after-first-heading or marker, which replaces a line containing only [TOC]. Defaults to end.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--permalinks",
+        value: Some("[style]"),
+        about: "Link the headings to themselves: anchor adds <a class=\"anchor\" href=\"#id\">#</a> after the heading text and wrap makes the heading text the link. Defaults to none.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--slug",
+        value: Some("[github|ascii]"),
+        about: "How the heading ids are generated. github keeps letters of any script, ascii replaces accented letters with plain ones and drops the rest. Defaults to github.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--slug-prefix",
+        value: Some("[text]"),
+        about: "Add the text before every heading id.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--slug-suffix",
+        value: Some("[text]"),
+        about: "Add the text after every heading id.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--obfuscate-emails",
+        value: None,
+        about: "Write the addresses of mailto links as character references to keep them from simple address scrapers.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--class-prefix",
+        value: Some("[prefix]"),
+        about: "Add the prefix to the class names of the output, like md-code and md-intend, so they don't collide with the classes of the site the HTML is embedded in. The language- classes of code blocks are kept as they are.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--external-class",
+        value: Some("[class]"),
+        about: "Add the class to the links pointing to other sites.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--site",
+        value: Some("[host]"),
+        about: "Host name of the site, like example.com. Links to it don't get the --external-class.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--base-url",
+        value: Some("[url]"),
+        about: "Add the url in front of the relative link and image urls, like https://example.com/docs/.",
+        commands: ALL,
+    },
+    Flag {
+        name: "--validate",
+        value: None,
+        about: "Check that the tags of the HTML are balanced and its attribute values quoted, and report where they aren't. Raw HTML passes through as is without --safe, so it's checked too.",
+        commands: &[CONVERT, "build"],
+    },
+    Flag {
+        name: "--check-reproducible",
+        value: None,
+        about: "Build everything a second time into a temporary directory and exit with status 1 if any file differs.",
+        commands: &["build"],
+    },
+    Flag {
+        name: "--drafts",
+        value: None,
+        about: "Build the pages with draft: true in their front matter too.",
+        commands: &["build"],
+    },
+    Flag {
+        name: "--search-index",
+        value: None,
+        about: "Write the url, title, headings and plain text of every page into search-index.json in the output directory, for client-side search libraries like lunr or fuse. The headings get ids for linking to them.",
+        commands: &["build"],
+    },
+];
+
+/// Examples shown in the general help, with what they do
+const EXAMPLES: &[(&str, &str)] = &[
+    (
+        "To parse a file named markdown.md into webpage.html, when both are in local directory:",
+        "md2htm markdown.md webpage.html",
+    ),
+    (
+        "To parse file named markdown.md into markdown.html, when source file is in local directory:",
+        "md2htm markdown.md",
+    ),
+    (
+        "To parse untrusted input with semantic emphasis tags:",
+        "md2htm --safe --semantic comment.md",
+    ),
+    (
+        "To build a site from the pages directory into the public directory:",
+        "md2htm build pages public",
+    ),
+    (
+        "To run the daemon on a different socket:",
+        "md2htm daemon /tmp/md2htm.sock",
+    ),
+];
+
+/// Finds the option by its name without the dashes. Returns whether it takes
+/// a value.
+pub(crate) fn flag(name: &str) -> Option<bool> {
+    flags()
+        .find(|(flag, _)| *flag == name)
+        .map(|(_, value)| value)
+}
+
+/// Names of the options without the dashes and whether they take a value
+pub(crate) fn flags() -> impl Iterator<Item = (&'static str, bool)> {
+    FLAGS
+        .iter()
+        .map(|flag| (&flag.name[2..], flag.value.is_some()))
+}
+
+/// Finds the command by its name or an alias
+pub(crate) fn command(name: &str) -> Option<&'static Command> {
+    COMMANDS
+        .iter()
+        .find(|command| command.name == name || command.aliases.contains(&name))
+}
+
+/// Writes the general help with the commands and the options of converting
+/// a single file
+pub(crate) fn general() -> String {
+    let mut help: String = String::new();
+
+    help.push_str(tr!(
+        "Usage: md2htm [options] [command|source file] [[arguments]]"
+    ));
+    help.push_str("\n\n");
+    paragraph(&mut help, translate(COMMANDS[0].about));
+    help.push_str("\n    ");
+    help.push_str(tr!("Commands:"));
+    help.push_str("\n\n");
+
+    for command in &COMMANDS[1..] {
+        let mut names: String = String::from(command.name);
+
+        for alias in command.aliases {
+            names.push_str(", ");
+            names.push_str(alias);
+        }
+
+        entry(&mut help, &names, translate(command.summary));
+    }
+
+    help.push_str("\n    ");
+    help.push_str(tr!(
+        "Run md2htm [command] --help for the options of the command."
+    ));
+    help.push_str("\n\n");
+    options(&mut help, CONVERT);
+    help.push_str("\n    ");
+    help.push_str(tr!("Examples:"));
+    help.push('\n');
+
+    for (about, example) in EXAMPLES {
+        help.push_str("\n    ");
+        help.push_str(translate(about));
+        help.push_str("\n    ");
+        help.push_str(example);
+        help.push('\n');
+    }
+
+    help.push_str("\n    ");
+    help.push_str(tr!(
+        "Bugs and issues should be reported in https://github.com/rronkkeli/md2htm"
+    ));
+
+    help
+}
+
+/// Writes the help of the command with the options it takes
+pub(crate) fn of(command: &Command) -> String {
+    let mut help: String = String::new();
+    let mut usage: String = String::from("md2htm");
+
+    if command.name != CONVERT {
+        usage.push(' ');
+        usage.push_str(command.name);
+    }
+
+    usage.push(' ');
+    usage.push_str(tr!("[options]"));
+
+    if !command.usage.is_empty() {
+        usage.push(' ');
+        usage.push_str(translate(command.usage));
+    }
+
+    help.push_str(&tr!("Usage: {}", usage));
+    help.push_str("\n\n");
+    paragraph(&mut help, translate(command.about));
+
+    if !matches!(command.name, "help" | "config" | "selftest") {
+        help.push('\n');
+        options(&mut help, command.name);
+    }
+
+    help
+}
+
+fn options(help: &mut String, command: &str) {
+    help.push_str("    ");
+    help.push_str(tr!("Options:"));
+    help.push_str("\n\n");
+
+    for flag in FLAGS.iter().filter(|flag| flag.commands.contains(&command)) {
+        let name: String = match flag.value {
+            Some(value) => format!("{} {}", flag.name, translate(value)),
+            None => flag.name.to_string(),
+        };
+
+        entry(help, &name, translate(flag.about));
+    }
+}
+
+/// Writes the name with the text wrapped in the column next to it
+fn entry(help: &mut String, name: &str, text: &str) {
+    let mut line: String = format!("    {name}");
+
+    for (i, row) in wrap(&placeholders(text), WIDTH - INDENT).iter().enumerate() {
+        if i == 0 && line.chars().count() < INDENT {
+            line.push_str(&" ".repeat(INDENT - line.chars().count()));
+        } else {
+            help.push_str(line.trim_end());
+            help.push('\n');
+            line = " ".repeat(INDENT);
+        }
+
+        line.push_str(row);
+    }
+
+    help.push_str(line.trim_end());
+    help.push_str("\n\n");
+}
+
+/// Writes the text wrapped and indented like the descriptions
+fn paragraph(help: &mut String, text: &str) {
+    for row in wrap(&placeholders(text), WIDTH - 4) {
+        help.push_str("    ");
+        help.push_str(&row);
+        help.push('\n');
+    }
+}
+
+/// Fills in the lists and paths that aren't known when writing the texts
+fn placeholders(text: &str) -> String {
+    text.replace("{sock}", SOCK)
+        .replace("{system}", config::SYSTEM)
+        .replace(
+            "{user}",
+            &config::user_path().map_or_else(
+                || "~/.config/md2htm/config.toml".to_string(),
+                |path| path.display().to_string(),
+            ),
+        )
+        .replace("{extensions}", &extension_names())
+        .replace("{codes}", &code_names())
+}
+
+/// Splits the text into rows of at most `width` characters at spaces
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut rows: Vec<String> = Vec::new();
+    let mut row: String = String::new();
+
+    for word in text.split(' ') {
+        if !row.is_empty() && row.chars().count() + 1 + word.chars().count() > width {
+            rows.push(std::mem::take(&mut row));
+        }
+
+        if !row.is_empty() {
+            row.push(' ');
+        }
+
+        row.push_str(word);
+    }
+
+    rows.push(row);
+    rows
+}
@@ -0,0 +1,790 @@
+//! Translations of the messages the CLI prints. The language is picked from
+//! `LC_ALL`, `LC_MESSAGES` or `LANG` like gettext does, and the English text
+//! is used for anything that isn't translated.
+
+use md2htm::{Code, Diagnostic};
+use std::{env, fmt::Display, sync::OnceLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Lang {
+    En,
+    Fi,
+}
+
+impl Lang {
+    /// Reads the first of the locale variables that is set, like `fi_FI.UTF-8`
+    fn from_env() -> Lang {
+        let locale: String = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+            .unwrap_or_default();
+
+        match locale.split(['_', '.', '@']).next() {
+            Some("fi") => Lang::Fi,
+            _ => Lang::En,
+        }
+    }
+}
+
+/// Language of the messages, read from the environment once
+pub(crate) fn lang() -> Lang {
+    static LANG: OnceLock<Lang> = OnceLock::new();
+    *LANG.get_or_init(Lang::from_env)
+}
+
+/// Translates the message, or gives the English text back
+pub(crate) fn translate(english: &'static str) -> &'static str {
+    let catalog: &[(&str, &str)] = match lang() {
+        Lang::En => return english,
+        Lang::Fi => FI,
+    };
+
+    catalog
+        .iter()
+        .find(|(key, _)| *key == english)
+        .map_or(english, |(_, translated)| translated)
+}
+
+/// Fills the `{}` placeholders of the template with the args in order
+pub(crate) fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut text: String = String::with_capacity(template.len());
+    let mut parts = template.split("{}");
+    let mut args = args.iter();
+
+    text.push_str(parts.next().unwrap_or_default());
+
+    for part in parts {
+        if let Some(arg) = args.next() {
+            text.push_str(&arg.to_string());
+        }
+
+        text.push_str(part);
+    }
+
+    text
+}
+
+/// Translates the message and fills in the args, like
+/// `tr!("{} expects the text to add.", arg)`
+macro_rules! tr {
+    ($text:literal) => {
+        $crate::i18n::translate($text)
+    };
+    ($text:literal, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::fill($crate::i18n::translate($text), &[$(&$arg),+])
+    };
+}
+
+pub(crate) use tr;
+
+/// Message of the diagnostic in the language of the messages
+pub(crate) fn message(diagnostic: &Diagnostic) -> String {
+    let template: &str = match lang() {
+        Lang::En => return diagnostic.message.clone(),
+        Lang::Fi => diagnostic_fi(diagnostic.code),
+    };
+
+    let args: Vec<&dyn Display> = diagnostic
+        .args
+        .iter()
+        .map(|arg| arg as &dyn Display)
+        .collect();
+
+    fill(template, &args)
+}
+
+/// The diagnostic like its `Display` writes it, in the language of the messages
+pub(crate) fn describe(diagnostic: &Diagnostic) -> String {
+    tr!(
+        "line {} column {}: {} [{}]",
+        diagnostic.position.line,
+        diagnostic.position.column,
+        message(diagnostic),
+        diagnostic.code.name()
+    )
+}
+
+fn diagnostic_fi(code: Code) -> &'static str {
+    match code {
+        Code::EmptyHeader => "Tyhjä otsikko? Oikeasti??",
+        Code::HeaderLevel => {
+            "Yritettiin ylittää HTML:n otsikkotaso 6. Ylimääräiset otsikkomerkit ohitetaan.."
+        }
+        Code::CodeBlockState => {
+            "Odottamaton koodilohkon tila! Määrittelemätöntä käytöstä voi esiintyä! \
+             Vahinkoja yritetään rajata ohittamalla edellinen merkki.."
+        }
+        Code::LinkState => "Odottamaton linkin tila. Näin ei pitäisi käydä.",
+        Code::CodeNewline => "Odottamaton rivinvaihto koodin keskellä.",
+        Code::FenceLength => "Odottamaton määrä koodilohkon merkkejä. Tarkoititko kolmea?",
+        Code::LinkNewline => {
+            "Rivinvaihtoja linkeissä ja kuvissa ei tueta. Tämä voi rikkoa tekstisi."
+        }
+        Code::LiteralStar => {
+            "Escapeamaton `*` lihavoidun tekstin keskellä. Se jäsennetään tavallisena merkkinä.."
+        }
+        Code::ListSpace => "Odottamaton merkki, kun odotettiin välilyöntiä",
+        Code::HeadingLevelSkip => "Otsikkotaso hyppää tasolta h{} tasolle h{}.",
+        Code::EmptyHeading => "Otsikossa ei ole tekstiä.",
+        Code::MissingAlt => "Kuvalla ei ole vaihtoehtoista tekstiä.",
+        Code::VagueLink => "Linkin teksti ei kerro, minne linkki vie.",
+        Code::DuplicateId => "Otsikon tunniste {} on jo käytössä. Käytetään tunnistetta {}.",
+        Code::BrokenFragment => "Linkki kohteeseen #{} ei vastaa mitään otsikon tunnistetta.",
+        Code::MissingTocMarker => {
+            "[TOC]-merkintää ei löytynyt. Sisällysluettelo kirjoitetaan loppuun."
+        }
+        Code::InvalidUtf8 => "Tavu {} ei ole kelvollista UTF-8:aa.",
+        Code::LinkTruncated => "Linkin teksti tai osoite on yli {} tavua pitkä. Se katkaistaan.",
+    }
+}
+
+/// Finnish translations keyed by the English text
+const FI: &[(&str, &str)] = &[
+    ("line {} column {}: {} [{}]", "rivi {} sarake {}: {} [{}]"),
+    ("Warning on {}", "Varoitus: {}"),
+    ("Error on {}", "Virhe: {}"),
+    ("Target parsed!", "Kohde jäsennetty!"),
+    ("{} words, {} min read.", "{} sanaa, lukuaika {} min."),
+    (
+        "Couldn't parse the target: {}",
+        "Kohdetta ei voitu jäsentää: {}",
+    ),
+    (
+        "The HTML isn't well-formed at {}",
+        "HTML ei ole hyvin muodostettua kohdassa {}",
+    ),
+    (
+        "{}: The HTML isn't well-formed at {}",
+        "{}: HTML ei ole hyvin muodostettua kohdassa {}",
+    ),
+    (
+        "No accessibility problems found in {}.",
+        "Saavutettavuusongelmia ei löytynyt tiedostosta {}.",
+    ),
+    (
+        "No problems found in {}.",
+        "Ongelmia ei löytynyt tiedostosta {}.",
+    ),
+    (
+        "Expected at least one argument!",
+        "Odotettiin vähintään yhtä argumenttia!",
+    ),
+    (
+        "Too many arguments! Expected at most 2.",
+        "Liikaa argumentteja! Odotettiin enintään kahta.",
+    ),
+    (
+        "Accessibility audit takes one argument, the source file.",
+        "Saavutettavuustarkastus ottaa yhden argumentin, lähdetiedoston.",
+    ),
+    (
+        "Check takes one argument, the source file.",
+        "Tarkistus ottaa yhden argumentin, lähdetiedoston.",
+    ),
+    (
+        "Build mode takes two arguments, the source and output directories.",
+        "Koontitila ottaa kaksi argumenttia, lähde- ja kohdehakemiston.",
+    ),
+    (
+        "Preview mode takes no arguments.",
+        "Esikatselutila ei ota argumentteja.",
+    ),
+    (
+        "Daemon mode takes at most one argument, the socket path.",
+        "Taustaprosessitila ottaa enintään yhden argumentin, soketin polun.",
+    ),
+    (
+        "--dialect expects auto, commonmark, gfm or legacy.",
+        "--dialect odottaa arvoa auto, commonmark, gfm tai legacy.",
+    ),
+    (
+        "--trace-parser expects the file to write the log into.",
+        "--trace-parser odottaa tiedostoa, johon loki kirjoitetaan.",
+    ),
+    (
+        "{} expects a comma separated list of extensions.",
+        "{} odottaa pilkuilla eroteltua luetteloa laajennuksista.",
+    ),
+    (
+        "Unknown extension {}. Known extensions are: {}.",
+        "Tuntematon laajennus {}. Tunnetut laajennukset: {}.",
+    ),
+    (
+        "{} expects a comma separated list of diagnostic codes.",
+        "{} odottaa pilkuilla eroteltua luetteloa diagnostiikkakoodeista.",
+    ),
+    (
+        "Unknown diagnostic code {}. Known codes are: {}.",
+        "Tuntematon diagnostiikkakoodi {}. Tunnetut koodit: {}.",
+    ),
+    (
+        "--indented expects div, code or join.",
+        "--indented odottaa arvoa div, code tai join.",
+    ),
+    (
+        "--expand-tabs expects the tab width in columns.",
+        "--expand-tabs odottaa sarkaimen leveyttä sarakkeina.",
+    ),
+    (
+        "--code-blocks expects div or pre.",
+        "--code-blocks odottaa arvoa div tai pre.",
+    ),
+    (
+        "--missing-alt expects ignore, warn or filename.",
+        "--missing-alt odottaa arvoa ignore, warn tai filename.",
+    ),
+    (
+        "--placement expects end, after-first-heading or marker.",
+        "--placement odottaa arvoa end, after-first-heading tai marker.",
+    ),
+    (
+        "--permalinks expects none, anchor or wrap.",
+        "--permalinks odottaa arvoa none, anchor tai wrap.",
+    ),
+    (
+        "--slug expects either github or ascii.",
+        "--slug odottaa arvoa github tai ascii.",
+    ),
+    (
+        "{} expects the text to add.",
+        "{} odottaa lisättävää tekstiä.",
+    ),
+    (
+        "--external-class expects the class name.",
+        "--external-class odottaa luokan nimeä.",
+    ),
+    (
+        "--site expects the host name of the site.",
+        "--site odottaa sivuston palvelinnimeä.",
+    ),
+    (
+        "--timeout expects a positive number of seconds.",
+        "--timeout odottaa positiivista sekuntimäärää.",
+    ),
+    (
+        "--max-output expects the number of bytes.",
+        "--max-output odottaa tavumäärää.",
+    ),
+    (
+        "Built {} pages and copied {} assets into {}.",
+        "Koottiin {} sivua ja kopioitiin {} liitetiedostoa hakemistoon {}.",
+    ),
+    (
+        "Skipped {} drafts. Build them too with --drafts.",
+        "Ohitettiin {} luonnosta. Kokoa nekin valitsimella --drafts.",
+    ),
+    ("{} pages have errors.", "Virheellisiä sivuja: {}."),
+    ("{} differs between builds.", "{} eroaa koontien välillä."),
+    (
+        "A second build produced identical files.",
+        "Toinen koonti tuotti samat tiedostot.",
+    ),
+    (
+        "{}: Not copying {}, because it is outside the source directory.",
+        "{}: Tiedostoa {} ei kopioida, koska se on lähdehakemiston ulkopuolella.",
+    ),
+    (
+        "{}: Couldn't copy {}: {}",
+        "{}: Tiedostoa {} ei voitu kopioida: {}",
+    ),
+    // Help
+    ("Unknown command {}.", "Tuntematon komento {}."),
+    (
+        "[source file] [[output file]]",
+        "[lähdetiedosto] [[kohdetiedosto]]",
+    ),
+    (
+        "Convert the source file into HTML. This is what runs without a command.",
+        "Muunna lähdetiedosto HTML:ksi. Tämä suoritetaan, kun komentoa ei anneta.",
+    ),
+    (
+        "Parses Markdown to HTML without adding any of the root tags, unless --standalone is given. The source file doesn't need the extension .md or any other. If the output file is omitted, the path of the source file is used with its extension replaced with .html, or .html appended.",
+        "Muuntaa Markdownin HTML:ksi lisäämättä juuritageja, ellei --standalone ole annettu. Lähdetiedosto ei tarvitse päätettä .md tai muutakaan. Jos kohdetiedosto puuttuu, käytetään lähdetiedoston polkua niin, että sen pääte korvataan päätteellä .html tai .html lisätään perään.",
+    ),
+    (
+        "[source file]",
+        "[lähdetiedosto]",
+    ),
+    (
+        "Report accessibility problems in the source file.",
+        "Raportoi lähdetiedoston saavutettavuusongelmat.",
+    ),
+    (
+        "Report accessibility problems in the source file: skipped heading levels, empty headings, images without alt text and links with texts like \"here\". Exits with status 1 if any are found.",
+        "Raportoi lähdetiedoston saavutettavuusongelmat: ohitetut otsikkotasot, tyhjät otsikot, kuvat ilman vaihtoehtoista tekstiä ja linkit, joiden teksti on kuten \"tästä\". Päättyy tilaan 1, jos ongelmia löytyy.",
+    ),
+    (
+        "Report problems in the source file.",
+        "Raportoi lähdetiedoston ongelmat.",
+    ),
+    (
+        "Report problems in the source file, like links to #fragments that don't match any heading id. Exits with status 1 if any are found.",
+        "Raportoi lähdetiedoston ongelmat, kuten linkit #kohteisiin, jotka eivät vastaa mitään otsikon tunnistetta. Päättyy tilaan 1, jos ongelmia löytyy.",
+    ),
+    (
+        "[source] [output]",
+        "[lähde] [kohde]",
+    ),
+    (
+        "Convert a directory of markdown files into a directory of HTML files.",
+        "Muunna hakemistollinen markdown-tiedostoja hakemistolliseksi HTML-tiedostoja.",
+    ),
+    (
+        "Convert every .md file in the source directory and its subdirectories into an .html file in the output directory. Images with relative paths are copied under assets in the output directory and the pages are rewritten to use them. Front matter between --- lines at the start of the files is left out.",
+        "Muunna jokainen lähdehakemiston ja sen alihakemistojen .md-tiedosto .html-tiedostoksi kohdehakemistoon. Kuvat, joilla on suhteellinen polku, kopioidaan kohdehakemiston assets-hakemistoon ja sivut kirjoitetaan käyttämään niitä. Tiedostojen alussa ---rivien välissä oleva esittelyosa jätetään pois.",
+    ),
+    (
+        "Start a preview server for editors.",
+        "Käynnistä esikatselupalvelin editoreille.",
+    ),
+    (
+        "Start a preview server for editors. It speaks JSON-RPC over stdio like a language server, takes the documents with didOpen and didChange and answers with md2htm/preview notifications holding the HTML and with publishDiagnostics.",
+        "Käynnistä esikatselupalvelin editoreille. Se puhuu JSON-RPC:tä stdion yli kuten kielipalvelin, ottaa dokumentit didOpen- ja didChange-viesteillä ja vastaa md2htm/preview-ilmoituksilla, joissa on HTML, sekä publishDiagnostics-ilmoituksilla.",
+    ),
+    (
+        "[[socket path]]",
+        "[[soketin polku]]",
+    ),
+    (
+        "Convert markdown sent through a unix domain socket.",
+        "Muunna unix-soketin kautta lähetettyä markdownia.",
+    ),
+    (
+        "Start the program in daemon mode that listens a socket in {sock}. An alternative socket path may be given as the only argument. The options can also be given as environment variables, like MD2HTM_SOCKET, MD2HTM_MAX_SIZE, MD2HTM_WORKERS and MD2HTM_LOG. A request can override the options of the daemon by starting with the length of its options with the highest bit set, followed by the options as key = value lines like in the config files. md2htm daemon --print-unit prints a systemd unit running the daemon with the options given. A socket passed by systemd socket activation is listened instead of the path. With the systemd feature SIGHUP starts the executable again with the socket, and the old daemon stops after answering the connections it accepted, so upgrades don't drop any clients. If the program doesn't have sufficient privileges to remove the socket file, it can be removed manually with: sudo rm {sock}",
+        "Käynnistä ohjelma taustaprosessina, joka kuuntelee sokettia {sock}. Toisen soketin polun voi antaa ainoana argumenttina. Valinnat voi antaa myös ympäristömuuttujina, kuten MD2HTM_SOCKET, MD2HTM_MAX_SIZE, MD2HTM_WORKERS ja MD2HTM_LOG. Pyyntö voi ohittaa taustaprosessin valinnat alkamalla valintojensa pituudella, jonka ylin bitti on asetettu, ja valinnoilla key = value -riveinä kuten asetustiedostoissa. md2htm daemon --print-unit tulostaa systemd-yksikön, joka käynnistää taustaprosessin annetuilla valinnoilla. Systemd-soketti-aktivoinnin antamaa sokettia kuunnellaan polun sijaan. systemd-ominaisuuden kanssa SIGHUP käynnistää ohjelman uudelleen samalla soketilla, ja vanha taustaprosessi pysähtyy vastattuaan hyväksymiinsä yhteyksiin, joten päivitykset eivät pudota asiakkaita. Jos ohjelmalla ei ole oikeuksia poistaa sokettitiedostoa, sen voi poistaa käsin: sudo rm {sock}",
+    ),
+    (
+        "[[command]]",
+        "[[komento]]",
+    ),
+    (
+        "Show this help, or the help of the command, and exit.",
+        "Näytä tämä ohje tai komennon ohje ja lopeta.",
+    ),
+    (
+        "Show the help of the command and exit. md2htm [command] --help does the same.",
+        "Näytä komennon ohje ja lopeta. md2htm [komento] --help tekee saman.",
+    ),
+    (
+        "Escape raw HTML and drop link and image urls that use other schemes than http, https, mailto or tel. Use this for untrusted input.",
+        "Escapeaa raaka HTML ja pudota linkkien ja kuvien osoitteet, joiden skeema on muu kuin http, https, mailto tai tel. Käytä tätä epäluotettavalle syötteelle.",
+    ),
+    (
+        "Use <em> and <strong> instead of <i> and <b> for emphasis.",
+        "Käytä korostukseen tageja <em> ja <strong> tagien <i> ja <b> sijaan.",
+    ),
+    (
+        "Add data-sourcepos=\"line:column\" attributes to the block elements telling where in the markdown they started.",
+        "Lisää lohkoelementteihin data-sourcepos=\"rivi:sarake\"-attribuutit, jotka kertovat, mistä kohtaa markdownia ne alkoivat.",
+    ),
+    (
+        "[bytes]",
+        "[tavut]",
+    ),
+    (
+        "Give up if the HTML would grow larger than this. In daemon mode the connection is closed without an answer.",
+        "Luovuta, jos HTML kasvaisi tätä suuremmaksi. Taustaprosessina yhteys suljetaan vastaamatta.",
+    ),
+    (
+        "[seconds]",
+        "[sekunnit]",
+    ),
+    (
+        "Cancel conversions that are still running this long after the client connected. Conversions are also cancelled when the client hangs up before the answer is ready.",
+        "Peru muunnokset, jotka ovat vielä käynnissä näin kauan asiakkaan yhdistämisen jälkeen. Muunnokset perutaan myös, jos asiakas katkaisee yhteyden ennen vastausta.",
+    ),
+    (
+        "Only convert the part before a <!-- more --> line, or the first paragraph without one.",
+        "Muunna vain <!-- more -->-riviä edeltävä osa, tai ilman sitä ensimmäinen kappale.",
+    ),
+    (
+        "Add the root tags making the output a whole HTML document. The title, description, image and url of the front matter between --- lines at the start go into the head as the title and the OpenGraph and Twitter tags, the title defaulting to the first top level heading and the description to the excerpt.",
+        "Lisää juuritagit, jotka tekevät tulosteesta kokonaisen HTML-dokumentin. Alun ---rivien välisen etumateriaalin title, description, image ja url menevät head-osaan otsikoksi sekä OpenGraph- ja Twitter-tageiksi. Otsikon oletus on ensimmäinen ylimmän tason otsikko ja kuvauksen ote.",
+    ),
+    (
+        "Print the word count and the reading time. With --standalone they are also added to the document as <meta> tags.",
+        "Tulosta sanamäärä ja lukuaika. Valinnan --standalone kanssa ne lisätään dokumenttiin myös <meta>-tageina.",
+    ),
+    (
+        "Give the headings ids generated from their text.",
+        "Anna otsikoille niiden tekstistä muodostetut tunnisteet.",
+    ),
+    (
+        "Write the text, id, line and level of every heading into a .headings.json file next to the output file.",
+        "Kirjoita jokaisen otsikon teksti, tunniste, rivi ja taso .headings.json-tiedostoon kohdetiedoston viereen.",
+    ),
+    (
+        "Write the byte ranges of every markdown line and the HTML written for it into a .map.json file next to the output file. The ranges don't include the root tags of --standalone.",
+        "Kirjoita jokaisen markdown-rivin ja siitä kirjoitetun HTML:n tavualueet .map.json-tiedostoon kohdetiedoston viereen. Alueet eivät sisällä valinnan --standalone juuritageja.",
+    ),
+    (
+        "[file]",
+        "[tiedosto]",
+    ),
+    (
+        "Log every state transition of the parser with the byte and position causing it into the file as JSON lines. Setting MD2HTM_TRACE=1 does the same into a .trace.jsonl file next to the output file.",
+        "Kirjaa jäsentimen jokainen tilasiirtymä sen aiheuttaneen tavun ja sijainnin kanssa tiedostoon JSON-riveinä. MD2HTM_TRACE=1 tekee saman .trace.jsonl-tiedostoon kohdetiedoston viereen.",
+    ),
+    (
+        "Prefix the headings with hierarchical numbers like 1., 1.1 and 1.1.1. The numbers are included in the heading ids.",
+        "Lisää otsikoiden eteen hierarkkiset numerot kuten 1., 1.1 ja 1.1.1. Numerot tulevat myös otsikoiden tunnisteisiin.",
+    ),
+    (
+        "[name]",
+        "[nimi]",
+    ),
+    (
+        "Start from the extensions, emphasis tags, code blocks and url encoding of a dialect: commonmark, gfm or legacy. With auto the dialect is guessed for each document from pipe tables, strikethrough and task lists, which are gfm, and setext headings, which are commonmark. The other options adjust the dialect wherever they are given. Defaults to legacy.",
+        "Aloita murteen laajennuksista, korostustageista, koodilohkoista ja osoitteiden koodauksesta: commonmark, gfm tai legacy. Arvolla auto murre arvataan kullekin dokumentille putkitaulukoista, yliviivauksista ja tehtävälistoista, jotka ovat gfm:ää, sekä setext-otsikoista, jotka ovat commonmarkia. Muut valinnat muokkaavat murretta missä kohtaa tahansa ne annetaan. Oletus on legacy.",
+    ),
+    (
+        "[extensions]",
+        "[laajennukset]",
+    ),
+    (
+        "Enable the comma separated syntax extensions.",
+        "Ota käyttöön pilkuilla erotellut syntaksilaajennukset.",
+    ),
+    (
+        "Disable the comma separated syntax extensions. Known extensions: {extensions}. Enabled by default: underline.",
+        "Poista käytöstä pilkuilla erotellut syntaksilaajennukset. Tunnetut laajennukset: {extensions}. Oletuksena käytössä: underline.",
+    ),
+    (
+        "[codes]",
+        "[koodit]",
+    ),
+    (
+        "Leave out the diagnostics with the comma separated codes.",
+        "Jätä pois diagnostiikat, joilla on pilkuilla erotellut koodit.",
+    ),
+    (
+        "Report the diagnostics with the codes as warnings, which is the default.",
+        "Raportoi koodien diagnostiikat varoituksina, mikä on oletus.",
+    ),
+    (
+        "Report the diagnostics with the codes as errors and exit with status 1 after writing the output. Known codes: {codes}.",
+        "Raportoi koodien diagnostiikat virheinä ja päätä tilaan 1 tulosteen kirjoittamisen jälkeen. Tunnetut koodit: {codes}.",
+    ),
+    (
+        "[what]",
+        "[mitä]",
+    ),
+    (
+        "What lines starting with spaces become: div wraps them in <div class=\"intend\">, code makes code blocks of lines indented 4 columns or more and join continues the previous paragraph. Indented lines right after a paragraph continue it with code too. Defaults to div.",
+        "Mitä välilyönneillä alkavista riveistä tulee: div käärii ne elementtiin <div class=\"intend\">, code tekee vähintään 4 saraketta sisennetyistä riveistä koodilohkoja ja join jatkaa edellistä kappaletta. Kappaleen perässä olevat sisennetyt rivit jatkavat sitä myös code-arvolla. Oletus on div.",
+    ),
+    (
+        "What code blocks become: div writes <div class=\"code\"> blocks and pre writes <pre><code class=\"language-x\"> blocks with escaped code. Defaults to div, or pre with the commonmark and gfm dialects.",
+        "Mitä koodilohkoista tulee: div kirjoittaa <div class=\"code\">-lohkoja ja pre <pre><code class=\"language-x\">-lohkoja, joiden koodi on escapeattu. Oletus on div, tai pre murteilla commonmark ja gfm.",
+    ),
+    (
+        "Leave out the indentation common to all lines of fenced code blocks.",
+        "Jätä pois aidattujen koodilohkojen kaikille riveille yhteinen sisennys.",
+    ),
+    (
+        "[width]",
+        "[leveys]",
+    ),
+    (
+        "Replace the tabs in fenced code blocks with spaces up to the next multiple of the width.",
+        "Korvaa aidattujen koodilohkojen sarkaimet välilyönneillä leveyden seuraavaan monikertaan asti.",
+    ),
+    (
+        "What to do with images without alt text: ignore them, warn about them or make the alt text from the filename. Defaults to ignore.",
+        "Mitä tehdä kuville ilman vaihtoehtoista tekstiä: ignore ohittaa ne, warn varoittaa niistä ja filename tekee tekstin tiedoston nimestä. Oletus on ignore.",
+    ),
+    (
+        "Warn about headings whose ids were already taken. Those get a number added either way, like setup-1.",
+        "Varoita otsikoista, joiden tunnisteet olivat jo käytössä. Niihin lisätään numero joka tapauksessa, kuten setup-1.",
+    ),
+    (
+        "Generate a table of contents linking to the headings.",
+        "Luo sisällysluettelo, joka linkittää otsikoihin.",
+    ),
+    (
+        "[where]",
+        "[minne]",
+    ),
+    (
+        "Where the table of contents is written: end, after-first-heading or marker, which replaces a line containing only [TOC]. Defaults to end.",
+        "Minne sisällysluettelo kirjoitetaan: end, after-first-heading tai marker, joka korvaa rivin, jolla on vain [TOC]. Oletus on end.",
+    ),
+    (
+        "[style]",
+        "[tyyli]",
+    ),
+    (
+        "Link the headings to themselves: anchor adds <a class=\"anchor\" href=\"#id\">#</a> after the heading text and wrap makes the heading text the link. Defaults to none.",
+        "Linkitä otsikot itseensä: anchor lisää otsikon tekstin perään <a class=\"anchor\" href=\"#id\">#</a> ja wrap tekee otsikon tekstistä linkin. Oletus on none.",
+    ),
+    (
+        "[github|ascii]",
+        "[github|ascii]",
+    ),
+    (
+        "How the heading ids are generated. github keeps letters of any script, ascii replaces accented letters with plain ones and drops the rest. Defaults to github.",
+        "Miten otsikoiden tunnisteet muodostetaan. github säilyttää kaikkien kirjoitusjärjestelmien kirjaimet, ascii korvaa tarkkeelliset kirjaimet tavallisilla ja pudottaa muut. Oletus on github.",
+    ),
+    (
+        "[text]",
+        "[teksti]",
+    ),
+    (
+        "Add the text before every heading id.",
+        "Lisää teksti jokaisen otsikon tunnisteen alkuun.",
+    ),
+    (
+        "Add the text after every heading id.",
+        "Lisää teksti jokaisen otsikon tunnisteen loppuun.",
+    ),
+    (
+        "Write the addresses of mailto links as character references to keep them from simple address scrapers.",
+        "Kirjoita mailto-linkkien osoitteet merkkiviittauksina, jotta yksinkertaiset osoitteiden kerääjät eivät löydä niitä.",
+    ),
+    (
+        "[class]",
+        "[luokka]",
+    ),
+    (
+        "Add the class to the links pointing to other sites.",
+        "Lisää luokka linkkeihin, jotka vievät muille sivustoille.",
+    ),
+    (
+        "[host]",
+        "[palvelin]",
+    ),
+    (
+        "Host name of the site, like example.com. Links to it don't get the --external-class.",
+        "Sivuston palvelinnimi, kuten example.com. Sinne vievät linkit eivät saa luokkaa --external-class.",
+    ),
+    (
+        "Check that the tags of the HTML are balanced and its attribute values quoted, and report where they aren't. Raw HTML passes through as is without --safe, so it's checked too.",
+        "Tarkista, että HTML:n tagit ovat tasapainossa ja attribuuttien arvot lainausmerkeissä, ja raportoi, missä eivät ole. Raaka HTML menee läpi sellaisenaan ilman valintaa --safe, joten sekin tarkistetaan.",
+    ),
+    (
+        "Build everything a second time into a temporary directory and exit with status 1 if any file differs.",
+        "Kokoa kaikki toiseen kertaan väliaikaiseen hakemistoon ja päätä tilaan 1, jos jokin tiedosto eroaa.",
+    ),
+    (
+        "Build the pages with draft: true in their front matter too.",
+        "Kokoa myös sivut, joiden esittelyosassa on draft: true.",
+    ),
+    (
+        "Write the url, title, headings and plain text of every page into search-index.json in the output directory, for client-side search libraries like lunr or fuse. The headings get ids for linking to them.",
+        "Kirjoita jokaisen sivun osoite, otsikko, otsikot ja pelkkä teksti kohdehakemiston search-index.json-tiedostoon selainpuolen hakukirjastoille kuten lunr tai fuse. Otsikot saavat tunnisteet, jotta niihin voi linkittää.",
+    ),
+    (
+        "To parse a file named markdown.md into webpage.html, when both are in local directory:",
+        "Tiedoston markdown.md muuntaminen tiedostoksi webpage.html, kun molemmat ovat nykyisessä hakemistossa:",
+    ),
+    (
+        "To parse file named markdown.md into markdown.html, when source file is in local directory:",
+        "Tiedoston markdown.md muuntaminen tiedostoksi markdown.html, kun lähdetiedosto on nykyisessä hakemistossa:",
+    ),
+    (
+        "To parse untrusted input with semantic emphasis tags:",
+        "Epäluotettavan syötteen muuntaminen semanttisilla korostustageilla:",
+    ),
+    (
+        "To build a site from the pages directory into the public directory:",
+        "Sivuston kokoaminen pages-hakemistosta public-hakemistoon:",
+    ),
+    (
+        "To run the daemon on a different socket:",
+        "Taustaprosessin käynnistäminen toiseen sokettiin:",
+    ),
+    (
+        "Usage: md2htm [options] [command|source file] [[arguments]]",
+        "Käyttö: md2htm [valinnat] [komento|lähdetiedosto] [[argumentit]]",
+    ),
+    (
+        "Commands:",
+        "Komennot:",
+    ),
+    (
+        "Run md2htm [command] --help for the options of the command.",
+        "Komennon valinnat näkee komennolla md2htm [komento] --help.",
+    ),
+    (
+        "Examples:",
+        "Esimerkkejä:",
+    ),
+    (
+        "Bugs and issues should be reported in https://github.com/rronkkeli/md2htm",
+        "Virheet ja ongelmat voi raportoida osoitteeseen https://github.com/rronkkeli/md2htm",
+    ),
+    (
+        "[options]",
+        "[valinnat]",
+    ),
+    (
+        "Usage: {}",
+        "Käyttö: {}",
+    ),
+    (
+        "Options:",
+        "Valinnat:",
+    ),
+    // Config
+    ("Show the options in effect and where they were set.", "Näytä voimassa olevat valinnat ja mistä ne on asetettu."),
+    ("show [[source file]]", "show [[lähdetiedosto]]"),
+    ("Show the options in effect and where they were set. The options are read from {system}, then from {user}, then from environment variables like MD2HTM_HEADING_IDS=1, then from the command line and last from a pragma like <!-- md2htm: toc = true --> on the first line of the source file, each overriding the ones before. The config files hold the names of the options without the dashes, like heading-ids = true. The extensions and diagnostic codes of every layer are applied in order instead of replacing each other.", "Näytä voimassa olevat valinnat ja mistä ne on asetettu. Valinnat luetaan tiedostosta {system}, sitten tiedostosta {user}, sitten ympäristömuuttujista kuten MD2HTM_HEADING_IDS=1, sitten komentoriviltä ja viimeisenä lähdetiedoston ensimmäisen rivin pragmasta kuten <!-- md2htm: toc = true -->, ja kukin ohittaa aiemmat. Asetustiedostoissa valintojen nimet ovat ilman viivoja, kuten heading-ids = true. Jokaisen tason laajennukset ja diagnostiikkakoodit otetaan huomioon järjestyksessä sen sijaan, että ne korvaisivat toisensa."),
+    ("Config takes show and optionally the source file whose pragma to include.", "Config ottaa argumentin show ja valinnaisesti lähdetiedoston, jonka pragma otetaan mukaan."),
+    ("default", "oletus"),
+    ("system config", "järjestelmän asetukset"),
+    ("user config", "käyttäjän asetukset"),
+    ("environment", "ympäristö"),
+    ("command line", "komentorivi"),
+    ("pragma", "pragma"),
+    ("Couldn't read {}: {}", "Tiedostoa {} ei voitu lukea: {}"),
+    ("{} expects 1, 0, true or false.", "{} odottaa arvoa 1, 0, true tai false."),
+    ("The pragma can't set {}.", "Pragma ei voi asettaa valintaa {}."),
+    ("Ignoring the pragma: {}", "Pragma ohitetaan: {}"),
+    ("Tables aren't supported.", "Taulukoita ei tueta."),
+    ("Expected the name of an option.", "Odotettiin valinnan nimeä."),
+    ("Unknown option {}.", "Tuntematon valinta {}."),
+    ("Expected = after {}.", "Odotettiin =-merkkiä nimen {} jälkeen."),
+    ("{} expects true or false.", "{} odottaa arvoa true tai false."),
+    ("{} expects a string.", "{} odottaa merkkijonoa."),
+    ("Expected a new line after the value.", "Odotettiin rivinvaihtoa arvon jälkeen."),
+    ("Arrays can only hold strings.", "Taulukoissa voi olla vain merkkijonoja."),
+    ("Expected a value.", "Odotettiin arvoa."),
+    ("String isn't closed.", "Merkkijonoa ei ole suljettu."),
+    ("Unknown escape in a string.", "Tuntematon escape-merkintä merkkijonossa."),
+    // Daemon
+    ("Socket to listen when it isn't given as the argument, or of the daemon to send the requests of a load test or of the status to. Defaults to {sock}.", "Kuunneltava soketti, kun sitä ei anneta argumenttina, tai sen daemonin soketti, jolle kuormitustestin tai tilan pyynnöt lähetetään. Oletus on {sock}."),
+    ("Drop the messages with more markdown than this without reading them.", "Pudota viestit, joissa on tätä enemmän markdownia, lukematta niitä."),
+    ("Serve the connections with this many threads, converting at most that many documents at once. Without it every connection gets a thread of its own.", "Palvele yhteyksiä näin monella säikeellä, jolloin enintään näin monta dokumenttia muunnetaan kerralla. Ilman tätä jokainen yhteys saa oman säikeensä."),
+    ("What is logged to stderr: off, error for failed connections or info for every conversion too. Defaults to error.", "Mitä kirjataan stderriin: off, error epäonnistuneille yhteyksille tai info myös jokaiselle muunnokselle. Oletus on error."),
+    ("Answer with the HTML in chunks, each prefixed with its length, and an empty chunk after the last one. The start of a large document is sent while the rest is still converted. Excerpts and standalone documents come in one chunk.", "Vastaa HTML:llä paloina, joiden edessä on niiden pituus, ja tyhjällä palalla viimeisen jälkeen. Suuren dokumentin alku lähetetään, kun loppua vielä muunnetaan. Otteet ja kokonaiset dokumentit tulevat yhtenä palana."),
+    ("Also answer requests sent as datagrams to this socket, one datagram each way, so small snippets don't need a connection. The datagrams are framed like on the stream socket and can hold at most 64 KiB. Dropped requests are answered with an empty datagram.", "Vastaa myös pyyntöihin, jotka lähetetään datagrammeina tähän sokettiin, yksi datagrammi kumpaankin suuntaan, jolloin pienet katkelmat eivät tarvitse yhteyttä. Datagrammit kehystetään kuten stream-soketissa, ja niissä voi olla enintään 64 KiB. Pudotettuihin pyyntöihin vastataan tyhjällä datagrammilla."),
+    ("Write a line for every request into this file: the time, the socket of the client, the bytes in and out, the duration and how the request ended.", "Kirjoita tähän tiedostoon rivi jokaisesta pyynnöstä: aika, asiakkaan soketti, tulleet ja lähteneet tavut, kesto ja miten pyyntö päättyi."),
+    ("Rotate the access log when it would grow larger than this, keeping five old files as .1 to .5 after its path. Defaults to 10 MiB.", "Kierrätä pääsyloki, kun se kasvaisi tätä suuremmaksi, ja säilytä viisi vanhaa tiedostoa polun perässä päätteillä .1–.5. Oletus on 10 MiB."),
+    ("[path]", "[polku]"),
+    // Differential testing
+    ("Compare the HTML of a corpus of markdown files with another implementation.", "Vertaa markdown-tiedostojen korpuksen HTML:ää toiseen toteutukseen."),
+    ("Convert every markdown file in the directory and its subdirectories with md2htm and with a reference command reading the markdown from stdin, and compare the two as sequences of tags and text. Prints the files that differ with the tokens around the first difference, and which tags the differences start from the most, to guide compatibility work. Exits with status 1 if any file differs.", "Muunna jokainen hakemiston ja sen alihakemistojen markdown-tiedosto md2htm:llä ja vertailukomennolla, joka lukee markdownin stdinistä, ja vertaa niitä tagien ja tekstin jonoina. Tulostaa eroavat tiedostot ja ensimmäisen eron ympärillä olevat osat sekä tagit, joista erot useimmiten alkavat, yhteensopivuustyön avuksi. Poistuu tilalla 1, jos jokin tiedosto eroaa."),
+    ("Command converting the markdown of its stdin for comparison, with its arguments. Defaults to cmark.", "Komento, joka muuntaa stdinistä lukemansa markdownin vertailua varten, argumentteineen. Oletus on cmark."),
+    ("[command]", "[komento]"),
+    ("[corpus directory]", "[korpushakemisto]"),
+    ("--reference expects the command to compare with.", "--reference odottaa komentoa, johon verrataan."),
+    ("The reference {} wasn't found, nothing to compare with.", "Vertailukomentoa {} ei löytynyt, ei mitään mihin verrata."),
+    ("The reference failed on {}: {}", "Vertailukomento epäonnistui tiedostolla {}: {}"),
+    ("{} differs at token {}:", "{} eroaa kohdassa {}:"),
+    ("{} of {} files match.", "{}/{} tiedostoa täsmää."),
+    ("Differences start from:", "Erot alkavat kohdista:"),
+    ("Xtest takes one argument, the corpus directory.", "Xtest ottaa yhden argumentin, korpushakemiston."),
+    // Self-test
+    ("Check that the build converts and serves markdown correctly.", "Tarkista, että koontiversio muuntaa ja tarjoilee markdownia oikein."),
+    ("Convert a set of documents built into the program and run requests against a daemon of this executable listening a temporary socket, printing how each check went. Exits with status 1 if any of them failed. Handy for checking a build on a new platform.", "Muunna ohjelmaan sisäänrakennetut dokumentit ja lähetä pyyntöjä tämän ohjelman daemonille, joka kuuntelee väliaikaista sokettia, ja tulosta jokaisen tarkistuksen tulos. Poistuu tilalla 1, jos jokin niistä epäonnistui. Kätevä koontiversion tarkistamiseen uudella alustalla."),
+    ("Self-test takes no arguments.", "Itsetestaus ei ota argumentteja."),
+    ("{} passed, {} failed.", "{} onnistui, {} epäonnistui."),
+    ("expected {}, got {}", "odotettiin {}, saatiin {}"),
+    ("expected the output limit, got {}", "odotettiin tulosteen rajaa, saatiin {}"),
+    ("it didn't start listening {}", "se ei alkanut kuunnella sokettia {}"),
+    ("the answer isn't a whole document", "vastaus ei ole kokonainen dokumentti"),
+    ("the answer came in {} chunks", "vastaus tuli {} palana"),
+    ("[threads]", "[säikeet]"),
+    ("[level]", "[taso]"),
+    ("--socket expects the path of the socket.", "--socket odottaa soketin polkua."),
+    ("--datagram expects the path of the socket.", "--datagram odottaa soketin polkua."),
+    ("--access-log expects the path of the log file.", "--access-log odottaa lokitiedoston polkua."),
+    ("--access-log-size expects the number of bytes.", "--access-log-size odottaa tavujen määrää."),
+    ("--max-size expects the number of bytes.", "--max-size odottaa tavujen määrää."),
+    ("--workers expects a positive number of threads.", "--workers odottaa positiivista säikeiden määrää."),
+    ("--log expects off, error or info.", "--log odottaa arvoa off, error tai info."),
+    ("Add the url in front of the relative link and image urls, like https://example.com/docs/.", "Lisää osoite suhteellisten linkkien ja kuvien osoitteiden eteen, kuten https://example.com/docs/."),
+    ("[url]", "[osoite]"),
+    ("--base-url expects the url to resolve the links against.", "--base-url odottaa osoitetta, jota vasten linkit tulkitaan."),
+    ("request", "pyyntö"),
+    ("A request can't set {}.", "Pyyntö ei voi asettaa valintaa {}."),
+    // Parallel parsing
+    ("Split documents of hundreds of kilobytes and more between top-level blocks and parse the parts on this many threads. The HTML is the same as without it.", "Jaa sadan kilotavun ja sitä suuremmat dokumentit päätason lohkojen välistä ja jäsennä osat näin monella säikeellä. HTML on sama kuin ilman tätä."),
+    ("--threads expects a positive number of threads.", "--threads odottaa positiivista säikeiden määrää."),
+    // Load test
+    ("Measure how many requests a running daemon answers per second.", "Mittaa, montako pyyntöä käynnissä oleva taustaprosessi vastaa sekunnissa."),
+    ("Send the document given with --file to the daemon listening the socket given with --socket, or {sock}, from many clients at once for a while, each request on a connection of its own. Prints how many requests were answered per second and the percentiles of the latencies, so deployments can be sized and regressions noticed. Exits with status 1 if none of the requests was answered.", "Lähetä --file-valinnalla annettua dokumenttia --socket-valinnalla annettua sokettia, tai sokettia {sock}, kuuntelevalle taustaprosessille monelta asiakkaalta yhtä aikaa jonkin aikaa, jokainen pyyntö omalla yhteydellään. Tulostaa, montako pyyntöä vastattiin sekunnissa, ja viiveiden persentiilit, jotta asennukset voidaan mitoittaa ja heikennykset huomata. Päättyy tilalla 1, jos yhteenkään pyyntöön ei vastattu."),
+    ("Markdown file the load test sends.", "Markdown-tiedosto, jota kuormitustesti lähettää."),
+    ("[clients]", "[asiakkaat]"),
+    ("How many clients send requests at once. Defaults to 4.", "Montako asiakasta lähettää pyyntöjä yhtä aikaa. Oletus on 4."),
+    ("How long the load test runs. Defaults to 10 seconds.", "Kuinka kauan kuormitustesti kestää. Oletus on 10 sekuntia."),
+    ("Load test takes no arguments, but the document to send with --file.", "Kuormitustesti ei ota argumentteja, mutta lähetettävän dokumentin --file-valinnalla."),
+    ("--clients expects a positive number of clients.", "--clients odottaa positiivista asiakkaiden määrää."),
+    ("--file expects the markdown file to send.", "--file odottaa lähetettävää markdown-tiedostoa."),
+    ("--duration expects a positive number of seconds.", "--duration odottaa positiivista sekuntimäärää."),
+    ("Couldn't connect to {}: {}", "Yhteys kohteeseen {} epäonnistui: {}"),
+    ("Sent {} requests in {} s with {} clients.", "Lähetettiin {} pyyntöä {} sekunnissa {} asiakkaalla."),
+    ("{} requests per second, {} failed.", "{} pyyntöä sekunnissa, {} epäonnistui."),
+    ("Latency: p50 {}, p90 {}, p99 {}, max {}.", "Viive: p50 {}, p90 {}, p99 {}, enintään {}."),
+    // Backpressure
+    ("--max-connections expects a positive number of connections.", "--max-connections odottaa positiivista yhteyksien määrää."),
+    ("Answer busy to the connections over this many being answered or waiting for a worker, instead of queueing them.", "Vastaa busy tätä useammille vastattaville tai työsäiettä odottaville yhteyksille niiden jonottamisen sijaan."),
+    // Idle connections
+    ("--idle-timeout expects a positive number of seconds.", "--idle-timeout odottaa positiivista sekuntimäärää."),
+    ("Close connections that send nothing for this long before the whole request has come, so clients that never hang up don't run the daemon out of file descriptors.", "Sulje yhteydet, jotka eivät lähetä mitään näin pitkään ennen kuin koko pyyntö on tullut, jotta yhteyttä koskaan sulkemattomat asiakkaat eivät kuluta daemonin tiedostokahvoja loppuun."),
+    // Status
+    ("Show the state of a running daemon.", "Näytä käynnissä olevan daemonin tila."),
+    ("Ask the daemon listening the socket given with --socket, or {sock}, for its state and print it as name value lines: uptime, connections being answered, refused and answered so far, open file descriptors and resident memory. The descriptors and memory are left out on platforms without /proc. Clients can ask the same by sending status = true as the options of a request. Exits with status 1 if the daemon didn't answer.", "Kysy --socket-valitsimella annettua tai {sock}-sokettia kuuntelevalta daemonilta sen tila ja tulosta se nimi arvo -riveinä: käyntiaika, vastattavat, torjutut ja tähän mennessä vastatut yhteydet, avoimet tiedostokahvat ja muistissa oleva koko. Kahvat ja muisti jätetään pois alustoilla, joilla ei ole /proc-hakemistoa. Asiakkaat voivat kysyä saman lähettämällä pyynnön valitsimina status = true. Palauttaa tilakoodin 1, jos daemon ei vastannut."),
+    ("Status takes no arguments, but the socket with --socket.", "Tila ei ota argumentteja, vaan soketin --socket-valitsimella."),
+    ("The daemon is busy.", "Daemon on varattu."),
+    ("Sent as the option of a request, answers the state of the daemon instead of HTML, like md2htm status prints it.", "Pyynnön valitsimena lähetettynä vastaa HTML:n sijaan daemonin tilalla, kuten md2htm status sen tulostaa."),
+    // Class prefix
+    ("--class-prefix expects the prefix to add.", "--class-prefix odottaa lisättävää etuliitettä."),
+    ("[prefix]", "[etuliite]"),
+    ("Add the prefix to the class names of the output, like md-code and md-intend, so they don't collide with the classes of the site the HTML is embedded in. The language- classes of code blocks are kept as they are.", "Lisää etuliite tulosteen luokkanimiin, kuten md-code ja md-intend, jotta ne eivät törmää sen sivuston luokkiin, johon HTML upotetaan. Koodilohkojen language-luokat pidetään ennallaan."),
+    // Content-Security-Policy
+    ("Leave out inline styles and scripts, so the HTML can be served under a Content-Security-Policy without unsafe-inline. The parser writes none, so this removes the style and event handler attributes, javascript: urls and script and style elements of raw HTML.", "Jätä pois upotetut tyylit ja skriptit, jotta HTML:n voi tarjoilla Content-Security-Policyn alla ilman unsafe-inlinea. Jäsennin ei kirjoita niitä itse, joten tämä poistaa raa'an HTML:n style- ja tapahtumankäsittelijäattribuutit, javascript:-osoitteet sekä script- ja style-elementit."),
+    // Injected snippets
+    ("{} expects the file to add.", "{} odottaa lisättävää tiedostoa."),
+    ("--nonce expects the nonce of the policy.", "--nonce odottaa käytännön nonce-arvoa."),
+    ("[nonce]", "[nonce]"),
+    ("Add the contents of the file to the end of the head of standalone documents, like the loader of a highlighter. The preview server sends it with every preview as head.", "Lisää tiedoston sisältö itsenäisten dokumenttien head-osan loppuun, kuten korostimen lataaja. Esikatselupalvelin lähettää sen jokaisen esikatselun mukana nimellä head."),
+    ("Add the contents of the file to the end of the body of standalone documents, like analytics. The preview server sends it with every preview as body.", "Lisää tiedoston sisältö itsenäisten dokumenttien body-osan loppuun, kuten analytiikka. Esikatselupalvelin lähettää sen jokaisen esikatselun mukana nimellä body."),
+    ("Give the script and style tags of the injected files and the inlined theme the nonce, so a Content-Security-Policy allowing it runs them.", "Anna lisättyjen tiedostojen ja upotetun teeman script- ja style-tageille nonce-arvo, jotta sen salliva Content-Security-Policy suorittaa ne."),
+    // Themes
+    ("--theme expects light, dark or print.", "--theme odottaa arvoa light, dark tai print."),
+    ("[theme]", "[teema]"),
+    ("Style standalone documents with the built-in light, dark or print theme. The stylesheet is written next to the output as md2htm-[theme].css and linked, and the daemon inlines it.", "Muotoile itsenäiset dokumentit sisäänrakennetulla light-, dark- tai print-teemalla. Tyylitiedosto kirjoitetaan tulosteen viereen nimellä md2htm-[teema].css ja linkitetään, ja daemon upottaa sen."),
+    ("Put the stylesheet of the theme into the head of the document instead of a file next to it.", "Laita teeman tyylitiedosto dokumentin head-osaan sen vieressä olevan tiedoston sijaan."),
+    // Print
+    ("Rewrite the HTML for printed handouts: pages break before the h1 and h2 headings after the first, the addresses of the links are listed at the end and numbered after the links like footnotes, and details elements are opened. The page-break, link-note and link-notes classes are for the stylesheet.", "Kirjoita HTML tulostettaviksi monisteiksi: sivu vaihtuu ensimmäisen jälkeisten h1- ja h2-otsikoiden edellä, linkkien osoitteet luetellaan loppuun ja numeroidaan linkkien perään kuin alaviitteet, ja details-elementit avataan. Luokat page-break, link-note ja link-notes ovat tyylitiedostoa varten."),
+    // EPUB
+    ("Serialize the output as XHTML: void elements like br close themselves, attribute values are quoted, named character references XML doesn't know become numeric ones and stray & and < are escaped. Standalone documents get the XML declaration and namespaces.", "Sarjallista tuloste XHTML:ksi: tyhjät elementit kuten br sulkevat itsensä, attribuuttien arvot lainataan, XML:lle tuntemattomat nimetyt merkkiviittaukset muutetaan numeerisiksi ja irralliset & ja < escapeataan. Itsenäiset dokumentit saavat XML-määrittelyn ja nimiavaruudet."),
+    ("Write an EPUB chapter: the output is XHTML with ids on the headings and defaults to the .xhtml extension, and a .nav.xhtml file next to it has the toc nav of the headings and the landmarks nav of the chapter for the EPUB packer.", "Kirjoita EPUB-luku: tuloste on XHTML:ää, otsikoilla on id:t ja oletuspääte on .xhtml, ja sen vieressä oleva .nav.xhtml-tiedosto sisältää EPUB-paketoijalle otsikoiden toc-navin ja luvun landmarks-navin."),
+    // Slides
+    ("Split the output into <section class=\"slide\"> elements at the --- rules and ## headings of the top level, turning an outline into slides for reveal.js. The rules are left out, and standalone documents put the sections in the reveal and slides containers reveal.js looks for.", "Jaa tuloste <section class=\"slide\">-elementteihin ylimmän tason ---viivojen ja ##-otsikoiden kohdalta, jolloin jäsennyksestä tulee diat reveal.js:lle. Viivat jätetään pois, ja itsenäiset dokumentit laittavat osiot reveal.js:n etsimiin reveal- ja slides-säiliöihin."),
+    // Batch
+    ("Convert JSON lines from the standard input.", "Muunna JSON-rivejä vakiosyötteestä."),
+    ("Read a JSON object with an id and the markdown from every line of the standard input, and write a line with the same id, the html and the diagnostics for each, in the same order. Lines that can't be converted get an error instead of the html and make the exit status 1. Pragmas and the options of the command apply as in conversion.", "Lue vakiosyötteen jokaiselta riviltä JSON-olio, jossa on id ja markdown, ja kirjoita kullekin rivi, jossa on sama id, html ja diagnostiikat, samassa järjestyksessä. Riveille, joita ei voi muuntaa, kirjoitetaan html:n sijaan error, ja ne tekevät tilakoodiksi 1. Pragmat ja komennon valitsimet toimivat kuten muunnoksessa."),
+    ("Batch takes no arguments, the lines come from the standard input.", "Eräajo ei ota argumentteja, rivit tulevat vakiosyötteestä."),
+    ("Expected a JSON object with the markdown.", "Odotettiin JSON-oliota, jossa on markdown."),
+    // Paragraphs
+    ("Leave out the <p> tags around the paragraphs, for HTML that goes into list items, table cells or other blocks of a template. The paragraphs run together, so this suits single paragraphs best.", "Jätä kappaleiden ympäriltä pois <p>-tagit HTML:lle, joka menee mallipohjan listan kohtiin, taulukon soluihin tai muihin lohkoihin. Kappaleet kulkevat yhteen, joten tämä sopii parhaiten yksittäisille kappaleille."),
+    // UTF-8
+    ("--invalid-utf8 expects keep, replace or reject.", "--invalid-utf8 odottaa arvoa keep, replace tai reject."),
+    ("What is done with markdown that isn't valid UTF-8: keep writes the bytes as they are, replace writes U+FFFD in place of them with an invalid-utf8 warning and reject writes nothing but an invalid-utf8 error. Defaults to keep.", "Mitä tehdään markdownille, joka ei ole kelvollista UTF-8:aa: keep kirjoittaa tavut sellaisinaan, replace kirjoittaa niiden tilalle U+FFFD:n invalid-utf8-varoituksen kera ja reject ei kirjoita mitään paitsi invalid-utf8-virheen. Oletus on keep."),
+    // Link length
+    ("--max-link-length expects the length in bytes.", "--max-link-length odottaa pituutta tavuina."),
+    ("Cut the text and the address of links and images short at this many bytes with a link-truncated warning. Defaults to 65536.", "Katkaise linkkien ja kuvien teksti ja osoite näin moneen tavuun link-truncated-varoituksen kera. Oletus on 65536."),
+    // URL encoding
+    ("Percent-encode the characters that can't be in the addresses of links and images, like spaces and non-ASCII letters, unless they are already. On by default with the commonmark and gfm dialects.", "Prosenttikoodaa merkit, jotka eivät voi olla linkkien ja kuvien osoitteissa, kuten välilyönnit ja muut kuin ASCII-kirjaimet, jos niitä ei ole jo koodattu. Oletuksena käytössä commonmark- ja gfm-murteilla."),
+    ("Write the addresses of links and images as they were written, which the legacy dialect does by default.", "Kirjoita linkkien ja kuvien osoitteet sellaisina kuin ne kirjoitettiin, kuten legacy-murre tekee oletuksena."),
+    ("Write the hosts of addresses with letters outside ASCII in Punycode, like xn--bcher-kva.example for bücher.example, as browsers send them.", "Kirjoita osoitteiden isäntänimet, joissa on muita kuin ASCII-kirjaimia, Punycodena, kuten xn--bcher-kva.example osoitteelle bücher.example, niin kuin selaimet ne lähettävät."),
+    // Table alignment
+    ("[how]", "[miten]"),
+    ("--table-align expects attribute, style or class.", "--table-align odottaa arvoa attribute, style tai class."),
+    ("How the alignment of table columns is written on the cells: attribute writes align=\"left\", style writes style=\"text-align: left\" and class writes class=\"align-left\" with the class prefix. Defaults to attribute.", "Miten taulukon sarakkeiden tasaus kirjoitetaan soluihin: attribute kirjoittaa align=\"left\", style kirjoittaa style=\"text-align: left\" ja class kirjoittaa class=\"align-left\" luokkaetuliitteen kera. Oletus on attribute."),
+    // Line endings
+    ("--eol expects lf, crlf or preserve.", "--eol odottaa arvoa lf, crlf tai preserve."),
+    ("[eol]", "[rivinvaihto]"),
+    ("Write the line breaks of the HTML as lf or crlf, whatever the markdown had. Defaults to preserve, which keeps the line breaks of the markdown and writes lf after the tags.", "Kirjoita HTML:n rivinvaihdot muodossa lf tai crlf riippumatta markdownista. Oletus on preserve, joka säilyttää markdownin rivinvaihdot ja kirjoittaa lf:n tagien perään."),
+    // Pragma
+    ("The pragma can't enable raw-fence in safe mode.", "Pragma ei voi ottaa raw-fence-laajennusta käyttöön turvatilassa."),
+    ("A request can't enable raw-fence in safe mode.", "Pyyntö ei voi ottaa raw-fence-laajennusta käyttöön turvatilassa."),
+    // List numbering
+    ("--list-numbering expects normalize or respect.", "--list-numbering odottaa arvoa normalize tai respect."),
+    ("How the items of ordered lists are numbered: normalize counts up from the number of the first item, respect keeps the numbers written in the markdown with value attributes on the items that don't follow the one before. Defaults to normalize.", "Miten numeroitujen listojen kohdat numeroidaan: normalize laskee ylöspäin ensimmäisen kohdan numerosta, respect säilyttää markdowniin kirjoitetut numerot value-attribuuteilla kohdissa, jotka eivät seuraa edellistä. Oletus on normalize."),
+    // Binary input
+    ("{} looks like a binary file, not markdown. Give --force-binary to convert it anyway.", "{} näyttää binääritiedostolta eikä markdownilta. Anna --force-binary, jos haluat muuntaa sen silti."),
+    ("Convert input that looks like a binary file: NUL bytes, or a start that is over a tenth control characters or invalid UTF-8. Without this the converter refuses it with an error and the daemon answers binary.", "Muunna syöte, joka näyttää binääritiedostolta: NUL-tavuja, tai alku, josta yli kymmenesosa on ohjausmerkkejä tai virheellistä UTF-8:aa. Ilman tätä muunnin kieltäytyy siitä virheellä ja palvelu vastaa binary."),
+];
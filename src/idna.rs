@@ -0,0 +1,176 @@
+//! Internationalized domain names. Hosts with letters outside ASCII are
+//! written in Punycode like browsers send them, `xn--` in front of each
+//! label that needs it, so `https://bücher.example/` links to
+//! `https://xn--bcher-kva.example/` in every browser and mail client.
+
+use alloc::{borrow::Cow, string::String, vec::Vec};
+
+const BASE: u32 = 36;
+const T_MIN: u32 = 1;
+const T_MAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 128;
+
+/// The url with the labels of its host in Punycode. Urls without a host,
+/// and hosts of ASCII only, are returned as they are.
+pub(crate) fn punycode_host(url: &[u8]) -> Cow<'_, [u8]> {
+    let Some(host) = host(url) else {
+        return Cow::Borrowed(url);
+    };
+
+    let Ok(name) = core::str::from_utf8(&url[host.clone()]) else {
+        return Cow::Borrowed(url);
+    };
+
+    if name.is_ascii() {
+        return Cow::Borrowed(url);
+    }
+
+    let mut ascii: Vec<u8> = Vec::with_capacity(url.len() + 16);
+    ascii.extend_from_slice(&url[..host.start]);
+
+    for (n, label) in name.split('.').enumerate() {
+        if n > 0 {
+            ascii.push(b'.');
+        }
+
+        if label.is_ascii() {
+            ascii.extend_from_slice(label.as_bytes());
+            continue;
+        }
+
+        let lower: String = label.chars().flat_map(char::to_lowercase).collect();
+
+        match punycode(&lower) {
+            Some(encoded) => {
+                ascii.extend_from_slice(b"xn--");
+                ascii.extend_from_slice(encoded.as_bytes());
+            }
+            None => return Cow::Borrowed(url),
+        }
+    }
+
+    ascii.extend_from_slice(&url[host.end..]);
+    Cow::Owned(ascii)
+}
+
+/// Range of the host in a url like `scheme://user@host:port/path`
+fn host(url: &[u8]) -> Option<core::ops::Range<usize>> {
+    let colon: usize = url.iter().position(|&b| b == b':')?;
+    let scheme: &[u8] = &url[..colon];
+
+    if scheme.is_empty()
+        || !scheme[0].is_ascii_alphabetic()
+        || !scheme
+            .iter()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.'))
+        || !url[colon + 1..].starts_with(b"//")
+    {
+        return None;
+    }
+
+    let start: usize = colon + 3;
+    let end: usize = url[start..]
+        .iter()
+        .position(|b| matches!(b, b'/' | b'?' | b'#'))
+        .map_or(url.len(), |i| start + i);
+
+    // Past the user info, and before the port
+    let start: usize = url[start..end]
+        .iter()
+        .rposition(|&b| b == b'@')
+        .map_or(start, |i| start + i + 1);
+    let end: usize = url[start..end]
+        .iter()
+        .rposition(|&b| b == b':')
+        .map_or(end, |i| start + i);
+
+    Some(start..end)
+}
+
+/// Encodes the label as in RFC 3492, without the `xn--` prefix. None if the
+/// label is too long to encode.
+fn punycode(label: &str) -> Option<String> {
+    let input: Vec<u32> = label.chars().map(u32::from).collect();
+    let mut output: String = label.chars().filter(char::is_ascii).collect();
+
+    let basic: u32 = u32::try_from(output.len()).ok()?;
+    let mut handled: u32 = basic;
+
+    if basic > 0 {
+        output.push('-');
+    }
+
+    let mut n: u32 = INITIAL_N;
+    let mut delta: u32 = 0;
+    let mut bias: u32 = INITIAL_BIAS;
+
+    while (handled as usize) < input.len() {
+        let m: u32 = input.iter().copied().filter(|&c| c >= n).min()?;
+        delta = delta.checked_add((m - n).checked_mul(handled + 1)?)?;
+        n = m;
+
+        for &c in &input {
+            if c < n {
+                delta = delta.checked_add(1)?;
+            }
+
+            if c == n {
+                let mut q: u32 = delta;
+                let mut k: u32 = BASE;
+
+                loop {
+                    let t: u32 = match k {
+                        k if k <= bias => T_MIN,
+                        k if k >= bias + T_MAX => T_MAX,
+                        k => k - bias,
+                    };
+
+                    if q < t {
+                        break;
+                    }
+
+                    output.push(digit(t + (q - t) % (BASE - t)));
+                    q = (q - t) / (BASE - t);
+                    k += BASE;
+                }
+
+                output.push(digit(q));
+                bias = adapt(delta, handled + 1, handled == basic);
+                delta = 0;
+                handled += 1;
+            }
+        }
+
+        delta = delta.checked_add(1)?;
+        n = n.checked_add(1)?;
+    }
+
+    Some(output)
+}
+
+fn adapt(delta: u32, points: u32, first: bool) -> u32 {
+    let mut delta: u32 = match first {
+        true => delta / DAMP,
+        false => delta / 2,
+    };
+    delta += delta / points;
+
+    let mut k: u32 = 0;
+
+    while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+        delta /= BASE - T_MIN;
+        k += BASE;
+    }
+
+    k + (BASE - T_MIN + 1) * delta / (delta + SKEW)
+}
+
+fn digit(d: u32) -> char {
+    match d {
+        0..=25 => char::from(b'a' + d as u8),
+        _ => char::from(b'0' + (d - 26) as u8),
+    }
+}
@@ -0,0 +1,217 @@
+//! Re-parsing only the changed part of a document. The parser goes back to
+//! its start state after blank lines between blocks, so the HTML of the
+//! unchanged blocks before and after an edit can be reused.
+
+use crate::cursor::Position;
+use crate::diagnostic::Diagnostic;
+use crate::events;
+use crate::limits::Limits;
+use crate::mdstate::{Boundary, Output};
+use crate::parser::Parser;
+use alloc::vec::Vec;
+
+/// Keeps the previous version of a document and its output, for previews
+/// that parse the document again on every keystroke.
+///
+/// ```
+/// use md2htm::{Incremental, Parser};
+///
+/// let mut document = Incremental::new(Parser::default());
+/// document.update(b"# Title\n\nFirst.\n\nSecond.\n");
+/// let output = document.update(b"# Title\n\nFirst!\n\nSecond.\n");
+/// assert_eq!(output.html, Parser::default().parse(b"# Title\n\nFirst!\n\nSecond.\n").html);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Incremental {
+    parser: Parser,
+    source: Vec<u8>,
+    output: Output,
+    /// Where parsing could start over, always starting with the start of the document
+    boundaries: Vec<Boundary>,
+}
+
+impl Incremental {
+    pub fn new(parser: Parser) -> Self {
+        Self {
+            parser,
+            source: Vec::new(),
+            output: Output::default(),
+            boundaries: Vec::from([Boundary::START]),
+        }
+    }
+
+    /// Output of the latest version
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// Parses the new version of the document, reusing what it can of the
+    /// previous one. Options that need the whole document, like heading ids
+    /// or stats, make every update a full parse.
+    pub fn update(&mut self, markdown: &[u8]) -> &Output {
+        if !self.parser.options().is_incremental() {
+            self.output = self.parser.parse(markdown);
+            self.source = markdown.to_vec();
+            return &self.output;
+        }
+
+        let old: &[u8] = &self.source;
+        let prefix: usize = old.iter().zip(markdown).take_while(|(a, b)| a == b).count();
+        let suffix: usize = old[prefix..]
+            .iter()
+            .rev()
+            .zip(markdown[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        // Start from the last boundary the edit didn't touch
+        let first: usize = self
+            .boundaries
+            .iter()
+            .rposition(|b| b.source <= prefix)
+            .unwrap_or(0);
+        let start: Boundary = self.boundaries[first];
+
+        // and continue until the first boundary after the edit
+        let last: Option<usize> = self
+            .boundaries
+            .iter()
+            .position(|b| b.source >= old.len() - suffix && b.source >= start.source);
+
+        let (middle, reused) = match last {
+            Some(last) => {
+                let end: usize = self.boundaries[last].source + markdown.len() - old.len();
+                let (output, boundaries) = self.parse(&markdown[start.source..end]);
+
+                // The edit must leave the parser back at the start state too
+                if boundaries
+                    .last()
+                    .is_some_and(|b| b.source == end - start.source)
+                {
+                    ((output, boundaries), Some(last))
+                } else {
+                    (self.parse(&markdown[start.source..]), None)
+                }
+            }
+
+            None => (self.parse(&markdown[start.source..]), None),
+        };
+
+        let (middle, mut middle_boundaries) = middle;
+        let mut output: Output = Output::default();
+        let mut boundaries: Vec<Boundary> = self.boundaries[..=first].to_vec();
+
+        events::copy(&mut output.marks, &self.output.marks, 0..start.html, 0);
+        output
+            .html
+            .extend_from_slice(&self.output.html[..start.html]);
+        output
+            .diagnostics
+            .extend_from_slice(&self.output.diagnostics[..start.diagnostics]);
+        output
+            .assets
+            .extend_from_slice(&self.output.assets[..start.assets]);
+
+        // The middle was parsed on its own, so its positions start from the boundary
+        let moved = |b: &Boundary, base: &Boundary| Boundary {
+            source: b.source + base.source,
+            html: b.html + base.html,
+            line: b.line + base.line - 1,
+            diagnostics: b.diagnostics + base.diagnostics,
+            assets: b.assets + base.assets,
+        };
+
+        let mut end: Boundary = moved(&Boundary::START, &start);
+        if reused.is_some() {
+            end = moved(&middle_boundaries.pop().unwrap_or(Boundary::START), &start);
+        }
+
+        boundaries.extend(middle_boundaries.iter().skip(1).map(|b| moved(b, &start)));
+        events::copy(
+            &mut output.marks,
+            &middle.marks,
+            0..middle.html.len(),
+            start.html,
+        );
+        output.html.extend_from_slice(&middle.html);
+        output.diagnostics.extend(
+            middle
+                .diagnostics
+                .into_iter()
+                .map(|d| shifted(d, start.line - 1, start.source)),
+        );
+        output.assets.extend(middle.assets);
+
+        if let Some(last) = reused {
+            let old_end: Boundary = self.boundaries[last];
+            let lines = |line: usize| line - old_end.line + end.line;
+            let offset = |source: usize| source - old_end.source + end.source;
+
+            let at: usize = output.html.len();
+            events::copy(
+                &mut output.marks,
+                &self.output.marks,
+                old_end.html..self.output.html.len(),
+                at,
+            );
+            output
+                .html
+                .extend_from_slice(&self.output.html[old_end.html..]);
+            output
+                .diagnostics
+                .extend(
+                    self.output.diagnostics[old_end.diagnostics..]
+                        .iter()
+                        .map(|d| Diagnostic {
+                            position: Position {
+                                line: lines(d.position.line),
+                                column: d.position.column,
+                                offset: offset(d.position.offset),
+                            },
+                            ..d.clone()
+                        }),
+                );
+            output
+                .assets
+                .extend_from_slice(&self.output.assets[old_end.assets..]);
+
+            boundaries.extend(self.boundaries[last..].iter().map(|b| Boundary {
+                source: offset(b.source),
+                html: b.html - old_end.html + end.html,
+                line: lines(b.line),
+                diagnostics: b.diagnostics - old_end.diagnostics + end.diagnostics,
+                assets: b.assets - old_end.assets + end.assets,
+            }));
+        }
+
+        // An empty middle leaves the boundary of the edit in twice
+        boundaries.dedup();
+        self.output = output;
+        self.boundaries = boundaries;
+        self.source = markdown.to_vec();
+        &self.output
+    }
+
+    /// Parses a part of the document on its own
+    fn parse(&self, markdown: &[u8]) -> (Output, Vec<Boundary>) {
+        let mut boundaries: Vec<Boundary> = Vec::from([Boundary::START]);
+
+        match self.parser.parse_recording(
+            markdown,
+            Position::START,
+            &Limits::default(),
+            &mut boundaries,
+        ) {
+            Ok(output) => (output, boundaries),
+            // Nothing can stop the parser without limits
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+/// Moves the diagnostic of a part parsed on its own to its place in the document
+fn shifted(mut diagnostic: Diagnostic, lines: usize, offset: usize) -> Diagnostic {
+    diagnostic.position.line += lines;
+    diagnostic.position.offset += offset;
+    diagnostic
+}
@@ -0,0 +1,345 @@
+//! Just enough JSON for the files written next to the HTML and for the
+//! messages of the preview server.
+
+use md2htm::{HeadingAnchor, Mapping, Transition};
+use std::fmt::Write;
+
+/// Writes the text as a quoted JSON string
+pub(crate) fn string(json: &mut String, text: &str) {
+    json.push('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+}
+
+/// Writes the headings as an array of objects
+pub(crate) fn headings(json: &mut String, headings: &[HeadingAnchor]) {
+    json.push('[');
+
+    for (i, heading) in headings.iter().enumerate() {
+        if i != 0 {
+            json.push(',');
+        }
+
+        json.push_str("\n  {\"text\": ");
+        string(json, &heading.text);
+        json.push_str(", \"id\": ");
+        string(json, &heading.id);
+        let _ = write!(
+            json,
+            ", \"line\": {}, \"level\": {}}}",
+            heading.line, heading.level
+        );
+    }
+
+    json.push_str(if headings.is_empty() { "]" } else { "\n]" });
+}
+
+/// Writes the transitions as JSON lines, one object per line
+pub(crate) fn transitions(json: &mut String, transitions: &[Transition]) {
+    for transition in transitions {
+        let _ = write!(
+            json,
+            "{{\"line\": {}, \"column\": {}, \"offset\": {}, \"byte\": ",
+            transition.position.line, transition.position.column, transition.position.offset
+        );
+        string(json, &char::from(transition.byte).to_string());
+        json.push_str(", \"states\": [");
+
+        for (i, state) in transition.states.iter().enumerate() {
+            if i != 0 {
+                json.push_str(", ");
+            }
+
+            string(json, state);
+        }
+
+        json.push_str("]}\n");
+    }
+}
+
+/// Writes the source map as an array of objects with `[start, end]` byte ranges
+pub(crate) fn mappings(json: &mut String, mappings: &[Mapping]) {
+    json.push('[');
+
+    for (i, mapping) in mappings.iter().enumerate() {
+        if i != 0 {
+            json.push(',');
+        }
+
+        let _ = write!(
+            json,
+            "\n  {{\"source\": [{}, {}], \"html\": [{}, {}]}}",
+            mapping.source.start, mapping.source.end, mapping.html.start, mapping.html.end
+        );
+    }
+
+    json.push_str(if mappings.is_empty() { "]" } else { "\n]" });
+}
+
+/// A parsed JSON value. Objects keep their keys in order.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Value of the key if this is an object that has it
+    pub(crate) fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_usize(&self) -> Option<usize> {
+        match self {
+            Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as usize),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Writes the value back as JSON
+    pub(crate) fn write(&self, json: &mut String) {
+        match self {
+            Value::Null => json.push_str("null"),
+            Value::Bool(b) => json.push_str(if *b { "true" } else { "false" }),
+            Value::Number(n) => {
+                let _ = write!(json, "{n}");
+            }
+            Value::String(s) => string(json, s),
+
+            Value::Array(values) => {
+                json.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i != 0 {
+                        json.push(',');
+                    }
+                    value.write(json);
+                }
+                json.push(']');
+            }
+
+            Value::Object(members) => {
+                json.push('{');
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i != 0 {
+                        json.push(',');
+                    }
+                    string(json, key);
+                    json.push(':');
+                    value.write(json);
+                }
+                json.push('}');
+            }
+        }
+    }
+}
+
+/// Parses the JSON text, or returns None if it isn't valid
+pub(crate) fn parse(text: &str) -> Option<Value> {
+    let mut reader: Reader = Reader {
+        bytes: text.as_bytes(),
+        at: 0,
+    };
+
+    let value: Value = reader.value()?;
+    reader.space();
+    (reader.at == reader.bytes.len()).then_some(value)
+}
+
+/// Reads the JSON values byte by byte
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl Reader<'_> {
+    fn space(&mut self) {
+        while self
+            .bytes
+            .get(self.at)
+            .is_some_and(|b| b.is_ascii_whitespace())
+        {
+            self.at += 1;
+        }
+    }
+
+    /// Moves over the expected text
+    fn expect(&mut self, text: &[u8]) -> Option<()> {
+        self.bytes[self.at..]
+            .starts_with(text)
+            .then(|| self.at += text.len())
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        self.space();
+
+        match self.bytes.get(self.at)? {
+            b'n' => self.expect(b"null").map(|_| Value::Null),
+            b't' => self.expect(b"true").map(|_| Value::Bool(true)),
+            b'f' => self.expect(b"false").map(|_| Value::Bool(false)),
+            b'"' => self.string().map(Value::String),
+
+            b'[' => {
+                self.at += 1;
+                let mut values: Vec<Value> = Vec::new();
+                self.space();
+
+                if self.expect(b"]").is_some() {
+                    return Some(Value::Array(values));
+                }
+
+                loop {
+                    values.push(self.value()?);
+                    self.space();
+
+                    match self.bytes.get(self.at)? {
+                        b',' => self.at += 1,
+                        b']' => {
+                            self.at += 1;
+                            return Some(Value::Array(values));
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+
+            b'{' => {
+                self.at += 1;
+                let mut members: Vec<(String, Value)> = Vec::new();
+                self.space();
+
+                if self.expect(b"}").is_some() {
+                    return Some(Value::Object(members));
+                }
+
+                loop {
+                    self.space();
+                    let key: String = self.string()?;
+                    self.space();
+                    self.expect(b":")?;
+                    members.push((key, self.value()?));
+                    self.space();
+
+                    match self.bytes.get(self.at)? {
+                        b',' => self.at += 1,
+                        b'}' => {
+                            self.at += 1;
+                            return Some(Value::Object(members));
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+
+            _ => {
+                let start: usize = self.at;
+
+                while self
+                    .bytes
+                    .get(self.at)
+                    .is_some_and(|b| matches!(b, b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9'))
+                {
+                    self.at += 1;
+                }
+
+                std::str::from_utf8(&self.bytes[start..self.at])
+                    .ok()?
+                    .parse()
+                    .ok()
+                    .map(Value::Number)
+            }
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.expect(b"\"")?;
+        let mut text: Vec<u8> = Vec::new();
+
+        loop {
+            match *self.bytes.get(self.at)? {
+                b'"' => {
+                    self.at += 1;
+                    return String::from_utf8(text).ok();
+                }
+
+                b'\\' => {
+                    self.at += 1;
+                    let escaped: u8 = *self.bytes.get(self.at)?;
+                    self.at += 1;
+
+                    let c: char = match escaped {
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'u' => {
+                            let high: u32 = self.hex()?;
+
+                            // Characters outside the basic plane come as surrogate pairs
+                            if (0xD800..0xDC00).contains(&high) {
+                                self.expect(b"\\u")?;
+                                let low: u32 = self.hex()?;
+                                char::from_u32(
+                                    0x10000 + ((high - 0xD800) << 10) + low.checked_sub(0xDC00)?,
+                                )?
+                            } else {
+                                char::from_u32(high)?
+                            }
+                        }
+                        b => b as char,
+                    };
+
+                    let mut buf: [u8; 4] = [0; 4];
+                    text.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+
+                b => {
+                    text.push(b);
+                    self.at += 1;
+                }
+            }
+        }
+    }
+
+    /// Reads the four hex digits of a `\u` escape
+    fn hex(&mut self) -> Option<u32> {
+        let digits: &[u8] = self.bytes.get(self.at..self.at + 4)?;
+        self.at += 4;
+        u32::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()
+    }
+}
@@ -0,0 +1,72 @@
+//! Markdown to HTML parser that doesn't add any of the root tags.
+//!
+//! ```
+//! use md2htm::{ParserBuilder, MDS};
+//!
+//! let options = ParserBuilder::new().safe(true).build();
+//! let html = MDS::parse_with(b"# Hello\n".to_vec(), &options);
+//! assert_eq!(html, b"<h1>Hello</h1>\n");
+//! ```
+//!
+//! Without the default `std` feature the parser is `no_std` and only needs
+//! `alloc`, so it has no I/O of its own.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod ast;
+mod csp;
+pub mod cursor;
+mod detect;
+pub mod diagnostic;
+pub mod error;
+mod estimate;
+mod events;
+mod excerpt;
+mod fragment;
+mod heading;
+mod idna;
+mod incremental;
+pub mod limits;
+pub mod mdstate;
+pub mod options;
+mod paragraphs;
+pub mod parser;
+mod print;
+mod scan;
+mod slides;
+mod sourcemap;
+pub mod stats;
+mod text;
+mod trace;
+mod validate;
+mod writeto;
+mod xhtml;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use cursor::Position;
+pub use detect::detect_dialect;
+pub use diagnostic::{Code, Diagnostic, Level, Severity};
+pub use error::Error;
+pub use estimate::estimate_output;
+pub use events::{push_html, Event, HtmlEvents, Kind, Tag};
+pub use fragment::BlockContext;
+pub use heading::HeadingAnchor;
+pub use incremental::Incremental;
+pub use limits::{Cancel, CancellationToken, Limits};
+pub use mdstate::{Output, MDS};
+pub use options::{
+    Align, CodeBlocks, Dialect, Em, Ext, Indented, MissingAlt, Numbering, Options, ParserBuilder,
+    Permalink, Placement, Slug, Utf8,
+};
+pub use parser::Parser;
+pub use sourcemap::Mapping;
+pub use stats::Stats;
+pub use trace::Transition;
+pub use validate::{validate, Invalid};
+pub use xhtml::to_xhtml;
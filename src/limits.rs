@@ -0,0 +1,59 @@
+//! Bounds for a single parse, so callers like the daemon can stop work that
+//! nobody is going to read.
+
+use crate::error::Error;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// How many input bytes are parsed between the checks of [`Cancel`].
+pub const CHECK_INTERVAL: usize = 4096;
+
+/// Cooperative cancellation checked periodically while parsing.
+pub trait Cancel {
+    fn is_cancelled(&self) -> bool;
+}
+
+/// Cancellation flag that can be set from another thread. Clones share the flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Cancel for CancellationToken {
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Limits of a single parse. The defaults don't limit anything.
+#[derive(Clone, Copy, Default)]
+pub struct Limits<'a> {
+    /// Checked every [`CHECK_INTERVAL`] bytes of input
+    pub cancel: Option<&'a dyn Cancel>,
+    /// Maximum size of the HTML in bytes. Inputs like long runs of `_*_*_*`
+    /// grow many times larger as HTML, so shared services should set this.
+    pub max_output: Option<usize>,
+}
+
+impl Limits<'_> {
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel.is_some_and(|cancel| cancel.is_cancelled())
+    }
+
+    pub(crate) fn check_output(&self, len: usize) -> Result<(), Error> {
+        match self.max_output {
+            Some(limit) if len > limit => Err(Error::OutputLimit { limit }),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Load test of a running daemon. Clients send the same document over and
+//! over for a while, and the throughput and the latencies are reported, so
+//! operators can size deployments and notice regressions.
+
+use crate::i18n::tr;
+use crate::PS;
+use std::{
+    io::{Read, Result, Write},
+    os::unix::net::UnixStream,
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Clients used when `--clients` isn't given
+pub(crate) const CLIENTS: usize = 4;
+/// How long the test runs when `--duration` isn't given
+pub(crate) const DURATION: Duration = Duration::from_secs(10);
+
+/// What a client saw
+#[derive(Debug, Default)]
+struct Results {
+    /// Latencies of the answered requests
+    latencies: Vec<Duration>,
+    failed: usize,
+}
+
+/// Sends the markdown from `clients` threads until the duration has passed
+/// and prints the results. Tells if any of the requests was answered.
+pub fn run(sock: &Path, markdown: &[u8], clients: usize, duration: Duration) -> Result<bool> {
+    if let Err(e) = UnixStream::connect(sock) {
+        eprintln!("{}", tr!("Couldn't connect to {}: {}", sock.display(), e));
+        return Ok(false);
+    }
+
+    let mut request: Vec<u8> = markdown.len().to_be_bytes().to_vec();
+    request.extend_from_slice(markdown);
+
+    let started: Instant = Instant::now();
+    let deadline: Instant = started + duration;
+
+    let results: Vec<Results> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..clients)
+            .map(|_| scope.spawn(|| client(sock, &request, deadline)))
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or_default())
+            .collect()
+    });
+
+    let elapsed: Duration = started.elapsed();
+    let failed: usize = results.iter().map(|results| results.failed).sum();
+    let mut latencies: Vec<Duration> = results
+        .into_iter()
+        .flat_map(|results| results.latencies)
+        .collect();
+    latencies.sort();
+
+    println!(
+        "{}",
+        tr!(
+            "Sent {} requests in {} s with {} clients.",
+            latencies.len() + failed,
+            format!("{:.1}", elapsed.as_secs_f64()),
+            clients
+        )
+    );
+    println!(
+        "{}",
+        tr!(
+            "{} requests per second, {} failed.",
+            format!("{:.0}", latencies.len() as f64 / elapsed.as_secs_f64()),
+            failed
+        )
+    );
+
+    if !latencies.is_empty() {
+        println!(
+            "{}",
+            tr!(
+                "Latency: p50 {}, p90 {}, p99 {}, max {}.",
+                millis(percentile(&latencies, 50)),
+                millis(percentile(&latencies, 90)),
+                millis(percentile(&latencies, 99)),
+                millis(latencies[latencies.len() - 1])
+            )
+        );
+    }
+
+    Ok(!latencies.is_empty())
+}
+
+/// Sends the request on a connection of its own until the deadline
+fn client(sock: &Path, request: &[u8], deadline: Instant) -> Results {
+    let mut results: Results = Results::default();
+
+    while Instant::now() < deadline {
+        let sent: Instant = Instant::now();
+
+        match convert(sock, request) {
+            Ok(_) => results.latencies.push(sent.elapsed()),
+            Err(_) => results.failed += 1,
+        }
+    }
+
+    results
+}
+
+fn convert(sock: &Path, request: &[u8]) -> Result<Vec<u8>> {
+    let mut stream: UnixStream = UnixStream::connect(sock)?;
+    stream.write_all(request)?;
+
+    let mut len: [u8; PS] = [0; PS];
+    stream.read_exact(&mut len)?;
+    let mut html: Vec<u8> = vec![0; usize::from_be_bytes(len)];
+    stream.read_exact(&mut html)?;
+    Ok(html)
+}
+
+/// The latency that this many percent of the requests were answered within
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    let rank: usize = (sorted.len() * percent).div_ceil(100);
+    sorted[rank.saturating_sub(1)]
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.2} ms", duration.as_secs_f64() * 1000.0)
+}
@@ -1,170 +1,1193 @@
 pub(crate) use std::{
     env,
-    fs::{remove_file, File},
+    fs::File,
     io::{Read, Result, Write},
-    os::unix::net::{UnixListener, UnixStream},
-    thread::spawn,
+    path::PathBuf,
+    time::Duration,
 };
 
-mod mdstate;
-mod writeto;
+use md2htm::{
+    validate, Align, Code, CodeBlocks, Diagnostic, Dialect, Em, Ext, Indented, Level, Limits,
+    MissingAlt, Numbering, Output, Parser, ParserBuilder, Permalink, Placement, Severity, Slug,
+    Stats, Utf8,
+};
+
+mod access;
+mod batch;
+mod build;
+mod config;
+mod daemon;
+mod eol;
+mod epub;
+mod head;
+mod help;
+mod i18n;
+mod json;
+mod loadtest;
+mod preview;
+mod selftest;
+#[cfg(feature = "systemd")]
+mod systemd;
+mod theme;
+mod xtest;
+
+use config::{Setting, Value};
+use eol::Eol;
+use head::{standalone, Head};
+use i18n::tr;
+use theme::Theme;
 
 const PS: usize = std::mem::size_of::<usize>();
 const SOCK: &str = "/run/mdserv/mdserv.sock";
+/// Bytes at the start of the input checked for text
+const BINARY_SAMPLE: usize = 8 * 1024;
 
 fn main() -> Result<()> {
-    // Try to remove the socket file but don't really care about the outcome,
-    // because the binding won't succeed if there is no privileges to write.
-    match remove_file(SOCK) {
-        _ => {}
-    };
     let args: Vec<String> = env::args().collect();
     handle_args(args)?;
     Ok(())
 }
 
-fn stream_handler(mut stream: UnixStream) {
-    let mut lbuf: [u8; PS] = [0; PS];
-
-    // These matches are just for debugging purposes
-    // will tidy up later..
-    match stream.read(&mut lbuf) {
-        Ok(_) => {
-            let len: usize = usize::from_be_bytes(lbuf);
-            let mut mdbuf: Vec<u8> = vec![0; len];
-
-            match stream.read(&mut mdbuf) {
-                Ok(_) => {
-                    let parsed = mdstate::MDS::parse(mdbuf);
-                    let plen: [u8; PS] = parsed.len().to_be_bytes();
-
-                    match stream.write(&plen) {
-                        Ok(_) => match stream.write(&parsed) {
-                            Ok(_) => match stream.flush() {
-                                Ok(_) => return,
-                                Err(e) => eprintln!("Flushing wasn't successful: {e}"),
-                            },
+fn handle_args(all_args: Vec<String>) -> Result<()> {
+    let Some(layers) = config::load() else {
+        return Ok(());
+    };
 
-                            Err(e) => eprintln!("Couldn't write the parsed data: {e}"),
-                        },
+    // The flags of the config layers come first, so the command line
+    // overrides them
+    let cli: Vec<Setting> = config::from_args(&all_args);
+    let mut flags: Vec<String> = config::to_args(&config::effective(&layers));
+    flags.extend(all_args);
 
-                        Err(e) => eprintln!("Couldn't write the length bytes: {e}"),
-                    }
-                }
+    let Some(Options {
+        builder,
+        mode,
+        daemon: mut config,
+        build: mut build_config,
+        args,
+    }) = options(flags.clone())
+    else {
+        return Ok(());
+    };
 
-                Err(e) => eprintln!("Failed to read the {len} message bytes: {e}"),
-            }
-        }
+    // Every layer below the pragmas of the documents
+    let mut settings: Vec<Setting> = layers;
+    settings.extend(cli.iter().cloned());
+    build_config.settings = settings.clone();
+    config.settings = settings.clone();
 
-        Err(e) => eprintln!("Failed to read the length of the message: {e}"),
-    }
-}
+    let parser: Parser = Parser::new(builder.clone().build());
 
-fn handle_args(args: Vec<String>) -> Result<()> {
     if args.len() == 1 {
-        eprintln!("Expected at least one argument!");
+        eprintln!("{}", tr!("Expected at least one argument!"));
         print_help();
         return Ok(());
     }
 
+    // `md2htm build --help` shows the help of the command
+    if args[2..].iter().any(|arg| arg == "--help" || arg == "-h") {
+        if let Some(command) = help::command(&args[1]) {
+            println!("{}", help::of(command));
+            return Ok(());
+        }
+    }
+
     match args[1].as_str() {
-        "help" | "--help" | "-h" | "h" | "?" => {
-            print_help();
+        "help" | "--help" | "-h" | "h" | "?" => match args.get(2).map(|name| help::command(name)) {
+            Some(Some(command)) => println!("{}", help::of(command)),
+            Some(None) => eprintln!("{}", tr!("Unknown command {}.", args[2])),
+            None => print_help(),
+        },
+
+        "a11y" => {
+            if args.len() == 3 {
+                audit(
+                    &args[2],
+                    &builder,
+                    &settings,
+                    ParserBuilder::a11y,
+                    tr!("No accessibility problems found in {}."),
+                )?;
+            } else {
+                eprintln!(
+                    "{}",
+                    tr!("Accessibility audit takes one argument, the source file.")
+                );
+            }
         }
 
-        "daemon" | "d" | "--daemon" | "-d" => {
+        "check" => {
+            if args.len() == 3 {
+                audit(
+                    &args[2],
+                    &builder,
+                    &settings,
+                    ParserBuilder::check_fragments,
+                    tr!("No problems found in {}."),
+                )?;
+            } else {
+                eprintln!("{}", tr!("Check takes one argument, the source file."));
+            }
+        }
+
+        "build" => {
+            if args.len() == 4 {
+                build::run(args[2].as_ref(), args[3].as_ref(), builder, build_config)?;
+            } else {
+                eprintln!(
+                    "{}",
+                    tr!("Build mode takes two arguments, the source and output directories.")
+                );
+            }
+        }
+
+        "config" => match (args.get(2).map(String::as_str), args.len()) {
+            (Some("show"), 3 | 4) => show_config(settings, args.get(3))?,
+            _ => eprintln!(
+                "{}",
+                tr!("Config takes show and optionally the source file whose pragma to include.")
+            ),
+        },
+
+        "preview" => {
             if args.len() == 2 {
-                let listener: UnixListener = UnixListener::bind(SOCK)?;
-
-                for stream in listener.incoming() {
-                    match stream {
-                        Ok(stream) => {
-                            spawn(|| {
-                                stream_handler(stream);
-                            });
-                        }
+                let snippets: preview::Snippets = preview::Snippets {
+                    head: head::snippet(mode.inject_head.as_deref(), mode.nonce.as_deref())?,
+                    body: head::snippet(mode.inject_body.as_deref(), mode.nonce.as_deref())?,
+                };
+                preview::run(parser, snippets)?;
+            } else {
+                eprintln!("{}", tr!("Preview mode takes no arguments."));
+            }
+        }
 
-                        Err(e) => eprintln!("Failed to catch the stream: {e}"),
-                    }
+        "xtest" => {
+            if args.len() == 3 {
+                let reference: &str = mode.reference.as_deref().unwrap_or(xtest::REFERENCE);
+
+                if !xtest::run(args[2].as_ref(), &parser, reference)? {
+                    std::process::exit(1);
+                }
+            } else {
+                eprintln!("{}", tr!("Xtest takes one argument, the corpus directory."));
+            }
+        }
+
+        "selftest" => {
+            if args.len() == 2 {
+                // The daemon gets the options given with this
+                let options: Vec<String> = config::to_args(&config::effective(&cli));
+
+                if !selftest::run(&parser, &options)? {
+                    std::process::exit(1);
+                }
+            } else {
+                eprintln!("{}", tr!("Self-test takes no arguments."));
+            }
+        }
+
+        "loadtest" => match (args.len(), &mode.sample) {
+            (2, Some(sample)) => {
+                let markdown: Vec<u8> = std::fs::read(sample)?;
+                let sock: &str = config.socket.as_deref().unwrap_or(SOCK);
+                let clients: usize = mode.clients.unwrap_or(loadtest::CLIENTS);
+                let duration: Duration = mode.duration.unwrap_or(loadtest::DURATION);
+
+                if !loadtest::run(sock.as_ref(), &markdown, clients, duration)? {
+                    std::process::exit(1);
+                }
+            }
+            _ => eprintln!(
+                "{}",
+                tr!("Load test takes no arguments, but the document to send with --file.")
+            ),
+        },
+
+        "batch" => match args.len() {
+            2 => {
+                if !batch::run(&builder, &settings, config.max_output)? {
+                    std::process::exit(1);
+                }
+            }
+            _ => eprintln!(
+                "{}",
+                tr!("Batch takes no arguments, the lines come from the standard input.")
+            ),
+        },
+
+        "status" => match args.len() {
+            2 => {
+                let sock: &str = config.socket.as_deref().unwrap_or(SOCK);
+
+                if !daemon::print_status(sock.as_ref())? {
+                    std::process::exit(1);
                 }
+            }
+            _ => eprintln!(
+                "{}",
+                tr!("Status takes no arguments, but the socket with --socket.")
+            ),
+        },
+
+        "daemon" | "d" | "--daemon" | "-d" => {
+            if args[2..].iter().any(|arg| arg == "--print-unit") {
+                // The unit runs the daemon with the options and the socket given with this
+                let mut options: Vec<String> = config::to_args(&config::effective(&cli));
+                options.extend(
+                    args[2..]
+                        .iter()
+                        .filter(|arg| *arg != "--print-unit")
+                        .cloned(),
+                );
+                let exe: PathBuf = env::current_exe().unwrap_or_else(|_| "/usr/bin/md2htm".into());
+                print!("{}", daemon::unit(&exe, &options));
+            } else if args.len() <= 3 {
+                let sock: String = args
+                    .get(2)
+                    .or(config.socket.as_ref())
+                    .map_or(SOCK.to_string(), String::clone);
+                daemon::run(&sock, parser, config)?;
             } else {
-                eprintln!("Daemon mode doesn't take arguments.");
+                eprintln!(
+                    "{}",
+                    tr!("Daemon mode takes at most one argument, the socket path.")
+                );
             }
         }
 
         _ => match args.len() {
             2 => {
                 let mut dst: String;
+                let extension: &str = match mode.epub {
+                    true => ".xhtml",
+                    false => ".html",
+                };
 
                 if args[1].find(".md").is_some_and(|x| x == args[1].len() - 3) {
-                    dst = args[1].replace(".md", ".html");
+                    dst = args[1].replace(".md", extension);
                 } else {
                     dst = args[1].clone();
-                    dst.push_str(".html");
+                    dst.push_str(extension);
                 }
 
-                parse(&args[1], &dst)?;
+                parse(&args[1], &dst, &builder, &settings, config.max_output, mode)?;
             }
 
-            3 => parse(&args[1], &args[2])?,
+            3 => parse(
+                &args[1],
+                &args[2],
+                &builder,
+                &settings,
+                config.max_output,
+                mode,
+            )?,
 
-            _ => eprintln!("Too many arguments! Expected at most 2."),
+            _ => eprintln!("{}", tr!("Too many arguments! Expected at most 2.")),
         },
     }
 
     Ok(())
 }
 
+/// Options gathered from the flags, and the arguments that aren't flags
+struct Options {
+    builder: ParserBuilder,
+    mode: Mode,
+    daemon: daemon::Config,
+    build: build::Config,
+    args: Vec<String>,
+}
+
+/// Handles the flags among the arguments. Prints what is wrong with them and
+/// returns None if anything is.
+fn options(all_args: Vec<String>) -> Option<Options> {
+    // Options are flags that can be given anywhere, so collect them first and
+    // handle the rest of the arguments positionally.
+    let mut builder: ParserBuilder = ParserBuilder::new();
+    let mut config: daemon::Config = daemon::Config::default();
+    let mut build_config: build::Config = build::Config::default();
+    let mut args: Vec<String> = Vec::with_capacity(all_args.len());
+
+    // The dialect is the starting point the other options adjust, wherever it was given
+    if let Some(i) = all_args.iter().rposition(|arg| arg == "--dialect") {
+        match all_args
+            .get(i + 1)
+            .and_then(|name| Dialect::from_name(name))
+        {
+            Some(dialect) => builder = builder.dialect(dialect),
+            // Detected from each document, see `pragma`
+            None if all_args.get(i + 1).is_some_and(|name| name == "auto") => {}
+            None => {
+                eprintln!(
+                    "{}",
+                    tr!("--dialect expects auto, commonmark, gfm or legacy.")
+                );
+                return None;
+            }
+        }
+    }
+
+    let mut all_args = all_args.into_iter();
+    let mut mode: Mode = Mode::default();
+    let mut slug_prefix: String = String::new();
+    let mut slug_suffix: String = String::new();
+
+    while let Some(arg) = all_args.next() {
+        match arg.as_str() {
+            "--safe" => builder = builder.safe(true),
+            "--csp" => builder = builder.csp(true),
+            "--print" => builder = builder.print(true),
+            "--xhtml" => builder = builder.xhtml(true),
+            "--slides" => builder = builder.slides(true),
+            "--no-paragraphs" => builder = builder.wrap_paragraphs(false),
+            "--epub" => {
+                builder = builder.xhtml(true).heading_map(true);
+                mode.epub = true;
+            }
+            "--semantic" => builder = builder.emphasis_style(Em::Semantic),
+            "--sourcepos" => builder = builder.sourcepos(true),
+            "--excerpt" => mode.excerpt = true,
+            "--standalone" => mode.standalone = true,
+            "--inline-theme" => mode.inline_theme = true,
+            "--force-binary" => {
+                mode.force_binary = true;
+                config.force_binary = true;
+            }
+            "--stats" => builder = builder.stats(true),
+            "--search-index" => build_config.search_index = true,
+            "--drafts" => build_config.drafts = true,
+            "--check-reproducible" => build_config.check_reproducible = true,
+            "--validate" => {
+                mode.validate = true;
+                build_config.validate = true;
+            }
+            "--heading-ids" => builder = builder.heading_ids(true),
+            "--source-map" => {
+                builder = builder.source_map(true);
+                mode.source_map = true;
+            }
+            "--heading-map" => {
+                builder = builder.heading_map(true);
+                mode.heading_map = true;
+            }
+            "--threads" => match all_args.next().and_then(|s| s.parse::<usize>().ok()) {
+                Some(threads) if threads > 0 => mode.threads = Some(threads),
+                _ => {
+                    eprintln!("{}", tr!("--threads expects a positive number of threads."));
+                    return None;
+                }
+            },
+            "--clients" => match all_args.next().and_then(|s| s.parse::<usize>().ok()) {
+                Some(clients) if clients > 0 => mode.clients = Some(clients),
+                _ => {
+                    eprintln!("{}", tr!("--clients expects a positive number of clients."));
+                    return None;
+                }
+            },
+            "--file" => match all_args.next() {
+                Some(file) => mode.sample = Some(file.into()),
+                None => {
+                    eprintln!("{}", tr!("--file expects the markdown file to send."));
+                    return None;
+                }
+            },
+            "--inject-head" | "--inject-body" => match all_args.next() {
+                Some(file) if arg == "--inject-head" => mode.inject_head = Some(file.into()),
+                Some(file) => mode.inject_body = Some(file.into()),
+                None => {
+                    eprintln!("{}", tr!("{} expects the file to add.", arg));
+                    return None;
+                }
+            },
+            "--eol" => match all_args.next().as_deref().and_then(Eol::from_name) {
+                Some(eol) => mode.eol = eol,
+                None => {
+                    eprintln!("{}", tr!("--eol expects lf, crlf or preserve."));
+                    return None;
+                }
+            },
+            "--theme" => match all_args.next().as_deref().and_then(Theme::from_name) {
+                Some(theme) => mode.theme = Some(theme),
+                None => {
+                    eprintln!("{}", tr!("--theme expects light, dark or print."));
+                    return None;
+                }
+            },
+            "--nonce" => match all_args.next() {
+                Some(nonce) => mode.nonce = Some(nonce),
+                None => {
+                    eprintln!("{}", tr!("--nonce expects the nonce of the policy."));
+                    return None;
+                }
+            },
+            "--duration" => match all_args.next().and_then(|s| s.parse::<f64>().ok()) {
+                Some(secs) if secs > 0.0 => mode.duration = Some(Duration::from_secs_f64(secs)),
+                _ => {
+                    eprintln!(
+                        "{}",
+                        tr!("--duration expects a positive number of seconds.")
+                    );
+                    return None;
+                }
+            },
+            "--reference" => match all_args.next() {
+                Some(command) => mode.reference = Some(command),
+                None => {
+                    eprintln!(
+                        "{}",
+                        tr!("--reference expects the command to compare with.")
+                    );
+                    return None;
+                }
+            },
+
+            "--trace-parser" => match all_args.next() {
+                Some(file) => {
+                    mode.trace = true;
+                    mode.trace_file = Some(file.into());
+                }
+                None => {
+                    eprintln!(
+                        "{}",
+                        tr!("--trace-parser expects the file to write the log into.")
+                    );
+                    return None;
+                }
+            },
+            "--number-headings" => builder = builder.number_headings(true),
+            "--warn-duplicate-ids" => builder = builder.warn_duplicate_ids(true),
+
+            "--dialect" => {
+                all_args.next();
+            }
+
+            "--enable" | "--disable" => {
+                let Some(list) = all_args.next() else {
+                    eprintln!(
+                        "{}",
+                        tr!("{} expects a comma separated list of extensions.", arg)
+                    );
+                    return None;
+                };
+
+                for name in list.split(',').filter(|name| !name.is_empty()) {
+                    match Ext::from_name(name) {
+                        Some(ext) if arg == "--enable" => builder = builder.extension(ext),
+                        Some(ext) => builder = builder.disable(ext),
+                        None => {
+                            eprintln!(
+                                "{}",
+                                tr!(
+                                    "Unknown extension {}. Known extensions are: {}.",
+                                    name,
+                                    extension_names()
+                                )
+                            );
+                            return None;
+                        }
+                    }
+                }
+            }
+
+            "--allow" | "--warn" | "--deny" => {
+                let Some(list) = all_args.next() else {
+                    eprintln!(
+                        "{}",
+                        tr!(
+                            "{} expects a comma separated list of diagnostic codes.",
+                            arg
+                        )
+                    );
+                    return None;
+                };
+
+                let level: Level = match arg.as_str() {
+                    "--allow" => Level::Allow,
+                    "--warn" => Level::Warn,
+                    _ => Level::Deny,
+                };
+
+                for name in list.split(',').filter(|name| !name.is_empty()) {
+                    match Code::from_name(name) {
+                        Some(code) => builder = builder.level(code, level),
+                        None => {
+                            eprintln!(
+                                "{}",
+                                tr!(
+                                    "Unknown diagnostic code {}. Known codes are: {}.",
+                                    name,
+                                    code_names()
+                                )
+                            );
+                            return None;
+                        }
+                    }
+                }
+            }
+
+            "--indented" => match all_args.next().as_deref() {
+                Some("div") => builder = builder.indented(Indented::Div),
+                Some("code") => builder = builder.indented(Indented::Code),
+                Some("join") => builder = builder.indented(Indented::Join),
+                _ => {
+                    eprintln!("{}", tr!("--indented expects div, code or join."));
+                    return None;
+                }
+            },
+
+            "--invalid-utf8" => match all_args.next().as_deref() {
+                Some("keep") => builder = builder.invalid_utf8(Utf8::Keep),
+                Some("replace") => builder = builder.invalid_utf8(Utf8::Replace),
+                Some("reject") => builder = builder.invalid_utf8(Utf8::Reject),
+                _ => {
+                    eprintln!("{}", tr!("--invalid-utf8 expects keep, replace or reject."));
+                    return None;
+                }
+            },
+
+            "--max-link-length" => match all_args.next().and_then(|s| s.parse::<usize>().ok()) {
+                Some(len) => builder = builder.max_link_length(len),
+                None => {
+                    eprintln!("{}", tr!("--max-link-length expects the length in bytes."));
+                    return None;
+                }
+            },
+
+            "--url-encoding" => builder = builder.encode_urls(true),
+            "--no-url-encoding" => builder = builder.encode_urls(false),
+            "--punycode" => builder = builder.punycode_hosts(true),
+            "--dedent-code" => builder = builder.dedent_code(true),
+
+            "--expand-tabs" => match all_args.next().and_then(|s| s.parse::<usize>().ok()) {
+                Some(width) => builder = builder.expand_tabs(width),
+                None => {
+                    eprintln!("{}", tr!("--expand-tabs expects the tab width in columns."));
+                    return None;
+                }
+            },
+
+            "--code-blocks" => match all_args.next().as_deref() {
+                Some("div") => builder = builder.code_blocks(CodeBlocks::Div),
+                Some("pre") => builder = builder.code_blocks(CodeBlocks::Pre),
+                _ => {
+                    eprintln!("{}", tr!("--code-blocks expects div or pre."));
+                    return None;
+                }
+            },
+
+            "--missing-alt" => match all_args.next().as_deref() {
+                Some("ignore") => builder = builder.missing_alt(MissingAlt::Ignore),
+                Some("warn") => builder = builder.missing_alt(MissingAlt::Warn),
+                Some("filename") => builder = builder.missing_alt(MissingAlt::Filename),
+                _ => {
+                    eprintln!("{}", tr!("--missing-alt expects ignore, warn or filename."));
+                    return None;
+                }
+            },
+
+            "--toc" => builder = builder.toc(true),
+
+            "--placement" => match all_args.next().as_deref() {
+                Some("end") => builder = builder.placement(Placement::End),
+                Some("after-first-heading") => {
+                    builder = builder.placement(Placement::AfterFirstHeading)
+                }
+                Some("marker") => builder = builder.placement(Placement::Marker),
+                _ => {
+                    eprintln!(
+                        "{}",
+                        tr!("--placement expects end, after-first-heading or marker.")
+                    );
+                    return None;
+                }
+            },
+
+            "--table-align" => match all_args.next().as_deref() {
+                Some("attribute") => builder = builder.table_align(Align::Attribute),
+                Some("style") => builder = builder.table_align(Align::Style),
+                Some("class") => builder = builder.table_align(Align::Class),
+                _ => {
+                    eprintln!(
+                        "{}",
+                        tr!("--table-align expects attribute, style or class.")
+                    );
+                    return None;
+                }
+            },
+
+            "--list-numbering" => match all_args.next().as_deref() {
+                Some("normalize") => builder = builder.list_numbering(Numbering::Normalize),
+                Some("respect") => builder = builder.list_numbering(Numbering::Respect),
+                _ => {
+                    eprintln!("{}", tr!("--list-numbering expects normalize or respect."));
+                    return None;
+                }
+            },
+
+            "--permalinks" => match all_args.next().as_deref() {
+                Some("none") => builder = builder.permalink(Permalink::None),
+                Some("anchor") => builder = builder.permalink(Permalink::Anchor),
+                Some("wrap") => builder = builder.permalink(Permalink::Wrap),
+                _ => {
+                    eprintln!("{}", tr!("--permalinks expects none, anchor or wrap."));
+                    return None;
+                }
+            },
+
+            "--slug" => match all_args.next().as_deref() {
+                Some("github") => builder = builder.slug(Slug::GitHub),
+                Some("ascii") => builder = builder.slug(Slug::Ascii),
+                _ => {
+                    eprintln!("{}", tr!("--slug expects either github or ascii."));
+                    return None;
+                }
+            },
+
+            "--slug-prefix" | "--slug-suffix" => match all_args.next() {
+                Some(affix) if arg == "--slug-prefix" => slug_prefix = affix,
+                Some(affix) => slug_suffix = affix,
+                None => {
+                    eprintln!("{}", tr!("{} expects the text to add.", arg));
+                    return None;
+                }
+            },
+
+            "--obfuscate-emails" => builder = builder.obfuscate_emails(true),
+
+            "--external-class" => match all_args.next() {
+                Some(class) => builder = builder.external_class(&class),
+                None => {
+                    eprintln!("{}", tr!("--external-class expects the class name."));
+                    return None;
+                }
+            },
+
+            "--class-prefix" => match all_args.next() {
+                Some(prefix) => builder = builder.class_prefix(&prefix),
+                None => {
+                    eprintln!("{}", tr!("--class-prefix expects the prefix to add."));
+                    return None;
+                }
+            },
+
+            "--site" => match all_args.next() {
+                Some(host) => builder = builder.site(&host),
+                None => {
+                    eprintln!("{}", tr!("--site expects the host name of the site."));
+                    return None;
+                }
+            },
+
+            "--base-url" => match all_args.next() {
+                Some(url) => builder = builder.base_url(&url),
+                None => {
+                    eprintln!(
+                        "{}",
+                        tr!("--base-url expects the url to resolve the links against.")
+                    );
+                    return None;
+                }
+            },
+
+            "--timeout" => match all_args.next().and_then(|s| s.parse::<f64>().ok()) {
+                Some(secs) if secs > 0.0 => config.timeout = Some(Duration::from_secs_f64(secs)),
+                _ => {
+                    eprintln!("{}", tr!("--timeout expects a positive number of seconds."));
+                    return None;
+                }
+            },
+
+            "--idle-timeout" => match all_args.next().and_then(|s| s.parse::<f64>().ok()) {
+                Some(secs) if secs > 0.0 => {
+                    config.idle_timeout = Some(Duration::from_secs_f64(secs))
+                }
+                _ => {
+                    eprintln!(
+                        "{}",
+                        tr!("--idle-timeout expects a positive number of seconds.")
+                    );
+                    return None;
+                }
+            },
+
+            "--chunked" => config.chunked = true,
+
+            "--socket" => match all_args.next() {
+                Some(path) => config.socket = Some(path),
+                None => {
+                    eprintln!("{}", tr!("--socket expects the path of the socket."));
+                    return None;
+                }
+            },
+
+            "--access-log" => match all_args.next() {
+                Some(path) => config.access_log = Some(path),
+                None => {
+                    eprintln!("{}", tr!("--access-log expects the path of the log file."));
+                    return None;
+                }
+            },
+
+            "--access-log-size" => match all_args.next().and_then(|s| s.parse::<u64>().ok()) {
+                Some(bytes) => config.access_log_size = Some(bytes),
+                None => {
+                    eprintln!("{}", tr!("--access-log-size expects the number of bytes."));
+                    return None;
+                }
+            },
+
+            "--datagram" => match all_args.next() {
+                Some(path) => config.datagram = Some(path),
+                None => {
+                    eprintln!("{}", tr!("--datagram expects the path of the socket."));
+                    return None;
+                }
+            },
+
+            "--max-size" => match all_args.next().and_then(|s| s.parse::<usize>().ok()) {
+                Some(bytes) => config.max_size = Some(bytes),
+                None => {
+                    eprintln!("{}", tr!("--max-size expects the number of bytes."));
+                    return None;
+                }
+            },
+
+            "--workers" => match all_args.next().and_then(|s| s.parse::<usize>().ok()) {
+                Some(workers) if workers > 0 => config.workers = Some(workers),
+                _ => {
+                    eprintln!("{}", tr!("--workers expects a positive number of threads."));
+                    return None;
+                }
+            },
+
+            "--max-connections" => match all_args.next().and_then(|s| s.parse::<usize>().ok()) {
+                Some(max) if max > 0 => config.max_connections = Some(max),
+                _ => {
+                    eprintln!(
+                        "{}",
+                        tr!("--max-connections expects a positive number of connections.")
+                    );
+                    return None;
+                }
+            },
+
+            "--log" => match all_args
+                .next()
+                .and_then(|name| daemon::Log::from_name(&name))
+            {
+                Some(log) => config.log = log,
+                None => {
+                    eprintln!("{}", tr!("--log expects off, error or info."));
+                    return None;
+                }
+            },
+
+            "--max-output" => match all_args.next().and_then(|s| s.parse::<usize>().ok()) {
+                Some(bytes) => config.max_output = Some(bytes),
+                None => {
+                    eprintln!("{}", tr!("--max-output expects the number of bytes."));
+                    return None;
+                }
+            },
+
+            _ => args.push(arg),
+        }
+    }
+
+    if env::var("MD2HTM_TRACE").is_ok_and(|trace| trace == "1") {
+        mode.trace = true;
+    }
+
+    builder = builder
+        .slug_affixes(&slug_prefix, &slug_suffix)
+        .trace(mode.trace);
+
+    Some(Options {
+        builder,
+        mode,
+        daemon: config,
+        build: build_config,
+        args,
+    })
+}
+
+/// Parser options of the document with its pragma applied on top of the
+/// other layers, and the markdown after the pragma. Problems with the pragma
+/// are printed and the options of the other layers are used.
+fn pragma<'a>(
+    settings: &[Setting],
+    builder: &ParserBuilder,
+    markdown: &'a [u8],
+) -> (ParserBuilder, &'a [u8]) {
+    let (pragma, body) = config::pragma(markdown);
+    let mut settings: Vec<Setting> = settings.to_vec();
+    let layers: usize = settings.len();
+
+    match pragma {
+        Ok(pragma) => settings.extend(pragma),
+        Err(e) => eprintln!("{}", tr!("Ignoring the pragma: {}", e)),
+    }
+
+    // Without a pragma or a dialect to detect, the other layers already made the builder
+    if settings.len() == layers && !config::auto_dialect(&settings) {
+        return (builder.clone(), body);
+    }
+
+    config::resolve_dialect(&mut settings, body);
+
+    let Some(options) = options(config::to_args(&config::effective(&settings))) else {
+        return (builder.clone(), body);
+    };
+
+    // Raw fences would let the document write the HTML safe mode escapes
+    let (before, after) = (builder.clone().build(), options.builder.clone().build());
+
+    if after.is_safe() && after.has(Ext::RawFence) && !before.has(Ext::RawFence) {
+        eprintln!(
+            "{}",
+            tr!(
+                "Ignoring the pragma: {}",
+                tr!("The pragma can't enable raw-fence in safe mode.")
+            )
+        );
+        return (builder.clone(), body);
+    }
+
+    (options.builder, body)
+}
+
+/// What is written to the output file besides the converted markdown
+#[derive(Debug, Clone, Default)]
+struct Mode {
+    /// Only the excerpt is converted
+    excerpt: bool,
+    /// The HTML is wrapped in the root tags
+    standalone: bool,
+    /// The headings are written into a JSON file next to the HTML
+    heading_map: bool,
+    /// The source map is written into a JSON file next to the HTML
+    source_map: bool,
+    /// The state transitions of the parser are written into a JSON lines file
+    trace: bool,
+    /// Where the transitions go, next to the HTML if not given
+    trace_file: Option<PathBuf>,
+    /// The HTML is checked to be well-formed
+    validate: bool,
+    /// Command the corpus of xtest is compared with
+    reference: Option<String>,
+    /// Large documents are parsed in parts on this many threads
+    threads: Option<usize>,
+    /// Document the load test sends
+    sample: Option<PathBuf>,
+    /// Connections the load test keeps busy at once
+    clients: Option<usize>,
+    /// How long the load test runs
+    duration: Option<Duration>,
+    /// Snippets added to the end of the head and of the body of the page
+    inject_head: Option<PathBuf>,
+    inject_body: Option<PathBuf>,
+    /// Nonce given to the scripts and styles of the snippets
+    nonce: Option<String>,
+    /// Stylesheet of standalone documents
+    theme: Option<Theme>,
+    /// The theme goes into the head instead of a file next to the page
+    inline_theme: bool,
+    /// The output is an EPUB chapter with its navs in a file next to it
+    epub: bool,
+    /// Input that looks binary is converted anyway
+    force_binary: bool,
+    /// Line endings the HTML is written with
+    eol: Eol,
+}
+
 /// Parse source file into destination file
-fn parse<P: AsRef<std::path::Path>>(src: P, dst: P) -> Result<()> {
-    let mut infile: File = File::open(src)?;
+fn parse<P: AsRef<std::path::Path>>(
+    src: P,
+    dst: P,
+    builder: &ParserBuilder,
+    settings: &[Setting],
+    max_output: Option<usize>,
+    mode: Mode,
+) -> Result<()> {
+    let mut infile: File = File::open(&src)?;
     let mut markdown: Vec<u8> = Vec::with_capacity(16 * 1024);
     infile.read_to_end(&mut markdown)?;
-    let output: Vec<u8> = mdstate::MDS::parse(markdown);
+
+    if !mode.force_binary && looks_binary(&markdown) {
+        eprintln!(
+            "{}",
+            tr!(
+                "{} looks like a binary file, not markdown. Give --force-binary to convert it anyway.",
+                src.as_ref().display()
+            )
+        );
+        std::process::exit(1);
+    }
+
+    let (front, markdown) = split_front(&mode, &markdown);
+    let (builder, markdown) = pragma(settings, builder, markdown);
+    let parser: Parser = Parser::new(builder.build());
+    let limits: Limits = Limits {
+        max_output,
+        ..Limits::default()
+    };
+
+    let parsed = match (mode.excerpt, mode.threads) {
+        (true, _) => parser.excerpt_limited(markdown, &limits),
+        (false, Some(threads)) => parser.parse_parallel(markdown, threads, &limits),
+        (false, None) => parser.parse_limited(markdown, &limits),
+    };
+
+    let output: Output = match parsed {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("{}", tr!("Couldn't parse the target: {}", e));
+            return Ok(());
+        }
+    };
+
+    if mode.validate {
+        if let Err(e) = validate(&output.html) {
+            eprintln!("{}", tr!("The HTML isn't well-formed at {}", e));
+        }
+    }
+
+    if mode.heading_map {
+        let mut map: String = String::new();
+        json::headings(&mut map, &output.headings);
+        map.push('\n');
+        std::fs::write(dst.as_ref().with_extension("headings.json"), map)?;
+    }
+
+    if mode.trace {
+        let mut log: String = String::new();
+        json::transitions(&mut log, &output.trace);
+        let file: PathBuf = mode
+            .trace_file
+            .clone()
+            .unwrap_or_else(|| dst.as_ref().with_extension("trace.jsonl"));
+        std::fs::write(file, log)?;
+    }
+
+    if mode.source_map {
+        let mut map: String = String::new();
+        json::mappings(&mut map, &output.source_map);
+        map.push('\n');
+        std::fs::write(dst.as_ref().with_extension("map.json"), map)?;
+    }
+
+    if mode.epub {
+        let chapter: &std::path::Path = dst.as_ref();
+        let title: String = head::front_value(front, "title")
+            .or_else(|| {
+                let first = output.headings.iter().find(|heading| heading.level == 1);
+                first.map(|heading| heading.text.clone())
+            })
+            .unwrap_or_else(|| {
+                chapter
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into()
+            });
+        let name: String = chapter
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into();
+        std::fs::write(
+            chapter.with_extension("nav.xhtml"),
+            epub::nav(&output.headings, &name, &title),
+        )?;
+    }
+
+    let stats: Option<Stats> = output.stats;
+    let errors: bool = has_errors(&output.diagnostics);
+    let mut output: Vec<u8> = report(output);
+
+    if let Some(stats) = stats {
+        println!(
+            "{}",
+            tr!("{} words, {} min read.", stats.words, stats.reading_minutes)
+        );
+    }
+
+    if mode.standalone {
+        let prefix: &str = parser.options().class_prefix();
+        let theme: Option<String> = match (mode.theme, mode.inline_theme) {
+            (Some(theme), true) => Some(theme.inline(prefix, mode.nonce.as_deref())),
+            (Some(theme), false) => {
+                let dir: &std::path::Path = dst.as_ref().parent().unwrap_or(".".as_ref());
+                Some(theme.link(prefix, dir)?)
+            }
+            (None, _) => None,
+        };
+        let head: Head = Head {
+            stats,
+            theme,
+            inject_head: head::snippet(mode.inject_head.as_deref(), mode.nonce.as_deref())?,
+            inject_body: head::snippet(mode.inject_body.as_deref(), mode.nonce.as_deref())?,
+            ..Head::new(&parser, front, markdown, &output)
+        };
+        output = standalone(output, &head);
+    }
+
     let mut outfile: File = File::create(dst)?;
-    outfile.write_all(&output)?;
-    println!("Target parsed!");
+    outfile.write_all(&mode.eol.apply(output))?;
+    println!("{}", tr!("Target parsed!"));
+
+    if errors {
+        std::process::exit(1);
+    }
+
     Ok(())
 }
 
-fn print_help() {
-    println!(
-        "Usage md2htm [daemon|source file|help] [[output file]]
+/// Checks if the input looks like a binary file picked by mistake: it has NUL
+/// bytes, or over a tenth of its start is control characters or not UTF-8
+fn looks_binary(input: &[u8]) -> bool {
+    if input.contains(&0) {
+        return true;
+    }
 
-    Parses Markdown to HTML without adding any of the root tags.
+    let sample: &[u8] = &input[..input.len().min(BINARY_SAMPLE)];
+    let odd: usize = sample
+        .utf8_chunks()
+        .map(|chunk| {
+            chunk.invalid().len()
+                + chunk
+                    .valid()
+                    .bytes()
+                    .filter(|b| b.is_ascii_control() && !b.is_ascii_whitespace())
+                    .count()
+        })
+        .sum();
 
-    help, --help, h, -h, ?      Show this help and exit.
+    odd * 10 > sample.len()
+}
 
-    daemon, --daemon, d, -d     Start the program in daemon mode that listens a socket in {}.
-                                If given, no other arguments are expected.
+/// Splits the front matter off the markdown of standalone documents, which
+/// have it in their head. Other documents are converted as they are.
+fn split_front<'a>(mode: &Mode, markdown: &'a [u8]) -> (&'a [u8], &'a [u8]) {
+    match mode.standalone {
+        true => head::front_matter(markdown),
+        false => (&[], markdown),
+    }
+}
 
-    [source file]               The path of the source file containing the Markdown text.
-                                Doesn't expect a file extension '.md' or anything else.
+/// Parses the source file with the checks of `audit` only to print the
+/// diagnostics. Exits with status 1 if there were any, or prints the
+/// `found_none` message with the file.
+fn audit(
+    src: &str,
+    builder: &ParserBuilder,
+    settings: &[Setting],
+    audit: fn(ParserBuilder, bool) -> ParserBuilder,
+    found_none: &str,
+) -> Result<()> {
+    let mut markdown: Vec<u8> = Vec::with_capacity(16 * 1024);
+    File::open(src)?.read_to_end(&mut markdown)?;
+
+    let (builder, markdown) = pragma(settings, builder, &markdown);
+    let output: Output = Parser::new(audit(builder, true).build()).parse(markdown);
+
+    for diagnostic in &output.diagnostics {
+        println!("{src}: {}", i18n::describe(diagnostic));
+    }
+
+    if output.diagnostics.is_empty() {
+        println!("{}", i18n::fill(found_none, &[&src]));
+        Ok(())
+    } else {
+        std::process::exit(1);
+    }
+}
+
+/// Prints the diagnostics and returns the HTML
+fn report(output: Output) -> Vec<u8> {
+    for diagnostic in &output.diagnostics {
+        print_diagnostic(diagnostic);
+    }
+
+    output.html
+}
+
+fn print_diagnostic(diagnostic: &Diagnostic) {
+    match diagnostic.severity {
+        Severity::Warning => eprintln!("{}", tr!("Warning on {}", i18n::describe(diagnostic))),
+        Severity::Error => eprintln!("{}", tr!("Error on {}", i18n::describe(diagnostic))),
+    }
+}
+
+/// Checks if any diagnostic was denied with --deny
+fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+}
 
-    [output file]               Optional. The path of the output file. If omitted,
-                                the program uses the same path as the source file,
-                                but replaces/appends the file extention to .html.
-                                Doesn't expect the file extension '.html'.
+/// Names of the diagnostic codes separated with commas
+fn code_names() -> String {
+    Code::ALL
+        .iter()
+        .map(|code| code.name())
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+/// Names of the extensions separated with commas
+fn extension_names() -> String {
+    Ext::ALL
+        .iter()
+        .map(|ext| ext.name())
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+/// Prints the options in effect as a config file, with the layer that set
+/// each of them. The pragma of the source file is included if it is given.
+fn show_config(mut settings: Vec<Setting>, src: Option<&String>) -> Result<()> {
+    if let Some(src) = src {
+        let markdown: Vec<u8> = std::fs::read(src)?;
+
+        match config::pragma(&markdown).0 {
+            Ok(pragma) => settings.extend(pragma),
+            Err(e) => eprintln!("{}", tr!("Ignoring the pragma: {}", e)),
+        }
+    }
+
+    let effective: Vec<&Setting> = config::effective(&settings);
 
-    Examples:
+    for (name, takes_value) in help::flags() {
+        let mut set = effective
+            .iter()
+            .filter(|setting| setting.key == name)
+            .peekable();
 
-    To parse a file named markdown.md into webpage.html, when both are in local directory:
-    md2htm markdown.md webpage.html
+        if set.peek().is_none() {
+            match takes_value {
+                true => println!("# {name} = ...  # {}", tr!("default")),
+                false => println!("{name} = false  # {}", tr!("default")),
+            }
+        }
 
-    To parse file named markdown.md into markdown.html, when source file is in local directory:
-    md2htm markdown.md
+        for setting in set {
+            let value: String = match &setting.value {
+                Value::Bool(value) => value.to_string(),
+                Value::Text(text) => {
+                    let mut value: String = String::new();
+                    json::string(&mut value, text);
+                    value
+                }
+            };
 
-    To run this program in daemon mode, any of these commands will do:
-    md2htm daemon
-    md2htm --daemon
-    md2htm d
-    md2htm -d
+            println!("{name} = {value}  # {}", setting.source.name());
+        }
+    }
 
-    If the program doesn't have sufficient privileges to remove the socket file,
-    it can be removed manually with:
-    sudo rm {}
+    Ok(())
+}
 
-    Bugs and issues should be reported in https://github.com/rronkkeli/md2htm",
-        SOCK, SOCK
-    );
+fn print_help() {
+    println!("{}", help::general());
 }
@@ -1,12 +1,43 @@
 //! This module converts markdown to html without the root elements.
 
+use crate::ast::Document;
+use crate::csp;
+use crate::cursor::{Cursor, Position, TAB_SIZE};
+use crate::diagnostic::{Code, Diagnostic, Level, Severity};
+use crate::error::Error;
+use crate::estimate::estimate_output;
+use crate::events::{Html, HtmlEvents, Mark};
+use crate::heading::{HeadingAnchor, Headings};
+use crate::idna;
+use crate::limits::{Limits, CHECK_INTERVAL};
+use crate::options::{
+    Align, CodeBlocks, Em, Ext, Indented, MissingAlt, Numbering, Options, Placement, Utf8,
+};
+use crate::paragraphs;
+use crate::print;
+use crate::scan;
+use crate::slides;
+use crate::sourcemap::{Mapping, SourceMap};
+use crate::stats::Stats;
+use crate::text;
+use crate::trace::Transition;
 use crate::writeto::*;
-use std::boxed::Box;
+use crate::xhtml;
+use alloc::{
+    borrow::Cow,
+    boxed::Box,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::ops::Range;
 
 const TAG_P_O: &[u8; 3] = b"<p>";
 const TAG_P_C: &[u8; 4] = b"</p>";
 const TAG_CODEB_O: &[u8; 37] = b"<div class=\"code\"><code class=\"code\">";
 const TAG_CODEB_C: &[u8; 13] = b"</code></div>";
+const TAG_PRE_O: &[u8; 11] = b"<pre><code>";
+const TAG_PRE_C: &[u8; 13] = b"</code></pre>";
 const TAG_CODEI_O: &[u8; 38] = b"<span class=\"code\"><code class=\"code\">";
 const TAG_CODEI_C: &[u8; 14] = b"</code></span>";
 const TAG_INT_O: &[u8; 20] = b"<div class=\"intend\">";
@@ -15,14 +46,98 @@ const TAG_I_O: &[u8; 3] = b"<i>";
 const TAG_I_C: &[u8; 4] = b"</i>";
 const TAG_B_O: &[u8; 3] = b"<b>";
 const TAG_B_C: &[u8; 4] = b"</b>";
+const TAG_EM_O: &[u8; 4] = b"<em>";
+const TAG_EM_C: &[u8; 5] = b"</em>";
+const TAG_STRONG_O: &[u8; 8] = b"<strong>";
+const TAG_STRONG_C: &[u8; 9] = b"</strong>";
 const TAG_U_O: &[u8; 3] = b"<u>";
 const TAG_U_C: &[u8; 4] = b"</u>";
 const TAG_LI_O: &[u8; 4] = b"<li>";
 const TAG_LI_C: &[u8; 5] = b"</li>";
 const TAG_UL_O: &[u8; 4] = b"<ul>";
 const TAG_UL_C: &[u8; 5] = b"</ul>";
+const TAG_OL_O: &[u8; 4] = b"<ol>";
+const TAG_OL_C: &[u8; 5] = b"</ol>";
 const TAG_HR: &[u8; 4] = b"<hr>";
+const TAG_KBD_O: &[u8; 5] = b"<kbd>";
+const TAG_KBD_C: &[u8; 6] = b"</kbd>";
+const TAG_DEL_O: &[u8; 5] = b"<del>";
+const TAG_DEL_C: &[u8; 6] = b"</del>";
+const TAG_BQ_O: &[u8; 12] = b"<blockquote>";
+const TAG_BQ_C: &[u8; 13] = b"</blockquote>";
+
+/// Byte written before the markdown parsed for its inline syntax only
+const INLINE_LEAD: u8 = 0x01;
+
+/// Lines indented at least this many columns are code with [`Indented::Code`]
+const CODE_INDENT: usize = 4;
+
+/// Quotes nested deeper than this are text, so the parser can't run out of stack
+const MAX_QUOTE_DEPTH: usize = 32;
+
+/// Tags that depend on the options, chosen once instead of on every key
+#[derive(Debug, Clone)]
+pub(crate) struct Tags {
+    i_o: &'static [u8],
+    i_c: &'static [u8],
+    b_o: &'static [u8],
+    b_c: &'static [u8],
+    code_o: Cow<'static, [u8]>,
+    code_c: &'static [u8],
+    /// Code blocks written byte by byte, which are always divs
+    codeb_o: Cow<'static, [u8]>,
+    /// Inline code
+    codei_o: Cow<'static, [u8]>,
+    /// Indented blocks
+    int_o: Cow<'static, [u8]>,
+}
+
+impl Tags {
+    pub(crate) fn new(options: &Options) -> Self {
+        let (i_o, i_c, b_o, b_c): (&[u8], &[u8], &[u8], &[u8]) = match options.emphasis {
+            Em::Presentational => (TAG_I_O, TAG_I_C, TAG_B_O, TAG_B_C),
+            Em::Semantic => (TAG_EM_O, TAG_EM_C, TAG_STRONG_O, TAG_STRONG_C),
+        };
+
+        let (code_o, code_c): (&[u8], &[u8]) = match options.code_blocks {
+            CodeBlocks::Div => (TAG_CODEB_O, TAG_CODEB_C),
+            CodeBlocks::Pre => (TAG_PRE_O, TAG_PRE_C),
+        };
+
+        Self {
+            i_o,
+            i_c,
+            b_o,
+            b_c,
+            code_o: prefixed(code_o, &options.class_prefix),
+            code_c,
+            codeb_o: prefixed(TAG_CODEB_O, &options.class_prefix),
+            codei_o: prefixed(TAG_CODEI_O, &options.class_prefix),
+            int_o: prefixed(TAG_INT_O, &options.class_prefix),
+        }
+    }
+}
+
+/// The tag with the prefix added to its class names
+fn prefixed(tag: &'static [u8], prefix: &str) -> Cow<'static, [u8]> {
+    const CLASS: &[u8] = b"class=\"";
+
+    if prefix.is_empty() {
+        return Cow::Borrowed(tag);
+    }
+
+    let mut prefixed: Vec<u8> = Vec::with_capacity(tag.len() + 2 * prefix.len());
+    let mut rest: &[u8] = tag;
+
+    while let Some(at) = rest.windows(CLASS.len()).position(|w| w == CLASS) {
+        prefixed.extend_from_slice(&rest[..at + CLASS.len()]);
+        prefixed.extend_from_slice(prefix.as_bytes());
+        rest = &rest[at + CLASS.len()..];
+    }
 
+    prefixed.extend_from_slice(rest);
+    Cow::Owned(prefixed)
+}
 
 /// Markdown states
 #[derive(Debug)]
@@ -43,6 +158,8 @@ enum State {
     /// has been identified.
     Italic(bool),
     Underscore,
+    /// Between the `~~` of a strikethrough
+    Strike,
     /// Counts the ` characters if they are in a sequence. True if the previous
     /// character was `, otherwise false.
     Code(bool, u8),
@@ -54,18 +171,55 @@ enum State {
     UList(bool, bool),
     LItem,
     Hor(u8),
+    /// The quote has been written and its lines are skipped
+    Blockquote,
+}
+
+impl State {
+    /// Name of the state without its data
+    fn name(&self) -> &'static str {
+        match self {
+            State::None => "None",
+            State::Header(_, _) => "Header",
+            State::Paragraph => "Paragraph",
+            State::Intendation(_, _) => "Intendation",
+            State::Bold(_) => "Bold",
+            State::Italic(_) => "Italic",
+            State::Underscore => "Underscore",
+            State::Strike => "Strike",
+            State::Code(_, _) => "Code",
+            State::Link(_) => "Link",
+            State::Exclamation => "Exclamation",
+            State::Image(_) => "Image",
+            State::Escape => "Escape",
+            State::UList(_, _) => "UList",
+            State::LItem => "LItem",
+            State::Hor(_) => "Hor",
+            State::Blockquote => "Blockquote",
+        }
+    }
 }
 
 #[derive(Debug)]
 struct IntenData {
+    /// Whitespace of the indented lines held back until it's known if the
+    /// indentation goes on. It's copied like the text of the links is into
+    /// the arena, since the events are read from the HTML and have no
+    /// markdown to borrow.
     inner: Vec<u8>,
 }
 
 #[derive(Debug)]
 struct Linkdata {
     status: Linkstatus,
-    alt: Vec<u8>,
-    link: Vec<u8>,
+    /// Where the text and the url are in the arena of the state machine
+    alt: Range<usize>,
+    link: Range<usize>,
+    /// Bytes over the limit have been left out
+    truncated: bool,
+    /// Where an image open in the text is. Its markdown goes to the text as
+    /// it is and becomes the image when the link is written.
+    image: Option<Linkstatus>,
 }
 
 #[derive(Debug)]
@@ -75,7 +229,85 @@ enum Linkstatus {
     Link,
 }
 
+/// Text and url of a link as they are in the arena
+struct Linkbytes<'a> {
+    alt: &'a [u8],
+    link: &'a [u8],
+}
+
 impl Linkdata {
+    /// A link starting at the end of the arena
+    fn new(arena: &[u8]) -> Self {
+        let end: usize = arena.len();
+
+        Self {
+            status: Linkstatus::Alt(0),
+            alt: end..end,
+            link: end..end,
+            truncated: false,
+            image: None,
+        }
+    }
+
+    /// The text and url in the arena
+    fn bytes<'a>(&self, arena: &'a [u8]) -> Linkbytes<'a> {
+        Linkbytes {
+            alt: &arena[self.alt.clone()],
+            link: &arena[self.link.clone()],
+        }
+    }
+
+    /// Adds the byte to the text, leaving out control characters and the
+    /// bytes over the limit. The first byte over the limit is reported.
+    fn push_alt(
+        &mut self,
+        arena: &mut Vec<u8>,
+        byte: u8,
+        position: Position,
+        options: &Options,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        if bounded_push(
+            arena,
+            &mut self.alt,
+            byte,
+            options.max_link_len,
+            &mut self.truncated,
+        ) {
+            diagnostics.push(link_truncated(position, options));
+        }
+
+        // The url starts after the text
+        self.link = self.alt.end..self.alt.end;
+    }
+
+    /// Adds the byte to the url like [`Self::push_alt`] adds it to the text
+    fn push_link(
+        &mut self,
+        arena: &mut Vec<u8>,
+        byte: u8,
+        position: Position,
+        options: &Options,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        if bounded_push(
+            arena,
+            &mut self.link,
+            byte,
+            options.max_link_len,
+            &mut self.truncated,
+        ) {
+            diagnostics.push(link_truncated(position, options));
+        }
+    }
+
+    /// Checks if the `[` after a `!` in the text of the link opens an image
+    fn opens_image(&self, arena: &[u8]) -> bool {
+        matches!(self.status, Linkstatus::Alt(0))
+            && self.image.is_none()
+            && arena[self.alt.clone()].ends_with(b"!")
+    }
+
     /// Checks if the linkstatus is Alt
     fn is_alt(&self) -> bool {
         self.status.is_alt()
@@ -95,6 +327,266 @@ impl Linkdata {
     }
 }
 
+impl Linkbytes<'_> {
+    /// Checks if the url is relative or uses a scheme that can't run scripts
+    fn has_safe_scheme(&self) -> bool {
+        safe_scheme(self.link)
+    }
+
+    /// Writes the url, or nothing at all in safe mode if the url isn't safe.
+    /// Relative urls get the base url in front.
+    fn write_url(&self, output: &mut Html, options: &Options) {
+        if !options.safe || self.has_safe_scheme() {
+            if let Some(base) = options.base_url.as_ref().filter(|_| is_relative(self.link)) {
+                output.escape(base.as_bytes(), true);
+            }
+
+            output.escape(&self.url(options), options.safe);
+        }
+    }
+
+    /// The url percent-encoded and its host in Punycode, as far as the
+    /// options ask for them
+    fn url(&self, options: &Options) -> Cow<'_, [u8]> {
+        let (url, _) = self.destination();
+        let url: Cow<[u8]> = match options.punycode {
+            true => idna::punycode_host(url),
+            false => Cow::Borrowed(url),
+        };
+
+        match (options.encode_urls, url) {
+            (true, Cow::Borrowed(url)) => encode_url(url),
+            (true, Cow::Owned(url)) => Cow::Owned(encode_url(&url).into_owned()),
+            (false, url) => url,
+        }
+    }
+
+    /// The url and the quoted title after it, like `a.html "Title"`
+    fn destination(&self) -> (&[u8], Option<&[u8]>) {
+        let link: &[u8] = self.link;
+
+        let title = |quote: u8| {
+            let start: usize = link.windows(2).position(|w| w == [b' ', quote])?;
+            let title: &[u8] = link[start + 2..].strip_suffix(&[quote])?;
+            Some((link[..start].trim_ascii_end(), title))
+        };
+
+        match link.last() {
+            Some(&quote @ (b'"' | b'\'')) => match title(quote) {
+                Some((url, title)) => (url, Some(title)),
+                None => (link, None),
+            },
+            _ => (link, None),
+        }
+    }
+
+    /// Writes the title attribute of the link or the image, if it has one
+    fn write_title(&self, output: &mut Html) {
+        if let (_, Some(title)) = self.destination() {
+            output.write(b" title=\"");
+            output.escape(title, true);
+            output.push(b'"');
+        }
+    }
+
+    /// Checks if the link is to an email address
+    fn is_mailto(&self) -> bool {
+        self.link.len() > 7 && self.link[..7].eq_ignore_ascii_case(b"mailto:")
+    }
+
+    /// Outputs an link
+    fn write_link(&self, output: &mut Html, options: &Options, assets: &mut Vec<String>) {
+        let obfuscate: bool = options.obfuscate_emails && self.is_mailto();
+        let tag: usize = output.len();
+        output.write(b"<a href=\"");
+
+        if obfuscate {
+            output.encode(self.link);
+        } else {
+            self.write_url(output, options);
+        }
+
+        output.write(b"\"");
+        self.write_title(output);
+
+        if let Some(class) = &options.external_class {
+            if is_external(self.link, options.site.as_deref()) {
+                output.write(b" class=\"");
+                output.escape(class.as_bytes(), true);
+                output.push(b'"');
+            }
+        }
+
+        output.push(b'>');
+        output.tagged(tag);
+
+        if obfuscate && self.alt.contains(&b'@') {
+            output.encode(self.alt);
+        } else {
+            self.write_text(output, options, assets);
+        }
+
+        output.tags(b"</a>");
+    }
+
+    /// Writes the text of the link with the images in it
+    fn write_text(&self, output: &mut Html, options: &Options, assets: &mut Vec<String>) {
+        let mut text: &[u8] = self.alt;
+
+        while let Some((before, image, after)) = split_image(text) {
+            output.escape(before, options.safe);
+            image.write_image(output, options, &[], assets);
+            text = after;
+        }
+
+        output.escape(text, options.safe);
+    }
+
+    /// Checks if the link text is something like "here" that means nothing
+    /// without the text around it
+    fn is_vague(&self) -> bool {
+        let text: &[u8] = self.alt.trim_ascii();
+
+        [
+            &b"here"[..],
+            b"click here",
+            b"link",
+            b"this link",
+            b"more",
+            b"read more",
+        ]
+        .iter()
+        .any(|vague| text.eq_ignore_ascii_case(vague))
+    }
+
+    /// Checks if the image has no alt text, or only whitespace
+    fn lacks_alt(&self) -> bool {
+        self.alt.iter().all(|b| b.is_ascii_whitespace())
+    }
+
+    /// Makes an alt text from the image filename. `my-cat_photo.jpg?w=2`
+    /// becomes `my cat photo`.
+    fn filename_alt(&self) -> Vec<u8> {
+        let path: &[u8] = self
+            .link
+            .split(|&b| b == b'?' || b == b'#')
+            .next()
+            .unwrap_or_default();
+        let name: &[u8] = path.rsplit(|&b| b == b'/').next().unwrap_or_default();
+        let stem: &[u8] = match name.iter().rposition(|&b| b == b'.') {
+            Some(0) | None => name,
+            Some(dot) => &name[..dot],
+        };
+
+        let mut alt: Vec<u8> = Vec::with_capacity(stem.len());
+        let mut i: usize = 0;
+
+        while i < stem.len() {
+            match stem[i] {
+                b'-' | b'_' | b'+' => alt.push(b' '),
+                b'%' if stem[i..].starts_with(b"%20") => {
+                    alt.push(b' ');
+                    i += 2;
+                }
+                b => alt.push(b),
+            }
+
+            i += 1;
+        }
+
+        alt.trim_ascii().to_vec()
+    }
+
+    /// Outputs an image. With the attributes extension a `{width=640}` block
+    /// right after the image adds attributes to it. Returns the length of the
+    /// block, so it can be skipped.
+    fn write_image(
+        &self,
+        output: &mut Html,
+        options: &Options,
+        rest: &[u8],
+        assets: &mut Vec<String>,
+    ) -> usize {
+        let tag: usize = output.len();
+        output.write(b"<img src=\"");
+
+        match options.asset_prefix {
+            Some(ref prefix) if is_relative(self.link) => {
+                // The file part without the query and fragment
+                let path: &[u8] = self
+                    .destination()
+                    .0
+                    .split(|&b| b == b'?' || b == b'#')
+                    .next()
+                    .unwrap_or_default();
+                assets.push(String::from_utf8_lossy(path).into_owned());
+                output.escape(prefix.as_bytes(), true);
+                output.escape(&self.url(options), options.safe);
+            }
+
+            _ => self.write_url(output, options),
+        }
+        output.write(b"\" alt=\"");
+
+        if options.missing_alt == MissingAlt::Filename && self.lacks_alt() {
+            output.escape(&self.filename_alt(), true);
+        } else {
+            output.escape(self.alt, options.safe);
+        }
+
+        output.push(b'"');
+        self.write_title(output);
+
+        let attributes = match options.has(Ext::Attributes) {
+            true => attributes(rest),
+            false => None,
+        };
+
+        let Some((attributes, len)) = attributes else {
+            output.push(b'>');
+            output.tagged(tag);
+            return 0;
+        };
+
+        // Written in this order whatever the order in the markdown was
+        for name in [&b"width"[..], b"height", b"srcset", b"sizes"] {
+            let Some(&(_, value)) = attributes.iter().find(|(key, _)| *key == name) else {
+                continue;
+            };
+
+            let valid: bool = match name {
+                b"width" | b"height" => !value.is_empty() && value.iter().all(u8::is_ascii_digit),
+                // The candidates are comma separated urls followed by their sizes
+                b"srcset" => {
+                    !options.safe
+                        || value.split(|&b| b == b',').all(|candidate| {
+                            safe_scheme(
+                                candidate
+                                    .trim_ascii()
+                                    .split(u8::is_ascii_whitespace)
+                                    .next()
+                                    .unwrap_or_default(),
+                            )
+                        })
+                }
+                _ => true,
+            };
+
+            if valid {
+                output.push(b' ');
+                output.write(name);
+                output.write(b"=\"");
+                output.escape(value, true);
+                output.push(b'"');
+            }
+        }
+
+        output.push(b'>');
+        output.tagged(tag);
+        len
+    }
+}
+
 impl Linkstatus {
     /// Checks if the linkstatus is Alt
     fn is_alt(&self) -> bool {
@@ -106,17 +598,11 @@ impl Linkstatus {
 
     /// Checks if a `]` is being expected at some point
     fn alt_expects_closure(&self) -> bool {
-        match self {
-            Self::Alt(0) => true,
-            _ => false,
-        }
+        matches!(self, Self::Alt(0))
     }
 
     fn alt_expects_url(&self) -> bool {
-        match self {
-            Self::Alt(1) => true,
-            _ => false,
-        }
+        matches!(self, Self::Alt(1))
     }
 
     /// Checks if the linkstatus is Link
@@ -128,180 +614,669 @@ impl Linkstatus {
     }
 }
 
+/// Where the parser is with the indented lines when they don't open intend divs
+#[derive(Debug)]
+enum Indent {
+    /// Not in the indentation of a line
+    None,
+    /// Skipping the indentation at the start of a line
+    Lead { columns: usize, start: Position },
+    /// On a line of an indented code block
+    Code,
+    /// At the start of a line after an indented code block. The line breaks
+    /// are held back until it's known if the block continues.
+    CodeLead { columns: usize, breaks: Vec<u8> },
+}
+
+/// Parsed HTML and the problems noticed while parsing
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Output {
+    pub html: Vec<u8>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Word count and reading time when asked for with [`crate::ParserBuilder::stats`]
+    pub stats: Option<Stats>,
+    /// Paths of the images relative to the document, collected when
+    /// [`crate::ParserBuilder::asset_prefix`] is set
+    pub assets: Vec<String>,
+    /// Ids and lines of the headings when asked for with
+    /// [`crate::ParserBuilder::heading_map`]
+    pub headings: Vec<HeadingAnchor>,
+    /// Byte ranges of the markdown lines and their HTML when asked for with
+    /// [`crate::ParserBuilder::source_map`]
+    pub source_map: Vec<Mapping>,
+    /// State transitions when asked for with [`crate::ParserBuilder::trace`]
+    pub trace: Vec<Transition>,
+    /// Where the parser wrote the tags of the HTML
+    pub(crate) marks: Vec<Mark>,
+}
+
+impl From<Output> for Html {
+    fn from(output: Output) -> Self {
+        Html {
+            bytes: output.html,
+            marks: output.marks,
+        }
+    }
+}
+
+impl Output {
+    /// Events read back from the HTML at the tags the parser marked, to
+    /// rewrite with [`crate::push_html`]
+    pub fn html_events(&self) -> HtmlEvents<'_> {
+        HtmlEvents::new(&self.html, &self.marks)
+    }
+
+    /// Tree of the blocks read from the HTML
+    pub fn document(&self) -> Document {
+        Document::from_events(self.html_events())
+    }
+
+    /// Plain text of the HTML without the tags and character references
+    pub fn text(&self) -> String {
+        text::plain(&self.html)
+    }
+}
+
+/// A line where the parser is back at the start state after a blank line, so
+/// the rest of the markdown parses the same on its own. The counts tell how
+/// much of the output was written before the line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Boundary {
+    pub(crate) source: usize,
+    pub(crate) html: usize,
+    pub(crate) line: usize,
+    pub(crate) diagnostics: usize,
+    pub(crate) assets: usize,
+}
+
+impl Boundary {
+    /// The start of the document
+    pub(crate) const START: Boundary = Boundary {
+        source: 0,
+        html: 0,
+        line: 1,
+        diagnostics: 0,
+        assets: 0,
+    };
+}
+
 /// Markdown State machine contains a linked list of current states.
 /// Once a state has been handled, the state goes to previous and continues
 /// handling it. States need to be ended in the reverse order they have been
 /// invoked so it makes sense to trave backwards to the root state.
+#[allow(clippy::upper_case_acronyms)]
 pub struct MDS {
     current: State,
     previous: Option<Box<Self>>,
+    /// Bump arena of the text and url of the open links. Only the link on
+    /// top of the stack grows, so each one takes the end of the arena and
+    /// gives it back when it ends. It moves with the top of the stack and
+    /// goes with the state machine, so every parse starts with an empty one.
+    arena: Vec<u8>,
 }
 
 impl MDS {
+    /// Parses the markdown with the default options
     pub fn parse(bytes: Vec<u8>) -> Vec<u8> {
+        Self::parse_with(bytes, &Options::default())
+    }
+
+    /// Parses the markdown and reads the tree of its blocks from the HTML,
+    /// discarding the diagnostics
+    pub fn parse_document(bytes: Vec<u8>, options: &Options) -> Document {
+        Self::convert(bytes, options).document()
+    }
+
+    /// Parses one line of markdown for its inline syntax only, without the
+    /// `<p>` around it, for titles, tooltips and the like
+    ///
+    /// ```
+    /// use md2htm::MDS;
+    ///
+    /// assert_eq!(MDS::convert_inline("# *Not* a heading"), "# <i>Not</i> a heading");
+    /// ```
+    pub fn convert_inline(markdown: &str) -> String {
+        let options: Options = Options::default();
+        let tags: Tags = Tags::new(&options);
+        // Nothing can stop the parser without limits
+        let html: Html =
+            inline(markdown.as_bytes(), &options, &tags, &Limits::default()).unwrap_or_default();
+        String::from_utf8_lossy(&html).into_owned()
+    }
+
+    /// Parses the markdown from the reader into the writer a piece at a time
+    /// with the default options, see [`crate::Parser::parse_stream`]
+    #[cfg(feature = "std")]
+    pub fn parse_stream<R: std::io::Read, W: std::io::Write>(
+        reader: R,
+        writer: W,
+    ) -> std::io::Result<()> {
+        crate::Parser::default().parse_stream(reader, writer, &Limits::default())
+    }
+
+    /// Parses the markdown discarding the diagnostics
+    pub fn parse_with(bytes: Vec<u8>, options: &Options) -> Vec<u8> {
+        Self::convert(bytes, options).html
+    }
+
+    /// Parses the markdown and collects the diagnostics instead of printing them,
+    /// so the parser itself never does any I/O.
+    pub fn convert(bytes: Vec<u8>, options: &Options) -> Output {
+        match Self::run(&bytes, options, &Tags::new(options), &Limits::default()) {
+            Ok(output) => output,
+            // Nothing can stop the parser without limits
+            Err(_) => unreachable!(),
+        }
+    }
+
+    pub(crate) fn run(
+        bytes: &[u8],
+        options: &Options,
+        tags: &Tags,
+        limits: &Limits,
+    ) -> Result<Output, Error> {
+        Self::run_recording(bytes, Position::START, options, tags, limits, None)
+    }
+
+    /// Parses the markdown starting at `start` of the document and records
+    /// the lines where parsing could start over from scratch into
+    /// `boundaries`, if given
+    pub(crate) fn run_recording(
+        bytes: &[u8],
+        start: Position,
+        options: &Options,
+        tags: &Tags,
+        limits: &Limits,
+        mut boundaries: Option<&mut Vec<Boundary>>,
+    ) -> Result<Output, Error> {
+        if options.utf8 != Utf8::Keep {
+            if let Err(e) = core::str::from_utf8(bytes) {
+                let mut cursor: Cursor = Cursor::at(start);
+                bytes[..e.valid_up_to()]
+                    .iter()
+                    .for_each(|&byte| cursor.advance(byte));
+
+                let mut diagnostics: Vec<Diagnostic> = vec![Diagnostic::with_args(
+                    cursor.position(),
+                    Code::InvalidUtf8,
+                    "Byte {} isn't valid UTF-8.",
+                    vec![(start.offset + e.valid_up_to()).to_string()],
+                )];
+
+                if options.utf8 == Utf8::Reject {
+                    diagnostics[0].severity = Severity::Error;
+                    return Ok(Output {
+                        diagnostics,
+                        ..Output::default()
+                    });
+                }
+
+                let text: String = String::from_utf8_lossy(bytes).into_owned();
+                let mut output: Output =
+                    Self::run_recording(text.as_bytes(), start, options, tags, limits, boundaries)?;
+
+                apply_levels(&mut diagnostics, options, None);
+                diagnostics.append(&mut output.diagnostics);
+                output.diagnostics = diagnostics;
+                return Ok(output);
+            }
+        }
+
         let mut state_machine: MDS = Self {
             current: State::None,
             previous: Option::None,
+            arena: Vec::new(),
         };
 
-        // HTML data output will be larger than Markdown data,
-        // so output buffer may be larger than the input buffer.
-        // This makes reallocation unlikely, resulting in faster
-        // processing speed.
-        let mut output: Vec<u8> = Vec::with_capacity(bytes.capacity() << 1);
+        // Allocating the output for the expected size of the HTML makes
+        // reallocation unlikely, resulting in faster processing speed.
+        let mut output: Html = Html::with_capacity(estimate_output(bytes));
+
+        let mut cursor: Cursor = Cursor::at(start);
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        let mut headings: Headings = Headings::new();
+        let mut toc_marker: Option<usize> = None;
+        // Links to #fragments and where they were
+        let mut fragments: Vec<(Position, Vec<u8>)> = Vec::new();
+        // Relative image paths
+        let mut assets: Vec<String> = Vec::new();
+        let mut source_map: SourceMap = SourceMap::new();
+        let mut trace: Vec<Transition> = Vec::new();
+        // The previous byte, which may have changed the states
+        let mut traced: Option<(Position, u8)> = None;
+
+        let mut indent: Indent = Indent::None;
+        // Bytes before this have been handled already
+        let mut skip_until: usize = 0;
+        // The line after a list item started with indentation
+        let mut list_indent: bool = false;
+        // Where the search for the closing `~~` of a strikethrough got to
+        let mut closer: Closer = Closer::default();
+
+        let safe: bool = options.safe;
+
+        for (i, &byte) in bytes.iter().enumerate() {
+            if i % CHECK_INTERVAL == 0 && limits.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
 
-        let mut line_counter: usize = 1;
-        // Counts the current bytes that are not new lines or carriage returns, on the line.
-        let mut column_counter: usize = 0;
+            let position: Position = cursor.position();
+            cursor.advance(byte);
+            let sourcepos: Option<Position> = options.sourcepos.then_some(position);
 
-        for byte in bytes {
-            match byte {
-                0..10 | 11..13 | 14..32 | 34..35 | 36..40 | 43..45 | 46..91 | 97..=255 => {
-                    match state_machine.current {
-                        State::None => {
-                            state_machine = state_machine.rise(State::Paragraph);
-                            output.write(TAG_P_O);
-                            output.push(byte);
-                        }
+            if options.trace {
+                if let Some((position, byte)) = traced.replace((position, byte)) {
+                    state_machine.trace(&mut trace, position, byte);
+                }
+            }
 
-                        State::Code(ls, n) => {
-                            if ls {
-                                match n {
-                                    1 => {
-                                        state_machine.current = State::Code(false, n);
-                                        // Open inline code span tag and code tag
-                                        output.write(TAG_CODEI_O);
-                                    }
+            if i < skip_until {
+                continue;
+            }
 
-                                    3 => {
-                                        state_machine.current = State::Code(false, n);
-                                        // Open code block div tag and code tag
-                                        output.write(TAG_CODEB_O);
-                                    }
+            // Lines skipped in one go belong to the line that started the block
+            if options.source_map && i != 0 && line_ended(&bytes[..i], byte) {
+                source_map.line(i, output.len());
+            }
 
-                                    _ => {
-                                        println!("Warning: Unexpected code block state! Undefined behaviour may occur! Trying to mitigate damage by ignoring previous key on line {} column {}..", line_counter, column_counter);
-                                        state_machine = state_machine.fall();
-                                    }
-                                }
-                            }
-                            output.push(byte);
-                        }
+            if let Some(boundaries) = boundaries.as_deref_mut() {
+                if after_blank_line(&bytes[..i])
+                    && line_ended(&bytes[..i], byte)
+                    && state_machine.is_none()
+                    && matches!(indent, Indent::None)
+                    && !list_indent
+                    && paragraph_end(&output).is_none()
+                    && list_item_end(&output).is_none()
+                {
+                    boundaries.push(Boundary {
+                        source: i,
+                        html: output.len(),
+                        line: position.line,
+                        diagnostics: diagnostics.len(),
+                        assets: assets.len(),
+                    });
+                }
+            }
 
-                        State::Escape => {
-                            match byte {
-                                b'<' => output.write(b"&lt;"),
-                                b'>' => output.write(b"&gt;"),
-                                _ => output.push(byte),
+            if options.has(Ext::RawFence)
+                && state_machine.is_none()
+                && (i == 0 || matches!(bytes[i - 1], b'\n' | b'\r'))
+            {
+                if let Some((contents, end)) = block(&bytes[i..], &[b"```raw", b"```html"], b"```")
+                {
+                    output.raw(&bytes[i..][contents]);
+                    limits.check_output(output.len())?;
+                    // The line break after the fence is handled as usual
+                    skip_until = i + end;
+                    continue;
+                }
+            }
+
+            if options.has(Ext::Comments)
+                && state_machine.is_none()
+                && (i == 0 || matches!(bytes[i - 1], b'\n' | b'\r'))
+            {
+                if let Some((_, end)) = block(&bytes[i..], &[b"```comment"], b"```").or_else(|| {
+                    block(
+                        &bytes[i..],
+                        &[b"<!-- md2htm:off -->"],
+                        b"<!-- md2htm:on -->",
+                    )
+                }) {
+                    // Leave out the line break after the comment too
+                    skip_until = i + end + line(&bytes[i + end..]).1;
+                    continue;
+                }
+            }
+
+            if options.intercepts_fences()
+                && state_machine.is_none()
+                && (i == 0 || matches!(bytes[i - 1], b'\n' | b'\r'))
+            {
+                if let Some((info, contents, end)) = fence(&bytes[i..]) {
+                    write_fence(
+                        &mut output,
+                        info,
+                        &bytes[i..][contents],
+                        options,
+                        tags,
+                        sourcepos,
+                    );
+                    limits.check_output(output.len())?;
+                    // The line break after the fence is handled as usual
+                    skip_until = i + end;
+                    continue;
+                }
+            }
+
+            if options.has(Ext::Tables)
+                && state_machine.is_none()
+                && (i == 0 || matches!(bytes[i - 1], b'\n' | b'\r'))
+            {
+                if let Some((table, end)) = table(&bytes[i..]) {
+                    write_table(&mut output, &table, options, tags, limits, sourcepos)?;
+                    limits.check_output(output.len())?;
+                    // The line break after the last row is handled as usual
+                    skip_until = i + end;
+                    continue;
+                }
+            }
+
+            if options.has(Ext::OrderedLists)
+                && state_machine.is_none()
+                && byte.is_ascii_digit()
+                && (i == 0 || matches!(bytes[i - 1], b'\n' | b'\r'))
+            {
+                if let Some((list, end)) = ordered_list(&bytes[i..]) {
+                    write_ordered_list(&mut output, &list, options, tags, limits, sourcepos)?;
+                    limits.check_output(output.len())?;
+                    // The line break after the last item is handled as usual
+                    skip_until = i + end;
+                    continue;
+                }
+            }
+
+            if byte == b'>'
+                && options.has(Ext::Blockquotes)
+                && state_machine.is_none()
+                && (i == 0 || matches!(bytes[i - 1], b'\n' | b'\r'))
+            {
+                if let Some((quote, end)) = blockquote(&bytes[i..]) {
+                    write_blockquote(&mut output, &quote, options, tags, limits, sourcepos)?;
+                    limits.check_output(output.len())?;
+                    state_machine = state_machine.rise(State::Blockquote);
+                    // The line break after the last line ends the state
+                    skip_until = i + end;
+                    continue;
+                }
+            }
+
+            if options.indented != Indented::Div {
+                let line_start: bool = i == 0 || matches!(bytes[i - 1], b'\n' | b'\r');
+
+                match indent {
+                    Indent::None => {
+                        if state_machine.is_none() && matches!(byte, b' ' | b'\t') {
+                            if line_start {
+                                indent = Indent::Lead {
+                                    columns: indent_width(0, byte),
+                                    start: position,
+                                };
+                            } else {
+                                output.push(byte);
                             }
 
-                            state_machine = state_machine.fall();
+                            continue;
                         }
+                    }
 
-                        State::Exclamation => {
-                            output.push(b'!');
-                            output.push(byte);
-                            state_machine = state_machine.fall();
+                    Indent::Lead { columns, start } => {
+                        if matches!(byte, b' ' | b'\t') {
+                            indent = Indent::Lead {
+                                columns: indent_width(columns, byte),
+                                start,
+                            };
+                            continue;
                         }
 
-                        State::Link(ref mut ld) | State::Image(ref mut ld) => match ld.status {
-                            Linkstatus::Alt(0) => {
-                                ld.alt.push(byte);
-                            }
+                        indent = Indent::None;
 
-                            Linkstatus::Alt(1) => {
-                                output.push(b'[');
-                                output.write(&ld.alt);
-                                output.push(b']');
-                                output.push(byte);
-                                state_machine = state_machine.fall();
+                        if !matches!(byte, b'\n' | b'\r') {
+                            if let Some(at) = paragraph_end(&output) {
+                                // The line continues the paragraph on the previous line
+                                output.splice(at..at + TAG_P_C.len(), Html::default());
+                                source_map.removed(at, TAG_P_C.len());
+                                state_machine = state_machine.rise(State::Paragraph);
+                            } else if options.indented == Indented::Code && columns >= CODE_INDENT {
+                                open_block(
+                                    &mut output,
+                                    &tags.code_o,
+                                    options.sourcepos.then_some(start),
+                                );
+                                output.escape(&[byte], true);
+                                indent = Indent::Code;
+                                limits.check_output(output.len())?;
+                                continue;
                             }
+                        }
+                    }
 
-                            Linkstatus::Link => {
-                                ld.link.push(byte);
-                            }
+                    Indent::Code => {
+                        if matches!(byte, b'\n' | b'\r') {
+                            indent = Indent::CodeLead {
+                                columns: 0,
+                                breaks: Vec::from([byte]),
+                            };
+                        } else {
+                            // The rest of the line is code as well
+                            let end: usize = scan::find(&bytes[i..], [b'\n', b'\r'])
+                                .map_or(bytes.len(), |end| i + end);
+                            output.escape(&bytes[i..end], true);
+                            skip_until = end;
+                        }
 
-                            _ => {
-                                println!("Warning: Unexpected link status. This shouldn't happen.");
-                            }
-                        },
+                        limits.check_output(output.len())?;
+                        continue;
+                    }
+
+                    Indent::CodeLead {
+                        columns,
+                        ref mut breaks,
+                    } => match byte {
+                        b'\n' | b'\r' => {
+                            // Blank lines don't end the block yet
+                            breaks.push(byte);
+                            indent = Indent::CodeLead {
+                                columns: 0,
+                                breaks: core::mem::take(breaks),
+                            };
+                            continue;
+                        }
 
-                        State::Intendation(exp, ref mut buf) => {
-                            if exp {
-                                // Close intend div tag
-                                output.write(TAG_INT_C);
-                                // Write the buffer of intendation
-                                output.write(&buf.inner);
-                                state_machine = state_machine.fall();
+                        b' ' | b'\t' => {
+                            let columns: usize = indent_width(columns, byte);
+
+                            if columns >= CODE_INDENT {
+                                output.write(breaks);
+                                indent = Indent::Code;
                             } else {
-                                output.write(&buf.inner);
-                                buf.inner.clear();
+                                indent = Indent::CodeLead {
+                                    columns,
+                                    breaks: core::mem::take(breaks),
+                                };
                             }
 
-                            output.write(TAG_P_O);
-                            output.push(byte);
-                            state_machine = state_machine.rise(State::Paragraph);
+                            continue;
                         }
 
-                        State::Italic(seen) => {
-                            if seen {
-                                // Open i tag
-                                output.write(TAG_I_O);
-                                state_machine.current = State::Italic(false);
+                        _ => {
+                            // The block ended on the previous line
+                            output.tags(tags.code_c);
+                            output.write(breaks);
+                            indent = Indent::None;
+                        }
+                    },
+                }
+            }
+
+            // Indented lines after a list item continue it with another paragraph
+            if let State::UList(false, true) = state_machine.current {
+                match byte {
+                    b' ' | b'\t'
+                        if list_indent || i == 0 || matches!(bytes[i - 1], b'\n' | b'\r') =>
+                    {
+                        list_indent = true;
+                        continue;
+                    }
+
+                    b'\n' | b'\r' => list_indent = false,
+
+                    _ if list_indent => {
+                        list_indent = false;
+
+                        if let Some(at) = list_item_end(&output) {
+                            // Wrap the text before into a paragraph too
+                            let content: usize = output[..at]
+                                .windows(3)
+                                .rposition(|w| w == b"<li")
+                                .and_then(|li| {
+                                    output[li..]
+                                        .iter()
+                                        .position(|&b| b == b'>')
+                                        .map(|end| li + end + 1)
+                                })
+                                .unwrap_or(at);
+
+                            output.splice(at..at + TAG_LI_C.len(), Html::default());
+                            source_map.removed(at, TAG_LI_C.len());
+
+                            if !output[content..].starts_with(b"<p") {
+                                output.edit(vec![
+                                    (content..content, Html::of_tags(TAG_P_O.to_vec())),
+                                    (at..at, Html::of_tags(TAG_P_C.to_vec())),
+                                ]);
+                                source_map.inserted(at, TAG_P_C.len());
+                                source_map.inserted(content, TAG_P_O.len());
                             }
 
-                            output.push(byte);
-                        }
+                            let quote: Option<(Vec<u8>, usize)> = match byte {
+                                b'>' if options.has(Ext::Blockquotes) => blockquote(&bytes[i..]),
+                                _ => None,
+                            };
+
+                            let fenced = match options.intercepts_fences() {
+                                true => fence(&bytes[i..]),
+                                false => None,
+                            };
+
+                            if let Some((info, contents, end)) = fenced {
+                                // The code is a block of the item like a quote
+                                write_fence(
+                                    &mut output,
+                                    info,
+                                    &bytes[i..][contents],
+                                    options,
+                                    tags,
+                                    sourcepos,
+                                );
+                                limits.check_output(output.len())?;
+                                state_machine = state_machine.rise(State::LItem);
+                                skip_until = i + end;
+                                continue;
+                            }
 
-                        State::Bold(seen) => {
-                            if seen {
-                                eprintln!("Warning: Non-escaped `*` in the middle of bolded on line {} column {}. Parsing it as a literal..",
-                                         line_counter, column_counter);
-                                output.push(b'*');
-                                state_machine.current = State::Bold(false);
+                            if let Some((quote, end)) = quote {
+                                // The quote is a block of the item, which the line break after it closes
+                                write_blockquote(
+                                    &mut output,
+                                    &quote,
+                                    options,
+                                    tags,
+                                    limits,
+                                    sourcepos,
+                                )?;
+                                limits.check_output(output.len())?;
+                                state_machine = state_machine.rise(State::LItem);
+                                skip_until = i + end;
+                                continue;
                             }
 
-                            output.push(byte);
+                            open_block(&mut output, TAG_P_O, sourcepos);
+                            state_machine = state_machine.rise(State::LItem).rise(State::Paragraph);
                         }
+                    }
 
-                        State::UList(seen, written) => {
-                            if seen {
-                                eprintln!("Unexpected character when expecting a space on line {} column {}",
-                                          line_counter, column_counter);
-                            }
+                    _ => {}
+                }
+            }
 
-                            if written {
-                                output.write(TAG_UL_C);
-                            }
+            // Keys of code followed by something else than a key
+            if let State::Code(true, n) = state_machine.current {
+                match (n, byte) {
+                    (_, b'`') | (2 | 3, b'\n') => {}
 
-                            output.write(TAG_P_C);
-                            state_machine = state_machine.fall().fall();
+                    (1, b'\n') => {
+                        output.push(b'`');
+                        state_machine = state_machine.fall();
+                    }
 
-                            match state_machine.current {
-                                State::Intendation(_, ref buf) => {
-                                    output.write(TAG_INT_C);
-                                    output.write(&buf.inner);
-                                    state_machine = state_machine.fall();
-                                }
-                                _ => {}
-                            }
+                    (1, _) => {
+                        output.tags(&tags.codei_o);
+                        state_machine.current = State::Code(false, 1);
+                    }
 
-                            output.write(TAG_P_O);
-                            output.push(byte);
+                    (2, _) => state_machine = state_machine.fall(),
 
-                            state_machine = state_machine.rise(State::Paragraph);
-                        }
+                    (3, _) => {
+                        open_block(&mut output, &tags.codeb_o, sourcepos);
+                        state_machine.current = State::Code(false, 3);
+                    }
 
-                        _ => output.push(byte),
+                    // Fewer keys than it takes to close the block
+                    _ => {
+                        output.extend(core::iter::repeat_n(b'`', usize::from(n) - 3));
+                        state_machine.current = State::Code(false, 3);
                     }
                 }
+            }
+
+            // The text and url of an image in a link go together
+            if let State::Link(ref mut ld) = state_machine.current {
+                if matches!(ld.image, Some(Linkstatus::Alt(1))) && byte != b'(' {
+                    ld.image = None;
+                }
+            }
+
+            // Keys of a heading without the space after them are text
+            if let State::Header(n, false) = state_machine.current {
+                if !matches!(byte, b'#' | b' ') {
+                    if matches!(byte, b'\n' | b'\r') {
+                        diagnostics.push(Diagnostic::new(
+                            position,
+                            Code::EmptyHeader,
+                            "Empty header? Really??",
+                        ));
+                    }
+
+                    let start: Option<Position> = sourcepos.map(|p| Position {
+                        column: p.column - n as usize,
+                        offset: p.offset - n as usize,
+                        ..p
+                    });
+
+                    open_block(&mut output, TAG_P_O, start);
+                    output.extend(core::iter::repeat_n(b'#', n.into()));
+                    state_machine.current = State::Paragraph;
+                }
+            }
 
+            match byte {
                 b'!' => match state_machine.current {
                     State::Escape => {
                         output.push(byte);
                         state_machine = state_machine.fall();
                     }
 
+                    State::Link(ref mut ld) | State::Image(ref mut ld) if ld.is_link() => {
+                        ld.push_link(
+                            &mut state_machine.arena,
+                            byte,
+                            position,
+                            options,
+                            &mut diagnostics,
+                        );
+                    }
+
+                    State::Link(ref mut ld) | State::Image(ref mut ld) if !ld.alt_expects_url() => {
+                        ld.push_alt(
+                            &mut state_machine.arena,
+                            byte,
+                            position,
+                            options,
+                            &mut diagnostics,
+                        );
+                    }
+
                     State::Exclamation | State::Link(_) | State::Image(_) | State::Code(_, _) => {
                         output.push(byte);
                     }
@@ -309,7 +1284,7 @@ impl MDS {
                     State::Intendation(exp, ref buf) => {
                         if exp {
                             // Close intend div tag
-                            output.write(TAG_INT_C);
+                            output.tags(TAG_INT_C);
                             output.write(&buf.inner);
                             state_machine = state_machine.fall();
                         }
@@ -333,6 +1308,25 @@ impl MDS {
                         state_machine = state_machine.fall().rise(State::Escape);
                     }
 
+                    State::None => {
+                        // Open p tag for the escaped key
+                        open_block(&mut output, TAG_P_O, sourcepos);
+                        state_machine = state_machine.rise(State::Paragraph).rise(State::Escape);
+                    }
+
+                    State::Intendation(exp, ref buf) => {
+                        if exp {
+                            // Close intend div tag
+                            output.tags(TAG_INT_C);
+                            output.write(&buf.inner);
+                            state_machine = state_machine.fall();
+                        }
+
+                        // Open p tag for the escaped key
+                        open_block(&mut output, TAG_P_O, sourcepos);
+                        state_machine = state_machine.rise(State::Paragraph).rise(State::Escape);
+                    }
+
                     _ => state_machine = state_machine.rise(State::Escape),
                 },
 
@@ -342,18 +1336,21 @@ impl MDS {
                     State::Intendation(exp, ref buf) => {
                         if exp {
                             // Close intend div tag
-                            output.write(TAG_INT_C);
+                            output.tags(TAG_INT_C);
                             output.write(&buf.inner);
                             state_machine = state_machine.fall();
                         }
                         state_machine = state_machine.rise(State::Header(1, false));
                     }
 
-                    State::Header(n, p) => {
+                    // Keys after the heading has started are text
+                    State::Header(_, true) => output.push(byte),
+
+                    State::Header(n, false) => {
                         if n < 6 {
-                            state_machine.current = State::Header(n + 1, p);
+                            state_machine.current = State::Header(n + 1, false);
                         } else {
-                            println!("Trying to exceed html header level 6. Ignoring excess header keys..");
+                            diagnostics.push(Diagnostic::new(position, Code::HeaderLevel, "Trying to exceed html header level 6. Ignoring excess header keys.."));
                         }
                     }
 
@@ -375,17 +1372,17 @@ impl MDS {
                                     state_machine.current = State::Code(false, n);
 
                                     // Open inline code span tag and code tag
-                                    output.write(TAG_CODEI_O);
+                                    output.tags(&tags.codei_o);
                                 }
 
                                 3 => {
                                     // Open code block div tag and code tag
-                                    output.write(TAG_CODEB_O);
+                                    open_block(&mut output, &tags.codeb_o, sourcepos);
                                     state_machine.current = State::Code(false, n);
                                 }
 
                                 _ => {
-                                    println!("Warning: Unexpected code block state! Undefined behaviour may occur! Trying to mitigate damage by ignoring previous key..");
+                                    diagnostics.push(Diagnostic::new(position, Code::CodeBlockState, "Unexpected code block state! Undefined behaviour may occur! Trying to mitigate damage by ignoring previous key.."));
 
                                     output.push(byte);
                                     state_machine = state_machine.fall();
@@ -397,19 +1394,31 @@ impl MDS {
 
                     State::Link(ref mut ld) | State::Image(ref mut ld) => match ld.status {
                         Linkstatus::Alt(0) => {
-                            ld.alt.push(byte);
+                            ld.push_alt(
+                                &mut state_machine.arena,
+                                byte,
+                                position,
+                                options,
+                                &mut diagnostics,
+                            );
                         }
 
                         Linkstatus::Link => {
-                            ld.link.push(byte);
+                            ld.push_link(
+                                &mut state_machine.arena,
+                                byte,
+                                position,
+                                options,
+                                &mut diagnostics,
+                            );
                         }
 
                         _ => {
                             output.push(b'[');
-                            output.write(&ld.alt);
+                            output.escape(ld.bytes(&state_machine.arena).alt, safe);
                             output.push(b']');
                             output.push(b'(');
-                            output.write(&ld.link);
+                            output.escape(ld.bytes(&state_machine.arena).link, safe);
                             output.push(byte);
                             state_machine = state_machine.fall();
                         }
@@ -423,17 +1432,31 @@ impl MDS {
                 b' ' => match state_machine.current {
                     State::None => {
                         // Open intend div tag
-                        output.write(TAG_INT_O);
+                        open_block(&mut output, &tags.int_o, sourcepos);
                         state_machine = state_machine
                             .rise(State::Intendation(false, IntenData { inner: Vec::new() }));
                     }
 
                     State::Header(n, p) => {
                         if !p {
-                            output.push(b'<');
-                            output.push(b'h');
-                            output.push(n + 48);
-                            output.push(b'>');
+                            // The header started from the first of the `#` keys
+                            let start: Option<Position> = sourcepos.map(|p| Position {
+                                column: p.column - n as usize,
+                                offset: p.offset - n as usize,
+                                ..p
+                            });
+
+                            if options.a11y && headings.level() != 0 && n > headings.level() + 1 {
+                                diagnostics.push(Diagnostic::with_args(
+                                    position,
+                                    Code::HeadingLevelSkip,
+                                    "Heading level skips from h{} to h{}.",
+                                    vec![headings.level().to_string(), n.to_string()],
+                                ));
+                            }
+
+                            open_block(&mut output, &[b'<', b'h', n + 48, b'>'], start);
+                            headings.open(&mut output, n, position, options);
 
                             state_machine.current = State::Header(n, true);
                         } else {
@@ -445,13 +1468,13 @@ impl MDS {
                         if prev {
                             match count {
                                 1 => {
-                                    output.write(TAG_CODEI_O);
+                                    output.tags(&tags.codei_o);
                                     output.push(byte);
                                     state_machine.current = State::Code(false, count);
                                 }
 
                                 3 => {
-                                    output.write(TAG_CODEB_O);
+                                    open_block(&mut output, &tags.codeb_o, sourcepos);
                                     output.push(byte);
                                     state_machine.current = State::Code(false, count);
                                 }
@@ -469,31 +1492,44 @@ impl MDS {
                     }
 
                     State::Italic(true) => {
-                        output.write(TAG_I_O);
+                        output.tags(tags.i_o);
                         output.push(byte);
                         state_machine.current = State::Italic(false);
                     }
 
                     State::Bold(true) => {
-                        output.write(TAG_B_O);
+                        diagnostics.push(Diagnostic::new(position, Code::LiteralStar, "Non-escaped `*` in the middle of bolded text. Parsing it as a literal.."));
+                        output.push(b'*');
                         output.push(byte);
                         state_machine.current = State::Bold(false);
                     }
 
-                    State::Link(ref mut ld) => {
+                    State::Link(ref mut ld) | State::Image(ref mut ld) => {
                         if ld.status.is_link() {
-                            // Convert space into url encoded space
-                            output.write(b"%20");
+                            // Encoded with the rest of the url when it's written
+                            ld.push_link(
+                                &mut state_machine.arena,
+                                byte,
+                                position,
+                                options,
+                                &mut diagnostics,
+                            );
                         } else {
                             if ld.status.alt_expects_url() {
                                 output.push(b'[');
-                                output.write(&ld.alt);
+                                output.escape(ld.bytes(&state_machine.arena).alt, safe);
                                 output.push(b']');
                                 output.push(byte);
 
                                 state_machine = state_machine.fall();
                             } else {
-                                ld.alt.push(byte);
+                                ld.push_alt(
+                                    &mut state_machine.arena,
+                                    byte,
+                                    position,
+                                    options,
+                                    &mut diagnostics,
+                                );
                             }
                         }
                     }
@@ -515,10 +1551,10 @@ impl MDS {
 
                     State::UList(true, written) => {
                         if !written {
-                            output.write(TAG_UL_O);
+                            open_block(&mut output, TAG_UL_O, sourcepos);
                         }
 
-                        output.write(TAG_LI_O);
+                        open_block(&mut output, TAG_LI_O, sourcepos);
                         state_machine.current = State::UList(false, true);
                         state_machine = state_machine.rise(State::LItem);
                     }
@@ -529,9 +1565,26 @@ impl MDS {
                 },
 
                 b'[' => match state_machine.current {
+                    State::Link(ref mut ld) if ld.opens_image(&state_machine.arena) => {
+                        ld.image = Some(Linkstatus::Alt(0));
+                        ld.push_alt(
+                            &mut state_machine.arena,
+                            byte,
+                            position,
+                            options,
+                            &mut diagnostics,
+                        );
+                    }
+
                     State::Link(ref mut ld) | State::Image(ref mut ld) => {
                         if ld.is_link() {
-                            ld.link.push(byte);
+                            ld.push_link(
+                                &mut state_machine.arena,
+                                byte,
+                                position,
+                                options,
+                                &mut diagnostics,
+                            );
                         }
                     }
 
@@ -540,12 +1593,31 @@ impl MDS {
                         state_machine = state_machine.fall();
                     }
 
+                    State::None
+                    | State::Paragraph
+                    | State::Header(_, true)
+                    | State::Bold(false)
+                    | State::Italic(false)
+                    | State::Underscore
+                    | State::Strike
+                    | State::LItem
+                        if options.has(Ext::Kbd) && kbd(&bytes[i..]).is_some() =>
+                    {
+                        if state_machine.is_none() {
+                            state_machine = state_machine.rise(State::Paragraph);
+                            open_block(&mut output, TAG_P_O, sourcepos);
+                        }
+
+                        if let Some((key, len)) = kbd(&bytes[i..]) {
+                            output.tags(TAG_KBD_O);
+                            output.escape(key, true);
+                            output.tags(TAG_KBD_C);
+                            skip_until = i + len;
+                        }
+                    }
+
                     _ => {
-                        let ld: Linkdata = Linkdata {
-                            status: Linkstatus::Alt(0),
-                            alt: Vec::with_capacity(255),
-                            link: Vec::with_capacity(255),
-                        };
+                        let ld: Linkdata = Linkdata::new(&state_machine.arena);
 
                         match state_machine.current {
                             State::Exclamation => state_machine.current = State::Image(ld),
@@ -553,7 +1625,7 @@ impl MDS {
                             State::Intendation(exp, ref buf) => {
                                 if exp {
                                     // Close intend div tag
-                                    output.write(TAG_INT_C);
+                                    output.tags(TAG_INT_C);
                                     output.write(&buf.inner);
                                     state_machine = state_machine.fall();
                                 }
@@ -563,13 +1635,10 @@ impl MDS {
 
                             State::UList(_, written) => {
                                 if written {
-                                    output.write(TAG_UL_C);
+                                    output.tags(TAG_UL_C);
                                 }
-                                output.write(TAG_P_C);
-                                state_machine = state_machine
-                                    .fall()
-                                    .fall()
-                                    .rise(State::Link(ld));
+                                output.tags(TAG_P_C);
+                                state_machine = state_machine.fall().fall().rise(State::Link(ld));
                             }
 
                             _ => state_machine = state_machine.rise(State::Link(ld)),
@@ -578,6 +1647,17 @@ impl MDS {
                 },
 
                 b'(' => match state_machine.current {
+                    State::Link(ref mut ld) if matches!(ld.image, Some(Linkstatus::Alt(1))) => {
+                        ld.image = Some(Linkstatus::Link);
+                        ld.push_alt(
+                            &mut state_machine.arena,
+                            byte,
+                            position,
+                            options,
+                            &mut diagnostics,
+                        );
+                    }
+
                     State::Link(ref mut ld) | State::Image(ref mut ld) => {
                         if ld.is_alt() {
                             if ld.alt_expects_url() {
@@ -585,16 +1665,16 @@ impl MDS {
                             } else {
                                 // Fall back from link/image and write the alt data as is
                                 output.push(b'[');
-                                output.write(&ld.alt);
+                                output.escape(ld.bytes(&state_machine.arena).alt, safe);
                                 output.push(byte);
                                 state_machine = state_machine.fall();
                             }
                         } else {
                             output.push(b'[');
-                            output.write(&ld.alt);
+                            output.escape(ld.bytes(&state_machine.arena).alt, safe);
                             output.push(b']');
                             output.push(b'(');
-                            output.write(&ld.link);
+                            output.escape(ld.bytes(&state_machine.arena).link, safe);
                             output.push(byte);
                             state_machine = state_machine.fall();
                         }
@@ -607,10 +1687,10 @@ impl MDS {
 
                     State::Intendation(_, buf) => {
                         // Close intend div tag
-                        output.write(TAG_INT_C);
+                        output.tags(TAG_INT_C);
                         output.write(&buf.inner);
                         // Open p tag
-                        output.write(TAG_P_O);
+                        open_block(&mut output, TAG_P_O, sourcepos);
                         output.push(byte);
                         state_machine.current = State::Paragraph;
                     }
@@ -627,16 +1707,16 @@ impl MDS {
                                     } else {
                                         // Fall back from link/image and write the alt data as is
                                         output.push(b'[');
-                                        output.write(&ld.alt);
+                                        output.escape(ld.bytes(&state_machine.arena).alt, safe);
                                         output.push(byte);
                                         state_machine = state_machine.fall();
                                     }
                                 } else {
                                     output.push(b'[');
-                                    output.write(&ld.alt);
+                                    output.escape(ld.bytes(&state_machine.arena).alt, safe);
                                     output.push(b']');
                                     output.push(b'(');
-                                    output.write(&ld.link);
+                                    output.escape(ld.bytes(&state_machine.arena).link, safe);
                                     output.push(byte);
                                     state_machine = state_machine.fall();
                                 }
@@ -649,14 +1729,13 @@ impl MDS {
                     State::UList(_, written) => {
                         if written {
                             // Start a new paragraph and end the list
-                            output.write(TAG_UL_C);
+                            output.tags(TAG_UL_C);
                         }
 
-                        output.write(TAG_P_C);
-                        output.write(TAG_P_O);
+                        output.tags(TAG_P_C);
+                        open_block(&mut output, TAG_P_O, sourcepos);
                         output.push(byte);
-                        state_machine = state_machine
-                            .fall();
+                        state_machine = state_machine.fall();
                     }
 
                     _ => {
@@ -665,18 +1744,38 @@ impl MDS {
                 },
 
                 b']' => match state_machine.current {
+                    State::Link(ref mut ld) if ld.image.is_some() => {
+                        if ld.image.as_ref().is_some_and(Linkstatus::is_alt) {
+                            ld.image = Some(Linkstatus::Alt(1));
+                        }
+
+                        ld.push_alt(
+                            &mut state_machine.arena,
+                            byte,
+                            position,
+                            options,
+                            &mut diagnostics,
+                        );
+                    }
+
                     State::Link(ref mut ld) | State::Image(ref mut ld) => {
                         if ld.status.is_alt() {
                             if ld.alt_expects_closure() {
                                 ld.status = Linkstatus::Alt(1);
                             } else {
                                 // Fall back from link and write the alt data as is
-                                output.write(&ld.alt);
+                                output.escape(ld.bytes(&state_machine.arena).alt, safe);
                                 output.push(byte);
                                 state_machine = state_machine.fall();
                             }
                         } else {
-                            ld.link.push(byte);
+                            ld.push_link(
+                                &mut state_machine.arena,
+                                byte,
+                                position,
+                                options,
+                                &mut diagnostics,
+                            );
                         }
                     }
 
@@ -696,12 +1795,18 @@ impl MDS {
                                         ld.status = Linkstatus::Alt(1);
                                     } else {
                                         // Fall back from link and write the alt data as is
-                                        output.write(&ld.alt);
+                                        output.escape(ld.bytes(&state_machine.arena).alt, safe);
                                         output.push(byte);
                                         state_machine = state_machine.fall();
                                     }
                                 } else {
-                                    ld.link.push(byte);
+                                    ld.push_link(
+                                        &mut state_machine.arena,
+                                        byte,
+                                        position,
+                                        options,
+                                        &mut diagnostics,
+                                    );
                                 }
                             }
 
@@ -711,10 +1816,10 @@ impl MDS {
 
                     State::Intendation(_, buf) => {
                         // Close intendation div tag
-                        output.write(TAG_INT_C);
+                        output.tags(TAG_INT_C);
                         output.write(&buf.inner);
                         // Open p tag
-                        output.write(TAG_P_O);
+                        open_block(&mut output, TAG_P_O, sourcepos);
                         state_machine.current = State::Paragraph;
                         output.push(byte);
                     }
@@ -725,14 +1830,35 @@ impl MDS {
                 },
 
                 b')' => match state_machine.current {
+                    State::Link(ref mut ld)
+                        if ld.image.as_ref().is_some_and(Linkstatus::is_link) =>
+                    {
+                        ld.push_alt(
+                            &mut state_machine.arena,
+                            byte,
+                            position,
+                            options,
+                            &mut diagnostics,
+                        );
+                        ld.image = None;
+                    }
+
                     State::Link(ref ld) => {
                         if ld.is_link() {
-                            // Output an link
-                            output.write(b"<a href=\"");
-                            output.write(&ld.link);
-                            output.write(b"\">");
-                            output.write(&ld.alt);
-                            output.write(b"</a>");
+                            let ld: Linkbytes = ld.bytes(&state_machine.arena);
+                            if options.check_fragments && ld.link.starts_with(b"#") {
+                                fragments.push((position, ld.link[1..].to_vec()));
+                            }
+
+                            if options.a11y && ld.is_vague() {
+                                diagnostics.push(Diagnostic::new(
+                                    position,
+                                    Code::VagueLink,
+                                    "Link text doesn't tell where the link goes.",
+                                ));
+                            }
+
+                            ld.write_link(&mut output, options, &mut assets);
                             state_machine = state_machine.fall();
                         } else {
                             output.push(byte);
@@ -741,12 +1867,25 @@ impl MDS {
 
                     State::Image(ref ld) => {
                         if ld.is_link() {
-                            // Output an image
-                            output.write(b"<img src=\"");
-                            output.write(&ld.link);
-                            output.write(b"\" alt=\"");
-                            output.write(&ld.alt);
-                            output.write(b"\">");
+                            let ld: Linkbytes = ld.bytes(&state_machine.arena);
+                            if (options.missing_alt == MissingAlt::Warn || options.a11y)
+                                && ld.lacks_alt()
+                            {
+                                diagnostics.push(Diagnostic::new(
+                                    position,
+                                    Code::MissingAlt,
+                                    "Image has no alt text.",
+                                ));
+                            }
+
+                            skip_until = i
+                                + 1
+                                + ld.write_image(
+                                    &mut output,
+                                    options,
+                                    &bytes[i + 1..],
+                                    &mut assets,
+                                );
                             state_machine = state_machine.fall();
                         } else {
                             output.push(byte);
@@ -760,10 +1899,10 @@ impl MDS {
 
                     State::Intendation(_, buf) => {
                         // Close intend div tag
-                        output.write(TAG_INT_C);
+                        output.tags(TAG_INT_C);
                         output.write(&buf.inner);
                         // Open p tag
-                        output.write(TAG_P_O);
+                        open_block(&mut output, TAG_P_O, sourcepos);
                         output.push(byte);
                         state_machine.current = State::Paragraph;
                     }
@@ -775,12 +1914,20 @@ impl MDS {
                         match state_machine.current {
                             State::Link(ref ld) => {
                                 if ld.is_link() {
-                                    // Output an link
-                                    output.write(b"<a href=\"");
-                                    output.write(&ld.link);
-                                    output.write(b"\">");
-                                    output.write(&ld.alt);
-                                    output.write(b"</a>");
+                                    let ld: Linkbytes = ld.bytes(&state_machine.arena);
+                                    if options.check_fragments && ld.link.starts_with(b"#") {
+                                        fragments.push((position, ld.link[1..].to_vec()));
+                                    }
+
+                                    if options.a11y && ld.is_vague() {
+                                        diagnostics.push(Diagnostic::new(
+                                            position,
+                                            Code::VagueLink,
+                                            "Link text doesn't tell where the link goes.",
+                                        ));
+                                    }
+
+                                    ld.write_link(&mut output, options, &mut assets);
                                     state_machine = state_machine.fall();
                                 } else {
                                     output.push(byte);
@@ -789,12 +1936,25 @@ impl MDS {
 
                             State::Image(ref ld) => {
                                 if ld.is_link() {
-                                    // Output an image
-                                    output.write(b"<img src=\"");
-                                    output.write(&ld.link);
-                                    output.write(b"\" alt=\"");
-                                    output.write(&ld.alt);
-                                    output.write(b"\">");
+                                    let ld: Linkbytes = ld.bytes(&state_machine.arena);
+                                    if (options.missing_alt == MissingAlt::Warn || options.a11y)
+                                        && ld.lacks_alt()
+                                    {
+                                        diagnostics.push(Diagnostic::new(
+                                            position,
+                                            Code::MissingAlt,
+                                            "Image has no alt text.",
+                                        ));
+                                    }
+
+                                    skip_until = i
+                                        + 1
+                                        + ld.write_image(
+                                            &mut output,
+                                            options,
+                                            &bytes[i + 1..],
+                                            &mut assets,
+                                        );
                                     state_machine = state_machine.fall();
                                 } else {
                                     output.push(byte);
@@ -809,32 +1969,31 @@ impl MDS {
                 },
 
                 b'\r' | b'\n' => {
-                    column_counter = 0;
-                    if byte == b'\n' {
-                        line_counter += 1;
+                    // The text inside took the closing `~~` of the strikethrough
+                    if let State::Strike = state_machine.current {
+                        output.tags(TAG_DEL_C);
+                        state_machine = state_machine.fall();
                     }
 
                     match state_machine.current {
                         State::None => output.push(byte),
 
-                        State::Header(n, p) => {
-                            if !p {
-                                println!("Empty header? Really??");
+                        State::Header(n, _) => {
+                            if !headings.close(&mut output, n, options) && options.a11y {
+                                diagnostics.push(Diagnostic::new(
+                                    position,
+                                    Code::EmptyHeading,
+                                    "Heading has no text.",
+                                ));
                             }
 
-                            output.write(b"</h");
-                            output.push(n + 48);
-                            output.push(b'>');
                             output.push(byte);
 
                             state_machine = state_machine.fall();
                         }
 
                         State::Paragraph => {
-                            output.push(b'<');
-                            output.push(b'/');
-                            output.push(b'p');
-                            output.push(b'>');
+                            output.tags(TAG_P_C);
 
                             state_machine = state_machine.fall();
 
@@ -844,34 +2003,52 @@ impl MDS {
                                     state_machine.current = State::Intendation(true, buf);
                                 }
 
+                                State::LItem => {
+                                    // The paragraph continued a list item
+                                    output.tags(TAG_LI_C);
+                                    output.push(byte);
+                                    state_machine = state_machine.fall();
+                                }
+
                                 _ => output.push(byte),
                             }
                         }
 
                         State::Code(seen, count) => {
                             if count == 1 {
-                                eprintln!("Unexpected new line in the middle of inline code.");
+                                diagnostics.push(Diagnostic::new(
+                                    position,
+                                    Code::CodeNewline,
+                                    "Unexpected new line in the middle of inline code.",
+                                ));
                                 // Close code block span tag and code tag
-                                output.write(TAG_CODEI_C);
-
-                                state_machine = state_machine.fall();
-
-                                while !state_machine.is_none() {
-                                    if state_machine.is_paragraph() {
-                                        output.write(TAG_P_C);
-                                    }
-
-                                    state_machine = state_machine.fall();
-                                }
+                                output.tags(TAG_CODEI_C);
+
+                                state_machine = state_machine.fall().close_all(
+                                    &mut output,
+                                    tags,
+                                    &mut headings,
+                                    options,
+                                    &mut diagnostics,
+                                    position,
+                                );
                             } else if count == 2 {
                                 if seen {
-                                    eprintln!("Unexpected number of code block keys. Maybe you meant to write 3?");
+                                    diagnostics.push(Diagnostic::new(position, Code::FenceLength, "Unexpected number of code block keys. Maybe you meant to write 3?"));
                                 }
 
                                 state_machine = state_machine.fall();
                             }
 
                             output.push(byte);
+
+                            if seen && count == 3 {
+                                // The code starts from the next line
+                                let start: Option<Position> =
+                                    options.sourcepos.then(|| cursor.position());
+                                open_block(&mut output, &tags.codeb_o, start);
+                                state_machine.current = State::Code(false, 3);
+                            }
                         }
 
                         State::Escape => {
@@ -879,19 +2056,31 @@ impl MDS {
                             state_machine = state_machine.fall();
                         }
 
+                        State::Link(ref ld)
+                            if options.toc
+                                && ld.alt_expects_url()
+                                && ld.bytes(&state_machine.arena).alt == b"TOC"
+                                && state_machine.previous.as_ref().is_some_and(|p| p.is_none()) =>
+                        {
+                            // A [TOC] line marks where the table of contents goes
+                            toc_marker = Some(output.len());
+                            output.push(byte);
+                            state_machine = state_machine.fall();
+                        }
+
                         State::Link(ref ld) | State::Image(ref ld) => {
-                            println!("Warning: New lines in links and images are not supported. This may cripple your text.");
+                            diagnostics.push(Diagnostic::new(position, Code::LinkNewline, "New lines in links and images are not supported. This may cripple your text."));
                             if ld.is_alt() {
                                 output.push(b'[');
-                                output.write(&ld.alt);
+                                output.escape(ld.bytes(&state_machine.arena).alt, safe);
                                 output.push(byte);
                                 state_machine = state_machine.fall();
                             } else {
                                 output.push(b'[');
-                                output.write(&ld.alt);
+                                output.escape(ld.bytes(&state_machine.arena).alt, safe);
                                 output.push(b']');
                                 output.push(b'(');
-                                output.write(&ld.link);
+                                output.escape(ld.bytes(&state_machine.arena).link, safe);
                                 output.push(byte);
                                 state_machine = state_machine.fall();
                             }
@@ -908,11 +2097,9 @@ impl MDS {
 
                             loop {
                                 match state_machine.current {
-                                    State::Paragraph => output.write(TAG_P_C),
+                                    State::Paragraph => output.tags(TAG_P_C),
                                     State::Header(n, _) => {
-                                        output.write(b"</h");
-                                        output.push(n + 48);
-                                        output.push(b'>');
+                                        headings.close(&mut output, n, options);
                                     }
                                     State::Intendation(_, mut buf) => {
                                         buf.inner.push(byte);
@@ -930,19 +2117,26 @@ impl MDS {
                         }
 
                         State::LItem => {
-                            output.write(TAG_LI_C);
+                            output.tags(TAG_LI_C);
                             output.push(byte);
                             state_machine = state_machine.fall();
                         }
 
                         State::UList(true, _) => {
                             output.push(byte);
-                            output.write(TAG_P_C);
+                            output.tags(TAG_P_C);
                             state_machine = state_machine.fall().fall();
                         }
 
+                        State::Blockquote => {
+                            output.push(byte);
+                            state_machine = state_machine.fall();
+                        }
+
                         State::Hor(3..) => {
-                            output.write(TAG_HR);
+                            open_block(&mut output, TAG_HR, sourcepos);
+                            // Close the p tag opened by the first dash
+                            output.tags(TAG_P_C);
                             output.push(byte);
                             state_machine = state_machine.fall().fall();
                         }
@@ -953,8 +2147,7 @@ impl MDS {
 
                 b'`' => match state_machine.current {
                     State::None => {
-                        output.write(TAG_P_O);
-                        println!("Code key increment to 1");
+                        open_block(&mut output, TAG_P_O, sourcepos);
                         state_machine = state_machine
                             .rise(State::Paragraph)
                             .rise(State::Code(true, 1));
@@ -963,18 +2156,22 @@ impl MDS {
                     State::Code(ls, n) => {
                         let x = n + 1;
                         if ls {
+                            if n == 3 {
+                                // More keys right after the opening ones start closing the block
+                                open_block(&mut output, &tags.codeb_o, sourcepos);
+                            }
+
                             state_machine.current = State::Code(ls, x);
-                             if x == 6 {
+                            if x == 6 {
                                 // Close code blog div tag and code tag
-                                output.write(TAG_CODEB_C);
+                                output.tags(TAG_CODEB_C);
                                 state_machine = state_machine.fall();
                             }
                         } else {
                             if x == 2 {
                                 // Close code blog span tag and code tag
-                                output.write(TAG_CODEI_C);
+                                output.tags(TAG_CODEI_C);
                                 state_machine = state_machine.fall();
-
                             } else {
                                 state_machine.current = State::Code(true, x);
                             }
@@ -989,17 +2186,20 @@ impl MDS {
                     State::Intendation(exp, ref buf) => {
                         if !exp {
                             // Open p tag
-                            output.write(TAG_P_O);
+                            open_block(&mut output, TAG_P_O, sourcepos);
                             state_machine = state_machine
                                 .rise(State::Paragraph)
                                 .rise(State::Code(true, 1));
                         } else {
                             // Close intend div tag
-                            output.write(TAG_INT_C);
+                            output.tags(TAG_INT_C);
                             output.write(&buf.inner);
                             // Open p tag
-                            output.write(TAG_P_O);
-                            state_machine.current = State::Code(true, 1);
+                            open_block(&mut output, TAG_P_O, sourcepos);
+                            state_machine = state_machine
+                                .fall()
+                                .rise(State::Paragraph)
+                                .rise(State::Code(true, 1));
                         }
                     }
 
@@ -1009,14 +2209,14 @@ impl MDS {
                     }
 
                     State::Italic(true) => {
-                        output.write(TAG_I_O);
+                        output.tags(tags.i_o);
                         state_machine.current = State::Italic(false);
                         state_machine = state_machine.rise(State::Code(true, 1));
                     }
 
                     State::Bold(seen) => {
                         if seen {
-                            println!("Warning: Non-escaped `*` in the middle of bolded text. Parsing it as a literal..");
+                            diagnostics.push(Diagnostic::new(position, Code::LiteralStar, "Non-escaped `*` in the middle of bolded text. Parsing it as a literal.."));
                             output.push(b'*');
                             state_machine.current = State::Bold(false);
                         }
@@ -1028,31 +2228,65 @@ impl MDS {
                     }
                 },
 
+                b'~' if options.has(Ext::Strikethrough)
+                    && matches!(state_machine.current, State::Strike)
+                    && bytes[i..].starts_with(b"~~") =>
+                {
+                    output.tags(TAG_DEL_C);
+                    state_machine = state_machine.fall();
+                    skip_until = i + 2;
+                }
+
+                b'~' if options.has(Ext::Strikethrough)
+                    && matches!(
+                        state_machine.current,
+                        State::None
+                            | State::Paragraph
+                            | State::Header(_, true)
+                            | State::Bold(false)
+                            | State::Italic(false)
+                            | State::Underscore
+                            | State::LItem
+                    )
+                    && closer.opens(bytes, i) =>
+                {
+                    if state_machine.is_none() {
+                        state_machine = state_machine.rise(State::Paragraph);
+                        open_block(&mut output, TAG_P_O, sourcepos);
+                    }
+
+                    output.tags(TAG_DEL_O);
+                    state_machine = state_machine.rise(State::Strike);
+                    skip_until = i + 2;
+                }
+
                 b'*' => match state_machine.current {
                     State::None => {
                         // Open p tag
-                        output.write(TAG_P_O);
+                        open_block(&mut output, TAG_P_O, sourcepos);
                         state_machine = state_machine
                             .rise(State::Paragraph)
                             .rise(State::Italic(true));
                     }
 
-                    State::Paragraph => state_machine = state_machine.rise(State::Italic(true)),
+                    State::Paragraph | State::LItem => {
+                        state_machine = state_machine.rise(State::Italic(true))
+                    }
 
                     State::Intendation(exp, ref buf) => {
                         if exp {
                             // Close intend div tag
-                            output.write(TAG_INT_C);
+                            output.tags(TAG_INT_C);
                             output.write(&buf.inner);
                             // Open p tag
-                            output.write(TAG_P_O);
+                            open_block(&mut output, TAG_P_O, sourcepos);
                             state_machine = state_machine
                                 .fall()
                                 .rise(State::Paragraph)
                                 .rise(State::Italic(true));
                         } else {
                             // Open p tag
-                            output.write(TAG_P_O);
+                            open_block(&mut output, TAG_P_O, sourcepos);
                             state_machine = state_machine
                                 .rise(State::Paragraph)
                                 .rise(State::Italic(true));
@@ -1065,21 +2299,21 @@ impl MDS {
                         match state_machine.current {
                             State::None => {
                                 // Open p tag
-                                output.write(TAG_P_O);
+                                open_block(&mut output, TAG_P_O, sourcepos);
                                 state_machine = state_machine.rise(State::Paragraph);
                             }
 
                             State::Intendation(exp, ref buf) => {
                                 if exp {
                                     // Close intend div tag
-                                    output.write(TAG_INT_C);
+                                    output.tags(TAG_INT_C);
                                     output.write(&buf.inner);
                                     // Open p tag
-                                    output.write(TAG_P_O);
+                                    open_block(&mut output, TAG_P_O, sourcepos);
                                     state_machine = state_machine.fall().rise(State::Paragraph);
                                 } else {
                                     // Open p tag
-                                    output.write(TAG_P_O);
+                                    open_block(&mut output, TAG_P_O, sourcepos);
                                     state_machine = state_machine.rise(State::Paragraph);
                                 }
                             }
@@ -1094,19 +2328,19 @@ impl MDS {
                         if ls {
                             match n {
                                 1 => {
-                                    output.write(TAG_CODEI_O);
+                                    output.tags(&tags.codei_o);
                                     output.push(byte);
                                     state_machine.current = State::Code(false, n);
                                 }
 
                                 3 => {
-                                    output.write(TAG_CODEB_O);
+                                    open_block(&mut output, &tags.codeb_o, sourcepos);
                                     output.push(byte);
                                     state_machine.current = State::Code(false, n);
                                 }
 
                                 _ => {
-                                    println!("Warning: Unexpected code block state! Undefined behaviour may occur! Trying to mitigate damage by ignoring previous key..");
+                                    diagnostics.push(Diagnostic::new(position, Code::CodeBlockState, "Unexpected code block state! Undefined behaviour may occur! Trying to mitigate damage by ignoring previous key.."));
                                     output.push(byte);
                                     state_machine = state_machine.fall();
                                 }
@@ -1126,13 +2360,13 @@ impl MDS {
                     State::Italic(seen) => {
                         if seen {
                             // Open b tag
-                            output.write(TAG_B_O);
+                            output.tags(tags.b_o);
                             // Switch state from Italic to Bold because there were two `*` characters
                             // in a row. Swtiching instead of rising to not preserve the Italic state.
                             state_machine.current = State::Bold(false);
                         } else {
                             // Close i tag
-                            output.write(TAG_I_C);
+                            output.tags(tags.i_c);
                             state_machine = state_machine.fall();
                         }
                     }
@@ -1140,95 +2374,112 @@ impl MDS {
                     State::Bold(seen) => {
                         if seen {
                             // Close b tag
-                            output.write(TAG_B_C);
+                            output.tags(tags.b_c);
                             state_machine = state_machine.fall();
                         } else {
                             state_machine.current = State::Bold(true);
                         }
                     }
 
-                    State::Underscore => {
+                    State::Underscore | State::Strike => {
                         state_machine = state_machine.rise(State::Italic(true));
                     }
 
                     _ => output.push(byte),
                 },
 
-                b'_' => match state_machine.current {
-                    State::None => {
-                        output.write(TAG_P_O);
-                        state_machine =
-                            state_machine.rise(State::Paragraph).rise(State::Underscore);
+                b'_' if options.has(Ext::Underline) => {
+                    // The `!` wasn't followed by an image, and the key goes to the state below
+                    if let State::Exclamation = state_machine.current {
+                        output.push(b'!');
+                        state_machine = state_machine.fall();
                     }
 
-                    State::Paragraph | State::Header(_, _) => {
-                        state_machine = state_machine.rise(State::Underscore)
-                    }
+                    match state_machine.current {
+                        State::None => {
+                            open_block(&mut output, TAG_P_O, sourcepos);
+                            output.tags(TAG_U_O);
+                            state_machine =
+                                state_machine.rise(State::Paragraph).rise(State::Underscore);
+                        }
 
-                    State::Intendation(exp, ref buf) => {
-                        if exp {
-                            output.write(TAG_INT_C);
-                            output.write(&buf.inner);
-                            output.write(TAG_P_O);
-                            output.write(TAG_U_O);
-                            state_machine = state_machine
-                                .fall()
-                                .rise(State::Paragraph)
-                                .rise(State::Underscore);
-                        } else {
-                            output.write(TAG_U_O);
-                            state_machine = state_machine.rise(State::Underscore);
+                        State::Paragraph | State::Header(_, _) | State::LItem | State::Strike => {
+                            output.tags(TAG_U_O);
+                            state_machine = state_machine.rise(State::Underscore)
                         }
-                    }
 
-                    State::Bold(seen) => {
-                        if seen {
-                            println!("Warning: Non-escaped `*` in the middle of bolded text. Parsing it as a literal..");
-                            output.push(b'*');
-                            state_machine.current = State::Bold(false);
+                        State::Intendation(exp, ref buf) => {
+                            if exp {
+                                output.tags(TAG_INT_C);
+                                output.write(&buf.inner);
+                                open_block(&mut output, TAG_P_O, sourcepos);
+                                output.tags(TAG_U_O);
+                                state_machine = state_machine
+                                    .fall()
+                                    .rise(State::Paragraph)
+                                    .rise(State::Underscore);
+                            } else {
+                                output.tags(TAG_U_O);
+                                state_machine = state_machine.rise(State::Underscore);
+                            }
                         }
-                        output.write(TAG_U_O);
-                        state_machine = state_machine.rise(State::Underscore);
-                    }
 
-                    State::Italic(seen) => {
-                        if seen {
-                            output.write(TAG_I_O);
-                            state_machine = state_machine.rise(State::Italic(false));
+                        State::Bold(seen) => {
+                            if seen {
+                                diagnostics.push(Diagnostic::new(position, Code::LiteralStar, "Non-escaped `*` in the middle of bolded text. Parsing it as a literal.."));
+                                output.push(b'*');
+                                state_machine.current = State::Bold(false);
+                            }
+                            output.tags(TAG_U_O);
+                            state_machine = state_machine.rise(State::Underscore);
                         }
-                        output.write(TAG_U_O);
-                        state_machine = state_machine.rise(State::Underscore);
-                    }
 
-                    State::Underscore => {
-                        output.write(TAG_U_C);
-                        state_machine = state_machine.fall();
-                    }
+                        State::Italic(seen) => {
+                            if seen {
+                                output.tags(tags.i_o);
+                                state_machine = state_machine.rise(State::Italic(false));
+                            }
+                            output.tags(TAG_U_O);
+                            state_machine = state_machine.rise(State::Underscore);
+                        }
 
-                    State::Escape => {
-                        output.push(byte);
-                        state_machine = state_machine.fall();
-                    }
+                        State::Underscore => {
+                            output.tags(TAG_U_C);
+                            state_machine = state_machine.fall();
+                        }
 
-                    State::Exclamation => {
-                        output.push(b'!');
-                        state_machine = state_machine.fall().rise(State::Underscore);
-                    }
+                        State::Escape => {
+                            output.push(byte);
+                            state_machine = state_machine.fall();
+                        }
 
-                    State::Link(ref mut ld) | State::Image(ref mut ld) => {
-                        if ld.is_alt() {
-                            ld.alt.push(byte);
-                        } else {
-                            ld.link.push(byte);
+                        State::Link(ref mut ld) | State::Image(ref mut ld) => {
+                            if ld.is_alt() {
+                                ld.push_alt(
+                                    &mut state_machine.arena,
+                                    byte,
+                                    position,
+                                    options,
+                                    &mut diagnostics,
+                                );
+                            } else {
+                                ld.push_link(
+                                    &mut state_machine.arena,
+                                    byte,
+                                    position,
+                                    options,
+                                    &mut diagnostics,
+                                );
+                            }
                         }
-                    }
 
-                    _ => output.push(byte),
-                },
+                        _ => output.push(byte),
+                    }
+                }
 
                 b'-' => match state_machine.current {
                     State::None => {
-                        output.write(TAG_P_O);
+                        open_block(&mut output, TAG_P_O, sourcepos);
                         state_machine = state_machine
                             .rise(State::Paragraph)
                             .rise(State::UList(true, false));
@@ -1236,7 +2487,7 @@ impl MDS {
 
                     State::Intendation(exp, ref mut buf) => {
                         if exp {
-                            output.write(TAG_INT_C);
+                            output.tags(TAG_INT_C);
                             output.write(&buf.inner);
                             state_machine = state_machine.fall();
                         } else {
@@ -1244,7 +2495,7 @@ impl MDS {
                             buf.inner.clear();
                         }
 
-                        output.write(TAG_P_O);
+                        open_block(&mut output, TAG_P_O, sourcepos);
                         state_machine = state_machine
                             .rise(State::Paragraph)
                             .rise(State::UList(true, false));
@@ -1253,15 +2504,13 @@ impl MDS {
                     State::UList(true, false) => state_machine.current = State::Hor(2),
 
                     State::UList(true, true) => {
-                        output.write(TAG_UL_C);
-                        state_machine = state_machine
-                            .fall()
-                            .rise(State::Hor(2));
+                        output.tags(TAG_UL_C);
+                        state_machine = state_machine.fall().rise(State::Hor(2));
                     }
 
                     State::UList(false, p) => state_machine.current = State::UList(true, p),
 
-                    State::Hor(n) => state_machine.current = State::Hor(n+1),
+                    State::Hor(n) => state_machine.current = State::Hor(n + 1),
 
                     State::Escape => {
                         output.push(byte);
@@ -1276,90 +2525,1446 @@ impl MDS {
 
                     State::Link(ref mut ld) | State::Image(ref mut ld) => {
                         if ld.is_alt() {
-                            ld.alt.push(byte);
+                            ld.push_alt(
+                                &mut state_machine.arena,
+                                byte,
+                                position,
+                                options,
+                                &mut diagnostics,
+                            );
                         } else {
-                            ld.link.push(byte);
+                            ld.push_link(
+                                &mut state_machine.arena,
+                                byte,
+                                position,
+                                options,
+                                &mut diagnostics,
+                            );
                         }
                     }
 
                     _ => output.push(byte),
-                }
-
-                _ => output.push(byte),
-            }
+                },
 
-            column_counter += 1;
-        }
+                _ => {
+                    match state_machine.current {
+                        State::None => {
+                            state_machine = state_machine.rise(State::Paragraph);
+                            open_block(&mut output, TAG_P_O, sourcepos);
+                            output.escape(&[byte], safe);
+                        }
 
-        if state_machine.is_ulist() {
-            // Close ul tag
-            output.write(TAG_UL_C);
-            state_machine = state_machine.fall();
-        }
+                        State::Code(ls, n) => {
+                            if ls {
+                                match n {
+                                    1 => {
+                                        state_machine.current = State::Code(false, n);
+                                        // Open inline code span tag and code tag
+                                        output.tags(&tags.codei_o);
+                                    }
 
-        if state_machine.is_paragraph() {
-            // Close p tag
-            output.write(TAG_P_C);
-            state_machine = state_machine.fall();
-        }
+                                    3 => {
+                                        state_machine.current = State::Code(false, n);
+                                        // Open code block div tag and code tag
+                                        open_block(&mut output, &tags.codeb_o, sourcepos);
+                                    }
 
-        if state_machine.is_intend() {
-            // Close intend div tag
-            output.write(TAG_INT_C);
-        }
+                                    _ => {
+                                        diagnostics.push(Diagnostic::new(position, Code::CodeBlockState, "Unexpected code block state! Undefined behaviour may occur! Trying to mitigate damage by ignoring previous key.."));
+                                        state_machine = state_machine.fall();
+                                    }
+                                }
+                            }
+                            output.escape(&[byte], safe);
+                        }
 
-        output
-    }
+                        State::Escape => {
+                            match byte {
+                                b'<' => output.write(b"&lt;"),
+                                b'>' => output.write(b"&gt;"),
+                                _ => output.escape(&[byte], safe),
+                            }
 
-    /// Switches the state to previous state discarding the current state
-    /// and consuming the current self value.
-    fn fall(self) -> Self {
-        #[cfg(debug_assertions)]
-        println!("Falling from state {:?}", &self.current);
+                            state_machine = state_machine.fall();
+                        }
 
-        if self.previous.is_some() {
-            *self.previous.unwrap()
-        } else {
-            println!("Warning: Already in root state! Cannot fall back.");
-            self
-        }
-    }
+                        State::Exclamation => {
+                            output.push(b'!');
+                            output.escape(&[byte], safe);
+                            state_machine = state_machine.fall();
+                        }
 
-    fn rise(self, top: State) -> Self {
-        #[cfg(debug_assertions)]
-        println!("Rising from state {:?} to state {:?}", &self.current, &top);
+                        State::Link(ref mut ld) | State::Image(ref mut ld) => match ld.status {
+                            Linkstatus::Alt(0) => {
+                                ld.push_alt(
+                                    &mut state_machine.arena,
+                                    byte,
+                                    position,
+                                    options,
+                                    &mut diagnostics,
+                                );
+                            }
 
-        Self {
-            current: top,
+                            Linkstatus::Alt(1) => {
+                                output.push(b'[');
+                                output.escape(ld.bytes(&state_machine.arena).alt, safe);
+                                output.push(b']');
+                                output.escape(&[byte], safe);
+                                state_machine = state_machine.fall();
+                            }
+
+                            Linkstatus::Link => {
+                                ld.push_link(
+                                    &mut state_machine.arena,
+                                    byte,
+                                    position,
+                                    options,
+                                    &mut diagnostics,
+                                );
+                            }
+
+                            _ => {
+                                diagnostics.push(Diagnostic::new(
+                                    position,
+                                    Code::LinkState,
+                                    "Unexpected link status. This shouldn't happen.",
+                                ));
+                            }
+                        },
+
+                        State::Intendation(exp, ref mut buf) => {
+                            if exp {
+                                // Close intend div tag
+                                output.tags(TAG_INT_C);
+                                // Write the buffer of intendation
+                                output.write(&buf.inner);
+                                state_machine = state_machine.fall();
+                            } else {
+                                output.write(&buf.inner);
+                                buf.inner.clear();
+                            }
+
+                            open_block(&mut output, TAG_P_O, sourcepos);
+                            output.escape(&[byte], safe);
+                            state_machine = state_machine.rise(State::Paragraph);
+                        }
+
+                        State::Italic(seen) => {
+                            if seen {
+                                // Open i tag
+                                output.tags(tags.i_o);
+                                state_machine.current = State::Italic(false);
+                            }
+
+                            output.escape(&[byte], safe);
+                        }
+
+                        State::Bold(seen) => {
+                            if seen {
+                                diagnostics.push(Diagnostic::new(position, Code::LiteralStar, "Non-escaped `*` in the middle of bolded text. Parsing it as a literal.."));
+                                output.push(b'*');
+                                state_machine.current = State::Bold(false);
+                            }
+
+                            output.escape(&[byte], safe);
+                        }
+
+                        State::UList(seen, written) => {
+                            if seen {
+                                diagnostics.push(Diagnostic::new(
+                                    position,
+                                    Code::ListSpace,
+                                    "Unexpected character when expecting a space",
+                                ));
+                            }
+
+                            if written {
+                                output.tags(TAG_UL_C);
+                            }
+
+                            output.tags(TAG_P_C);
+                            state_machine = state_machine.fall().fall();
+
+                            if let State::Intendation(_, ref buf) = state_machine.current {
+                                output.tags(TAG_INT_C);
+                                output.write(&buf.inner);
+                                state_machine = state_machine.fall();
+                            }
+
+                            open_block(&mut output, TAG_P_O, sourcepos);
+                            output.escape(&[byte], safe);
+
+                            state_machine = state_machine.rise(State::Paragraph);
+                        }
+
+                        _ => output.escape(&[byte], safe),
+                    }
+                }
+            }
+
+            limits.check_output(output.len())?;
+        }
+
+        if let Some((position, byte)) = traced {
+            state_machine.trace(&mut trace, position, byte);
+        }
+
+        if let Some(boundaries) = boundaries.as_deref_mut() {
+            // Blocks found by looking ahead may continue past the end
+            if after_blank_line(bytes)
+                && skip_until < bytes.len()
+                && state_machine.is_none()
+                && matches!(indent, Indent::None)
+                && !list_indent
+                && paragraph_end(&output).is_none()
+                && list_item_end(&output).is_none()
+            {
+                boundaries.push(Boundary {
+                    source: bytes.len(),
+                    html: output.len(),
+                    line: cursor.position().line,
+                    diagnostics: diagnostics.len(),
+                    assets: assets.len(),
+                });
+            }
+        }
+
+        if let Indent::Code | Indent::CodeLead { .. } = indent {
+            // Close the indented code block on the last line
+            output.tags(tags.code_c);
+
+            if let Indent::CodeLead { breaks, .. } = indent {
+                output.write(&breaks);
+            }
+        }
+
+        // Close everything left open on the last line
+        state_machine.close_all(
+            &mut output,
+            tags,
+            &mut headings,
+            options,
+            &mut diagnostics,
+            cursor.position(),
+        );
+
+        if options.source_map {
+            source_map.line(bytes.len(), output.len());
+        }
+
+        diagnostics.append(&mut headings.take_duplicates());
+
+        for (position, fragment) in fragments {
+            if !headings.has_id(&fragment) {
+                diagnostics.push(Diagnostic::with_args(
+                    position,
+                    Code::BrokenFragment,
+                    "Link to #{} doesn't match any heading id.",
+                    vec![String::from_utf8_lossy(&fragment).into_owned()],
+                ));
+            }
+        }
+
+        if options.toc {
+            let at: Option<usize> = match options.placement {
+                Placement::End => None,
+                Placement::AfterFirstHeading => headings.first_end(),
+                Placement::Marker => {
+                    if toc_marker.is_none() && headings.has_any() {
+                        diagnostics.push(Diagnostic::new(
+                            cursor.position(),
+                            Code::MissingTocMarker,
+                            "No [TOC] marker found. Writing the table of contents at the end.",
+                        ));
+                    }
+
+                    toc_marker
+                }
+            };
+
+            let len: usize = output.len();
+            headings.write_toc(&mut output, at, options);
+            source_map.inserted(at.unwrap_or(len), output.len() - len);
+        }
+
+        if options.csp {
+            csp::strip(&mut output, &mut source_map);
+        }
+
+        if options.slides {
+            slides::split(&mut output, &mut source_map, &options.class_prefix);
+        }
+
+        if options.print {
+            print::print(&mut output, &mut source_map, &options.class_prefix);
+        }
+
+        if !options.wrap_paragraphs {
+            paragraphs::unwrap(&mut output, &mut source_map);
+        }
+
+        if options.xhtml {
+            xhtml::serialize(&mut output, &mut source_map);
+        }
+
+        apply_levels(&mut diagnostics, options, boundaries);
+        limits.check_output(output.len())?;
+
+        Ok(Output {
+            stats: options.stats.then(|| Stats::count(&output)),
+            html: output.bytes,
+            marks: output.marks,
+            diagnostics,
+            assets,
+            headings: match options.heading_map {
+                true => headings.anchors(),
+                false => Vec::new(),
+            },
+            source_map: source_map.finish(),
+            trace,
+        })
+    }
+
+    /// Switches the state to previous state discarding the current state
+    /// and consuming the current self value.
+    fn fall(self) -> Self {
+        let Some(mut previous) = self.previous else {
+            return self;
+        };
+
+        previous.arena = self.arena;
+
+        // The link was on top of the arena too
+        if let State::Link(ld) | State::Image(ld) = self.current {
+            previous.arena.truncate(ld.alt.start);
+        }
+
+        *previous
+    }
+
+    fn rise(mut self, top: State) -> Self {
+        Self {
+            current: top,
+            arena: core::mem::take(&mut self.arena),
             previous: Some(Box::new(self)),
         }
     }
 
     fn is_none(&self) -> bool {
+        matches!(self.current, State::None)
+    }
+
+    /// Writes what ends the inline state: its closing tag, or the keys it was
+    /// still waiting to make sense of. False if the state isn't inline.
+    fn close_inline(&self, output: &mut Html, tags: &Tags, safe: bool) -> bool {
         match self.current {
-            State::None => true,
-            _ => false,
+            State::Bold(seen) => {
+                if seen {
+                    output.push(b'*');
+                }
+                output.tags(tags.b_c);
+            }
+            State::Italic(true) => output.push(b'*'),
+            State::Italic(false) => output.tags(tags.i_c),
+            State::Underscore => output.tags(TAG_U_C),
+            State::Strike => output.tags(TAG_DEL_C),
+            State::Exclamation => output.push(b'!'),
+            State::Escape => {}
+            // The keys of a code block that were never followed by code
+            State::Code(true, n @ 1..=3) => output.extend(core::iter::repeat_n(b'`', n.into())),
+            // Keys inside a code block that didn't add up to its end
+            State::Code(true, n) => {
+                output.extend(core::iter::repeat_n(b'`', usize::from(n).saturating_sub(3)));
+                output.tags(TAG_CODEB_C);
+            }
+            State::Code(false, 1) => output.tags(TAG_CODEI_C),
+            State::Code(false, _) => output.tags(TAG_CODEB_C),
+            State::Link(ref ld) | State::Image(ref ld) => {
+                output.push(b'[');
+                output.escape(ld.bytes(&self.arena).alt, safe);
+
+                if !ld.is_alt() {
+                    output.push(b']');
+                    output.push(b'(');
+                    output.escape(ld.bytes(&self.arena).link, safe);
+                }
+            }
+            _ => return false,
         }
+
+        true
     }
 
-    fn is_paragraph(&self) -> bool {
-        match self.current {
-            State::Paragraph => true,
-            _ => false,
+    /// Closes every state on the stack, like at the end of the document
+    fn close_all(
+        mut self,
+        output: &mut Html,
+        tags: &Tags,
+        headings: &mut Headings,
+        options: &Options,
+        diagnostics: &mut Vec<Diagnostic>,
+        position: Position,
+    ) -> Self {
+        while !self.is_none() {
+            if !self.close_inline(output, tags, options.safe) {
+                match self.current {
+                    State::Header(n, false) => {
+                        output.extend(core::iter::repeat_n(b'#', n.into()));
+                    }
+                    State::Header(n, true) => {
+                        let text: bool = headings.close(output, n, options);
+
+                        if !text && options.a11y {
+                            diagnostics.push(Diagnostic::new(
+                                position,
+                                Code::EmptyHeading,
+                                "Heading has no text.",
+                            ));
+                        }
+                    }
+                    State::UList(seen, written) => {
+                        if written {
+                            output.tags(TAG_UL_C);
+                        }
+                        if seen {
+                            output.push(b'-');
+                        }
+                    }
+                    State::Hor(3..) => output.tags(TAG_HR),
+                    State::Hor(n) => output.extend(core::iter::repeat_n(b'-', n.into())),
+                    State::Paragraph => output.tags(TAG_P_C),
+                    State::Intendation(_, _) => output.tags(TAG_INT_C),
+                    State::LItem => output.tags(TAG_LI_C),
+                    _ => {}
+                }
+            }
+
+            self = self.fall();
         }
+
+        self
     }
 
-    fn is_ulist(&self) -> bool {
-        match self.current {
-            State::UList(_, true) => true,
-            _ => false,
+    /// Logs the states if the byte changed them
+    fn trace(&self, trace: &mut Vec<Transition>, position: Position, byte: u8) {
+        let mut states: Vec<String> = Vec::new();
+        let mut machine: Option<&MDS> = Some(self);
+
+        while let Some(m) = machine {
+            states.push(m.current.name().into());
+            machine = m.previous.as_deref();
+        }
+
+        states.reverse();
+
+        let changed: bool = match trace.last() {
+            Some(last) => last.states != states,
+            None => states.len() > 1 || states[0] != "None",
+        };
+
+        if changed {
+            trace.push(Transition {
+                position,
+                byte,
+                states,
+            });
         }
     }
+}
 
-    fn is_intend(&self) -> bool {
-        match self.current {
-            State::Intendation(_, _) => true,
-            _ => false,
+/// Checks if the url is relative or uses a scheme that can't run scripts
+fn safe_scheme(url: &[u8]) -> bool {
+    for (i, b) in url.iter().enumerate() {
+        match b {
+            b':' => {
+                let scheme: &[u8] = &url[..i];
+                return [&b"http"[..], b"https", b"mailto", b"tel"]
+                    .iter()
+                    .any(|safe| scheme.eq_ignore_ascii_case(safe));
+            }
+
+            b'/' | b'?' | b'#' => return true,
+            _ => {}
+        }
+    }
+
+    true
+}
+
+/// Checks if the url is absolute and points to another host than the site
+fn is_external(url: &[u8], site: Option<&str>) -> bool {
+    let rest: &[u8] = if let Some(rest) = url.strip_prefix(b"//") {
+        rest
+    } else {
+        let Some(colon) = url.iter().position(|&b| b == b':') else {
+            return false;
+        };
+
+        let scheme: &[u8] = &url[..colon];
+        if !(scheme.eq_ignore_ascii_case(b"http") || scheme.eq_ignore_ascii_case(b"https")) {
+            return false;
+        }
+
+        match url[colon + 1..].strip_prefix(b"//") {
+            Some(rest) => rest,
+            None => return false,
+        }
+    };
+
+    let end: usize = rest
+        .iter()
+        .position(|&b| matches!(b, b'/' | b'?' | b'#'))
+        .unwrap_or(rest.len());
+    // Leave out the user info and the port
+    let host: &[u8] = &rest[..end];
+    let host: &[u8] = match host.iter().rposition(|&b| b == b'@') {
+        Some(at) => &host[at + 1..],
+        None => host,
+    };
+    let host: &[u8] = match host.iter().rposition(|&b| b == b':') {
+        Some(colon) => &host[..colon],
+        None => host,
+    };
+
+    match site {
+        Some(site) => !host.eq_ignore_ascii_case(site.as_bytes()),
+        None => true,
+    }
+}
+
+/// Finds a key like `[[Ctrl]]` at the start of the bytes. Returns the name of
+/// the key and the length of the markup.
+fn kbd(bytes: &[u8]) -> Option<(&[u8], usize)> {
+    let rest: &[u8] = bytes.strip_prefix(b"[[")?;
+    let end: usize = rest
+        .iter()
+        .position(|&b| matches!(b, b']' | b'[' | b'\n' | b'\r'))?;
+
+    if end == 0 || !rest[end..].starts_with(b"]]") {
+        return None;
+    }
+
+    Some((&rest[..end], end + 4))
+}
+
+/// The closing `~~` of a strikethrough, searched once for all of the `~~`
+/// before it on the line so a line full of them is read only once
+#[derive(Default)]
+struct Closer {
+    /// The search covered the bytes from here up to `end`
+    from: usize,
+    end: usize,
+    /// The `~~` at `end`, if the search stopped at one
+    found: bool,
+}
+
+impl Closer {
+    /// Checks if `~~` at the index opens a strikethrough, which needs text
+    /// right after it and a closing `~~` right after text on the same line.
+    /// Code spans and links may not cross the `~~`.
+    fn opens(&mut self, bytes: &[u8], i: usize) -> bool {
+        let start: usize = i + 2;
+
+        if !bytes[i..].starts_with(b"~~") {
+            return false;
+        }
+
+        if start < self.from || start > self.end {
+            let len: usize = bytes[start..]
+                .windows(2)
+                .position(|w| w == b"~~" || matches!(w[0], b'`' | b'[' | b']' | b'\n' | b'\r'))
+                .unwrap_or(bytes.len() - start);
+
+            self.from = start;
+            self.end = start + len;
+            self.found = bytes[self.end..].starts_with(b"~~");
+        }
+
+        self.found
+            && self.end > start
+            && !bytes[start].is_ascii_whitespace()
+            && !bytes[self.end - 1].is_ascii_whitespace()
+    }
+}
+
+/// Table of the lines starting at the bytes
+#[derive(Debug)]
+struct Table<'a> {
+    alignments: Vec<Option<&'static [u8]>>,
+    rows: Vec<Vec<&'a [u8]>>,
+}
+
+/// Reads the table at the start of the bytes: a header row of cells between
+/// `|` pipes, a delimiter row with as many cells of `-` and the rows after
+/// them up to a blank line or the start of another block. Returns the table
+/// and the index of the line break after it.
+fn table(bytes: &[u8]) -> Option<(Table<'_>, usize)> {
+    let (header, mut end) = line(bytes);
+    let header: Vec<&[u8]> = cells(header)?;
+    let (delimiter_line, len) = line(&bytes[end..]);
+    let delimiter: Vec<&[u8]> = cells(delimiter_line)?;
+
+    if header.len() != delimiter.len() {
+        return None;
+    }
+
+    let mut alignments: Vec<Option<&'static [u8]>> = Vec::with_capacity(delimiter.len());
+
+    for cell in delimiter {
+        let dashes: &[u8] = cell.strip_prefix(b":").unwrap_or(cell);
+        let dashes: &[u8] = dashes.strip_suffix(b":").unwrap_or(dashes);
+
+        if dashes.is_empty() || dashes.iter().any(|&b| b != b'-') {
+            return None;
+        }
+
+        alignments.push(match (cell.starts_with(b":"), cell.ends_with(b":")) {
+            (true, true) => Some(b"center"),
+            (true, false) => Some(b"left"),
+            (false, true) => Some(b"right"),
+            (false, false) => None,
+        });
+    }
+
+    let mut rows: Vec<Vec<&[u8]>> = vec![header];
+    let mut last: usize = end + delimiter_line.len();
+    end += len;
+
+    while end < bytes.len() {
+        let (row, len) = line(&bytes[end..]);
+        let mut cells: Vec<&[u8]> = match cells(row) {
+            Some(cells) => cells,
+            // Text without pipes is a row with only the first cell, like on GitHub
+            None if !row.trim_ascii().is_empty() && !starts_block(row) => vec![row.trim_ascii()],
+            None => break,
+        };
+
+        cells.resize(alignments.len(), b"");
+        rows.push(cells);
+        last = end + row.len();
+        end += len;
+    }
+
+    Some((Table { alignments, rows }, last))
+}
+
+/// Checks if the line starts a block that ends the table before it: a
+/// heading, a quote, a fence, a rule or an item of a list
+fn starts_block(line: &[u8]) -> bool {
+    let line: &[u8] = line.trim_ascii();
+
+    matches!(line.first(), Some(b'#' | b'>'))
+        || line.starts_with(b"```")
+        || line.starts_with(b"~~~")
+        || (line.len() >= 3
+            && line
+                .iter()
+                .all(|&b| b == line[0] && matches!(b, b'-' | b'*' | b'_')))
+        || matches!(line, [b'-' | b'*' | b'+', b' ' | b'\t', ..])
+        || list_marker(line).is_some()
+}
+
+/// Cells of the table row, or None if the line has no pipes between them.
+/// Pipes escaped with `\` or inside code spans don't split the cells.
+fn cells(line: &[u8]) -> Option<Vec<&[u8]>> {
+    let line: &[u8] = line.trim_ascii();
+
+    if !line.contains(&b'|') {
+        return None;
+    }
+
+    let line: &[u8] = line.strip_prefix(b"|").unwrap_or(line);
+    let line: &[u8] = match line.strip_suffix(b"|") {
+        Some(inner) if !inner.ends_with(b"\\") => inner,
+        _ => line,
+    };
+
+    let mut cells: Vec<&[u8]> = Vec::new();
+    let mut start: usize = 0;
+    let mut code: bool = false;
+    let mut i: usize = 0;
+
+    while i < line.len() {
+        match line[i] {
+            b'\\' => i += 1,
+            b'`' => code = !code,
+            b'|' if !code => {
+                cells.push(line[start..i].trim_ascii());
+                start = i + 1;
+            }
+            _ => {}
+        }
+
+        i += 1;
+    }
+
+    cells.push(line[start..].trim_ascii());
+    Some(cells)
+}
+
+/// Writes the table, parsing the text of each cell for its inline syntax
+fn write_table(
+    output: &mut Html,
+    table: &Table,
+    options: &Options,
+    tags: &Tags,
+    limits: &Limits,
+    sourcepos: Option<Position>,
+) -> Result<(), Error> {
+    open_block(output, b"<table>", sourcepos);
+    output.push(b'\n');
+
+    for (n, row) in table.rows.iter().enumerate() {
+        match n {
+            0 => output.tags(b"<thead>\n"),
+            1 => output.tags(b"<tbody>\n"),
+            _ => {}
         }
+
+        output.tags(b"<tr>\n");
+
+        let tag: &[u8] = match n {
+            0 => b"th",
+            _ => b"td",
+        };
+
+        for (cell, alignment) in row.iter().zip(&table.alignments) {
+            let start: usize = output.len();
+            output.push(b'<');
+            output.write(tag);
+
+            if let Some(alignment) = alignment {
+                match options.align {
+                    Align::Attribute => output.write(b" align=\""),
+                    Align::Style => output.write(b" style=\"text-align: "),
+                    Align::Class => {
+                        output.write(b" class=\"");
+                        output.escape(options.class_prefix.as_bytes(), true);
+                        output.write(b"align-");
+                    }
+                }
+
+                output.write(alignment);
+                output.push(b'"');
+            }
+
+            output.push(b'>');
+            output.tagged(start);
+
+            output.append(&inline(cell, options, tags, limits)?);
+
+            let end: usize = output.len();
+            output.write(b"</");
+            output.write(tag);
+            output.write(b">\n");
+            output.tagged(end);
+        }
+
+        output.tags(b"</tr>\n");
+
+        if n == 0 {
+            output.tags(b"</thead>\n");
+        }
+    }
+
+    if table.rows.len() > 1 {
+        output.tags(b"</tbody>\n");
+    }
+
+    output.tags(b"</table>");
+    Ok(())
+}
+
+/// Ordered list of the lines starting at the bytes
+#[derive(Debug)]
+struct OrderedList {
+    start: usize,
+    /// Number and text of the items, the lines of each joined by line breaks
+    items: Vec<(usize, Vec<u8>)>,
+}
+
+/// Reads the ordered list at the start of the bytes: lines starting with a
+/// number and the `.` or `)` of the first one, and the indented lines that
+/// continue them. Returns the list and the index of the line break after it.
+fn ordered_list(bytes: &[u8]) -> Option<(OrderedList, usize)> {
+    let (first, _) = line(bytes);
+    let (start, delimiter, _) = list_marker(first)?;
+    let mut items: Vec<(usize, Vec<u8>)> = Vec::new();
+    let mut at: usize = 0;
+    let mut end: usize = 0;
+
+    while at < bytes.len() {
+        let (text, len) = line(&bytes[at..]);
+
+        match list_marker(text) {
+            Some((number, d, item)) if d == delimiter => items.push((number, item.to_vec())),
+            // Indented lines continue the item
+            _ if matches!(text.first(), Some(b' ' | b'\t')) && !text.trim_ascii().is_empty() => {
+                if let Some((_, item)) = items.last_mut() {
+                    item.push(b'\n');
+                    item.extend_from_slice(text.trim_ascii());
+                }
+            }
+            _ => break,
+        }
+
+        end = at + text.len();
+        at += len;
+    }
+
+    Some((OrderedList { start, items }, end))
+}
+
+/// Number, delimiter and text of the item at the start of the line, like
+/// `3. text` or `3) text`
+fn list_marker(line: &[u8]) -> Option<(usize, u8, &[u8])> {
+    let digits: usize = line.iter().take_while(|b| b.is_ascii_digit()).count();
+
+    // More digits could overflow browsers
+    if digits == 0 || digits > 9 {
+        return None;
+    }
+
+    let delimiter: u8 = *line.get(digits).filter(|&&b| b == b'.' || b == b')')?;
+    let rest: &[u8] = &line[digits + 1..];
+
+    if !rest.is_empty() && !matches!(rest[0], b' ' | b'\t') {
+        return None;
+    }
+
+    let number: usize = line[..digits]
+        .iter()
+        .fold(0, |n, &b| n * 10 + usize::from(b - b'0'));
+
+    Some((number, delimiter, rest.trim_ascii()))
+}
+
+/// Writes the ordered list, parsing the text of each item for its inline
+/// syntax
+fn write_ordered_list(
+    output: &mut Html,
+    list: &OrderedList,
+    options: &Options,
+    tags: &Tags,
+    limits: &Limits,
+    sourcepos: Option<Position>,
+) -> Result<(), Error> {
+    match list.start {
+        1 => open_block(output, TAG_OL_O, sourcepos),
+        start => {
+            let mut tag: Vec<u8> = b"<ol start=\"".to_vec();
+            tag.write_number(start);
+            tag.write(b"\">");
+            open_block(output, &tag, sourcepos);
+        }
+    }
+
+    // Number of the next item when it follows the one before
+    let mut next: usize = list.start;
+
+    for (n, (number, item)) in list.items.iter().enumerate() {
+        if n > 0 {
+            output.push(b'\n');
+        }
+
+        match options.numbering {
+            Numbering::Respect if *number != next => {
+                let start: usize = output.len();
+                output.write(b"<li value=\"");
+                output.write_number(*number);
+                output.write(b"\">");
+                output.tagged(start);
+                next = *number;
+            }
+            _ => output.tags(TAG_LI_O),
+        }
+
+        next += 1;
+        output.append(&inline(item, options, tags, limits)?);
+        output.tags(TAG_LI_C);
+    }
+
+    output.tags(TAG_OL_C);
+    Ok(())
+}
+
+/// Lines of the quote starting at the bytes, each without its `>` and the
+/// space after it, and the index of the line break after the last one.
+/// None if the quotes nest deeper than [`MAX_QUOTE_DEPTH`].
+fn blockquote(bytes: &[u8]) -> Option<(Vec<u8>, usize)> {
+    let mut quote: Vec<u8> = Vec::new();
+    let mut at: usize = 0;
+    let mut end: usize = 0;
+
+    while at < bytes.len() {
+        let (text, len) = line(&bytes[at..]);
+        // Quotes in list items are indented like the rest of the item
+        let Some(rest) = text.trim_ascii_start().strip_prefix(b">") else {
+            break;
+        };
+
+        let depth: usize = text
+            .iter()
+            .filter(|&&b| b != b' ' && b != b'\t')
+            .take_while(|&&b| b == b'>')
+            .count();
+
+        if depth > MAX_QUOTE_DEPTH {
+            return None;
+        }
+
+        if at > 0 {
+            quote.push(b'\n');
+        }
+
+        quote.extend_from_slice(rest.strip_prefix(b" ").unwrap_or(rest));
+        end = at + text.len();
+        at += len;
+    }
+
+    // The blocks inside end with the last line like they would in a document
+    quote.push(b'\n');
+
+    Some((quote, end))
+}
+
+/// Writes the quote, parsing its lines as a document of their own so the
+/// quotes nested in it are parsed the same way
+fn write_blockquote(
+    output: &mut Html,
+    quote: &[u8],
+    options: &Options,
+    tags: &Tags,
+    limits: &Limits,
+    sourcepos: Option<Position>,
+) -> Result<(), Error> {
+    let mut html: Html = MDS::run(quote, &nested(options), tags, limits)?.into();
+    html.crop(trimmed(&html));
+
+    open_block(output, TAG_BQ_O, sourcepos);
+    output.push(b'\n');
+
+    if !html.is_empty() {
+        output.append(&html);
+        output.push(b'\n');
+    }
+
+    output.tags(TAG_BQ_C);
+    Ok(())
+}
+
+/// Options for parsing a piece of the document, without the ones that apply
+/// to the whole document
+fn nested(options: &Options) -> Options {
+    let mut options: Options = options.clone();
+    options.toc = false;
+    options.csp = false;
+    options.print = false;
+    options.slides = false;
+    options.xhtml = false;
+    options.wrap_paragraphs = true;
+    options.utf8 = Utf8::Keep;
+    options.sourcepos = false;
+    options.source_map = false;
+    options.heading_map = false;
+    options.trace = false;
+    options.stats = false;
+    options.a11y = false;
+    options.check_fragments = false;
+    options
+}
+
+/// Parses the text for its inline syntax only, as one line without the
+/// paragraph around it
+pub(crate) fn inline(
+    text: &[u8],
+    options: &Options,
+    tags: &Tags,
+    limits: &Limits,
+) -> Result<Html, Error> {
+    let options: Options = nested(options);
+
+    // Nothing starts a block after the lead, not even `#` or `-` at the start
+    let mut line: Vec<u8> = Vec::with_capacity(text.len() + 1);
+    line.push(INLINE_LEAD);
+    line.extend(text.trim_ascii().iter().map(|&b| match b {
+        b'\n' | b'\r' => b' ',
+        b => b,
+    }));
+
+    let mut html: Html = MDS::run(&line, &options, tags, limits)?.into();
+
+    let mut end: usize = html.trim_ascii_end().len();
+    if html[..end].ends_with(TAG_P_C) {
+        end -= TAG_P_C.len();
+    }
+    let start: usize = match html.starts_with(TAG_P_O) {
+        true => TAG_P_O.len(),
+        false => 0,
+    };
+    html.crop(start..end.max(start));
+
+    let leads: Vec<(Range<usize>, Html)> = html
+        .iter()
+        .enumerate()
+        .filter(|&(_, &b)| b == INLINE_LEAD)
+        .map(|(i, _)| (i..i + 1, Html::default()))
+        .collect();
+    html.edit(leads);
+    Ok(html)
+}
+
+/// Range of the bytes without the whitespace around them
+fn trimmed(bytes: &[u8]) -> Range<usize> {
+    let start: usize = bytes.len() - bytes.trim_ascii_start().len();
+    start..start.max(bytes.trim_ascii_end().len())
+}
+
+/// Pushes the byte to the range at the end of the arena unless it's a
+/// control character or the range is full. Returns true for the first byte
+/// left out because the range was full.
+fn bounded_push(
+    arena: &mut Vec<u8>,
+    range: &mut Range<usize>,
+    byte: u8,
+    max: usize,
+    truncated: &mut bool,
+) -> bool {
+    if byte.is_ascii_control() {
+        return false;
+    }
+
+    if range.len() < max {
+        // What's after the range is left by the links that have ended
+        arena.truncate(range.end);
+        arena.push(byte);
+        range.end += 1;
+        return false;
+    }
+
+    !core::mem::replace(truncated, true)
+}
+
+/// Splits the text of a link at its first `![alt](url)`
+fn split_image(text: &[u8]) -> Option<(&[u8], Linkbytes<'_>, &[u8])> {
+    let mut start: usize = 0;
+
+    // The `![` of an image that didn't go on to its url is text
+    let (start, middle) = loop {
+        start += text[start..].windows(2).position(|w| w == b"![")?;
+        let alt: &[u8] = &text[start + 2..];
+
+        match alt.iter().position(|&b| b == b']') {
+            Some(end) if alt.get(end + 1) == Some(&b'(') => break (start, start + 2 + end),
+            _ => start += 2,
+        }
+    };
+
+    let end: usize = middle + 2 + text[middle + 2..].iter().position(|&b| b == b')')?;
+
+    let image: Linkbytes = Linkbytes {
+        alt: &text[start + 2..middle],
+        link: &text[middle + 2..end],
+    };
+
+    Some((&text[..start], image, &text[end + 1..]))
+}
+
+fn link_truncated(position: Position, options: &Options) -> Diagnostic {
+    Diagnostic::with_args(
+        position,
+        Code::LinkTruncated,
+        "Link text or url is longer than {} bytes. Cutting it short.",
+        vec![options.max_link_len.to_string()],
+    )
+}
+
+/// Percent-encodes the bytes that can't be in a url, like spaces, quotes
+/// and the bytes of non-ASCII characters. A `%` before two hex digits is
+/// taken as encoded already, so nothing gets encoded twice.
+fn encode_url(url: &[u8]) -> Cow<'_, [u8]> {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+    let allowed = |i: usize| match url[i] {
+        b'%' => url
+            .get(i + 1..i + 3)
+            .is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit)),
+        b => b.is_ascii_alphanumeric() || b"-._~:/?#[]@!$&'()*+,;=".contains(&b),
+    };
+
+    if (0..url.len()).all(allowed) {
+        return Cow::Borrowed(url);
+    }
+
+    let mut encoded: Vec<u8> = Vec::with_capacity(url.len() + 16);
+
+    for (i, &b) in url.iter().enumerate() {
+        match allowed(i) {
+            true => encoded.push(b),
+            false => encoded.extend_from_slice(&[
+                b'%',
+                HEX[usize::from(b >> 4)],
+                HEX[usize::from(b & 15)],
+            ]),
+        }
+    }
+
+    Cow::Owned(encoded)
+}
+
+/// Checks if the url is a path relative to the document
+fn is_relative(url: &[u8]) -> bool {
+    !url.is_empty()
+        && !url.starts_with(b"/")
+        && !url.starts_with(b"#")
+        && !url.starts_with(b"?")
+        && url
+            .iter()
+            .take_while(|&&b| !matches!(b, b'/' | b'?' | b'#'))
+            .all(|&b| b != b':')
+}
+
+/// Names and values of attributes
+type Attributes<'a> = Vec<(&'a [u8], &'a [u8])>;
+
+/// Parses an attribute block like `{width=640 sizes="50vw"}` at the start of
+/// the bytes. Returns the names and values, and the length of the block. The
+/// block must end on the same line.
+fn attributes(bytes: &[u8]) -> Option<(Attributes<'_>, usize)> {
+    if bytes.first() != Some(&b'{') {
+        return None;
+    }
+
+    let mut pairs: Attributes = Vec::new();
+    let mut at: usize = 1;
+
+    loop {
+        while bytes.get(at).is_some_and(|&b| b == b' ' || b == b'\t') {
+            at += 1;
+        }
+
+        match bytes.get(at)? {
+            b'}' => return Some((pairs, at + 1)),
+            b'\n' | b'\r' => return None,
+            _ => {}
+        }
+
+        // A byte that can't be in a name ends the block, so an unclosed `{`
+        // isn't scanned to the end of the line
+        let name_end: usize = at
+            + bytes[at..]
+                .iter()
+                .position(|&b| !(b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_')))?;
+        let name: &[u8] = &bytes[at..name_end];
+        at = name_end;
+
+        if !matches!(bytes[at], b'=' | b'}' | b' ' | b'\t') {
+            return None;
+        }
+
+        if bytes[at] != b'=' {
+            // Names without values are ignored
+            continue;
+        }
+
+        at += 1;
+
+        let value: &[u8] = if bytes.get(at) == Some(&b'"') {
+            let end: usize = at
+                + 1
+                + bytes[at + 1..]
+                    .iter()
+                    .position(|&b| matches!(b, b'"' | b'\n' | b'\r'))?;
+
+            if bytes[end] != b'"' {
+                return None;
+            }
+
+            let value: &[u8] = &bytes[at + 1..end];
+            at = end + 1;
+            value
+        } else {
+            let end: usize = at
+                + bytes[at..]
+                    .iter()
+                    .position(|&b| matches!(b, b'{' | b'}' | b' ' | b'\t' | b'\n' | b'\r'))?;
+
+            if bytes[end] == b'{' {
+                return None;
+            }
+
+            let value: &[u8] = &bytes[at..end];
+            at = end;
+            value
+        };
+
+        pairs.push((name, value));
+    }
+}
+
+/// Checks if the bytes end with a line that has only whitespace on it
+pub(crate) fn after_blank_line(before: &[u8]) -> bool {
+    let Some(text) = before
+        .strip_suffix(b"\r\n")
+        .or_else(|| before.strip_suffix(b"\n"))
+        .or_else(|| before.strip_suffix(b"\r"))
+    else {
+        return false;
+    };
+
+    let start: usize = text
+        .iter()
+        .rposition(|&b| matches!(b, b'\n' | b'\r'))
+        .map_or(0, |at| at + 1);
+
+    text[start..].iter().all(|&b| matches!(b, b' ' | b'\t'))
+}
+
+/// Checks if a line ended right before the byte
+fn line_ended(before: &[u8], byte: u8) -> bool {
+    match before.last() {
+        Some(b'\n') => true,
+        Some(b'\r') => byte != b'\n',
+        _ => false,
+    }
+}
+
+/// Splits the first line off the bytes, without the line break. Returns the
+/// line and where the next one starts.
+pub(crate) fn line(bytes: &[u8]) -> (&[u8], usize) {
+    match scan::find(bytes, [b'\n', b'\r']) {
+        Some(end) if bytes[end..].starts_with(b"\r\n") => (&bytes[..end], end + 2),
+        Some(end) => (&bytes[..end], end + 1),
+        None => (bytes, bytes.len()),
+    }
+}
+
+/// Finds a block at the start of the bytes opened by one of the lines in
+/// `open` and closed by the `close` line, which may be indented. Returns the
+/// range of its contents and where the closing line ends, before its line
+/// break. Without a closing line the block runs to the end.
+fn block(bytes: &[u8], open: &[&[u8]], close: &[u8]) -> Option<(Range<usize>, usize)> {
+    let (first, start) = line(bytes);
+
+    if !open.contains(&first.trim_ascii_end()) {
+        return None;
+    }
+
+    let mut at: usize = start;
+
+    while at < bytes.len() {
+        let (text, next) = line(&bytes[at..]);
+
+        if text.trim_ascii() == close {
+            // Leave out the line break before the closing line
+            let contents_end: usize = bytes[..at]
+                .strip_suffix(b"\r\n")
+                .or_else(|| bytes[..at].strip_suffix(b"\n"))
+                .or_else(|| bytes[..at].strip_suffix(b"\r"))
+                .map_or(at, |b| b.len().max(start));
+
+            return Some((start..contents_end, at + text.len()));
+        }
+
+        at += next;
+    }
+
+    Some((start..bytes.len(), bytes.len()))
+}
+
+/// Info string, contents and end of the fenced code block the bytes start with
+fn fence(bytes: &[u8]) -> Option<(&[u8], Range<usize>, usize)> {
+    let (first, _) = line(bytes);
+    let info: &[u8] = first.trim_ascii_start().strip_prefix(b"```")?;
+    let (contents, end) = block(bytes, &[first.trim_ascii_end()], b"```")?;
+
+    Some((info, contents, end))
+}
+
+/// Writes the fenced code block like the options ask
+fn write_fence(
+    output: &mut Html,
+    info: &[u8],
+    code: &[u8],
+    options: &Options,
+    tags: &Tags,
+    sourcepos: Option<Position>,
+) {
+    match options.code_blocks {
+        CodeBlocks::Pre => {
+            // Only the first word of the info string names the language
+            let language: &[u8] = info
+                .trim_ascii()
+                .split(|b| b.is_ascii_whitespace())
+                .next()
+                .unwrap_or_default();
+
+            let tag: usize = output.len();
+            output.write(b"<pre><code");
+
+            if !language.is_empty() {
+                output.write(b" class=\"language-");
+                output.escape(language, true);
+                output.push(b'"');
+            }
+
+            if let Some(position) = sourcepos {
+                output.write(b" data-sourcepos=\"");
+                output.write_number(position.line);
+                output.push(b':');
+                output.write_number(position.column);
+                output.push(b'"');
+            }
+
+            output.push(b'>');
+            output.tagged(tag);
+            write_code(output, code, options, true);
+            output.tags(TAG_PRE_C);
+        }
+
+        // The info string stays on the first line as it always has
+        CodeBlocks::Div => {
+            open_block(output, &tags.code_o, sourcepos);
+            output.escape(info.trim_ascii(), options.safe);
+            output.push(b'\n');
+            write_code(output, code, options, options.safe);
+
+            if !code.is_empty() {
+                output.push(b'\n');
+            }
+
+            output.tags(tags.code_c);
+        }
+    }
+}
+
+/// Writes the code of a fenced block. The indentation common to all of its
+/// lines is left out and the tabs are expanded if asked to.
+fn write_code(output: &mut Html, code: &[u8], options: &Options, escape: bool) {
+    let lines = || code.split(|&b| b == b'\n');
+    let mut common: &[u8] = &[];
+
+    if options.dedent_code {
+        let mut indented = lines().filter(|l| !l.trim_ascii().is_empty()).map(|l| {
+            let width: usize = l.iter().take_while(|&&b| matches!(b, b' ' | b'\t')).count();
+            &l[..width]
+        });
+
+        common = indented.next().unwrap_or_default();
+
+        for indentation in indented {
+            let same: usize = common
+                .iter()
+                .zip(indentation)
+                .take_while(|(a, b)| a == b)
+                .count();
+            common = &common[..same];
+        }
+    }
+
+    for (n, l) in lines().enumerate() {
+        if n != 0 {
+            output.push(b'\n');
+        }
+
+        let l: &[u8] = l.strip_prefix(common).unwrap_or(l.trim_ascii_start());
+
+        match options.expand_tabs {
+            Some(width) => {
+                let mut column: usize = 0;
+
+                for (i, &b) in l.iter().enumerate() {
+                    if b == b'\t' {
+                        let spaces: usize = width - column % width;
+                        output.extend(core::iter::repeat_n(b' ', spaces));
+                        column += spaces;
+                    } else {
+                        output.escape(&l[i..i + 1], escape);
+
+                        // Continuation bytes of UTF-8 characters take no columns
+                        if b & 0xC0 != 0x80 {
+                            column += 1;
+                        }
+                    }
+                }
+            }
+
+            None => output.escape(l, escape),
+        }
+    }
+}
+
+/// Columns the indentation takes after the space or tab
+fn indent_width(columns: usize, byte: u8) -> usize {
+    match byte {
+        b'\t' => columns + TAB_SIZE - columns % TAB_SIZE,
+        _ => columns + 1,
+    }
+}
+
+/// Leaves out the allowed diagnostics and turns the denied ones into errors.
+/// The boundaries are moved to count only the diagnostics kept.
+fn apply_levels(
+    diagnostics: &mut Vec<Diagnostic>,
+    options: &Options,
+    boundaries: Option<&mut Vec<Boundary>>,
+) {
+    // How many of the first n diagnostics are kept
+    let mut kept: Vec<usize> = Vec::with_capacity(diagnostics.len() + 1);
+    kept.push(0);
+
+    diagnostics.retain_mut(|diagnostic| {
+        let level: Level = options.level(diagnostic.code);
+
+        if level == Level::Deny {
+            diagnostic.severity = Severity::Error;
+        }
+
+        kept.push(kept[kept.len() - 1] + usize::from(level != Level::Allow));
+        level != Level::Allow
+    });
+
+    for boundary in boundaries.into_iter().flatten() {
+        boundary.diagnostics = kept[boundary.diagnostics];
     }
 }
+
+/// Index of the `</p>` if the output ends with a paragraph followed by a line break
+fn paragraph_end(output: &[u8]) -> Option<usize> {
+    let text: &[u8] = output
+        .strip_suffix(b"\r\n")
+        .or_else(|| output.strip_suffix(b"\n"))
+        .or_else(|| output.strip_suffix(b"\r"))?;
+
+    text.ends_with(TAG_P_C).then(|| text.len() - TAG_P_C.len())
+}
+
+/// Index of the `</li>` if the output ends with a list item followed by line breaks
+fn list_item_end(output: &[u8]) -> Option<usize> {
+    let end: usize = output.iter().rposition(|&b| !matches!(b, b'\n' | b'\r'))? + 1;
+
+    output[..end]
+        .ends_with(TAG_LI_C)
+        .then(|| end - TAG_LI_C.len())
+}
+
+/// Writes the opening tag of a block element, with the position of the
+/// markdown it came from if source positions are asked for.
+fn open_block(output: &mut Html, tag: &[u8], sourcepos: Option<Position>) {
+    let start: usize = output.len();
+
+    match sourcepos {
+        Some(position) => {
+            output.write(&tag[..tag.len() - 1]);
+            output.write(b" data-sourcepos=\"");
+            output.write_number(position.line);
+            output.push(b':');
+            output.write_number(position.column);
+            output.write(b"\">");
+        }
+
+        None => output.write(tag),
+    }
+
+    output.tagged(start);
+}
@@ -0,0 +1,776 @@
+//! Parser options and the builder that creates them. Everything that changes
+//! the output lives here so the CLI, the daemon and library callers all
+//! configure the parser the same way.
+
+use crate::diagnostic::{Code, Level};
+use alloc::string::{String, ToString};
+
+/// Longest text or url of a link or an image by default, in bytes. Long
+/// enough for the data urls of small images.
+const MAX_LINK_LEN: usize = 64 * 1024;
+
+/// Optional syntax extensions. Enabled extensions are stored as bits, so each
+/// variant must have its own bit index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ext {
+    /// `_text_` is underlined with `<u>` tags.
+    Underline = 0,
+    /// ```` ```raw ```` and ```` ```html ```` blocks are written out as is,
+    /// even in safe mode. Never enable this for untrusted input.
+    RawFence = 1,
+    /// ```` ```comment ```` blocks and everything between
+    /// `<!-- md2htm:off -->` and `<!-- md2htm:on -->` lines is left out.
+    Comments = 2,
+    /// `{width=640 height=480}` right after an image adds the attributes to
+    /// it. `width`, `height`, `srcset` and `sizes` are supported.
+    Attributes = 3,
+    /// `[[Ctrl]]+[[C]]` becomes `<kbd>Ctrl</kbd>+<kbd>C</kbd>` for keyboard
+    /// shortcuts in documentation.
+    Kbd = 4,
+    /// `~~text~~` is struck through with `<del>` tags.
+    Strikethrough = 5,
+    /// Lines between `|` pipes with a `| --- | :-: |` line under the first
+    /// become a `<table>`, the colons aligning the columns.
+    Tables = 6,
+    /// Lines starting with `1.` or `1)` become the items of an `<ol>`, which
+    /// starts from the number of the first item.
+    OrderedLists = 7,
+    /// Lines starting with `>` are quoted in a `<blockquote>`, and `>>`
+    /// nests a quote in it.
+    Blockquotes = 8,
+}
+
+impl Ext {
+    /// Every extension, in bit order
+    pub const ALL: &'static [Ext] = &[
+        Ext::Underline,
+        Ext::RawFence,
+        Ext::Comments,
+        Ext::Attributes,
+        Ext::Kbd,
+        Ext::Strikethrough,
+        Ext::Tables,
+        Ext::OrderedLists,
+        Ext::Blockquotes,
+    ];
+
+    /// Name of the extension used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Ext::Underline => "underline",
+            Ext::RawFence => "raw-fence",
+            Ext::Comments => "comments",
+            Ext::Attributes => "attributes",
+            Ext::Kbd => "kbd",
+            Ext::Strikethrough => "strikethrough",
+            Ext::Tables => "tables",
+            Ext::OrderedLists => "ordered-lists",
+            Ext::Blockquotes => "blockquotes",
+        }
+    }
+
+    /// Finds the extension by its name
+    pub fn from_name(name: &str) -> Option<Ext> {
+        Self::ALL.iter().copied().find(|ext| ext.name() == name)
+    }
+}
+
+/// Tags used for the emphasis keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Em {
+    /// `<i>` and `<b>` as the parser has always done.
+    Presentational,
+    /// `<em>` and `<strong>`
+    Semantic,
+}
+
+/// How the heading ids are generated from the heading texts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slug {
+    /// Like GitHub does: lowercased letters and numbers of any script,
+    /// spaces become `-` and other punctuation than `-` and `_` is removed.
+    GitHub,
+    /// Like [`Slug::GitHub`], but accented latin letters are replaced with
+    /// plain ASCII ones and other non-ASCII characters are removed.
+    Ascii,
+}
+
+/// Named bundles of extensions and emphasis tags
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// The output the parser has always produced. Same as the defaults.
+    Legacy,
+    /// `<em>` and `<strong>` emphasis, `<pre>` code blocks, ordered lists and
+    /// blockquotes without the other extensions
+    CommonMark,
+    /// Like [`Dialect::CommonMark`] with the strikethrough and the tables of
+    /// GitHub
+    Gfm,
+}
+
+impl Dialect {
+    /// Every dialect
+    pub const ALL: &'static [Dialect] = &[Dialect::Legacy, Dialect::CommonMark, Dialect::Gfm];
+
+    /// Name of the dialect used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Dialect::Legacy => "legacy",
+            Dialect::CommonMark => "commonmark",
+            Dialect::Gfm => "gfm",
+        }
+    }
+
+    /// Finds the dialect by its command line name
+    pub fn from_name(name: &str) -> Option<Dialect> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|dialect| dialect.name() == name)
+    }
+}
+
+/// What to do with images that have no alt text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingAlt {
+    /// Leave the alt attribute empty
+    Ignore,
+    /// Leave the alt attribute empty and add a diagnostic
+    Warn,
+    /// Make the alt text from the image filename
+    Filename,
+}
+
+/// What lines starting with spaces or tabs become
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indented {
+    /// `<div class="intend">` blocks as the parser has always done
+    Div,
+    /// Code blocks when indented at least 4 columns, like in CommonMark.
+    /// Indented lines right after a paragraph line continue the paragraph.
+    Code,
+    /// The indentation is ignored and the line continues the paragraph on
+    /// the previous line, if there is one.
+    Join,
+}
+
+/// What is done with markdown that isn't valid UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Utf8 {
+    /// The bytes go into the HTML as they are, as the parser has always done.
+    Keep,
+    /// Invalid sequences are replaced with U+FFFD and reported with an
+    /// `invalid-utf8` diagnostic.
+    Replace,
+    /// Nothing is written but an `invalid-utf8` error, whatever its level.
+    Reject,
+}
+
+/// What the code blocks become
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeBlocks {
+    /// `<div class="code"><code class="code">` as the parser has always done
+    Div,
+    /// `<pre><code class="language-x">` that stylesheets and highlighters
+    /// expect. The code is always escaped.
+    Pre,
+}
+
+/// Links from the headings to themselves, so their addresses are easy to copy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permalink {
+    /// No links, as the parser has always done
+    None,
+    /// `<a class="anchor" href="#id">#</a>` after the heading text
+    Anchor,
+    /// The heading text is wrapped in the link. Links in the heading text
+    /// would end up nested, so use this only for plain headings.
+    Wrap,
+}
+
+/// How the alignment of the table columns is written on the cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    /// `align="left"` like GitHub writes it
+    Attribute,
+    /// `style="text-align: left"`, as HTML5 has no align attribute. `--csp`
+    /// strips these.
+    Style,
+    /// `class="align-left"` for the stylesheet, with the class prefix
+    Class,
+}
+
+/// How the numbers of the ordered list items after the first are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Numbering {
+    /// The items count up from the first one, whatever their numbers
+    Normalize,
+    /// Items not numbered one up from the item before get their number as
+    /// the `value` attribute
+    Respect,
+}
+
+/// Where the generated sections like the table of contents are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// After the rest of the document
+    End,
+    /// Right after the first heading, or at the end without headings
+    AfterFirstHeading,
+    /// In place of a `[TOC]` line, or at the end without one
+    Marker,
+}
+
+/// Compiled parser options. Create these with [`ParserBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    extensions: u32,
+    pub(crate) safe: bool,
+    pub(crate) csp: bool,
+    pub(crate) print: bool,
+    pub(crate) slides: bool,
+    pub(crate) xhtml: bool,
+    pub(crate) wrap_paragraphs: bool,
+    pub(crate) utf8: Utf8,
+    pub(crate) max_link_len: usize,
+    pub(crate) encode_urls: bool,
+    pub(crate) punycode: bool,
+    pub(crate) align: Align,
+    pub(crate) numbering: Numbering,
+    pub(crate) emphasis: Em,
+    pub(crate) sourcepos: bool,
+    pub(crate) heading_ids: bool,
+    pub(crate) number_headings: bool,
+    pub(crate) slug: Slug,
+    pub(crate) slug_prefix: String,
+    pub(crate) slug_suffix: String,
+    pub(crate) toc: bool,
+    pub(crate) placement: Placement,
+    pub(crate) missing_alt: MissingAlt,
+    pub(crate) a11y: bool,
+    pub(crate) warn_duplicate_ids: bool,
+    pub(crate) check_fragments: bool,
+    pub(crate) indented: Indented,
+    pub(crate) code_blocks: CodeBlocks,
+    pub(crate) dedent_code: bool,
+    pub(crate) expand_tabs: Option<usize>,
+    pub(crate) stats: bool,
+    pub(crate) asset_prefix: Option<String>,
+    pub(crate) permalink: Permalink,
+    pub(crate) heading_map: bool,
+    pub(crate) source_map: bool,
+    pub(crate) trace: bool,
+    pub(crate) external_class: Option<String>,
+    pub(crate) class_prefix: String,
+    pub(crate) site: Option<String>,
+    pub(crate) base_url: Option<String>,
+    pub(crate) obfuscate_emails: bool,
+    /// Codes of the allowed and denied diagnostics as bits
+    pub(crate) allowed: u32,
+    pub(crate) denied: u32,
+}
+
+impl Options {
+    /// Checks if the extension is enabled
+    pub fn has(&self, ext: Ext) -> bool {
+        self.extensions & (1 << ext as u32) != 0
+    }
+
+    /// Prefix of the class names the parser writes
+    pub fn class_prefix(&self) -> &str {
+        &self.class_prefix
+    }
+
+    /// Checks if raw HTML is escaped and unsafe urls dropped
+    pub fn is_safe(&self) -> bool {
+        self.safe
+    }
+
+    /// Checks if the HTML is serialized as XHTML
+    pub fn is_xhtml(&self) -> bool {
+        self.xhtml
+    }
+
+    /// Checks if the HTML is split into slides
+    pub fn is_slides(&self) -> bool {
+        self.slides
+    }
+
+    /// What is done with the diagnostics of the code
+    pub fn level(&self, code: Code) -> Level {
+        let bit: u32 = 1 << code as u32;
+
+        if self.allowed & bit != 0 {
+            Level::Allow
+        } else if self.denied & bit != 0 {
+            Level::Deny
+        } else {
+            Level::Warn
+        }
+    }
+
+    /// Checks if the blocks of the document can be parsed apart from each
+    /// other. Options that number, link or count things across the whole
+    /// document need every block.
+    pub(crate) fn is_incremental(&self) -> bool {
+        !(self.has_heading_ids()
+            || self.number_headings
+            || self.a11y
+            || self.stats
+            || self.sourcepos
+            || self.source_map
+            || self.trace
+            || self.csp
+            || self.print
+            || self.slides
+            || self.xhtml
+            || !self.wrap_paragraphs
+            || self.utf8 != Utf8::Keep
+            || self.has(Ext::Comments))
+    }
+
+    /// Checks if the fenced code blocks are written in one go instead of
+    /// byte by byte, which some other options need
+    pub(crate) fn intercepts_fences(&self) -> bool {
+        self.code_blocks == CodeBlocks::Pre || self.dedent_code || self.expand_tabs.is_some()
+    }
+
+    /// Checks if the headings get ids, which some other options need
+    pub(crate) fn has_heading_ids(&self) -> bool {
+        self.heading_ids
+            || self.toc
+            || self.check_fragments
+            || self.heading_map
+            || self.permalink != Permalink::None
+    }
+}
+
+impl Default for Options {
+    /// Options that produce the same output as the parser always has.
+    fn default() -> Self {
+        Self {
+            extensions: 1 << Ext::Underline as u32,
+            safe: false,
+            csp: false,
+            print: false,
+            slides: false,
+            xhtml: false,
+            wrap_paragraphs: true,
+            utf8: Utf8::Keep,
+            max_link_len: MAX_LINK_LEN,
+            encode_urls: false,
+            punycode: false,
+            align: Align::Attribute,
+            numbering: Numbering::Normalize,
+            emphasis: Em::Presentational,
+            sourcepos: false,
+            heading_ids: false,
+            number_headings: false,
+            slug: Slug::GitHub,
+            slug_prefix: String::new(),
+            slug_suffix: String::new(),
+            toc: false,
+            placement: Placement::End,
+            missing_alt: MissingAlt::Ignore,
+            a11y: false,
+            warn_duplicate_ids: false,
+            check_fragments: false,
+            indented: Indented::Div,
+            code_blocks: CodeBlocks::Div,
+            dedent_code: false,
+            expand_tabs: None,
+            stats: false,
+            asset_prefix: None,
+            permalink: Permalink::None,
+            heading_map: false,
+            source_map: false,
+            trace: false,
+            external_class: None,
+            class_prefix: String::new(),
+            site: None,
+            base_url: None,
+            obfuscate_emails: false,
+            allowed: 0,
+            denied: 0,
+        }
+    }
+}
+
+/// Builds [`Options`] starting from the defaults.
+///
+/// ```
+/// use md2htm::{Em, Ext, ParserBuilder};
+///
+/// let options = ParserBuilder::new()
+///     .disable(Ext::Underline)
+///     .safe(true)
+///     .emphasis_style(Em::Semantic)
+///     .build();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ParserBuilder {
+    options: Options,
+}
+
+impl ParserBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables an extension
+    pub fn extension(mut self, ext: Ext) -> Self {
+        self.options.extensions |= 1 << ext as u32;
+        self
+    }
+
+    /// Disables an extension
+    pub fn disable(mut self, ext: Ext) -> Self {
+        self.options.extensions &= !(1 << ext as u32);
+        self
+    }
+
+    /// Replaces the extensions, the emphasis tags, the code blocks and the
+    /// encoding of the urls with the ones of the dialect
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        let legacy: Options = Options::default();
+
+        match dialect {
+            Dialect::Legacy => {
+                self.options.extensions = legacy.extensions;
+                self.options.emphasis = legacy.emphasis;
+                self.options.code_blocks = legacy.code_blocks;
+                self.options.encode_urls = legacy.encode_urls;
+            }
+
+            Dialect::CommonMark => {
+                self.options.extensions =
+                    1 << Ext::OrderedLists as u32 | 1 << Ext::Blockquotes as u32;
+                self.options.emphasis = Em::Semantic;
+                self.options.code_blocks = CodeBlocks::Pre;
+                self.options.encode_urls = true;
+            }
+
+            Dialect::Gfm => {
+                self.options.extensions = 1 << Ext::OrderedLists as u32
+                    | 1 << Ext::Blockquotes as u32
+                    | 1 << Ext::Strikethrough as u32
+                    | 1 << Ext::Tables as u32;
+                self.options.emphasis = Em::Semantic;
+                self.options.code_blocks = CodeBlocks::Pre;
+                self.options.encode_urls = true;
+            }
+        }
+
+        self
+    }
+
+    /// Chooses what is done with markdown that isn't valid UTF-8. The bytes
+    /// are kept as they are by default.
+    pub fn invalid_utf8(mut self, utf8: Utf8) -> Self {
+        self.options.utf8 = utf8;
+        self
+    }
+
+    /// Cuts the text and the url of links and images short at this many
+    /// bytes with a `link-truncated` diagnostic. The limit is 64 KiB by
+    /// default.
+    pub fn max_link_length(mut self, len: usize) -> Self {
+        self.options.max_link_len = len;
+        self
+    }
+
+    /// Percent-encodes the characters that can't be in the urls of links
+    /// and images, like spaces and non-ASCII letters, leaving the ones
+    /// encoded already alone. Off by default, which writes the urls as they
+    /// were written, and on with the commonmark and gfm dialects.
+    pub fn encode_urls(mut self, encode: bool) -> Self {
+        self.options.encode_urls = encode;
+        self
+    }
+
+    /// Writes the hosts of urls with letters outside ASCII in Punycode, like
+    /// `xn--bcher-kva.example` for `bücher.example`. The rest of the url is
+    /// percent-encoded if [`Self::encode_urls`] asks for it. Off by default.
+    pub fn punycode_hosts(mut self, punycode: bool) -> Self {
+        self.options.punycode = punycode;
+        self
+    }
+
+    /// Chooses how the alignment of table columns is written. The align
+    /// attribute by default.
+    pub fn table_align(mut self, align: Align) -> Self {
+        self.options.align = align;
+        self
+    }
+
+    /// Chooses if the ordered list items keep their own numbers. They count
+    /// up from the first one by default.
+    pub fn list_numbering(mut self, numbering: Numbering) -> Self {
+        self.options.numbering = numbering;
+        self
+    }
+
+    /// Chooses what the indented lines become
+    pub fn indented(mut self, indented: Indented) -> Self {
+        self.options.indented = indented;
+        self
+    }
+
+    /// Chooses what the code blocks become
+    pub fn code_blocks(mut self, code_blocks: CodeBlocks) -> Self {
+        self.options.code_blocks = code_blocks;
+        self
+    }
+
+    /// Leaves out the indentation common to all lines of the fenced code
+    /// blocks, so code indented as a whole isn't shifted right. The fences
+    /// themselves may be indented too.
+    pub fn dedent_code(mut self, dedent: bool) -> Self {
+        self.options.dedent_code = dedent;
+        self
+    }
+
+    /// Replaces the tabs in the fenced code blocks with spaces up to the next
+    /// multiple of the width, so the code looks the same everywhere. The
+    /// tabs are left as is by default.
+    pub fn expand_tabs(mut self, width: usize) -> Self {
+        self.options.expand_tabs = Some(width.max(1));
+        self
+    }
+
+    /// Safe mode escapes raw HTML and drops link and image URLs with schemes
+    /// other than `http`, `https`, `mailto` and `tel`. Use this for untrusted input.
+    pub fn safe(mut self, safe: bool) -> Self {
+        self.options.safe = safe;
+        self
+    }
+
+    /// Guarantees that the HTML has no inline styles or scripts, so it can be
+    /// served under a Content-Security-Policy without `'unsafe-inline'`. The
+    /// parser writes none itself, so this removes the `style` and event
+    /// handler attributes, `javascript:` urls and `<script>` and `<style>`
+    /// elements of the raw HTML that isn't escaped without safe mode.
+    pub fn csp(mut self, csp: bool) -> Self {
+        self.options.csp = csp;
+        self
+    }
+
+    /// Rewrites the HTML for printed handouts. Pages break before the `<h1>`
+    /// and `<h2>` headings after the first, the addresses of the links are
+    /// listed at the end and numbered after the links like footnotes, and
+    /// `<details>` elements are opened.
+    pub fn print(mut self, print: bool) -> Self {
+        self.options.print = print;
+        self
+    }
+
+    /// Splits the HTML into `<section class="slide">` elements at the `---`
+    /// rules and `##` headings of the top level, which reveal.js and similar
+    /// tools show as slides. The rules are left out.
+    pub fn slides(mut self, slides: bool) -> Self {
+        self.options.slides = slides;
+        self
+    }
+
+    /// Writes the paragraphs without the `<p>` tags around them, for HTML
+    /// put in list items, table cells and other blocks of a template. The
+    /// paragraphs are wrapped by default.
+    pub fn wrap_paragraphs(mut self, wrap: bool) -> Self {
+        self.options.wrap_paragraphs = wrap;
+        self
+    }
+
+    /// Serializes the HTML as XHTML for EPUB chapters: void elements close
+    /// themselves, attribute values are quoted, named character references
+    /// become numeric and stray `&` and `<` are escaped.
+    pub fn xhtml(mut self, xhtml: bool) -> Self {
+        self.options.xhtml = xhtml;
+        self
+    }
+
+    pub fn emphasis_style(mut self, em: Em) -> Self {
+        self.options.emphasis = em;
+        self
+    }
+
+    /// Adds `data-sourcepos="line:column"` attributes to the block elements
+    /// telling where in the markdown they started.
+    pub fn sourcepos(mut self, sourcepos: bool) -> Self {
+        self.options.sourcepos = sourcepos;
+        self
+    }
+
+    /// Gives the headings ids generated from their text, so they can be linked to.
+    pub fn heading_ids(mut self, heading_ids: bool) -> Self {
+        self.options.heading_ids = heading_ids;
+        self
+    }
+
+    /// Prefixes the headings with hierarchical numbers like `1.`, `1.1` and
+    /// `1.1.1`. The numbers are also included in the generated ids.
+    pub fn number_headings(mut self, number_headings: bool) -> Self {
+        self.options.number_headings = number_headings;
+        self
+    }
+
+    /// Collects the byte ranges of every markdown line and the HTML written
+    /// for it into [`crate::Output::source_map`].
+    pub fn source_map(mut self, source_map: bool) -> Self {
+        self.options.source_map = source_map;
+        self
+    }
+
+    /// Leaves the diagnostics of the code out, reports them as warnings or
+    /// as errors, like `#[allow]`, `#[warn]` and `#[deny]` do for compiler lints.
+    pub fn level(mut self, code: Code, level: Level) -> Self {
+        let bit: u32 = 1 << code as u32;
+        self.options.allowed &= !bit;
+        self.options.denied &= !bit;
+
+        match level {
+            Level::Allow => self.options.allowed |= bit,
+            Level::Warn => {}
+            Level::Deny => self.options.denied |= bit,
+        }
+
+        self
+    }
+
+    /// Logs the state transitions of the parser into [`crate::Output::trace`]
+    pub fn trace(mut self, trace: bool) -> Self {
+        self.options.trace = trace;
+        self
+    }
+
+    /// Adds a diagnostic for every heading id that was already taken. The ids
+    /// are made unique with a number either way, like `setup-1`.
+    pub fn warn_duplicate_ids(mut self, warn: bool) -> Self {
+        self.options.warn_duplicate_ids = warn;
+        self
+    }
+
+    /// Adds a diagnostic for every link to a `#fragment` that doesn't match
+    /// any heading id. The headings get ids for this even if
+    /// [`ParserBuilder::heading_ids`] is off.
+    pub fn check_fragments(mut self, check: bool) -> Self {
+        self.options.check_fragments = check;
+        self
+    }
+
+    /// Adds links from the headings to themselves. The headings get ids for
+    /// this even if [`ParserBuilder::heading_ids`] is off.
+    pub fn permalink(mut self, permalink: Permalink) -> Self {
+        self.options.permalink = permalink;
+        self
+    }
+
+    /// Collects the text, id and line of every heading into
+    /// [`crate::Output::headings`]. The headings get ids for this even if
+    /// [`ParserBuilder::heading_ids`] is off.
+    pub fn heading_map(mut self, heading_map: bool) -> Self {
+        self.options.heading_map = heading_map;
+        self
+    }
+
+    /// Chooses how the heading ids are generated
+    pub fn slug(mut self, slug: Slug) -> Self {
+        self.options.slug = slug;
+        self
+    }
+
+    /// Text added before and after every heading id. Handy for keeping the
+    /// anchors of an existing site, like `section-` prefixed ones.
+    pub fn slug_affixes(mut self, prefix: &str, suffix: &str) -> Self {
+        self.options.slug_prefix = prefix.to_string();
+        self.options.slug_suffix = suffix.to_string();
+        self
+    }
+
+    /// Chooses what to do with images that have no alt text
+    pub fn missing_alt(mut self, missing_alt: MissingAlt) -> Self {
+        self.options.missing_alt = missing_alt;
+        self
+    }
+
+    /// Adds diagnostics about accessibility problems: skipped heading levels,
+    /// empty headings, images without alt text and links with vague texts
+    /// like "here".
+    pub fn a11y(mut self, a11y: bool) -> Self {
+        self.options.a11y = a11y;
+        self
+    }
+
+    /// Counts the words and estimates the reading time of the text
+    pub fn stats(mut self, stats: bool) -> Self {
+        self.options.stats = stats;
+        self
+    }
+
+    /// Adds the prefix to the image urls that are relative to the document,
+    /// and collects them into [`crate::Output::assets`] so the files can be
+    /// copied next to the HTML.
+    pub fn asset_prefix(mut self, prefix: &str) -> Self {
+        self.options.asset_prefix = Some(prefix.to_string());
+        self
+    }
+
+    /// Adds the class to the links pointing to other sites, so stylesheets
+    /// can mark them. Links with `http`, `https` or `//` urls are external
+    /// unless they point to the host set with [`ParserBuilder::site`].
+    pub fn external_class(mut self, class: &str) -> Self {
+        self.options.external_class = Some(class.to_string());
+        self
+    }
+
+    /// Adds the prefix to the class names the parser writes, like `md-code`
+    /// and `md-intend`, so they don't collide with the classes of the site
+    /// the HTML is embedded in. The `language-` classes highlighters look for
+    /// and the class of [`ParserBuilder::external_class`] are kept as they
+    /// are. Characters other than letters, digits, `-` and `_` are left out.
+    pub fn class_prefix(mut self, prefix: &str) -> Self {
+        self.options.class_prefix = prefix
+            .chars()
+            .filter(|c| c.is_alphanumeric() || matches!(c, '-' | '_'))
+            .collect();
+        self
+    }
+
+    /// Host name of the site, like `example.com`. Links to it aren't external.
+    pub fn site(mut self, host: &str) -> Self {
+        self.options.site = Some(host.to_string());
+        self
+    }
+
+    /// Url the relative link and image urls are resolved against, like
+    /// `https://example.com/docs/`. It is added in front of them as is, so it
+    /// usually ends with a slash.
+    pub fn base_url(mut self, url: &str) -> Self {
+        self.options.base_url = Some(url.to_string());
+        self
+    }
+
+    /// Writes the addresses of `mailto:` links as character references like
+    /// `&#64;` to keep them from the simplest address scrapers. The link text
+    /// is encoded too if it looks like an address.
+    pub fn obfuscate_emails(mut self, obfuscate: bool) -> Self {
+        self.options.obfuscate_emails = obfuscate;
+        self
+    }
+
+    /// Generates a table of contents linking to the headings. The headings get
+    /// ids for the links even if [`ParserBuilder::heading_ids`] is off.
+    pub fn toc(mut self, toc: bool) -> Self {
+        self.options.toc = toc;
+        self
+    }
+
+    /// Chooses where the generated sections are written
+    pub fn placement(mut self, placement: Placement) -> Self {
+        self.options.placement = placement;
+        self
+    }
+
+    pub fn build(self) -> Options {
+        self.options
+    }
+}
@@ -0,0 +1,68 @@
+//! Leaving the `<p>` tags out of the HTML for places that already give the
+//! blocks, like list items and table cells of a template. The paragraphs
+//! run together, so this suits single paragraphs best.
+
+use crate::csp::{find, find_ignore_case};
+use crate::events::Html;
+use crate::sourcemap::SourceMap;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Elements whose content is left alone
+const RAW: &[&[u8]] = &[b"pre", b"code", b"script", b"style"];
+
+/// Removes the start and end tags of the paragraphs from the HTML
+pub(crate) fn unwrap(html: &mut Html, source_map: &mut SourceMap) {
+    let mut removed: Vec<Range<usize>> = Vec::new();
+    let mut at: usize = 0;
+
+    while let Some(start) = html[at..].iter().position(|&b| b == b'<').map(|i| at + i) {
+        if html[start..].starts_with(b"<!--") {
+            at = find(html, start, b"-->").map_or(html.len(), |end| end + 3);
+            continue;
+        }
+
+        let rest: &[u8] = &html[start + 1..];
+
+        // `<p>`, or `<p data-sourcepos="…">` with the source positions
+        if rest.starts_with(b"p>") || rest.starts_with(b"p ") {
+            let end: usize = find(html, start, b">").map_or(html.len(), |end| end + 1);
+            removed.push(start..end);
+            at = end;
+            continue;
+        }
+
+        if rest.starts_with(b"/p>") {
+            removed.push(start..start + 4);
+            at = start + 4;
+            continue;
+        }
+
+        let raw: Option<&&[u8]> = RAW.iter().find(|name| {
+            rest.len() > name.len()
+                && rest[..name.len()].eq_ignore_ascii_case(name)
+                && matches!(rest[name.len()], b'>' | b' ')
+        });
+
+        at = match raw {
+            Some(name) => {
+                let mut close: Vec<u8> = Vec::with_capacity(name.len() + 2);
+                close.extend_from_slice(b"</");
+                close.extend_from_slice(name);
+                find_ignore_case(html, start, &close).unwrap_or(html.len())
+            }
+            None => start + 1,
+        };
+    }
+
+    for range in removed.iter().rev() {
+        source_map.removed(range.start, range.len());
+    }
+
+    html.edit(
+        removed
+            .into_iter()
+            .map(|range| (range, Html::default()))
+            .collect(),
+    );
+}
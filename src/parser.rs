@@ -0,0 +1,614 @@
+//! Reusable parser that validates and compiles its options only once.
+
+use crate::cursor::Position;
+use crate::error::Error;
+use crate::events::{self, Html};
+use crate::excerpt::{first_paragraph, more_marker};
+use crate::fragment::BlockContext;
+use crate::limits::Limits;
+use crate::mdstate::{inline, Boundary, Output, Tags, MDS};
+use crate::options::Options;
+use crate::sourcemap::crop;
+use alloc::{string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::io::{self, Read, Write};
+
+/// Smallest part of a document worth a thread of its own
+#[cfg(feature = "std")]
+const MIN_PART: usize = 64 * 1024;
+/// How much is parsed at first after a part that didn't end between blocks
+#[cfg(feature = "std")]
+const RESUME: usize = 4 * 1024;
+/// How much [`Parser::parse_stream`] reads before parsing
+#[cfg(feature = "std")]
+const STREAM_PIECE: usize = 64 * 1024;
+
+/// Parser holding compiled options. It is `Send + Sync`, so one parser can be
+/// put in an `Arc` and shared by every thread that needs to parse markdown.
+///
+/// ```
+/// use md2htm::{Parser, ParserBuilder};
+/// use std::{sync::Arc, thread};
+///
+/// let parser = Arc::new(Parser::new(ParserBuilder::new().safe(true).build()));
+/// let shared = Arc::clone(&parser);
+/// let html = thread::spawn(move || shared.parse(b"*hi*\n").html).join().unwrap();
+/// assert_eq!(html, b"<p><i>hi</i></p>\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Parser {
+    options: Options,
+    tags: Tags,
+}
+
+impl Parser {
+    pub fn new(options: Options) -> Self {
+        Self {
+            tags: Tags::new(&options),
+            options,
+        }
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Parses the markdown into HTML and diagnostics
+    pub fn parse(&self, bytes: &[u8]) -> Output {
+        match self.parse_limited(bytes, &Limits::default()) {
+            Ok(output) => output,
+            // Nothing can stop the parser without limits
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Parses one line of markdown for its inline syntax only, without the
+    /// `<p>` around it. Line breaks become spaces.
+    ///
+    /// ```
+    /// use md2htm::{Parser, ParserBuilder};
+    ///
+    /// let parser = Parser::new(ParserBuilder::new().safe(true).build());
+    /// assert_eq!(parser.convert_inline("Tips for `<b>`"), "Tips for <span class=\"code\"><code class=\"code\">&lt;b&gt;</code></span>");
+    /// ```
+    pub fn convert_inline(&self, markdown: &str) -> String {
+        // Nothing can stop the parser without limits
+        let html: Html = inline(
+            markdown.as_bytes(),
+            &self.options,
+            &self.tags,
+            &Limits::default(),
+        )
+        .unwrap_or_default();
+        String::from_utf8_lossy(&html).into_owned()
+    }
+
+    /// Parses the markdown unless the limits stop it first
+    pub fn parse_limited(&self, bytes: &[u8], limits: &Limits) -> Result<Output, Error> {
+        MDS::run(bytes, &self.options, &self.tags, limits)
+    }
+
+    /// Parses the markdown starting at `start` of the document and records
+    /// where parsing could start over
+    pub(crate) fn parse_recording(
+        &self,
+        bytes: &[u8],
+        start: Position,
+        limits: &Limits,
+        boundaries: &mut Vec<Boundary>,
+    ) -> Result<Output, Error> {
+        MDS::run_recording(
+            bytes,
+            start,
+            &self.options,
+            &self.tags,
+            limits,
+            Some(boundaries),
+        )
+    }
+
+    /// Parses a fragment of a document starting at the context, like the
+    /// block an editor just changed. The positions of the diagnostics, the
+    /// headings and the source map are the ones in the whole document.
+    /// Options that need the whole document, like heading ids or stats,
+    /// only see the fragment.
+    ///
+    /// ```
+    /// use md2htm::Parser;
+    ///
+    /// let parser = Parser::default();
+    /// let markdown: &[u8] = b"# Title\n\nFirst.\n\nSecond *word*.\n";
+    /// let context = parser.block_contexts(markdown)[2];
+    /// let output = parser.parse_fragment(&markdown[context.position.offset..], context);
+    /// assert_eq!(output.html, b"<p>Second <i>word</i>.</p>\n");
+    /// ```
+    pub fn parse_fragment(&self, bytes: &[u8], context: BlockContext) -> Output {
+        match MDS::run_recording(
+            bytes,
+            context.position,
+            &self.options,
+            &self.tags,
+            &Limits::default(),
+            None,
+        ) {
+            Ok(mut output) => {
+                for mapping in &mut output.source_map {
+                    mapping.source.start += context.position.offset;
+                    mapping.source.end += context.position.offset;
+                }
+
+                output
+            }
+            // Nothing can stop the parser without limits
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Where fragments of the markdown can start, in order and starting with
+    /// the start of the document. With options that need the whole document
+    /// the start is the only one.
+    pub fn block_contexts(&self, bytes: &[u8]) -> Vec<BlockContext> {
+        if !self.options.is_incremental() {
+            return Vec::from([BlockContext::START]);
+        }
+
+        let mut boundaries: Vec<Boundary> = Vec::from([Boundary::START]);
+
+        match self.parse_recording(bytes, Position::START, &Limits::default(), &mut boundaries) {
+            Ok(_) => boundaries.iter().map(BlockContext::from).collect(),
+            // Nothing can stop the parser without limits
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Parses the markdown before the `<!-- more -->` line, or only the first
+    /// paragraph without one. Handy for the listing pages of blogs.
+    ///
+    /// ```
+    /// use md2htm::Parser;
+    ///
+    /// let parser = Parser::default();
+    /// let output = parser.excerpt(b"# Post\n\nFirst *words*.\n\nThe rest.\n");
+    /// assert_eq!(output.html, b"<p>First <i>words</i>.</p>");
+    /// ```
+    pub fn excerpt(&self, bytes: &[u8]) -> Output {
+        match self.excerpt_limited(bytes, &Limits::default()) {
+            Ok(output) => output,
+            // Nothing can stop the parser without limits
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Parses the markdown about `piece` bytes at a time, handing the HTML of
+    /// every finished piece to `write` before parsing the rest, so the start
+    /// of a large document can be sent on early. Options that need the whole
+    /// document, like heading ids or stats, make it a single piece. Returning
+    /// false from `write` cancels the parsing.
+    ///
+    /// ```
+    /// use md2htm::{Limits, Parser};
+    ///
+    /// let markdown: &[u8] = b"# Title\n\nFirst.\n\nSecond.\n";
+    /// let mut html: Vec<u8> = Vec::new();
+    ///
+    /// Parser::default()
+    ///     .parse_chunked(markdown, 8, &Limits::default(), &mut |chunk: &[u8]| {
+    ///         html.extend_from_slice(chunk);
+    ///         true
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(html, Parser::default().parse(markdown).html);
+    /// ```
+    pub fn parse_chunked(
+        &self,
+        bytes: &[u8],
+        piece: usize,
+        limits: &Limits,
+        write: &mut dyn FnMut(&[u8]) -> bool,
+    ) -> Result<(), Error> {
+        let mut written: usize = 0;
+        let mut send = |html: &[u8]| -> Result<(), Error> {
+            written += html.len();
+            limits.check_output(written)?;
+
+            match html.is_empty() || write(html) {
+                true => Ok(()),
+                false => Err(Error::Cancelled),
+            }
+        };
+
+        let mut start: usize = 0;
+        // Grows while no block ends inside the piece
+        let mut size: usize = piece.max(1);
+
+        while start < bytes.len() {
+            let end: usize = start.saturating_add(size);
+
+            if end >= bytes.len() || !self.options.is_incremental() {
+                return send(&self.parse_limited(&bytes[start..], limits)?.html);
+            }
+
+            let mut boundaries: Vec<Boundary> = Vec::from([Boundary::START]);
+            let output: Output =
+                self.parse_recording(&bytes[start..end], Position::START, limits, &mut boundaries)?;
+
+            // The blocks around the cut may come out differently than in the
+            // whole document, so only the ones before them are finished
+            match boundaries
+                .iter()
+                .rev()
+                .find(|b| b.source != 0 && b.source < end - start)
+            {
+                Some(boundary) => {
+                    send(&output.html[..boundary.html])?;
+                    start += boundary.source;
+                    size = piece.max(1);
+                }
+
+                None => size = size.saturating_mul(2),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses the markdown from `reader` a piece at a time, writing the HTML
+    /// of the blocks finished so far to `writer` before reading on, so large
+    /// documents and sockets are never held in memory whole. The HTML is the
+    /// same as from [`Parser::parse_limited`], without the diagnostics.
+    /// Options that need the whole document, like heading ids or stats,
+    /// read all of it first. Errors of the limits come out as
+    /// [`io::ErrorKind::Other`].
+    ///
+    /// ```
+    /// use md2htm::{Limits, Parser};
+    ///
+    /// let markdown: Vec<u8> = b"# Title\n\nSome *words*.\n\n".repeat(10_000);
+    /// let mut html: Vec<u8> = Vec::new();
+    ///
+    /// Parser::default()
+    ///     .parse_stream(markdown.as_slice(), &mut html, &Limits::default())
+    ///     .unwrap();
+    /// assert_eq!(html, Parser::default().parse(&markdown).html);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn parse_stream<R: Read, W: Write>(
+        &self,
+        mut reader: R,
+        mut writer: W,
+        limits: &Limits,
+    ) -> io::Result<()> {
+        let mut pending: Vec<u8> = Vec::with_capacity(STREAM_PIECE);
+        let mut written: usize = 0;
+        // Grows while no block ends inside the pending markdown
+        let mut size: usize = STREAM_PIECE;
+
+        loop {
+            let wanted: usize = size.saturating_sub(pending.len());
+            let read: usize = reader
+                .by_ref()
+                .take(wanted as u64)
+                .read_to_end(&mut pending)?;
+
+            if read < wanted {
+                break;
+            }
+
+            if !self.options.is_incremental() {
+                size = size.saturating_mul(2);
+                continue;
+            }
+
+            let mut boundaries: Vec<Boundary> = Vec::from([Boundary::START]);
+            let output: Output = self
+                .parse_recording(&pending, Position::START, limits, &mut boundaries)
+                .map_err(io::Error::other)?;
+
+            // The blocks around the end may continue in what is read next
+            match boundaries
+                .iter()
+                .rev()
+                .find(|b| b.source != 0 && b.source < pending.len())
+            {
+                Some(boundary) => {
+                    written += boundary.html;
+                    limits.check_output(written).map_err(io::Error::other)?;
+                    writer.write_all(&output.html[..boundary.html])?;
+                    pending.drain(..boundary.source);
+                    size = STREAM_PIECE;
+                }
+
+                None => size = size.saturating_mul(2),
+            }
+        }
+
+        let output: Output = self
+            .parse_limited(&pending, limits)
+            .map_err(io::Error::other)?;
+        limits
+            .check_output(written + output.html.len())
+            .map_err(io::Error::other)?;
+        writer.write_all(&output.html)?;
+        writer.flush()
+    }
+
+    /// Parses a large document in parts on up to `threads` threads. The
+    /// document is split between top-level blocks. When a part turns out to
+    /// continue the block before it, the blocks around the split are parsed
+    /// again until the parse meets the part, so the output is the same as from
+    /// [`Parser::parse_limited`]. Small documents and options that need the
+    /// whole document, like heading ids or stats, are parsed on the calling
+    /// thread. The cancellation is only checked after the parts are done.
+    ///
+    /// ```
+    /// use md2htm::{Limits, Parser};
+    ///
+    /// let markdown: Vec<u8> = b"# Title\n\nSome *words*.\n\n".repeat(20_000);
+    /// let parser = Parser::default();
+    /// let output = parser.parse_parallel(&markdown, 4, &Limits::default()).unwrap();
+    /// assert_eq!(output, parser.parse(&markdown));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn parse_parallel(
+        &self,
+        bytes: &[u8],
+        threads: usize,
+        limits: &Limits,
+    ) -> Result<Output, Error> {
+        let parts: usize = threads.min(bytes.len() / MIN_PART);
+
+        if parts < 2 || !self.options.is_incremental() {
+            return self.parse_limited(bytes, limits);
+        }
+
+        let starts: Vec<Position> = split(bytes, parts);
+        let end = |i: usize| starts.get(i + 1).map_or(bytes.len(), |next| next.offset);
+        let max_output: Option<usize> = limits.max_output;
+
+        // A part is parsed with the position it has in the whole document
+        let parse = |start: Position, end: usize| -> Result<Part, Error> {
+            let mut boundaries: Vec<Boundary> = Vec::from([Boundary::START]);
+            let limits: Limits = Limits {
+                cancel: None,
+                max_output,
+            };
+            let output: Output =
+                self.parse_recording(&bytes[start.offset..end], start, &limits, &mut boundaries)?;
+
+            Ok(Part {
+                start,
+                output,
+                boundaries,
+            })
+        };
+
+        let parsed: Vec<Result<Part, Error>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = starts
+                .iter()
+                .enumerate()
+                .map(|(i, &start)| scope.spawn(move || parse(start, end(i))))
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|e| std::panic::resume_unwind(e))
+                })
+                .collect()
+        });
+
+        if limits.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        let mut output: Output = Output::default();
+        let mut parsed = parsed.into_iter();
+        let mut part: Part = parsed.next().unwrap_or_else(|| unreachable!())?;
+        // How much of the part is in the output already
+        let mut taken: Boundary = Boundary::START;
+
+        for i in 0..starts.len() {
+            let last: Boundary = part.boundaries.last().copied().unwrap_or(Boundary::START);
+            let whole: bool = i + 1 == starts.len() || last.source == end(i) - part.start.offset;
+
+            // The blocks after the last boundary continue in the next part
+            let finished: Boundary = match whole {
+                true => Boundary {
+                    html: part.output.html.len(),
+                    diagnostics: part.output.diagnostics.len(),
+                    assets: part.output.assets.len(),
+                    ..last
+                },
+                false => last,
+            };
+
+            extend(&mut output, &part.output, &taken, &finished);
+            limits.check_output(output.html.len())?;
+
+            let Some(next) = parsed.next() else {
+                break;
+            };
+            let next: Part = next?;
+
+            if whole {
+                part = next;
+                taken = Boundary::START;
+                continue;
+            }
+
+            let resume: Position = match last.source {
+                0 => part.start,
+                _ => Position {
+                    line: last.line,
+                    column: 1,
+                    offset: part.start.offset + last.source,
+                },
+            };
+
+            // The next part didn't start at the start state. Its blocks come
+            // out the same once the parse from the last boundary is back at
+            // the start state at one of its boundaries.
+            let mut size: usize = RESUME;
+
+            loop {
+                let stop: usize = resume.offset.saturating_add(size).min(end(i + 1));
+                let resumed: Part = parse(resume, stop)?;
+
+                let met = resumed
+                    .boundaries
+                    .iter()
+                    .skip(1)
+                    // Blocks around the cut may come out differently
+                    .filter(|b| stop == end(i + 1) || resume.offset + b.source < stop)
+                    .find_map(|b| {
+                        next.boundaries
+                            .iter()
+                            .find(|n| next.start.offset + n.source == resume.offset + b.source)
+                            .map(|n| (*b, *n))
+                    });
+
+                match met {
+                    Some((at, from)) => {
+                        extend(&mut output, &resumed.output, &Boundary::START, &at);
+                        limits.check_output(output.html.len())?;
+                        part = next;
+                        taken = from;
+                        break;
+                    }
+
+                    // The part was parsed again as a whole
+                    None if stop == end(i + 1) => {
+                        part = resumed;
+                        taken = Boundary::START;
+                        break;
+                    }
+
+                    None => size = size.saturating_mul(2),
+                }
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Parses the excerpt of the markdown unless the limits stop it first
+    pub fn excerpt_limited(&self, bytes: &[u8], limits: &Limits) -> Result<Output, Error> {
+        if let Some(end) = more_marker(bytes) {
+            return self.parse_limited(&bytes[..end], limits);
+        }
+
+        let mut output: Output = self.parse_limited(bytes, limits)?;
+        let paragraph = first_paragraph(&output.html).unwrap_or_default();
+        output.html.truncate(paragraph.end);
+        output.html.drain(..paragraph.start);
+        events::crop(&mut output.marks, paragraph.clone());
+        crop(&mut output.source_map, paragraph);
+        Ok(output)
+    }
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new(Options::default())
+    }
+}
+
+/// A part of a document parsed on its own
+#[cfg(feature = "std")]
+struct Part {
+    start: Position,
+    output: Output,
+    /// Where parsing could start over, counted from the start of the part
+    boundaries: Vec<Boundary>,
+}
+
+/// Adds the output of a part between the boundaries
+#[cfg(feature = "std")]
+fn extend(output: &mut Output, part: &Output, from: &Boundary, to: &Boundary) {
+    let at: usize = output.html.len();
+    events::copy(&mut output.marks, &part.marks, from.html..to.html, at);
+    output
+        .html
+        .extend_from_slice(&part.html[from.html..to.html]);
+    output
+        .diagnostics
+        .extend_from_slice(&part.diagnostics[from.diagnostics..to.diagnostics]);
+    output
+        .assets
+        .extend_from_slice(&part.assets[from.assets..to.assets]);
+}
+
+/// Starts of the parts of a document split into about equal parts. The parts
+/// start after blank lines outside of fenced blocks, which the parser finds by
+/// looking ahead, and not after list items or before indented lines, which
+/// may continue the block before them.
+#[cfg(feature = "std")]
+fn split(bytes: &[u8], parts: usize) -> Vec<Position> {
+    use crate::mdstate::{after_blank_line, line};
+
+    let mut starts: Vec<Position> = Vec::from([Position::START]);
+    let mut at: usize = 0;
+    let mut line_number: usize = 1;
+    let mut fenced: bool = false;
+    let mut off: bool = false;
+    // The last line that wasn't blank
+    let mut previous: &[u8] = b"";
+
+    while at < bytes.len() && starts.len() < parts {
+        let target: usize = bytes.len() / parts * starts.len();
+
+        if at >= target
+            && !fenced
+            && !off
+            && !list_item(previous)
+            && !matches!(bytes[at], b' ' | b'\t')
+            && after_blank_line(&bytes[..at])
+        {
+            starts.push(Position {
+                line: line_number,
+                column: 1,
+                offset: at,
+            });
+        }
+
+        let (text, next) = line(&bytes[at..]);
+
+        if text.trim_ascii_start().starts_with(b"```") {
+            fenced = !fenced;
+        } else if text.trim_ascii() == b"<!-- md2htm:off -->" {
+            off = true;
+        } else if text.trim_ascii() == b"<!-- md2htm:on -->" {
+            off = false;
+        }
+
+        if !text.trim_ascii().is_empty() {
+            previous = text;
+        }
+
+        at += next;
+        line_number += 1;
+    }
+
+    starts
+}
+
+/// Checks if the line starts a list item, like `- a` or `1. a`
+#[cfg(feature = "std")]
+fn list_item(line: &[u8]) -> bool {
+    let line: &[u8] = line.trim_ascii_start();
+    let digits: usize = line.iter().take_while(|b| b.is_ascii_digit()).count();
+    let marker: &[u8] = &line[digits..];
+
+    match marker.len() < line.len() {
+        true => marker.starts_with(b". ") || marker.starts_with(b") "),
+        false => matches!(line, [b'-' | b'*' | b'+', b' ' | b'\t', ..]),
+    }
+}
+
+// Fails to compile if the parser stops being shareable between threads.
+const _: fn() = || {
+    fn shareable<T: Send + Sync>() {}
+    shareable::<Parser>();
+};
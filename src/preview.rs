@@ -0,0 +1,321 @@
+//! Preview server for editors. It speaks JSON-RPC over stdio with the framing
+//! of the language server protocol, keeps the open documents up to date with
+//! the incremental changes and answers every change with the HTML and the
+//! diagnostics, so editor plugins don't need to start the CLI per keystroke.
+//! Only the blocks around the changes are parsed again.
+
+use crate::i18n;
+use crate::json::{self, Value};
+use md2htm::{Diagnostic, Incremental, Output, Parser, Severity};
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    io::{self, BufRead, Result, Write},
+};
+
+/// `textDocumentSync` kind telling the editor to send only the changed ranges
+const SYNC_INCREMENTAL: u8 = 2;
+/// Severities of the diagnostics
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+/// Error code for requests the server doesn't know
+const METHOD_NOT_FOUND: i32 = -32601;
+
+/// Snippets of `--inject-head` and `--inject-body`, sent with every preview
+/// for the editor to add to the head and the body of the page
+#[derive(Debug, Default)]
+pub struct Snippets {
+    pub head: Option<String>,
+    pub body: Option<String>,
+}
+
+pub fn run(parser: Parser, snippets: Snippets) -> Result<()> {
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    // The text of every open document and what it was parsed into
+    let mut documents: HashMap<String, (String, Incremental)> = HashMap::new();
+
+    while let Some(body) = read_message(&mut input)? {
+        let Some(message) = json::parse(&body) else {
+            eprintln!("Ignoring a message that isn't valid JSON.");
+            continue;
+        };
+
+        let method: &str = message.get("method").and_then(Value::as_str).unwrap_or("");
+        let params: &Value = message.get("params").unwrap_or(&Value::Null);
+        let id: Option<&Value> = message.get("id");
+
+        match method {
+            "initialize" => {
+                let result: String = format!(
+                    "{{\"capabilities\":{{\"textDocumentSync\":{{\"openClose\":true,\"change\":{SYNC_INCREMENTAL}}}}},\
+                     \"serverInfo\":{{\"name\":\"md2htm\",\"version\":\"{}\"}}}}",
+                    env!("CARGO_PKG_VERSION")
+                );
+                respond(&mut output, id, &result)?;
+            }
+
+            "textDocument/didOpen" => {
+                let document: &Value = params.get("textDocument").unwrap_or(&Value::Null);
+
+                if let (Some(uri), Some(text)) = (
+                    document.get("uri").and_then(Value::as_str),
+                    document.get("text").and_then(Value::as_str),
+                ) {
+                    let mut document: Incremental = Incremental::new(parser.clone());
+                    publish(
+                        &mut output,
+                        uri,
+                        text,
+                        document.update(text.as_bytes()),
+                        &snippets,
+                    )?;
+                    documents.insert(uri.to_string(), (text.to_string(), document));
+                }
+            }
+
+            "textDocument/didChange" => {
+                let Some(uri) = params
+                    .get("textDocument")
+                    .and_then(|document| document.get("uri"))
+                    .and_then(Value::as_str)
+                else {
+                    continue;
+                };
+
+                let Some((text, document)) = documents.get_mut(uri) else {
+                    eprintln!("Ignoring changes to {uri}, which isn't open.");
+                    continue;
+                };
+
+                for change in params
+                    .get("contentChanges")
+                    .and_then(Value::as_array)
+                    .unwrap_or_default()
+                {
+                    apply_change(text, change);
+                }
+
+                publish(
+                    &mut output,
+                    uri,
+                    text,
+                    document.update(text.as_bytes()),
+                    &snippets,
+                )?;
+            }
+
+            "textDocument/didClose" => {
+                if let Some(uri) = params
+                    .get("textDocument")
+                    .and_then(|document| document.get("uri"))
+                    .and_then(Value::as_str)
+                {
+                    documents.remove(uri);
+                }
+            }
+
+            "shutdown" => respond(&mut output, id, "null")?,
+            "exit" => break,
+
+            // Notifications that aren't needed don't get answers
+            _ if id.is_none() => {}
+
+            _ => {
+                let mut error: String = String::new();
+                let _ = write!(
+                    error,
+                    "{{\"jsonrpc\":\"2.0\",\"id\":{},\"error\":{{\"code\":{METHOD_NOT_FOUND},\"message\":",
+                    id_json(id)
+                );
+                json::string(&mut error, &format!("Unknown method {method}."));
+                error.push_str("}}");
+                write_message(&mut output, &error)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the next message body, or None when the input ends
+fn read_message(input: &mut impl BufRead) -> Result<Option<String>> {
+    let mut length: Option<usize> = None;
+    let mut header: String = String::new();
+
+    loop {
+        header.clear();
+
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header: &str = header.trim_end();
+
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+
+    let Some(length) = length else {
+        eprintln!("Message without Content-Length, skipping it.");
+        return Ok(Some(String::new()));
+    };
+
+    let mut body: Vec<u8> = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+fn write_message(output: &mut impl Write, body: &str) -> Result<()> {
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()
+}
+
+fn id_json(id: Option<&Value>) -> String {
+    let mut json: String = String::new();
+    id.unwrap_or(&Value::Null).write(&mut json);
+    json
+}
+
+fn respond(output: &mut impl Write, id: Option<&Value>, result: &str) -> Result<()> {
+    write_message(
+        output,
+        &format!(
+            "{{\"jsonrpc\":\"2.0\",\"id\":{},\"result\":{result}}}",
+            id_json(id)
+        ),
+    )
+}
+
+/// Sends the HTML of the document as `md2htm/preview` and its problems as
+/// `textDocument/publishDiagnostics`
+fn publish(
+    output: &mut impl Write,
+    uri: &str,
+    text: &str,
+    parsed: &Output,
+    snippets: &Snippets,
+) -> Result<()> {
+    let mut preview: String =
+        String::from("{\"jsonrpc\":\"2.0\",\"method\":\"md2htm/preview\",\"params\":{\"uri\":");
+    json::string(&mut preview, uri);
+    preview.push_str(",\"html\":");
+    json::string(&mut preview, &String::from_utf8_lossy(&parsed.html));
+
+    for (name, snippet) in [("head", &snippets.head), ("body", &snippets.body)] {
+        if let Some(snippet) = snippet {
+            preview.push_str(&format!(",\"{name}\":"));
+            json::string(&mut preview, snippet);
+        }
+    }
+
+    preview.push_str("}}");
+    write_message(output, &preview)?;
+
+    let mut diagnostics: String = String::from(
+        "{\"jsonrpc\":\"2.0\",\"method\":\"textDocument/publishDiagnostics\",\"params\":{\"uri\":",
+    );
+    json::string(&mut diagnostics, uri);
+    diagnostics.push_str(",\"diagnostics\":[");
+
+    for (i, diagnostic) in parsed.diagnostics.iter().enumerate() {
+        if i != 0 {
+            diagnostics.push(',');
+        }
+
+        write_diagnostic(&mut diagnostics, text, diagnostic);
+    }
+
+    diagnostics.push_str("]}}");
+    write_message(output, &diagnostics)
+}
+
+/// Writes the diagnostic as an LSP diagnostic covering the byte it is about
+fn write_diagnostic(json: &mut String, text: &str, diagnostic: &Diagnostic) {
+    let offset: usize = diagnostic.position.offset.min(text.len());
+    let line_start: usize = text[..offset].rfind(['\n', '\r']).map_or(0, |at| at + 1);
+    let character: usize = text
+        .get(line_start..offset)
+        .map_or(0, |line| line.encode_utf16().count());
+    let line: usize = diagnostic.position.line - 1;
+
+    let _ = write!(
+        json,
+        "{{\"range\":{{\"start\":{{\"line\":{line},\"character\":{character}}},\
+         \"end\":{{\"line\":{line},\"character\":{}}}}},\
+         \"severity\":{},\"source\":\"md2htm\",\"code\":\"{}\",\"message\":",
+        character + 1,
+        match diagnostic.severity {
+            Severity::Warning => SEVERITY_WARNING,
+            Severity::Error => SEVERITY_ERROR,
+        },
+        diagnostic.code.name()
+    );
+    json::string(json, &i18n::message(diagnostic));
+    json.push('}');
+}
+
+/// Applies a change from `didChange`. Changes without a range replace the
+/// whole document.
+fn apply_change(text: &mut String, change: &Value) {
+    let Some(new_text) = change.get("text").and_then(Value::as_str) else {
+        return;
+    };
+
+    let Some(range) = change.get("range") else {
+        *text = new_text.to_string();
+        return;
+    };
+
+    let position = |key: &str| -> Option<usize> {
+        let position: &Value = range.get(key)?;
+        Some(offset(
+            text,
+            position.get("line")?.as_usize()?,
+            position.get("character")?.as_usize()?,
+        ))
+    };
+
+    if let (Some(start), Some(end)) = (position("start"), position("end")) {
+        text.replace_range(start..end.max(start), new_text);
+    }
+}
+
+/// Byte offset of the LSP position, where the character counts UTF-16 code
+/// units. Positions past the end of a line or the text are clamped.
+fn offset(text: &str, line: usize, character: usize) -> usize {
+    let mut start: usize = 0;
+
+    for _ in 0..line {
+        match text[start..].find(['\n', '\r']) {
+            Some(at) => {
+                start += at;
+                start += if text[start..].starts_with("\r\n") {
+                    2
+                } else {
+                    1
+                };
+            }
+            None => return text.len(),
+        }
+    }
+
+    let mut units: usize = 0;
+
+    for (at, c) in text[start..].char_indices() {
+        if units >= character || c == '\n' || c == '\r' {
+            return start + at;
+        }
+
+        units += c.len_utf16();
+    }
+
+    text.len()
+}
@@ -0,0 +1,165 @@
+//! Making the HTML fit for paper: pages break before the top level headings,
+//! the addresses of the links are listed at the end with numbers after the
+//! links like footnotes, and `<details>` elements are opened so nothing
+//! stays hidden on a printed handout.
+
+use crate::csp::{find, find_ignore_case, tag_end};
+use crate::events::Html;
+use crate::sourcemap::SourceMap;
+use alloc::{format, vec::Vec};
+
+/// Rewrites the HTML for printing, with the prefix on the class names
+pub(crate) fn print(html: &mut Html, source_map: &mut SourceMap, class_prefix: &str) {
+    let page_break: Vec<u8> = format!("<div class=\"{class_prefix}page-break\"></div>\n").into();
+    let mut inserts: Vec<(usize, Html)> = Vec::new();
+    let mut urls: Vec<&[u8]> = Vec::new();
+    let mut at: usize = 0;
+
+    while let Some(start) = html[at..].iter().position(|&b| b == b'<').map(|i| at + i) {
+        if html[start..].starts_with(b"<!--") {
+            at = find(html, start, b"-->").map_or(html.len(), |end| end + 3);
+            continue;
+        }
+
+        let name_end: usize = start
+            + 1
+            + html[start + 1..]
+                .iter()
+                .take_while(|b| b.is_ascii_alphanumeric())
+                .count();
+        let name: &[u8] = &html[start + 1..name_end];
+
+        let Some(end) = tag_end(html, name_end) else {
+            break;
+        };
+
+        // The first heading of the document starts the first page anyway
+        if (name.eq_ignore_ascii_case(b"h1") || name.eq_ignore_ascii_case(b"h2"))
+            && !html[..start].trim_ascii().is_empty()
+        {
+            inserts.push((start, Html::of_tags(page_break.clone())));
+        } else if name.eq_ignore_ascii_case(b"details")
+            && attribute(&html[name_end..end], b"open").is_none()
+        {
+            inserts.push((name_end, Html::text(b" open".to_vec())));
+        } else if name.eq_ignore_ascii_case(b"a") {
+            let url: Option<&[u8]> = attribute(&html[name_end..end], b"href")
+                .filter(|url| !url.is_empty() && !url.starts_with(b"#"));
+
+            if let (Some(url), Some(close)) = (url, find_ignore_case(html, end, b"</a>")) {
+                let number: usize = match urls.iter().position(|known| *known == url) {
+                    Some(i) => i + 1,
+                    None => {
+                        urls.push(url);
+                        urls.len()
+                    }
+                };
+
+                inserts.push((
+                    close + 4,
+                    Html::of_tags(
+                        format!("<sup class=\"{class_prefix}link-note\">[{number}]</sup>").into(),
+                    ),
+                ));
+            }
+        }
+
+        at = end;
+    }
+
+    if !urls.is_empty() {
+        let mut notes: Vec<u8> = format!("<ol class=\"{class_prefix}link-notes\">\n").into();
+
+        for url in &urls {
+            notes.extend_from_slice(b"<li>");
+
+            // The value is a text now, where a stray `<` would start a tag
+            for &b in *url {
+                match b {
+                    b'<' => notes.extend_from_slice(b"&lt;"),
+                    b => notes.push(b),
+                }
+            }
+
+            notes.extend_from_slice(b"</li>\n");
+        }
+
+        notes.extend_from_slice(b"</ol>\n");
+        inserts.push((html.len(), Html::of_tags(notes)));
+    }
+
+    // The notes of the links go after them, past the tags inside the links
+    inserts.sort_by_key(|(at, _)| *at);
+
+    for (at, insert) in inserts.iter().rev() {
+        source_map.inserted(*at, insert.len());
+    }
+
+    html.edit(
+        inserts
+            .into_iter()
+            .map(|(at, insert)| (at..at, insert))
+            .collect(),
+    );
+}
+
+/// Value of the attribute in the attributes of a tag, already escaped the
+/// way the parser writes it
+fn attribute<'a>(tag: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    let mut at: usize = 0;
+
+    while at < tag.len() {
+        while at < tag.len() && tag[at].is_ascii_whitespace() {
+            at += 1;
+        }
+
+        let name_start: usize = at;
+
+        while at < tag.len()
+            && !matches!(tag[at], b'=' | b'/' | b'>')
+            && !tag[at].is_ascii_whitespace()
+        {
+            at += 1;
+        }
+
+        let found: bool = tag[name_start..at].eq_ignore_ascii_case(name);
+
+        if tag.get(at) != Some(&b'=') {
+            if found && at > name_start {
+                return Some(b"");
+            }
+
+            at += usize::from(at == name_start);
+            continue;
+        }
+
+        at += 1;
+        let value: &[u8] = match tag.get(at) {
+            Some(&quote @ (b'"' | b'\'')) => {
+                let end: usize = tag[at + 1..]
+                    .iter()
+                    .position(|&b| b == quote)
+                    .map_or(tag.len(), |i| at + 1 + i);
+                let value: &[u8] = &tag[(at + 1).min(end)..end];
+                at = end + 1;
+                value
+            }
+            _ => {
+                let end: usize = at
+                    + tag[at..]
+                        .iter()
+                        .take_while(|b| !b.is_ascii_whitespace() && **b != b'>')
+                        .count();
+                let value: &[u8] = &tag[at..end];
+                at = end;
+                value
+            }
+        };
+
+        if found {
+            return Some(value);
+        }
+    }
+
+    None
+}
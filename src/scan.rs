@@ -0,0 +1,98 @@
+//! Finding the next byte the parser has to look at. With the `simd` feature
+//! the bytes are compared 16 at a time with SSE2 on x86_64 and NEON on
+//! aarch64, and one at a time elsewhere.
+
+/// Index of the first byte that is one of the needles
+pub(crate) fn find<const N: usize>(bytes: &[u8], needles: [u8; N]) -> Option<usize> {
+    // None of the bytes before this is a needle
+    let start: usize = vector::skip(bytes, &needles);
+
+    bytes[start..]
+        .iter()
+        .position(|b| needles.contains(b))
+        .map(|at| start + at)
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", target_feature = "sse2"))]
+mod vector {
+    use core::arch::x86_64::{
+        __m128i, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8, _mm_or_si128, _mm_set1_epi8,
+        _mm_setzero_si128,
+    };
+
+    /// Index of the first needle found a block at a time, or the start of
+    /// the bytes left over after the last whole block
+    pub(super) fn skip<const N: usize>(bytes: &[u8], needles: &[u8; N]) -> usize {
+        let mut at: usize = 0;
+
+        // SAFETY: SSE2 is enabled for the target and the loads are unaligned
+        // ones of whole blocks inside the slice
+        unsafe {
+            let needles: [__m128i; N] = needles.map(|n| _mm_set1_epi8(n as i8));
+
+            while at + 16 <= bytes.len() {
+                let block: __m128i = _mm_loadu_si128(bytes.as_ptr().add(at).cast());
+                let mut found: __m128i = _mm_setzero_si128();
+
+                for needle in &needles {
+                    found = _mm_or_si128(found, _mm_cmpeq_epi8(block, *needle));
+                }
+
+                match _mm_movemask_epi8(found) {
+                    0 => at += 16,
+                    mask => return at + mask.trailing_zeros() as usize,
+                }
+            }
+        }
+
+        at
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "aarch64", target_feature = "neon"))]
+mod vector {
+    use core::arch::aarch64::{uint8x16_t, vceqq_u8, vdupq_n_u8, vld1q_u8, vmaxvq_u8, vorrq_u8};
+
+    /// Start of the first block with a needle in it, or the start of the
+    /// bytes left over after the last whole block
+    pub(super) fn skip<const N: usize>(bytes: &[u8], needles: &[u8; N]) -> usize {
+        let mut at: usize = 0;
+
+        // SAFETY: NEON is enabled for the target and the loads are of whole
+        // blocks inside the slice
+        unsafe {
+            let needles: [uint8x16_t; N] = needles.map(|n| vdupq_n_u8(n));
+
+            while at + 16 <= bytes.len() {
+                let block: uint8x16_t = vld1q_u8(bytes.as_ptr().add(at));
+                let mut found: uint8x16_t = vdupq_n_u8(0);
+
+                for needle in &needles {
+                    found = vorrq_u8(found, vceqq_u8(block, *needle));
+                }
+
+                if vmaxvq_u8(found) != 0 {
+                    return at;
+                }
+
+                at += 16;
+            }
+        }
+
+        at
+    }
+}
+
+#[cfg(not(all(
+    feature = "simd",
+    any(
+        all(target_arch = "x86_64", target_feature = "sse2"),
+        all(target_arch = "aarch64", target_feature = "neon")
+    )
+)))]
+mod vector {
+    /// Without vector instructions the search starts from the first byte
+    pub(super) fn skip<const N: usize>(_: &[u8], _: &[u8; N]) -> usize {
+        0
+    }
+}
@@ -0,0 +1,321 @@
+//! Self-test for packagers validating a build on a new platform. Converts a
+//! set of embedded documents and runs the protocol of the daemon against a
+//! daemon of this executable listening a temporary socket.
+
+use crate::i18n::tr;
+use crate::PS;
+use md2htm::{Em, Error, Incremental, Limits, Parser, ParserBuilder};
+use std::{
+    env, fs,
+    io::{Read, Result, Write},
+    os::unix::net::{UnixDatagram, UnixStream},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+/// How long the daemon gets to start and to answer
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Document converted with the options of the builder, and the HTML it must give
+struct Case {
+    name: &'static str,
+    builder: fn() -> ParserBuilder,
+    markdown: &'static str,
+    html: &'static str,
+}
+
+const CASES: &[Case] = &[
+    Case {
+        name: "heading",
+        builder: ParserBuilder::new,
+        markdown: "# Title\n",
+        html: "<h1>Title</h1>\n",
+    },
+    Case {
+        name: "emphasis",
+        builder: ParserBuilder::new,
+        markdown: "*a* **b**\n",
+        html: "<p><i>a</i> <b>b</b></p>\n",
+    },
+    Case {
+        name: "semantic emphasis",
+        builder: || ParserBuilder::new().emphasis_style(Em::Semantic),
+        markdown: "*a* **b**\n",
+        html: "<p><em>a</em> <strong>b</strong></p>\n",
+    },
+    Case {
+        name: "link",
+        builder: ParserBuilder::new,
+        markdown: "See [a](b.html).\n",
+        html: "<p>See <a href=\"b.html\">a</a>.</p>\n",
+    },
+    Case {
+        name: "list",
+        builder: ParserBuilder::new,
+        markdown: "- one\n- two\n",
+        html: "<p><ul><li>one</li>\n<li>two</li>\n</ul></p>",
+    },
+    Case {
+        name: "code block",
+        builder: || ParserBuilder::new().safe(true),
+        markdown: "```\n<code>\n```\n",
+        html: "<p>\n<div class=\"code\"><code class=\"code\">&lt;code&gt;\n</code></div></p>\n",
+    },
+    Case {
+        name: "safe mode",
+        builder: || ParserBuilder::new().safe(true),
+        markdown: "a <b>x</b> [a](javascript:alert(1)) b\n",
+        html: "<p>a &lt;b&gt;x&lt;/b&gt; [a](javascript:alert(1)) b</p>\n",
+    },
+    Case {
+        name: "heading ids",
+        builder: || ParserBuilder::new().heading_ids(true),
+        markdown: "# A b\n\n# A b\n",
+        html: "<h1 id=\"a-b\">A b</h1>\n\n<h1 id=\"a-b-1\">A b</h1>\n",
+    },
+];
+
+/// Counts the checks and prints how each went
+#[derive(Default)]
+struct Suite {
+    passed: usize,
+    failed: usize,
+}
+
+impl Suite {
+    fn check(&mut self, name: &str, result: std::result::Result<(), String>) {
+        match result {
+            Ok(_) => {
+                self.passed += 1;
+                println!("ok      {name}");
+            }
+            Err(e) => {
+                self.failed += 1;
+                println!("FAILED  {name}: {e}");
+            }
+        }
+    }
+}
+
+/// Runs the checks and tells if all of them passed. The daemon is started
+/// with `args` and its answers are compared with the HTML of `parser`.
+pub fn run(parser: &Parser, args: &[String]) -> Result<bool> {
+    let mut suite: Suite = Suite::default();
+
+    for case in CASES {
+        let output: Vec<u8> = Parser::new((case.builder)().build())
+            .parse(case.markdown.as_bytes())
+            .html;
+        suite.check(case.name, same(&output, case.html.as_bytes()));
+    }
+
+    library(&mut suite);
+    protocol(&mut suite, parser, args)?;
+
+    println!();
+    println!(
+        "{}",
+        tr!("{} passed, {} failed.", suite.passed, suite.failed)
+    );
+    Ok(suite.failed == 0)
+}
+
+fn same(got: &[u8], expected: &[u8]) -> std::result::Result<(), String> {
+    match got == expected {
+        true => Ok(()),
+        false => Err(tr!(
+            "expected {}, got {}",
+            format!("{:?}", String::from_utf8_lossy(expected)),
+            format!("{:?}", String::from_utf8_lossy(got))
+        )),
+    }
+}
+
+/// Document with every kind of block many times over
+fn large_document() -> Vec<u8> {
+    b"# Title\n\nSome *italic* and [a link](page.html).\n\n- one\n- two\n\n```\ncode\n\nmore\n```\n\n"
+        .repeat(5000)
+}
+
+/// The ways of parsing other than a whole document at once
+fn library(suite: &mut Suite) {
+    let parser: Parser = Parser::default();
+    let markdown: Vec<u8> = large_document();
+    let whole: Vec<u8> = parser.parse(&markdown).html;
+
+    let mut chunked: Vec<u8> = Vec::new();
+    let result = parser.parse_chunked(&markdown, 4096, &Limits::default(), &mut |chunk| {
+        chunked.extend_from_slice(chunk);
+        true
+    });
+    suite.check(
+        "chunked parsing",
+        result
+            .map_err(|e| e.to_string())
+            .and_then(|_| same(&chunked, &whole)),
+    );
+
+    let mut document: Incremental = Incremental::new(parser.clone());
+    document.update(&markdown);
+    let edited: Vec<u8> = [b"Intro.\n\n".as_slice(), &markdown].concat();
+    suite.check(
+        "incremental parsing",
+        same(&document.update(&edited).html, &parser.parse(&edited).html),
+    );
+
+    let limits: Limits = Limits {
+        cancel: None,
+        max_output: Some(1000),
+    };
+    suite.check(
+        "output limit",
+        match parser.parse_limited(&markdown, &limits) {
+            Err(Error::OutputLimit { limit: 1000 }) => Ok(()),
+            other => Err(tr!(
+                "expected the output limit, got {}",
+                format!("{:?}", other.map(|_| ()))
+            )),
+        },
+    );
+}
+
+/// Requests to a daemon of this executable
+fn protocol(suite: &mut Suite, parser: &Parser, args: &[String]) -> Result<()> {
+    let dir: PathBuf = env::temp_dir();
+    let sock: PathBuf = dir.join(format!("md2htm-selftest-{}.sock", std::process::id()));
+    let datagram: PathBuf = dir.join(format!("md2htm-selftest-{}.dgram", std::process::id()));
+    let own: PathBuf = dir.join(format!("md2htm-selftest-{}.client", std::process::id()));
+
+    let mut daemon: Child = Command::new(env::current_exe()?)
+        .arg("daemon")
+        .arg(&sock)
+        .arg("--datagram")
+        .arg(&datagram)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    let started: Instant = Instant::now();
+
+    while UnixStream::connect(&sock).is_err() {
+        if started.elapsed() > TIMEOUT || daemon.try_wait()?.is_some() {
+            suite.check(
+                "daemon",
+                Err(tr!("it didn't start listening {}", sock.display())),
+            );
+            let _ = daemon.kill();
+            return Ok(());
+        }
+
+        sleep(Duration::from_millis(20));
+    }
+
+    let markdown: Vec<u8> = large_document();
+    let whole: Vec<u8> = parser.parse(&markdown).html;
+
+    suite.check(
+        "daemon request",
+        request(&sock, None, &markdown)
+            .map_err(|e| e.to_string())
+            .and_then(|answer| same(&answer, &whole)),
+    );
+
+    suite.check(
+        "daemon request with options",
+        request(&sock, Some("standalone = true"), b"# Title\n")
+            .map_err(|e| e.to_string())
+            .and_then(|answer| match answer.starts_with(b"<!DOCTYPE html>\n") {
+                true => Ok(()),
+                false => Err(tr!("the answer isn't a whole document").to_string()),
+            }),
+    );
+
+    suite.check(
+        "daemon chunked answer",
+        chunks(&sock, &markdown)
+            .map_err(|e| e.to_string())
+            .and_then(|chunks| match chunks.len() {
+                0 | 1 => Err(tr!("the answer came in {} chunks", chunks.len())),
+                _ => same(&chunks.concat(), &whole),
+            }),
+    );
+
+    suite.check(
+        "daemon datagram",
+        datagram_request(&datagram, &own, b"# Title\n")
+            .map_err(|e| e.to_string())
+            .and_then(|answer| same(&answer, &parser.parse(b"# Title\n").html)),
+    );
+
+    let _ = daemon.kill();
+    let _ = daemon.wait();
+
+    for path in [sock, datagram, own] {
+        let _ = fs::remove_file(path);
+    }
+
+    Ok(())
+}
+
+/// The framed request with the options, if given
+fn framed(options: Option<&str>, markdown: &[u8]) -> Vec<u8> {
+    let mut request: Vec<u8> = Vec::new();
+
+    if let Some(options) = options {
+        request.extend_from_slice(&(options.len() | 1 << (usize::BITS - 1)).to_be_bytes());
+        request.extend_from_slice(options.as_bytes());
+    }
+
+    request.extend_from_slice(&markdown.len().to_be_bytes());
+    request.extend_from_slice(markdown);
+    request
+}
+
+fn connect(sock: &Path, options: Option<&str>, markdown: &[u8]) -> Result<UnixStream> {
+    let mut stream: UnixStream = UnixStream::connect(sock)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    stream.write_all(&framed(options, markdown))?;
+    Ok(stream)
+}
+
+fn receive(stream: &mut UnixStream) -> Result<Vec<u8>> {
+    let mut len: [u8; PS] = [0; PS];
+    stream.read_exact(&mut len)?;
+    let mut bytes: Vec<u8> = vec![0; usize::from_be_bytes(len)];
+    stream.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn request(sock: &Path, options: Option<&str>, markdown: &[u8]) -> Result<Vec<u8>> {
+    receive(&mut connect(sock, options, markdown)?)
+}
+
+fn chunks(sock: &Path, markdown: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let mut stream: UnixStream = connect(sock, Some("chunked = true"), markdown)?;
+    let mut chunks: Vec<Vec<u8>> = Vec::new();
+
+    loop {
+        let chunk: Vec<u8> = receive(&mut stream)?;
+
+        if chunk.is_empty() {
+            return Ok(chunks);
+        }
+
+        chunks.push(chunk);
+    }
+}
+
+fn datagram_request(datagram: &Path, own: &Path, markdown: &[u8]) -> Result<Vec<u8>> {
+    let _ = fs::remove_file(own);
+    let socket: UnixDatagram = UnixDatagram::bind(own)?;
+    socket.set_read_timeout(Some(TIMEOUT))?;
+    socket.send_to(&framed(None, markdown), datagram)?;
+
+    let mut answer: Vec<u8> = vec![0; 64 * 1024];
+    let len: usize = socket.recv(&mut answer)?;
+    Ok(answer.get(PS..len).unwrap_or_default().to_vec())
+}
@@ -0,0 +1,132 @@
+//! Splitting the HTML into slides for presentations. Every `---` rule and
+//! `##` heading at the top level starts a new `<section>`, which is what
+//! reveal.js and similar tools take as a slide.
+
+use crate::csp::{find, find_ignore_case, tag_end};
+use crate::events::Html;
+use crate::sourcemap::SourceMap;
+use alloc::{format, vec, vec::Vec};
+use core::ops::Range;
+
+/// Elements that have no end tag and so don't nest
+const VOID: &[&[u8]] = &[
+    b"area", b"base", b"br", b"col", b"embed", b"hr", b"img", b"input", b"link", b"meta",
+    b"source", b"track", b"wbr",
+];
+
+/// Wraps the slides of the HTML in sections, with the prefix on their class
+pub(crate) fn split(html: &mut Html, source_map: &mut SourceMap, class_prefix: &str) {
+    if html.trim_ascii().is_empty() {
+        return;
+    }
+
+    let open: Vec<u8> = format!("<section class=\"{class_prefix}slide\">\n").into();
+    let mut between: Vec<u8> = b"</section>\n".to_vec();
+    between.extend_from_slice(&open);
+
+    let mut edits: Vec<(Range<usize>, Vec<u8>)> = vec![(0..0, open)];
+    let mut depth: usize = 0;
+    let mut slide: usize = 0;
+    let mut at: usize = 0;
+
+    while let Some(start) = html[at..].iter().position(|&b| b == b'<').map(|i| at + i) {
+        if html[start..].starts_with(b"<!--") {
+            at = find(html, start, b"-->").map_or(html.len(), |end| end + 3);
+            continue;
+        }
+
+        let closing: bool = html.get(start + 1) == Some(&b'/');
+        let name_start: usize = start + 1 + usize::from(closing);
+        let name_end: usize = name_start
+            + html[name_start..]
+                .iter()
+                .take_while(|b| b.is_ascii_alphanumeric())
+                .count();
+        let name: &[u8] = &html[name_start..name_end];
+
+        let Some(end) = tag_end(html, name_end).filter(|_| !name.is_empty()) else {
+            at = start + 1;
+            continue;
+        };
+
+        let void: bool = VOID.iter().any(|void| name.eq_ignore_ascii_case(void))
+            || html[..end - 1].ends_with(b"/");
+
+        // A rule goes with its line break, and the paragraph the parser
+        // writes around it, a heading stays on its slide
+        let boundary: Option<Range<usize>> = match (depth, closing) {
+            (0, false) if name.eq_ignore_ascii_case(b"hr") => Some(start..line_end(html, end)),
+            (0, false) if name.eq_ignore_ascii_case(b"p") => rule(html, end)
+                .filter(|rule_end| html[*rule_end..].starts_with(b"</p>"))
+                .map(|rule_end| start..line_end(html, rule_end + 4)),
+            (0, false) if name.eq_ignore_ascii_case(b"h2") => Some(start..start),
+            _ => None,
+        };
+
+        if let Some(boundary) = boundary {
+            // Nothing is left on a slide of its own
+            let replacement: Vec<u8> = match html[slide..boundary.start].trim_ascii().is_empty() {
+                true => Vec::new(),
+                false => between.clone(),
+            };
+
+            slide = boundary.end;
+            edits.push((boundary.clone(), replacement));
+
+            // The rule is left out, a heading is still parsed
+            if !boundary.is_empty() {
+                at = boundary.end;
+                continue;
+            }
+        }
+
+        match (closing, void) {
+            (true, _) => depth = depth.saturating_sub(1),
+            (false, false) => depth += 1,
+            (false, true) => {}
+        }
+
+        at = end;
+
+        // Scripts and styles hold no tags
+        if !closing && (name.eq_ignore_ascii_case(b"script") || name.eq_ignore_ascii_case(b"style"))
+        {
+            let mut close: Vec<u8> = b"</".to_vec();
+            close.extend_from_slice(name);
+            at = find_ignore_case(html, end, &close).unwrap_or(html.len());
+        }
+    }
+
+    // A rule at the end leaves no empty slide after it
+    match edits.last_mut() {
+        Some((_, bytes)) if html[slide..].trim_ascii().is_empty() && *bytes == between => {
+            bytes.truncate(b"</section>\n".len());
+        }
+        _ => edits.push((html.len()..html.len(), b"</section>\n".to_vec())),
+    }
+
+    for (range, bytes) in edits.iter().rev() {
+        source_map.removed(range.start, range.len());
+        source_map.inserted(range.start, bytes.len());
+    }
+
+    html.edit(
+        edits
+            .into_iter()
+            .map(|(range, bytes)| (range, Html::of_tags(bytes)))
+            .collect(),
+    );
+}
+
+/// Index after the `<hr>` at the index, if there's one
+fn rule(html: &[u8], at: usize) -> Option<usize> {
+    match html[at..].starts_with(b"<hr") {
+        true => tag_end(html, at + 3),
+        false => None,
+    }
+}
+
+/// Index after the line break at the index, if there's one
+fn line_end(html: &[u8], at: usize) -> usize {
+    at + usize::from(html.get(at) == Some(&b'\n'))
+}
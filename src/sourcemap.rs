@@ -0,0 +1,91 @@
+//! Byte ranges of the markdown lines and the HTML written for them, so tools
+//! can map positions both ways without sourcepos attributes in the HTML.
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// A line of the markdown and the HTML written while parsing it. Blocks
+/// written in one go, like fenced code, cover all of their lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mapping {
+    pub source: Range<usize>,
+    pub html: Range<usize>,
+}
+
+/// Collects the mappings while parsing
+#[derive(Debug)]
+pub(crate) struct SourceMap {
+    mappings: Vec<Mapping>,
+    /// Where the unfinished line starts in the markdown and in the HTML
+    start: (usize, usize),
+}
+
+impl SourceMap {
+    pub(crate) fn new() -> Self {
+        Self {
+            mappings: Vec::new(),
+            start: (0, 0),
+        }
+    }
+
+    /// Finishes the line before `source` with the HTML written so far
+    pub(crate) fn line(&mut self, source: usize, html: usize) {
+        let (source_start, html_start) = self.start;
+
+        if source > source_start {
+            self.mappings.push(Mapping {
+                source: source_start..source,
+                html: html_start..html,
+            });
+        }
+
+        self.start = (source, html);
+    }
+
+    /// Moves the HTML offsets after `at` when bytes are inserted there
+    pub(crate) fn inserted(&mut self, at: usize, len: usize) {
+        for mapping in &mut self.mappings {
+            if mapping.html.start >= at && mapping.html.start != mapping.html.end {
+                mapping.html.start += len;
+            }
+
+            if mapping.html.end > at {
+                mapping.html.end += len;
+            }
+        }
+
+        if self.start.1 >= at {
+            self.start.1 += len;
+        }
+    }
+
+    /// Moves the HTML offsets after `at` when bytes are removed from there
+    pub(crate) fn removed(&mut self, at: usize, len: usize) {
+        let shift = |offset: &mut usize| {
+            if *offset > at {
+                *offset = (*offset - len).max(at);
+            }
+        };
+
+        for mapping in &mut self.mappings {
+            shift(&mut mapping.html.start);
+            shift(&mut mapping.html.end);
+        }
+
+        shift(&mut self.start.1);
+    }
+
+    pub(crate) fn finish(self) -> Vec<Mapping> {
+        self.mappings
+    }
+}
+
+/// Keeps the mappings of the HTML in the range, relative to its start
+pub(crate) fn crop(mappings: &mut Vec<Mapping>, range: Range<usize>) {
+    mappings.retain_mut(|mapping| {
+        mapping.html.start = mapping.html.start.clamp(range.start, range.end) - range.start;
+        mapping.html.end = mapping.html.end.clamp(range.start, range.end) - range.start;
+        mapping.html.start != mapping.html.end
+    });
+}
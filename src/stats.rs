@@ -0,0 +1,54 @@
+//! Word count and reading time of the converted text.
+
+/// Reading speed used for the reading time estimate
+pub const WORDS_PER_MINUTE: usize = 200;
+
+/// How long the text is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stats {
+    pub words: usize,
+    /// Rounded up, so any text takes at least a minute
+    pub reading_minutes: usize,
+}
+
+impl Stats {
+    /// Counts the words in the text of the HTML. Tags and character references
+    /// aren't words, and neither is punctuation standing on its own.
+    pub(crate) fn count(html: &[u8]) -> Self {
+        let mut words: usize = 0;
+        let mut in_tag: bool = false;
+        let mut in_reference: bool = false;
+        let mut in_word: bool = false;
+
+        for &b in html {
+            match b {
+                b'<' => {
+                    in_tag = true;
+                    in_word = false;
+                }
+                b'>' if in_tag => in_tag = false,
+                _ if in_tag => {}
+
+                b'&' => in_reference = true,
+                b';' if in_reference => in_reference = false,
+                _ if in_reference => {}
+
+                _ if b.is_ascii_whitespace() => in_word = false,
+
+                // Non-ASCII bytes are parts of letters
+                _ if !in_word && (b.is_ascii_alphanumeric() || b >= 0x80) => {
+                    words += 1;
+                    in_word = true;
+                }
+
+                _ => {}
+            }
+        }
+
+        Self {
+            words,
+            reading_minutes: words.div_ceil(WORDS_PER_MINUTE),
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! Service integration of the daemon. Notifications go to systemd over the
+//! socket in `NOTIFY_SOCKET`, so a unit with `Type=notify` knows when the
+//! daemon is ready and when it is stopping, and `WatchdogSec` gets its
+//! keep-alives. Nothing is sent when the daemon wasn't started by systemd.
+//! The signals stop the daemon gracefully or hand its listener over to an
+//! upgraded executable.
+
+use crate::daemon::{HANDED_OVER, LISTEN_FD};
+use std::{
+    env,
+    ffi::c_int,
+    io::{self, ErrorKind},
+    os::{
+        fd::{AsRawFd, RawFd},
+        unix::{
+            ffi::OsStrExt,
+            net::{UnixDatagram, UnixListener, UnixStream},
+            process::CommandExt,
+        },
+    },
+    path::Path,
+    process::{self, Command},
+    ptr,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::Duration,
+};
+
+/// Set when the daemon stops accepting connections
+static STOPPING: AtomicBool = AtomicBool::new(false);
+
+/// Sends the state, like `READY=1`, to systemd
+pub(crate) fn notify(state: &str) {
+    let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+
+    let Ok(socket) = UnixDatagram::unbound() else {
+        return;
+    };
+
+    let _ = match path.as_bytes().strip_prefix(b"@") {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::{linux::net::SocketAddrExt, unix::net::SocketAddr};
+
+            SocketAddr::from_abstract_name(name)
+                .and_then(|address| socket.send_to_addr(state.as_bytes(), &address))
+        }
+        _ => socket.send_to(state.as_bytes(), Path::new(&path)),
+    };
+}
+
+/// Pings the watchdog twice per `WatchdogSec` from a thread of its own
+pub(crate) fn watchdog() {
+    let Some(usec) = env::var("WATCHDOG_USEC")
+        .ok()
+        .and_then(|usec| usec.parse::<u64>().ok())
+    else {
+        return;
+    };
+
+    // The watchdog is meant for the main process only
+    if env::var("WATCHDOG_PID").is_ok_and(|pid| pid != process::id().to_string()) {
+        return;
+    }
+
+    thread::spawn(move || loop {
+        notify("WATCHDOG=1");
+        thread::sleep(Duration::from_micros(usec / 2));
+    });
+}
+
+/// Handles the signals in a thread of its own. SIGTERM and SIGINT stop the
+/// daemon after it has answered the connections it accepted, and SIGHUP
+/// starts the executable again with the listener before stopping, so an
+/// upgrade doesn't drop any clients. A second signal exits at once. Has to be
+/// called before spawning the other threads, which inherit the blocked signals.
+pub(crate) fn on_signals(listener: RawFd) {
+    // SAFETY: the set is initialized by sigemptyset before it is used, and
+    // blocking the signals only changes which thread receives them
+    let set: libc::sigset_t = unsafe {
+        let mut set: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut set);
+        libc::sigaddset(&mut set, libc::SIGTERM);
+        libc::sigaddset(&mut set, libc::SIGINT);
+        libc::sigaddset(&mut set, libc::SIGHUP);
+
+        if libc::pthread_sigmask(libc::SIG_BLOCK, &set, ptr::null_mut()) != 0 {
+            return;
+        }
+
+        set
+    };
+
+    thread::spawn(move || loop {
+        let mut signal: c_int = 0;
+
+        // SAFETY: both pointers are to live locals
+        if unsafe { libc::sigwait(&set, &mut signal) } != 0 {
+            return;
+        }
+
+        if STOPPING.load(Ordering::Acquire) {
+            process::exit(1);
+        }
+
+        if signal == libc::SIGHUP {
+            match upgrade(listener, set) {
+                Ok(pid) => {
+                    notify(&format!("MAINPID={pid}"));
+                    HANDED_OVER.store(true, Ordering::Release);
+                }
+                Err(e) => {
+                    eprintln!("Couldn't start the upgraded daemon, keeping this one: {e}");
+                    continue;
+                }
+            }
+        } else {
+            notify("STOPPING=1");
+        }
+
+        STOPPING.store(true, Ordering::Release);
+    });
+}
+
+/// Starts the executable again with the same arguments and the listener in
+/// fd 3. Returns the process id.
+fn upgrade(listener: RawFd, signals: libc::sigset_t) -> io::Result<u32> {
+    // The path the daemon was started from has the new executable, unlike
+    // /proc/self/exe
+    let mut args = env::args_os();
+    let mut command: Command = Command::new(args.next().unwrap_or_default());
+    command.args(args).env(LISTEN_FD, "3");
+
+    // SAFETY: only async-signal-safe functions are called between the fork
+    // and the exec
+    unsafe {
+        command.pre_exec(move || {
+            // The new daemon blocks the signals itself when it is ready for them
+            libc::sigprocmask(libc::SIG_UNBLOCK, &signals, ptr::null_mut());
+
+            // dup2 onto the same fd would keep it closed on exec
+            let moved: c_int = match listener {
+                3 => libc::fcntl(3, libc::F_SETFD, 0),
+                _ => libc::dup2(listener, 3),
+            };
+
+            match moved {
+                -1 => Err(io::Error::last_os_error()),
+                _ => Ok(()),
+            }
+        });
+    }
+
+    Ok(command.spawn()?.id())
+}
+
+/// Waits for the next connection on the non-blocking listener, checking every
+/// tenth of a second if the daemon is stopping. Returns None once it is.
+pub(crate) fn accept(listener: &UnixListener) -> Option<io::Result<UnixStream>> {
+    loop {
+        if STOPPING.load(Ordering::Acquire) {
+            return None;
+        }
+
+        match listener.accept() {
+            // Some systems give the connections the flags of the listener
+            Ok((stream, _)) => return Some(stream.set_nonblocking(false).map(|()| stream)),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                let mut fd: libc::pollfd = libc::pollfd {
+                    fd: listener.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+
+                // SAFETY: the pointer is to a live local and the count is 1
+                unsafe { libc::poll(&mut fd, 1, 100) };
+            }
+            Err(e) => return Some(Err(e)),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Plain text of the converted HTML, for search indexes and previews.
+
+use alloc::{string::String, vec::Vec};
+
+/// Copies the HTML leaving out the tags
+pub(crate) fn strip_tags(html: &[u8], text: &mut Vec<u8>) {
+    let mut in_tag: bool = false;
+
+    for &b in html {
+        match b {
+            b'<' => in_tag = true,
+            b'>' => in_tag = false,
+            _ if !in_tag => text.push(b),
+            _ => {}
+        }
+    }
+}
+
+/// Turns the escaped HTML text back into plain text
+pub(crate) fn unescape(html: &[u8]) -> String {
+    String::from_utf8_lossy(html)
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
+/// Text of the HTML without the tags, with every run of whitespace turned
+/// into a single space
+pub(crate) fn plain(html: &[u8]) -> String {
+    let mut text: Vec<u8> = Vec::with_capacity(html.len());
+    strip_tags(html, &mut text);
+
+    unescape(&text)
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
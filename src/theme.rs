@@ -0,0 +1,64 @@
+//! Stylesheets for standalone documents, so a converted page looks finished
+//! without writing any CSS. The themes are built into the binary and either
+//! inlined into the head or copied next to the page and linked.
+
+use std::{fs, io::Result, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Theme {
+    Light,
+    Dark,
+    /// Serif text and the addresses of the links written out for paper
+    Print,
+}
+
+impl Theme {
+    pub(crate) const ALL: [Theme; 3] = [Theme::Light, Theme::Dark, Theme::Print];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Light => "light",
+            Self::Dark => "dark",
+            Self::Print => "print",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Theme> {
+        Self::ALL.into_iter().find(|theme| theme.name() == name)
+    }
+
+    /// The stylesheet for the classes the parser writes with the prefix
+    pub(crate) fn css(self, class_prefix: &str) -> String {
+        let css: &str = match self {
+            Self::Light => include_str!("themes/light.css"),
+            Self::Dark => include_str!("themes/dark.css"),
+            Self::Print => include_str!("themes/print.css"),
+        };
+
+        css.replace("{p}", class_prefix)
+    }
+
+    /// Name of the stylesheet copied next to the page
+    pub(crate) fn file_name(self) -> String {
+        format!("md2htm-{}.css", self.name())
+    }
+
+    /// `<style>` element with the stylesheet, given the nonce so a
+    /// Content-Security-Policy allowing it applies the style
+    pub(crate) fn inline(self, class_prefix: &str, nonce: Option<&str>) -> String {
+        let nonce: String = nonce.map_or_else(String::new, |nonce| {
+            format!(" nonce=\"{}\"", crate::head::escape(nonce))
+        });
+
+        format!("<style{nonce}>\n{}</style>\n", self.css(class_prefix))
+    }
+
+    /// Writes the stylesheet into the directory and gives the `<link>` to it
+    pub(crate) fn link(self, class_prefix: &str, dir: &Path) -> Result<String> {
+        fs::write(dir.join(self.file_name()), self.css(class_prefix))?;
+        Ok(format!(
+            "<link rel=\"stylesheet\" href=\"{}\">\n",
+            self.file_name()
+        ))
+    }
+}
@@ -0,0 +1,16 @@
+//! Log of the state transitions of the parser, for debugging it on real
+//! documents.
+
+use crate::cursor::Position;
+use alloc::{string::String, vec::Vec};
+
+/// The states the parser was in after handling a byte. Only the bytes that
+/// changed the states are logged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Transition {
+    pub position: Position,
+    pub byte: u8,
+    /// The stack of states from the bottom up, like `["None", "Paragraph", "Bold"]`
+    pub states: Vec<String>,
+}
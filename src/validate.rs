@@ -0,0 +1,212 @@
+//! Lightweight well-formedness checker for the HTML the parser writes. It
+//! isn't a full HTML parser, only strict enough to catch unbalanced tags and
+//! broken attributes.
+
+use alloc::{format, string::String, vec::Vec};
+use core::fmt;
+
+/// Elements that have no closing tag
+const VOID: [&[u8]; 13] = [
+    b"area", b"base", b"br", b"col", b"embed", b"hr", b"img", b"input", b"link", b"meta",
+    b"source", b"track", b"wbr",
+];
+
+/// Elements whose content isn't parsed as HTML
+const RAW_TEXT: [&[u8]; 2] = [b"script", b"style"];
+
+/// Why the HTML isn't well-formed and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Invalid {
+    /// Byte offset in the HTML
+    pub offset: usize,
+    pub message: String,
+}
+
+impl Invalid {
+    fn new(offset: usize, message: &str) -> Self {
+        Self {
+            offset,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Invalid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "byte {}: {}", self.offset, self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Invalid {}
+
+/// Checks that the tags of the HTML fragment are balanced and its attribute
+/// values quoted.
+///
+/// ```
+/// use md2htm::{validate, Parser};
+///
+/// assert!(validate(&Parser::default().parse(b"*hi*\n").html).is_ok());
+/// assert!(validate(b"<p><b>hi</p>").is_err());
+/// ```
+pub fn validate(html: &[u8]) -> Result<(), Invalid> {
+    let mut open: Vec<(usize, &[u8])> = Vec::new();
+    let mut i: usize = 0;
+
+    while i < html.len() {
+        if html[i] != b'<' {
+            i += 1;
+            continue;
+        }
+
+        let start: usize = i;
+
+        if html[i..].starts_with(b"<!--") {
+            i = find(html, i + 4, b"-->")
+                .ok_or_else(|| Invalid::new(start, "Comment isn't closed."))?
+                + 3;
+            continue;
+        }
+
+        if html[i..].starts_with(b"</") {
+            let (name, end) = name(html, i + 2);
+
+            if name.is_empty() || html.get(end) != Some(&b'>') {
+                return Err(Invalid::new(start, "Broken closing tag."));
+            }
+
+            match open.pop() {
+                Some((_, expected)) if expected.eq_ignore_ascii_case(name) => {}
+                Some((at, expected)) => {
+                    return Err(Invalid::new(
+                        start,
+                        &format!(
+                            "</{}> closes <{}> opened at byte {at}.",
+                            String::from_utf8_lossy(name),
+                            String::from_utf8_lossy(expected)
+                        ),
+                    ))
+                }
+                None => {
+                    return Err(Invalid::new(
+                        start,
+                        &format!("</{}> closes nothing.", String::from_utf8_lossy(name)),
+                    ))
+                }
+            }
+
+            i = end + 1;
+            continue;
+        }
+
+        let (name, end) = name(html, i + 1);
+
+        if name.is_empty() {
+            return Err(Invalid::new(start, "Unescaped `<`."));
+        }
+
+        let (end, closed) = attributes(html, end)?;
+        i = end;
+
+        if closed || VOID.iter().any(|void| void.eq_ignore_ascii_case(name)) {
+            continue;
+        }
+
+        if RAW_TEXT.iter().any(|raw| raw.eq_ignore_ascii_case(name)) {
+            // Skip to the closing tag, which is checked as usual
+            i = (i..html.len())
+                .find(|&at| {
+                    html[at..].starts_with(b"</")
+                        && html
+                            .get(at + 2..at + 2 + name.len())
+                            .is_some_and(|tag| tag.eq_ignore_ascii_case(name))
+                })
+                .unwrap_or(html.len());
+        }
+
+        open.push((start, name));
+    }
+
+    match open.pop() {
+        Some((at, name)) => Err(Invalid::new(
+            at,
+            &format!("<{}> isn't closed.", String::from_utf8_lossy(name)),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Reads a tag or attribute name starting at `i` and returns it with the
+/// offset after it
+fn name(html: &[u8], i: usize) -> (&[u8], usize) {
+    let rest: &[u8] = html.get(i..).unwrap_or_default();
+    let len: usize = rest
+        .iter()
+        .take_while(|&&byte| byte.is_ascii_alphanumeric() || byte == b'-')
+        .count();
+
+    let name: &[u8] = &rest[..len];
+
+    match name.first() {
+        Some(first) if first.is_ascii_alphabetic() => (name, i + len),
+        _ => (&[], i),
+    }
+}
+
+/// Checks the attributes of a start tag from `i` to its `>` and returns the
+/// offset after the tag and whether it closed itself with `/>`
+fn attributes(html: &[u8], mut i: usize) -> Result<(usize, bool), Invalid> {
+    let start: usize = i;
+
+    loop {
+        while html.get(i).is_some_and(u8::is_ascii_whitespace) {
+            i += 1;
+        }
+
+        match html.get(i) {
+            Some(b'>') => return Ok((i + 1, false)),
+            Some(b'/') if html.get(i + 1) == Some(&b'>') => return Ok((i + 2, true)),
+            None => return Err(Invalid::new(start, "Tag isn't closed with `>`.")),
+            _ => {}
+        }
+
+        let (attribute, end) = name(html, i);
+
+        if attribute.is_empty() {
+            return Err(Invalid::new(i, "Broken attribute name."));
+        }
+
+        let had_space: bool = i > start && html[i - 1].is_ascii_whitespace();
+
+        if !had_space {
+            return Err(Invalid::new(i, "Attributes must be separated by spaces."));
+        }
+
+        i = end;
+
+        if html.get(i) != Some(&b'=') {
+            continue;
+        }
+
+        i += 1;
+
+        match html.get(i) {
+            Some(&quote @ (b'"' | b'\'')) => {
+                let end: usize = html[i + 1..]
+                    .iter()
+                    .position(|&byte| byte == quote)
+                    .ok_or_else(|| Invalid::new(i, "Attribute value isn't closed."))?;
+                i += end + 2;
+            }
+            _ => return Err(Invalid::new(i, "Attribute value isn't quoted.")),
+        }
+    }
+}
+
+fn find(html: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    html.get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|at| from + at)
+}
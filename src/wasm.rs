@@ -0,0 +1,47 @@
+//! JavaScript bindings so the same parser can run in the browser, for example
+//! to preview the text before it is sent to the server. Build with
+//!
+//! ```text
+//! cargo rustc --lib --release --target wasm32-unknown-unknown --features wasm --crate-type cdylib
+//! wasm-bindgen --target web target/wasm32-unknown-unknown/release/md2htm.wasm --out-dir pkg
+//! ```
+
+use crate::{Em, Ext, ParserBuilder, MDS};
+use js_sys::Reflect;
+use wasm_bindgen::prelude::*;
+
+/// Converts the markdown to HTML. The options may be `undefined` or an object
+/// with any of these fields:
+///
+/// - `safe`: boolean, see [`ParserBuilder::safe`]
+/// - `emphasis`: `"semantic"` or `"presentational"`
+/// - `underline`: boolean, whether `_text_` is underlined
+#[wasm_bindgen]
+pub fn convert(markdown: &str, options: JsValue) -> String {
+    let mut builder: ParserBuilder = ParserBuilder::new();
+
+    if options.is_object() {
+        if let Some(safe) = field(&options, "safe").as_bool() {
+            builder = builder.safe(safe);
+        }
+
+        match field(&options, "emphasis").as_string().as_deref() {
+            Some("semantic") => builder = builder.emphasis_style(Em::Semantic),
+            Some("presentational") => builder = builder.emphasis_style(Em::Presentational),
+            _ => {}
+        }
+
+        match field(&options, "underline").as_bool() {
+            Some(true) => builder = builder.extension(Ext::Underline),
+            Some(false) => builder = builder.disable(Ext::Underline),
+            None => {}
+        }
+    }
+
+    let html: Vec<u8> = MDS::parse_with(markdown.as_bytes().to_vec(), &builder.build());
+    String::from_utf8_lossy(&html).into_owned()
+}
+
+fn field(object: &JsValue, name: &str) -> JsValue {
+    Reflect::get(object, &JsValue::from_str(name)).unwrap_or(JsValue::UNDEFINED)
+}
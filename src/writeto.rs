@@ -1,11 +1,68 @@
+use crate::scan;
+use alloc::vec::Vec;
+
 pub trait WtiteTo {
     fn write(&mut self, bytes: &[u8]);
+
+    /// Writes the bytes replacing the characters that have a meaning in HTML
+    /// with entities, but only if `escape` is set.
+    fn escape(&mut self, bytes: &[u8], escape: bool);
+
+    /// Writes the number in decimal
+    fn write_number(&mut self, n: usize);
+
+    /// Writes the ASCII characters as numeric character references like
+    /// `&#64;`, which browsers show as is but simple scrapers don't read.
+    fn encode(&mut self, bytes: &[u8]);
 }
 
 impl WtiteTo for Vec<u8> {
     fn write(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+
+    fn escape(&mut self, bytes: &[u8], escape: bool) {
+        if !escape {
+            self.write(bytes);
+            return;
+        }
+
+        let mut rest: &[u8] = bytes;
+
+        // The text between the characters is copied as is
+        while let Some(at) = scan::find(rest, [b'<', b'>', b'&', b'"']) {
+            self.write(&rest[..at]);
+
+            match rest[at] {
+                b'<' => self.write(b"&lt;"),
+                b'>' => self.write(b"&gt;"),
+                b'&' => self.write(b"&amp;"),
+                _ => self.write(b"&quot;"),
+            }
+
+            rest = &rest[at + 1..];
+        }
+
+        self.write(rest);
+    }
+
+    fn write_number(&mut self, n: usize) {
+        if n >= 10 {
+            self.write_number(n / 10);
+        }
+
+        self.push(b'0' + (n % 10) as u8);
+    }
+
+    fn encode(&mut self, bytes: &[u8]) {
         for b in bytes {
-            self.push(*b);
+            if b.is_ascii() {
+                self.write(b"&#");
+                self.write_number(*b as usize);
+                self.push(b';');
+            } else {
+                self.push(*b);
+            }
         }
     }
 }
@@ -0,0 +1,258 @@
+//! XHTML serialization of the HTML, which EPUB readers parse as XML. Void
+//! elements close themselves, attributes get quoted values, named character
+//! references XML doesn't know become numeric ones and stray `&` and `<`
+//! are escaped. Scripts and styles are left as they are.
+
+use crate::csp::{find, find_ignore_case, tag_end};
+use crate::events::Html;
+use crate::sourcemap::SourceMap;
+use alloc::{format, vec::Vec};
+use core::ops::Range;
+
+/// Elements that have no closing tag in HTML
+const VOID: &[&[u8]] = &[
+    b"area", b"base", b"br", b"col", b"embed", b"hr", b"img", b"input", b"link", b"meta",
+    b"source", b"track", b"wbr",
+];
+
+/// Elements with text that isn't markup
+const RAW: &[&[u8]] = &[b"script", b"style"];
+
+/// Named references of HTML that XML needs as numbers
+const ENTITIES: &[(&[u8], u32)] = &[
+    (b"nbsp", 160),
+    (b"iexcl", 161),
+    (b"cent", 162),
+    (b"pound", 163),
+    (b"yen", 165),
+    (b"sect", 167),
+    (b"copy", 169),
+    (b"laquo", 171),
+    (b"shy", 173),
+    (b"reg", 174),
+    (b"deg", 176),
+    (b"plusmn", 177),
+    (b"para", 182),
+    (b"middot", 183),
+    (b"raquo", 187),
+    (b"frac12", 189),
+    (b"iquest", 191),
+    (b"times", 215),
+    (b"divide", 247),
+    (b"ndash", 8211),
+    (b"mdash", 8212),
+    (b"lsquo", 8216),
+    (b"rsquo", 8217),
+    (b"ldquo", 8220),
+    (b"rdquo", 8221),
+    (b"bull", 8226),
+    (b"hellip", 8230),
+    (b"euro", 8364),
+    (b"trade", 8482),
+    (b"larr", 8592),
+    (b"rarr", 8594),
+];
+
+/// Serializes the HTML as XHTML. Handy for the standalone document around
+/// the output, which the parser doesn't write.
+///
+/// ```
+/// use md2htm::to_xhtml;
+///
+/// assert_eq!(to_xhtml(b"<p>a&nbsp;&<br></p>"), b"<p>a&#160;&amp;<br /></p>");
+/// ```
+pub fn to_xhtml(html: &[u8]) -> Vec<u8> {
+    let mut html: Html = Html::text(html.to_vec());
+    serialize(&mut html, &mut SourceMap::new());
+    html.bytes
+}
+
+/// Rewrites the HTML as XHTML in place
+pub(crate) fn serialize(html: &mut Html, source_map: &mut SourceMap) {
+    let mut edits: Vec<(Range<usize>, Vec<u8>)> = Vec::new();
+    let mut at: usize = 0;
+
+    while let Some(start) = html[at..]
+        .iter()
+        .position(|&b| b == b'<' || b == b'&')
+        .map(|i| at + i)
+    {
+        if html[start] == b'&' {
+            at = reference(html, start, &mut edits);
+            continue;
+        }
+
+        // Comments, the doctype and processing instructions are kept
+        if html[start..].starts_with(b"<!--") {
+            at = find(html, start, b"-->").map_or(html.len(), |end| end + 3);
+            continue;
+        }
+
+        if matches!(html.get(start + 1), Some(b'!' | b'?')) {
+            at = find(html, start, b">").map_or(html.len(), |end| end + 1);
+            continue;
+        }
+
+        let closing: bool = html.get(start + 1) == Some(&b'/');
+        let name_start: usize = start + 1 + usize::from(closing);
+        let name_end: usize = name_start
+            + html[name_start..]
+                .iter()
+                .take_while(|b| b.is_ascii_alphanumeric())
+                .count();
+
+        let end: Option<usize> = match name_end > name_start {
+            true => tag_end(html, name_end),
+            false => None,
+        };
+
+        // Not a tag, like `a < b`
+        let Some(end) = end else {
+            edits.push((start..start + 1, b"&lt;".to_vec()));
+            at = start + 1;
+            continue;
+        };
+
+        if closing {
+            at = end;
+            continue;
+        }
+
+        let name: &[u8] = &html[name_start..name_end];
+        let self_closed: bool = html[..end - 1].ends_with(b"/");
+        let attributes_end: usize = end - 1 - usize::from(self_closed);
+        attributes(html, name_end, attributes_end, &mut edits);
+
+        if !self_closed && VOID.iter().any(|void| name.eq_ignore_ascii_case(void)) {
+            edits.push((end - 1..end, b" />".to_vec()));
+        }
+
+        at = end;
+
+        if RAW.iter().any(|raw| name.eq_ignore_ascii_case(raw)) && !self_closed {
+            let mut close: Vec<u8> = Vec::with_capacity(name.len() + 2);
+            close.extend_from_slice(b"</");
+            close.extend_from_slice(name);
+            at = find_ignore_case(html, end, &close).unwrap_or(html.len());
+        }
+    }
+
+    for (range, bytes) in edits.iter().rev() {
+        source_map.removed(range.start, range.len());
+        source_map.inserted(range.start, bytes.len());
+    }
+
+    html.edit(
+        edits
+            .into_iter()
+            .map(|(range, bytes)| (range, Html::text(bytes)))
+            .collect(),
+    );
+}
+
+/// Quotes the values of the attributes between `from` and `to`, giving the
+/// ones without a value their name as the value
+fn attributes(html: &[u8], from: usize, to: usize, edits: &mut Vec<(Range<usize>, Vec<u8>)>) {
+    let mut at: usize = from;
+
+    while at < to {
+        while at < to && html[at].is_ascii_whitespace() {
+            at += 1;
+        }
+
+        let name_start: usize = at;
+
+        while at < to && html[at] != b'=' && !html[at].is_ascii_whitespace() {
+            at += 1;
+        }
+
+        let name: &[u8] = &html[name_start..at];
+
+        if name.is_empty() {
+            at += 1;
+            continue;
+        }
+
+        if html.get(at) != Some(&b'=') || at >= to {
+            let mut value: Vec<u8> = b"=\"".to_vec();
+            value.extend_from_slice(name);
+            value.push(b'"');
+            edits.push((at..at, value));
+            continue;
+        }
+
+        at += 1;
+
+        match html.get(at) {
+            Some(&quote @ (b'"' | b'\'')) => {
+                let end: usize = html[at + 1..to]
+                    .iter()
+                    .position(|&b| b == quote)
+                    .map_or(to, |i| at + 1 + i);
+                value(html, at + 1..end, edits);
+                at = (end + 1).min(to);
+            }
+            _ => {
+                let end: usize = at
+                    + html[at..to]
+                        .iter()
+                        .take_while(|b| !b.is_ascii_whitespace())
+                        .count();
+                edits.push((at..at, b"\"".to_vec()));
+                value(html, at..end, edits);
+                edits.push((end..end, b"\"".to_vec()));
+                at = end;
+            }
+        }
+    }
+}
+
+/// Escapes what XML doesn't allow in the attribute value
+fn value(html: &[u8], range: Range<usize>, edits: &mut Vec<(Range<usize>, Vec<u8>)>) {
+    let mut at: usize = range.start;
+
+    while at < range.end {
+        match html[at] {
+            b'&' => at = reference(html, at, edits),
+            b'<' => {
+                edits.push((at..at + 1, b"&lt;".to_vec()));
+                at += 1;
+            }
+            _ => at += 1,
+        }
+    }
+}
+
+/// Checks the character reference at the `&`, turning names XML doesn't
+/// know into numbers and escaping the `&` of anything else. Returns the
+/// index after it.
+fn reference(html: &[u8], at: usize, edits: &mut Vec<(Range<usize>, Vec<u8>)>) -> usize {
+    let len: usize = html[at + 1..]
+        .iter()
+        .take(32)
+        .take_while(|b| b.is_ascii_alphanumeric() || **b == b'#')
+        .count();
+    let name: &[u8] = &html[at + 1..at + 1 + len];
+    let terminated: bool = html.get(at + 1 + len) == Some(&b';');
+
+    let numeric: bool = match name {
+        [b'#', b'x' | b'X', hex @ ..] => !hex.is_empty() && hex.iter().all(u8::is_ascii_hexdigit),
+        [b'#', digits @ ..] => !digits.is_empty() && digits.iter().all(u8::is_ascii_digit),
+        _ => false,
+    };
+
+    if terminated && (numeric || matches!(name, b"amp" | b"lt" | b"gt" | b"quot" | b"apos")) {
+        return at + len + 2;
+    }
+
+    match ENTITIES.iter().find(|(entity, _)| *entity == name) {
+        Some((_, code)) if terminated => {
+            edits.push((at..at + len + 2, format!("&#{code};").into()));
+            at + len + 2
+        }
+        _ => {
+            edits.push((at..at + 1, b"&amp;".to_vec()));
+            at + 1
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Differential testing against another implementation. Every markdown file
+//! of a corpus is converted with md2htm and with a reference command, like
+//! cmark, and the two are compared as sequences of tags and text, so
+//! formatting differences that don't change the structure are ignored.
+
+use crate::build::find_markdown;
+use crate::i18n::tr;
+use md2htm::Parser;
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{ErrorKind, Result, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+};
+
+/// Reference used when `--reference` isn't given
+pub(crate) const REFERENCE: &str = "cmark";
+/// Tokens shown around the first difference
+const CONTEXT: usize = 2;
+/// Longest text shown of a text token
+const TEXT: usize = 40;
+
+/// Piece of the structure of an HTML fragment
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// Name and the attributes in order, `<br />` and `<br>` being the same
+    Open(String, Vec<String>),
+    Close(String),
+    /// Text with the whitespace collapsed
+    Text(String),
+}
+
+impl Token {
+    /// What the differences are grouped by
+    fn kind(&self) -> String {
+        match self {
+            Self::Open(name, _) => format!("<{name}>"),
+            Self::Close(name) => format!("</{name}>"),
+            Self::Text(_) => String::from("text"),
+        }
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Open(name, attributes) if attributes.is_empty() => write!(f, "<{name}>"),
+            Self::Open(name, attributes) => write!(f, "<{name} {}>", attributes.join(" ")),
+            Self::Close(name) => write!(f, "</{name}>"),
+            Self::Text(text) if text.chars().count() > TEXT => {
+                write!(f, "{:?}…", text.chars().take(TEXT).collect::<String>())
+            }
+            Self::Text(text) => write!(f, "{text:?}"),
+        }
+    }
+}
+
+/// Converts the corpus with both and prints the files that differ, and which
+/// tags the differences start from the most. Tells if all of them matched.
+pub fn run(dir: &Path, parser: &Parser, reference: &str) -> Result<bool> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    find_markdown(dir, &mut files)?;
+    files.sort();
+
+    let mut command = reference.split_whitespace();
+    let Some(program) = command.next() else {
+        eprintln!(
+            "{}",
+            tr!("--reference expects the command to compare with.")
+        );
+        return Ok(false);
+    };
+    let args: Vec<&str> = command.collect();
+    let name: String = Path::new(program)
+        .file_name()
+        .map_or(program.into(), |name| name.to_string_lossy().into_owned());
+    let width: usize = name.len().max("md2htm".len()) + 1;
+
+    let mut same: usize = 0;
+    // How many files started to differ from each pair of tokens
+    let mut starts: HashMap<(String, String), usize> = HashMap::new();
+
+    for path in &files {
+        let markdown: Vec<u8> = std::fs::read(path)?;
+
+        let expected: Vec<u8> = match convert(program, &args, &markdown) {
+            Ok(html) => html,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                eprintln!(
+                    "{}",
+                    tr!(
+                        "The reference {} wasn't found, nothing to compare with.",
+                        program
+                    )
+                );
+                return Ok(false);
+            }
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    tr!("The reference failed on {}: {}", path.display(), e)
+                );
+                continue;
+            }
+        };
+
+        let ours: Vec<Token> = tokens(&parser.parse(&markdown).html);
+        let theirs: Vec<Token> = tokens(&expected);
+
+        let Some(at) = difference(&ours, &theirs) else {
+            same += 1;
+            continue;
+        };
+
+        let shown = |tokens: &[Token]| -> String {
+            tokens[at.saturating_sub(CONTEXT)..(at + CONTEXT + 1).min(tokens.len())]
+                .iter()
+                .map(Token::to_string)
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        let kind = |tokens: &[Token]| tokens.get(at).map_or(String::from("end"), Token::kind);
+
+        println!("{}", tr!("{} differs at token {}:", path.display(), at + 1));
+        println!("    {:<width$} {}", "md2htm:", shown(&ours));
+        println!("    {:<width$} {}", format!("{name}:"), shown(&theirs));
+        *starts.entry((kind(&ours), kind(&theirs))).or_default() += 1;
+    }
+
+    println!();
+    println!("{}", tr!("{} of {} files match.", same, files.len()));
+
+    if !starts.is_empty() {
+        let mut starts: Vec<((String, String), usize)> = starts.into_iter().collect();
+        starts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        println!();
+        println!("{}", tr!("Differences start from:"));
+
+        for ((ours, theirs), count) in starts {
+            println!("    {count:>5}  md2htm {ours} / {name} {theirs}");
+        }
+    }
+
+    Ok(same == files.len())
+}
+
+/// Output of the reference for the markdown given as its input
+fn convert(program: &str, args: &[&str], markdown: &[u8]) -> Result<Vec<u8>> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    // Written from another thread, so a reference writing before it has
+    // read everything can't fill the pipe and wait for us forever
+    let mut stdin = child.stdin.take();
+    let markdown: Vec<u8> = markdown.to_vec();
+    let writer = thread::spawn(move || stdin.as_mut().map(|stdin| stdin.write_all(&markdown)));
+
+    let output = child.wait_with_output()?;
+    let _ = writer.join();
+    Ok(output.stdout)
+}
+
+/// Index of the first token that differs, if any
+fn difference(ours: &[Token], theirs: &[Token]) -> Option<usize> {
+    match ours.iter().zip(theirs).position(|(a, b)| a != b) {
+        Some(at) => Some(at),
+        None if ours.len() != theirs.len() => Some(ours.len().min(theirs.len())),
+        None => None,
+    }
+}
+
+/// Splits the HTML into tags and text, leaving out comments, doctypes and
+/// whitespace between the tags
+fn tokens(html: &[u8]) -> Vec<Token> {
+    let html: String = String::from_utf8_lossy(html).into_owned();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut rest: &str = &html;
+
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            text(&mut tokens, rest);
+            break;
+        };
+
+        text(&mut tokens, &rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+
+        let Some(end) = rest.find('>') else {
+            text(&mut tokens, rest);
+            break;
+        };
+
+        let tag: &str = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            tokens.push(Token::Close(name.trim().to_ascii_lowercase()));
+        } else if !tag.starts_with('!') && !tag.starts_with('?') {
+            let tag: &str = tag.trim_end_matches('/');
+            let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+            tokens.push(Token::Open(
+                name.to_ascii_lowercase(),
+                split_attributes(attributes),
+            ));
+        }
+    }
+
+    tokens
+}
+
+/// Adds the text unless it's only whitespace, with the entities that are
+/// written in more than one way spelled the same
+fn text(tokens: &mut Vec<Token>, text: &str) {
+    let text: String = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    if text.is_empty() {
+        return;
+    }
+
+    let text: String = text
+        .replace("&quot;", "\"")
+        .replace("&#34;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&apos;", "'");
+
+    // Text interrupted by a comment is still one piece of text
+    match tokens.last_mut() {
+        Some(Token::Text(previous)) => {
+            previous.push(' ');
+            previous.push_str(&text);
+        }
+        _ => tokens.push(Token::Text(text)),
+    }
+}
+
+/// The attributes as `name="value"`, keeping quoted values whole
+fn split_attributes(attributes: &str) -> Vec<String> {
+    let mut split: Vec<String> = Vec::new();
+    let mut current: String = String::new();
+    let mut quote: Option<char> = None;
+
+    for c in attributes.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                current.push('"');
+            }
+            (Some(q), _) if c == q => {
+                quote = None;
+                current.push('"');
+            }
+            (None, c) if c.is_whitespace() => {
+                if !current.is_empty() {
+                    split.push(std::mem::take(&mut current));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        split.push(current);
+    }
+
+    split
+}
@@ -0,0 +1,66 @@
+use md2htm::ast::{Block, Document, Inline};
+use md2htm::{Dialect, Kind, Parser, ParserBuilder, MDS};
+use std::fs;
+
+#[test]
+fn renders_the_same_html() {
+    for entry in fs::read_dir("tests/legacy").unwrap() {
+        let path = entry.unwrap().path();
+
+        if path.extension().is_some_and(|extension| extension == "md") {
+            let markdown: Vec<u8> = fs::read(&path).unwrap();
+
+            for dialect in [Dialect::Legacy, Dialect::Gfm] {
+                let options = ParserBuilder::new().dialect(dialect).toc(true).build();
+                let html: Vec<u8> = MDS::parse_with(markdown.clone(), &options);
+                let document: Document = MDS::parse_document(markdown.clone(), &options);
+                assert_eq!(
+                    String::from_utf8_lossy(&document.render_html()),
+                    String::from_utf8_lossy(&html),
+                    "{}",
+                    path.display()
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn nests_the_blocks() {
+    let parser: Parser = Parser::new(ParserBuilder::new().dialect(Dialect::Gfm).build());
+    let document: Document = parser.parse(b"# One *two*\n\n- a\n- b\n").document();
+
+    let headings: Vec<(u8, String)> = document
+        .headings()
+        .into_iter()
+        .map(|(level, _, text)| (level, text))
+        .collect();
+    assert_eq!(headings, [(1, String::from("One two"))]);
+
+    // The parser writes the list in a paragraph
+    let Some(Block::Container { blocks, .. }) = document.blocks.last() else {
+        panic!("{document:?}");
+    };
+    let Some(Block::Container { tag, blocks }) = blocks.first() else {
+        panic!("{document:?}");
+    };
+    assert_eq!(tag.kind(), Kind::List);
+
+    let items: usize = blocks
+        .iter()
+        .filter(|block| matches!(block, Block::Container { tag, .. } if tag.kind() == Kind::Item))
+        .count();
+    assert_eq!(items, 2, "{blocks:?}");
+}
+
+#[test]
+fn keeps_the_raw_html_as_text() {
+    let output = md2htm::Parser::default().parse(b"<div>a <b>b\n");
+    let document: Document = output.document();
+    assert_eq!(document.render_html(), output.html);
+
+    let Block::Paragraph { content, .. } = &document.blocks[0] else {
+        panic!("{document:?}");
+    };
+    assert_eq!(content[0], Inline::Text(String::from("<div>a <b>b")));
+}
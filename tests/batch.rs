@@ -0,0 +1,72 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+fn batch(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_md2htm"))
+        .arg("batch")
+        .args(args)
+        .env("XDG_CONFIG_HOME", "/nonexistent")
+        .env("LANG", "C")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn answers_every_line_in_order() {
+    let output: Output = batch(
+        &["--warn-duplicate-ids", "--heading-ids"],
+        "{\"id\": 1, \"markdown\": \"# Hi\\n\"}\n\n\
+         {\"id\": \"b\", \"markdown\": \"<!-- md2htm: semantic = true -->\\n*x*\\n\"}\n\
+         {\"id\": [3], \"markdown\": \"# A\\n\\n# A\\n\"}\n",
+    );
+    assert!(output.status.success());
+
+    let stdout: String = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3, "{stdout}");
+    assert_eq!(
+        lines[0],
+        "{\"id\":1,\"html\":\"<h1 id=\\\"hi\\\">Hi</h1>\\n\",\"diagnostics\":[]}"
+    );
+    assert!(
+        lines[1].starts_with("{\"id\":\"b\",\"html\":\"<p><em>x</em></p>"),
+        "{stdout}"
+    );
+    assert!(lines[2].starts_with("{\"id\":[3],"), "{stdout}");
+    assert!(
+        lines[2].contains("\"diagnostics\":[{\"line\":3,\"column\":2,\"severity\":\"warning\",\"code\":\"duplicate-id\""),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn reports_broken_lines_and_goes_on() {
+    let output: Output = batch(
+        &[],
+        "not json\n{\"id\": 2}\n{\"id\": 3, \"markdown\": \"ok\\n\"}\n",
+    );
+    assert_eq!(output.status.code(), Some(1));
+
+    let stdout: String = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines[..2],
+        [
+            "{\"id\":null,\"error\":\"Expected a JSON object with the markdown.\"}",
+            "{\"id\":2,\"error\":\"Expected a JSON object with the markdown.\"}",
+        ]
+    );
+    assert!(lines[2].starts_with("{\"id\":3,\"html\":"), "{stdout}");
+}
@@ -0,0 +1,86 @@
+use md2htm::{Em, Ext, Parser, ParserBuilder};
+
+fn html(markdown: &str) -> String {
+    html_with(
+        &Parser::new(ParserBuilder::new().extension(Ext::Blockquotes).build()),
+        markdown,
+    )
+}
+
+fn html_with(parser: &Parser, markdown: &str) -> String {
+    String::from_utf8(parser.parse(markdown.as_bytes()).html).unwrap()
+}
+
+#[test]
+fn quotes_the_lines() {
+    assert_eq!(
+        html("> *one*\n> two\n\nafter\n"),
+        "<blockquote>\n<p><i>one</i></p>\n<p>two</p>\n</blockquote>\n\n<p>after</p>\n"
+    );
+}
+
+#[test]
+fn nests_quotes() {
+    assert_eq!(
+        html("> outer\n>> inner\n> > also inner\n"),
+        "<blockquote>\n<p>outer</p>\n<blockquote>\n<p>inner</p>\n<p>also inner</p>\n</blockquote>\n</blockquote>\n"
+    );
+}
+
+#[test]
+fn parses_blocks_inside() {
+    let parser: Parser = Parser::new(
+        ParserBuilder::new()
+            .extension(Ext::Blockquotes)
+            .emphasis_style(Em::Semantic)
+            .build(),
+    );
+
+    assert_eq!(
+        html_with(&parser, "> ## Title\n>\n> **text**"),
+        "<blockquote>\n<h2>Title</h2>\n\n<p><strong>text</strong></p>\n</blockquote>"
+    );
+}
+
+#[test]
+fn leaves_deep_quotes_as_text() {
+    let markdown: String = ">".repeat(100);
+    assert_eq!(html(&markdown), format!("<p>{markdown}</p>"));
+}
+
+#[test]
+fn closes_the_blocks_inside() {
+    assert_eq!(
+        html("> - a\n> - b\n"),
+        "<blockquote>\n<p><ul><li>a</li>\n<li>b</li>\n</ul></p>\n</blockquote>\n"
+    );
+    assert_eq!(
+        html("> **bold\n"),
+        "<blockquote>\n<p><b>bold\n</b></p>\n</blockquote>\n"
+    );
+    assert_eq!(
+        html("> *one* _two\n\nafter\n"),
+        "<blockquote>\n<p><i>one</i> <u>two\n</u></p>\n</blockquote>\n\n<p>after</p>\n"
+    );
+}
+
+#[test]
+fn leaves_the_quotes_as_text_in_legacy() {
+    assert_eq!(
+        html_with(&Parser::default(), "> quoted\n"),
+        "<p>> quoted</p>\n"
+    );
+}
+
+#[test]
+fn quotes_go_inside_list_items_and_lists_inside_quotes() {
+    assert_eq!(
+        html("- a\n\n  > q\n- b\n"),
+        "<p><ul><li><p>a</p>\n\n<blockquote>\n<p>q</p>\n</blockquote></li>\n<li>b</li>\n</ul></p>"
+    );
+    assert_eq!(
+        html("> - a\n>\n>   > q\n> - b\n"),
+        "<blockquote>\n<p><ul><li><p>a</p>\n\n<blockquote>\n<p>q</p>\n</blockquote></li>\n\
+         <li>b</li>\n</ul></p>\n</blockquote>\n"
+    );
+}
@@ -0,0 +1,38 @@
+use md2htm::{Parser, ParserBuilder, Permalink};
+
+fn html(parser: &Parser, markdown: &str) -> String {
+    String::from_utf8(parser.parse(markdown.as_bytes()).html).unwrap()
+}
+
+#[test]
+fn prefixes_every_class_of_the_parser() {
+    let parser: Parser = Parser::new(
+        ParserBuilder::new()
+            .class_prefix("md-")
+            .toc(true)
+            .permalink(Permalink::Anchor)
+            .external_class("external")
+            .build(),
+    );
+    let html: String = html(
+        &parser,
+        "# Title\n\nSome `code` and [a link](https://example.com).\n\n    indented\n\n```\nblock\n```\n",
+    );
+
+    for class in ["md-code", "md-intend", "md-anchor", "md-toc", "external"] {
+        assert!(
+            html.contains(&format!("class=\"{class}\"")),
+            "{class}: {html}"
+        );
+    }
+    assert!(!html.contains("class=\"code\""), "{html}");
+}
+
+#[test]
+fn leaves_out_characters_that_would_break_the_attribute() {
+    let parser: Parser = Parser::new(ParserBuilder::new().class_prefix("x\" y").build());
+    assert_eq!(
+        html(&parser, "`a`\n"),
+        "<p><span class=\"xycode\"><code class=\"xycode\">a</code></span></p>\n"
+    );
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::Daemon;
+use md2htm_client::{Client, Error, Options};
+use std::time::Duration;
+
+const DOC: &[u8] = b"# Title\n\nSome *italic* and [a link](page.html).\n";
+
+#[test]
+fn converts_with_and_without_options() {
+    let daemon: Daemon = Daemon::start();
+    let mut client: Client = Client::connect(&daemon.sock)
+        .unwrap()
+        .timeout(Duration::from_secs(10));
+
+    assert_eq!(
+        client.convert(DOC, &Options::new()).unwrap(),
+        daemon.convert(DOC)
+    );
+
+    // Every conversion after the first needs a new connection
+    let options: Options = Options::new()
+        .flag("semantic", true)
+        .value("base-url", "https://example.com/\"docs\"/")
+        .list("enable", &["kbd"]);
+    let html: Vec<u8> = client.convert(DOC, &options).unwrap();
+
+    assert_eq!(
+        String::from_utf8(html).unwrap(),
+        "<h1>Title</h1>\n\n<p>Some <em>italic</em> and \
+         <a href=\"https://example.com/&quot;docs&quot;/page.html\">a link</a>.</p>\n"
+    );
+}
+
+#[test]
+fn hands_over_the_chunks() {
+    let daemon: Daemon = Daemon::start();
+    let markdown: Vec<u8> = DOC.repeat(20_000);
+    let mut chunks: Vec<Vec<u8>> = Vec::new();
+
+    Client::connect(&daemon.sock)
+        .unwrap()
+        .convert_chunked(&markdown, &Options::new(), |chunk| {
+            chunks.push(chunk.to_vec())
+        })
+        .unwrap();
+
+    assert!(chunks.len() > 1);
+    assert_eq!(chunks.concat(), daemon.convert(&markdown));
+}
+
+#[test]
+fn tells_when_the_daemon_drops_the_request() {
+    let daemon: Daemon = Daemon::start_with_env(&[("MD2HTM_MAX_SIZE", "64")]);
+    let mut client: Client = Client::connect(&daemon.sock).unwrap();
+
+    assert!(matches!(
+        client.convert(&[b'a'; 1 << 20], &Options::new()),
+        Err(Error::Dropped)
+    ));
+    assert!(matches!(
+        client.convert(DOC, &Options::new().flag("safe", false)),
+        Err(Error::Dropped)
+    ));
+    assert!(client.convert(DOC, &Options::new()).is_ok());
+}
+
+#[test]
+fn retries_connecting_while_the_daemon_restarts() {
+    let mut daemon: Daemon = Daemon::start();
+    let mut client: Client = Client::connect(&daemon.sock).unwrap().retries(8);
+    client.convert(DOC, &Options::new()).unwrap();
+
+    daemon.kill();
+    let restart = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(100));
+        daemon.restart();
+        daemon
+    });
+
+    assert!(client.convert(DOC, &Options::new()).is_ok());
+    drop(restart.join());
+}
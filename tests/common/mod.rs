@@ -0,0 +1,143 @@
+//! Shared harness for the tests that talk to a running daemon.
+
+use std::{
+    env,
+    io::{Read, Result, Write},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread::sleep,
+    time::Duration,
+};
+
+pub const PS: usize = std::mem::size_of::<usize>();
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A daemon listening a temporary socket. The process is killed and the socket
+/// removed when the value is dropped, even if the test panics.
+pub struct Daemon {
+    child: Child,
+    pub sock: PathBuf,
+    /// Environment variables of the daemon. With any the socket is given in
+    /// `MD2HTM_SOCKET` instead of the argument.
+    env: Vec<(String, String)>,
+}
+
+impl Daemon {
+    /// Starts a daemon on a fresh socket path and waits until it accepts connections.
+    pub fn start() -> Self {
+        Self::start_with_env(&[])
+    }
+
+    /// Starts a daemon configured with the environment variables.
+    pub fn start_with_env(env: &[(&str, &str)]) -> Self {
+        let sock: PathBuf = env::temp_dir().join(format!(
+            "md2htm-{}-{}.sock",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let env: Vec<(String, String)> = env
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+
+        let daemon: Self = Self {
+            child: spawn(&sock, &env),
+            sock,
+            env,
+        };
+        daemon.wait_until_listening();
+        daemon
+    }
+
+    fn wait_until_listening(&self) {
+        for _ in 0..100 {
+            if UnixStream::connect(&self.sock).is_ok() {
+                return;
+            }
+
+            sleep(Duration::from_millis(20));
+        }
+
+        panic!("the daemon never started listening {:?}", self.sock);
+    }
+
+    /// Kills the daemon without cleaning up, leaving a stale socket file behind.
+    pub fn kill(&mut self) {
+        self.child.kill().unwrap();
+        self.child.wait().unwrap();
+    }
+
+    /// Kills the daemon if it is still running and starts a new one on the same socket path.
+    pub fn restart(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        self.child = spawn(&self.sock, &self.env);
+        self.wait_until_listening();
+    }
+
+    pub fn connect(&self) -> UnixStream {
+        UnixStream::connect(&self.sock).expect("failed to connect the daemon")
+    }
+
+    /// Sends a single request and returns the parsed HTML.
+    pub fn convert(&self, markdown: &[u8]) -> Vec<u8> {
+        let mut stream: UnixStream = self.connect();
+        send(&mut stream, markdown).unwrap();
+        let html: Vec<u8> = receive(&mut stream).unwrap();
+
+        // The daemon shouldn't send anything after the announced length.
+        let mut rest: Vec<u8> = Vec::new();
+        stream.read_to_end(&mut rest).unwrap();
+        assert!(
+            rest.is_empty(),
+            "{} trailing bytes after the response",
+            rest.len()
+        );
+
+        html
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_file(&self.sock);
+    }
+}
+
+fn spawn(sock: &Path, env: &[(String, String)]) -> Child {
+    let mut command: Command = Command::new(env!("CARGO_BIN_EXE_md2htm"));
+    command.arg("daemon");
+
+    if env.is_empty() {
+        command.arg(sock);
+    } else {
+        command.env("MD2HTM_SOCKET", sock).envs(env.iter().cloned());
+    }
+
+    command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to start the daemon")
+}
+
+/// Writes a length prefixed message.
+pub fn send(stream: &mut UnixStream, bytes: &[u8]) -> Result<()> {
+    stream.write_all(&bytes.len().to_be_bytes())?;
+    stream.write_all(bytes)
+}
+
+/// Reads a length prefixed message.
+pub fn receive(stream: &mut UnixStream) -> Result<Vec<u8>> {
+    let mut lbuf: [u8; PS] = [0; PS];
+    stream.read_exact(&mut lbuf)?;
+    let mut bytes: Vec<u8> = vec![0; usize::from_be_bytes(lbuf)];
+    stream.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
@@ -0,0 +1,405 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+/// Fresh directory holding the user config under `md2htm/config.toml`
+fn config_home(name: &str, config: &str) -> PathBuf {
+    let dir: PathBuf = env::temp_dir().join(format!("md2htm-config-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("md2htm")).unwrap();
+    fs::write(dir.join("md2htm").join("config.toml"), config).unwrap();
+    dir
+}
+
+fn md2htm(home: &Path, env: &[(&str, &str)], args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_md2htm"))
+        .args(args)
+        .env("XDG_CONFIG_HOME", home)
+        .env("LANG", "C")
+        .envs(env.iter().copied())
+        .current_dir(home)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn later_layers_override_earlier_ones() {
+    let home: PathBuf = config_home("layers", "semantic = true\ntoc = true # comment\n");
+    fs::write(
+        home.join("doc.md"),
+        "<!-- md2htm: toc = false -->\n# Title\n\n*text*\n",
+    )
+    .unwrap();
+
+    let output: Output = md2htm(
+        &home,
+        &[("MD2HTM_HEADING_IDS", "1"), ("MD2HTM_SEMANTIC", "0")],
+        &["doc.md"],
+    );
+    assert!(output.status.success());
+
+    let html: String = fs::read_to_string(home.join("doc.html")).unwrap();
+    assert_eq!(html, "<h1 id=\"title\">Title</h1>\n\n<p><i>text</i></p>\n");
+
+    md2htm(&home, &[], &["--semantic", "--slug-prefix", "s-", "doc.md"]);
+    let html: String = fs::read_to_string(home.join("doc.html")).unwrap();
+    assert!(html.contains("<em>text</em>"), "{html}");
+    assert!(!html.contains("toc"), "{html}");
+
+    fs::remove_dir_all(home).unwrap();
+}
+
+#[test]
+fn config_show_tells_where_the_values_come_from() {
+    let home: PathBuf = config_home("show", "dialect = \"gfm\"\nenable = [\"kbd\"]\n");
+
+    let output: Output = md2htm(
+        &home,
+        &[("MD2HTM_DIALECT", "commonmark")],
+        &["--disable", "kbd", "config", "show"],
+    );
+    let shown: String = String::from_utf8(output.stdout).unwrap();
+
+    assert!(
+        shown.contains("dialect = \"commonmark\"  # environment"),
+        "{shown}"
+    );
+    assert!(shown.contains("enable = \"kbd\"  # user config"), "{shown}");
+    assert!(
+        shown.contains("disable = \"kbd\"  # command line"),
+        "{shown}"
+    );
+    assert!(shown.contains("safe = false  # default"), "{shown}");
+
+    fs::remove_dir_all(home).unwrap();
+}
+
+#[test]
+fn reports_the_line_of_a_broken_config() {
+    let home: PathBuf = config_home("broken", "toc = true\nnope = 1\n");
+
+    let output: Output = md2htm(&home, &[], &["config", "show"]);
+    let error: String = String::from_utf8(output.stderr).unwrap();
+
+    assert!(
+        error.ends_with("config.toml:2: Unknown option nope.\n"),
+        "{error}"
+    );
+    assert!(output.stdout.is_empty());
+
+    fs::remove_dir_all(home).unwrap();
+}
+
+#[test]
+fn pragmas_cant_turn_safe_mode_off() {
+    let home: PathBuf = config_home("pragma", "");
+    fs::write(
+        home.join("doc.md"),
+        "<!-- md2htm: safe = false -->\n<b>hi</b>\n",
+    )
+    .unwrap();
+
+    let output: Output = md2htm(&home, &[], &["--safe", "doc.md"]);
+    let error: String = String::from_utf8(output.stderr).unwrap();
+    let html: String = fs::read_to_string(home.join("doc.html")).unwrap();
+
+    assert!(error.contains("The pragma can't set safe."), "{error}");
+    assert!(html.contains("&lt;b&gt;"), "{html}");
+
+    fs::remove_dir_all(home).unwrap();
+}
+
+#[test]
+fn detects_the_dialect_of_each_document() {
+    let home: PathBuf = config_home("auto", "dialect = \"auto\"\n");
+    fs::write(home.join("gfm.md"), "- [x] *done*\n").unwrap();
+    fs::write(home.join("legacy.md"), "*text*\n").unwrap();
+
+    assert!(md2htm(&home, &[], &["gfm.md"]).status.success());
+    assert!(md2htm(&home, &[], &["legacy.md"]).status.success());
+
+    let gfm: String = fs::read_to_string(home.join("gfm.html")).unwrap();
+    let legacy: String = fs::read_to_string(home.join("legacy.html")).unwrap();
+    assert!(gfm.contains("<em>done</em>"), "{gfm}");
+    assert!(legacy.contains("<i>text</i>"), "{legacy}");
+
+    let _ = fs::remove_dir_all(home);
+}
+
+#[test]
+fn standalone_documents_get_their_head_from_the_front_matter() {
+    let home: PathBuf = config_home("head", "");
+    fs::write(
+        home.join("page.md"),
+        "---\ntitle: \"A <page>\"\nimage: cover.png\n---\n# Heading\n\nThe first paragraph.\n",
+    )
+    .unwrap();
+    fs::write(home.join("plain.md"), "# Heading\n\nThe first paragraph.\n").unwrap();
+
+    assert!(md2htm(&home, &[], &["--standalone", "page.md"])
+        .status
+        .success());
+    assert!(md2htm(&home, &[], &["--standalone", "plain.md"])
+        .status
+        .success());
+
+    let page: String = fs::read_to_string(home.join("page.html")).unwrap();
+    assert!(page.contains("<title>A &lt;page&gt;</title>\n"), "{page}");
+    assert!(
+        page.contains("<meta property=\"og:image\" content=\"cover.png\">\n"),
+        "{page}"
+    );
+    assert!(
+        page.contains("<meta name=\"twitter:card\" content=\"summary_large_image\">\n"),
+        "{page}"
+    );
+    assert!(!page.contains("title:"), "{page}");
+
+    let plain: String = fs::read_to_string(home.join("plain.html")).unwrap();
+    assert!(plain.contains("<title>Heading</title>\n"), "{plain}");
+    assert!(
+        plain.contains("<meta name=\"description\" content=\"The first paragraph.\">\n"),
+        "{plain}"
+    );
+
+    let _ = fs::remove_dir_all(home);
+}
+
+#[test]
+fn injects_the_snippets_into_standalone_documents() {
+    let home: PathBuf = config_home("inject", "");
+    fs::write(home.join("doc.md"), "# Title\n").unwrap();
+    fs::write(
+        home.join("head.html"),
+        "<script src=\"highlight.js\"></script>",
+    )
+    .unwrap();
+    fs::write(home.join("body.html"), "<style>p {}</style>\n").unwrap();
+
+    let output: Output = md2htm(
+        &home,
+        &[],
+        &[
+            "--standalone",
+            "--inject-head",
+            "head.html",
+            "--inject-body",
+            "body.html",
+            "--nonce",
+            "abc",
+            "doc.md",
+        ],
+    );
+    assert!(output.status.success());
+
+    let html: String = fs::read_to_string(home.join("doc.html")).unwrap();
+    assert!(
+        html.contains("<script nonce=\"abc\" src=\"highlight.js\"></script>\n</head>"),
+        "{html}"
+    );
+    assert!(
+        html.contains("<style nonce=\"abc\">p {}</style>\n</body>"),
+        "{html}"
+    );
+
+    let _ = fs::remove_dir_all(home);
+}
+
+#[test]
+fn links_or_inlines_the_theme() {
+    let home: PathBuf = config_home("theme", "");
+    fs::write(home.join("doc.md"), "# Title\n").unwrap();
+
+    let output: Output = md2htm(&home, &[], &["--standalone", "--theme", "dark", "doc.md"]);
+    assert!(output.status.success());
+
+    let html: String = fs::read_to_string(home.join("doc.html")).unwrap();
+    assert!(
+        html.contains("<link rel=\"stylesheet\" href=\"md2htm-dark.css\">\n"),
+        "{html}"
+    );
+    let css: String = fs::read_to_string(home.join("md2htm-dark.css")).unwrap();
+    assert!(css.contains("div.code"), "{css}");
+
+    let output: Output = md2htm(
+        &home,
+        &[],
+        &[
+            "--standalone",
+            "--theme",
+            "print",
+            "--inline-theme",
+            "--class-prefix",
+            "md-",
+            "--nonce",
+            "abc",
+            "doc.md",
+        ],
+    );
+    assert!(output.status.success());
+
+    let html: String = fs::read_to_string(home.join("doc.html")).unwrap();
+    assert!(html.contains("<style nonce=\"abc\">\n"), "{html}");
+    assert!(html.contains(".md-anchor, .md-toc"), "{html}");
+    assert!(!html.contains("<link"), "{html}");
+
+    let output: Output = md2htm(&home, &[], &["--theme", "sepia", "doc.md"]);
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("--theme expects light, dark or print.")
+    );
+
+    let _ = fs::remove_dir_all(home);
+}
+
+#[test]
+fn writes_epub_chapters_with_their_navs() {
+    let home: PathBuf = config_home("epub", "");
+    fs::write(
+        home.join("chapter.md"),
+        "# One\n\ntext&nbsp;here\n\n## Two\n\n### Three\n\n## Four\n",
+    )
+    .unwrap();
+
+    let output: Output = md2htm(&home, &[], &["--epub", "--standalone", "chapter.md"]);
+    assert!(output.status.success());
+
+    let html: String = fs::read_to_string(home.join("chapter.xhtml")).unwrap();
+    assert!(
+        html.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n"),
+        "{html}"
+    );
+    assert!(html.contains("<meta charset=\"utf-8\" />"), "{html}");
+    assert!(html.contains("text&#160;here"), "{html}");
+
+    let nav: String = fs::read_to_string(home.join("chapter.nav.xhtml")).unwrap();
+    assert!(
+        nav.contains(
+            "<ol>\n<li><a href=\"chapter.xhtml#one\">One</a>\n<ol>\n\
+             <li><a href=\"chapter.xhtml#two\">Two</a>\n<ol>\n\
+             <li><a href=\"chapter.xhtml#three\">Three</a></li>\n</ol>\n</li>\n\
+             <li><a href=\"chapter.xhtml#four\">Four</a></li>\n</ol>\n</li>\n</ol>\n"
+        ),
+        "{nav}"
+    );
+    assert!(
+        nav.contains("<li><a epub:type=\"bodymatter\" href=\"chapter.xhtml\">One</a></li>"),
+        "{nav}"
+    );
+
+    let _ = fs::remove_dir_all(home);
+}
+
+#[test]
+fn puts_slides_in_the_reveal_containers() {
+    let home: PathBuf = config_home("slides", "");
+    fs::write(home.join("talk.md"), "# Talk\n\n## One\n").unwrap();
+
+    let output: Output = md2htm(&home, &[], &["--slides", "--standalone", "talk.md"]);
+    assert!(output.status.success());
+
+    let html: String = fs::read_to_string(home.join("talk.html")).unwrap();
+    assert!(
+        html.contains("<body>\n<div class=\"reveal\">\n<div class=\"slides\">\n<section class=\"slide\">\n<h1>Talk</h1>"),
+        "{html}"
+    );
+    assert!(
+        html.contains("</section>\n</div>\n</div>\n</body>"),
+        "{html}"
+    );
+
+    let _ = fs::remove_dir_all(home);
+}
+
+#[test]
+fn refuses_binary_files_unless_forced() {
+    let home: PathBuf = config_home("binary", "");
+    fs::write(home.join("image.md"), b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+
+    let output: Output = md2htm(&home, &[], &["image.md"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("looks like a binary file"),
+        "{output:?}"
+    );
+    assert!(!home.join("image.html").exists());
+
+    let output: Output = md2htm(&home, &[], &["--force-binary", "image.md"]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(home.join("image.html").exists());
+
+    let _ = fs::remove_dir_all(home);
+}
+
+#[test]
+fn writes_the_line_endings_asked_for() {
+    let home: PathBuf = config_home("eol", "");
+    fs::write(home.join("doc.md"), "# Title\r\n\r\ntext\n").unwrap();
+
+    let output: Output = md2htm(&home, &[], &["doc.md"]);
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read(home.join("doc.html")).unwrap(),
+        b"<h1>Title</h1>\r\n\r\n<p>text</p>\n"
+    );
+
+    let output: Output = md2htm(&home, &[], &["--eol", "crlf", "doc.md"]);
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read(home.join("doc.html")).unwrap(),
+        b"<h1>Title</h1>\r\n\r\n<p>text</p>\r\n"
+    );
+
+    let output: Output = md2htm(&home, &[("MD2HTM_EOL", "lf")], &["doc.md"]);
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read(home.join("doc.html")).unwrap(),
+        b"<h1>Title</h1>\n\n<p>text</p>\n"
+    );
+
+    let _ = fs::remove_dir_all(home);
+}
+
+#[test]
+fn pragmas_cant_enable_raw_fences_in_safe_mode() {
+    let home: PathBuf = config_home("raw-pragma", "");
+    fs::write(
+        home.join("doc.md"),
+        "<!-- md2htm: enable = \"raw-fence\" -->\n```html\n<img src=x onerror=alert(1)>\n```\n",
+    )
+    .unwrap();
+
+    let output: Output = md2htm(&home, &[], &["--safe", "doc.md"]);
+    let error: String = String::from_utf8(output.stderr).unwrap();
+    let html: String = fs::read_to_string(home.join("doc.html")).unwrap();
+
+    assert!(
+        error.contains("can't enable raw-fence in safe mode"),
+        "{error}"
+    );
+    assert!(!html.contains("<img"), "{html}");
+    assert!(html.contains("&lt;img"), "{html}");
+
+    fs::remove_dir_all(home).unwrap();
+}
+
+#[test]
+fn comment_markers_arent_pragmas() {
+    let home: PathBuf = config_home("off-marker", "");
+    fs::write(
+        home.join("doc.md"),
+        "<!-- md2htm:off -->\ndraft\n<!-- md2htm:on -->\ntext\n",
+    )
+    .unwrap();
+
+    let output: Output = md2htm(&home, &[], &["--enable", "comments", "doc.md"]);
+    let error: String = String::from_utf8(output.stderr).unwrap();
+    let html: String = fs::read_to_string(home.join("doc.html")).unwrap();
+
+    assert!(!error.contains("pragma"), "{error}");
+    assert!(!html.contains("draft"), "{html}");
+    assert!(html.contains("text"), "{html}");
+
+    fs::remove_dir_all(home).unwrap();
+}
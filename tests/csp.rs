@@ -0,0 +1,67 @@
+use md2htm::{Parser, ParserBuilder};
+
+fn csp(markdown: &str) -> String {
+    let parser: Parser = Parser::new(ParserBuilder::new().csp(true).build());
+    String::from_utf8(parser.parse(markdown.as_bytes()).html).unwrap()
+}
+
+#[test]
+fn removes_inline_styles_and_scripts_of_raw_html() {
+    assert_eq!(
+        csp("<span style=\"color: red\" class=\"x\" onclick='go()'>text</span>\n"),
+        "<p><span class=\"x\">text</span></p>\n"
+    );
+    assert_eq!(
+        csp("a <script>alert(\"<b>\")</script> b <STYLE>p {}</STYLE> c\n"),
+        "<p>a  b  c</p>\n"
+    );
+    assert_eq!(
+        csp("<a href=\" javascript:go()\" title=\"a > b\">x</a>\n"),
+        "<p><a title=\"a > b\">x</a></p>\n"
+    );
+}
+
+#[test]
+fn decodes_character_references_of_urls() {
+    assert_eq!(
+        csp("<a href=\"java&#x73;cript:alert(1)\">x</a>\n"),
+        "<p><a>x</a></p>\n"
+    );
+    assert_eq!(
+        csp("<a href=\"&#106avascript&colon;go()\" title=t>x</a>\n"),
+        "<p><a title=t>x</a></p>\n"
+    );
+    assert_eq!(
+        csp("<a href=\"java&Tab;script:go()\">x</a>\n"),
+        "<p><a>x</a></p>\n"
+    );
+    assert_eq!(
+        csp("<a href=\"&#x6a;ava.html\">x</a>\n"),
+        "<p><a href=\"&#x6a;ava.html\">x</a></p>\n"
+    );
+}
+
+#[test]
+fn removes_the_values_of_the_removed_attributes() {
+    assert_eq!(
+        csp("<img src=x onerror = \"alert(1)\">\n"),
+        "<p><img src=x></p>\n"
+    );
+    assert_eq!(
+        csp("<span style= 'color: red' class=x>text</span>\n"),
+        "<p><span class=x>text</span></p>\n"
+    );
+    assert_eq!(
+        csp("<input disabled onfocus =go() >\n"),
+        "<p><input disabled ></p>\n"
+    );
+}
+
+#[test]
+fn keeps_the_output_of_the_parser() {
+    let markdown: &str = "# Title\n\n*a* `b < c` [d](e.html) ![f](g.png)\n\n- h\n";
+    assert_eq!(
+        csp(markdown),
+        String::from_utf8(Parser::default().parse(markdown.as_bytes()).html).unwrap()
+    );
+}
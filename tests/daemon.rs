@@ -0,0 +1,527 @@
+mod common;
+
+use common::{receive, send, Daemon, PS};
+use std::{
+    env, fs,
+    io::{Read, Write},
+    net::Shutdown,
+    os::{
+        fd::OwnedFd,
+        unix::net::{UnixDatagram, UnixListener, UnixStream},
+    },
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    thread::{self, sleep},
+    time::Duration,
+};
+
+const DOC: &[u8] = b"# Title\n\nSome *italic* and **bold** text.\n";
+const HTML: &[u8] = b"<h1>Title</h1>\n\n<p>Some <i>italic</i> and <b>bold</b> text.</p>\n";
+
+#[test]
+fn round_trips_a_multi_megabyte_document() {
+    let daemon: Daemon = Daemon::start();
+    let line: &[u8] = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit\n\n";
+    let expected_line: &[u8] =
+        b"<p>Lorem ipsum dolor sit amet, consectetur adipiscing elit</p>\n\n";
+    let repeats: usize = (4 << 20) / line.len();
+
+    let html: Vec<u8> = daemon.convert(&line.repeat(repeats));
+
+    assert_eq!(html.len(), expected_line.len() * repeats);
+    assert!(html.chunks(expected_line.len()).all(|c| c == expected_line));
+}
+
+#[test]
+fn serves_concurrent_clients() {
+    let daemon: Daemon = Daemon::start();
+
+    thread::scope(|s| {
+        let handles: Vec<_> = (0..16)
+            .map(|i| {
+                let daemon: &Daemon = &daemon;
+                s.spawn(move || {
+                    let markdown: String = format!("Client number {i}\n");
+                    let html: Vec<u8> = daemon.convert(markdown.as_bytes());
+                    assert_eq!(html, format!("<p>Client number {i}</p>\n").as_bytes());
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    });
+}
+
+#[test]
+fn answers_an_empty_message_with_an_empty_response() {
+    let daemon: Daemon = Daemon::start();
+    assert_eq!(daemon.convert(b""), b"");
+}
+
+#[test]
+fn prefixes_the_response_with_its_big_endian_length() {
+    let daemon: Daemon = Daemon::start();
+    let mut stream: UnixStream = daemon.connect();
+    send(&mut stream, DOC).unwrap();
+
+    let mut response: Vec<u8> = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+
+    assert_eq!(&response[..PS], &HTML.len().to_be_bytes());
+    assert_eq!(&response[PS..], HTML);
+}
+
+#[test]
+fn survives_clients_that_hang_up_early() {
+    let daemon: Daemon = Daemon::start();
+
+    // Only a part of the length prefix
+    let mut stream: UnixStream = daemon.connect();
+    stream.write_all(&[0, 0]).unwrap();
+    stream.shutdown(Shutdown::Both).unwrap();
+
+    // A length prefix promising more than what is sent
+    let mut stream: UnixStream = daemon.connect();
+    stream.write_all(&1024usize.to_be_bytes()).unwrap();
+    stream.write_all(b"# Cut").unwrap();
+    stream.shutdown(Shutdown::Both).unwrap();
+
+    // A client that doesn't wait for the answer
+    let mut stream: UnixStream = daemon.connect();
+    send(&mut stream, DOC).unwrap();
+    drop(stream);
+
+    assert_eq!(daemon.convert(DOC), HTML);
+}
+
+#[test]
+fn restarts_over_a_stale_socket() {
+    let mut daemon: Daemon = Daemon::start();
+    assert_eq!(daemon.convert(DOC), HTML);
+
+    daemon.kill();
+    assert!(
+        daemon.sock.exists(),
+        "killing the daemon should leave the socket behind"
+    );
+    assert!(UnixStream::connect(&daemon.sock).is_err());
+
+    daemon.restart();
+    assert_eq!(daemon.convert(DOC), HTML);
+
+    let mut stream: UnixStream = daemon.connect();
+    send(&mut stream, DOC).unwrap();
+    assert_eq!(receive(&mut stream).unwrap(), HTML);
+}
+
+#[test]
+fn takes_its_settings_from_the_environment() {
+    let daemon: Daemon = Daemon::start_with_env(&[
+        ("MD2HTM_MAX_SIZE", "64"),
+        ("MD2HTM_WORKERS", "2"),
+        ("MD2HTM_LOG", "info"),
+    ]);
+    assert_eq!(daemon.convert(DOC), HTML);
+
+    // Messages over the limit are dropped without an answer
+    let mut stream: UnixStream = daemon.connect();
+    // The daemon may hang up before the whole message is written
+    let _ = send(&mut stream, &[b'a'; 65]);
+    let mut response: Vec<u8> = Vec::new();
+    let _ = stream.read_to_end(&mut response);
+    assert!(response.is_empty());
+
+    // More clients than workers wait for their turn
+    thread::scope(|s| {
+        for _ in 0..8 {
+            s.spawn(|| assert_eq!(daemon.convert(DOC), HTML));
+        }
+    });
+}
+
+#[test]
+fn prints_a_unit_running_the_daemon_with_the_options() {
+    let output = Command::new(env!("CARGO_BIN_EXE_md2htm"))
+        .args([
+            "--workers",
+            "4",
+            "daemon",
+            "--print-unit",
+            "/tmp/md2htm test.sock",
+        ])
+        .output()
+        .unwrap();
+    let unit: String = String::from_utf8(output.stdout).unwrap();
+
+    assert!(unit.starts_with("[Unit]\n"), "{unit}");
+    assert!(
+        unit.contains(" daemon --workers 4 \"/tmp/md2htm test.sock\"\n"),
+        "{unit}"
+    );
+    assert!(unit.contains("WantedBy=multi-user.target\n"), "{unit}");
+}
+
+#[test]
+fn listens_a_socket_handed_over_to_it() {
+    let sock: PathBuf =
+        env::temp_dir().join(format!("md2htm-{}-inherited.sock", std::process::id()));
+    let unused: PathBuf = sock.with_extension("unused");
+    let _ = fs::remove_file(&sock);
+    let listener: UnixListener = UnixListener::bind(&sock).unwrap();
+
+    // The listener goes in as stdin, which is fd 0
+    let mut daemon: Child = Command::new(env!("CARGO_BIN_EXE_md2htm"))
+        .arg("daemon")
+        .arg(&unused)
+        .env("MD2HTM_LISTEN_FD", "0")
+        .stdin(Stdio::from(OwnedFd::from(listener)))
+        .spawn()
+        .unwrap();
+
+    // The connection waits in the backlog until the daemon accepts it
+    let mut stream: UnixStream = UnixStream::connect(&sock).unwrap();
+    send(&mut stream, DOC).unwrap();
+    let html: Vec<u8> = receive(&mut stream).unwrap();
+
+    daemon.kill().unwrap();
+    daemon.wait().unwrap();
+    fs::remove_file(&sock).unwrap();
+
+    assert_eq!(html, HTML);
+    assert!(!unused.exists(), "the daemon shouldn't bind its own socket");
+}
+
+/// Sends a request with options before the markdown
+fn convert_with_options(daemon: &Daemon, options: &str, markdown: &[u8]) -> Vec<u8> {
+    let mut stream: UnixStream = daemon.connect();
+    let len: usize = options.len() | 1 << (usize::BITS - 1);
+    stream.write_all(&len.to_be_bytes()).unwrap();
+    stream.write_all(options.as_bytes()).unwrap();
+    send(&mut stream, markdown).unwrap();
+
+    let mut response: Vec<u8> = Vec::new();
+    let _ = stream.read_to_end(&mut response);
+    response.get(PS..).unwrap_or_default().to_vec()
+}
+
+#[test]
+fn takes_options_with_the_request() {
+    let daemon: Daemon = Daemon::start();
+    let options: &str = "semantic = true\nbase-url = \"https://example.com/docs/\"\n";
+    let html: Vec<u8> = convert_with_options(&daemon, options, b"*a* [b](b.html)\n");
+
+    assert_eq!(
+        html,
+        b"<p><em>a</em> <a href=\"https://example.com/docs/b.html\">b</a></p>\n"
+    );
+
+    let html: Vec<u8> = convert_with_options(&daemon, "standalone = true", DOC);
+    assert!(html.starts_with(b"<!DOCTYPE html>\n"));
+
+    // The options of one request don't stick to the next
+    assert_eq!(daemon.convert(DOC), HTML);
+}
+
+/// Reads the chunks of the answer until the empty one ending it
+fn receive_chunks(stream: &mut UnixStream) -> Vec<Vec<u8>> {
+    let mut chunks: Vec<Vec<u8>> = Vec::new();
+
+    loop {
+        let chunk: Vec<u8> = receive(stream).unwrap();
+
+        if chunk.is_empty() {
+            return chunks;
+        }
+
+        chunks.push(chunk);
+    }
+}
+
+#[test]
+fn sends_large_documents_in_chunks() {
+    let daemon: Daemon = Daemon::start();
+    let markdown: Vec<u8> = [
+        b"# Title\n\nSome *italic* text.\n\n".as_slice(),
+        b"- one\n- two\n\n```\ncode\n\nmore\n```\n\n",
+    ]
+    .concat()
+    .repeat(10_000);
+    let options: &str = "chunked = true";
+
+    let mut stream: UnixStream = daemon.connect();
+    let len: usize = options.len() | 1 << (usize::BITS - 1);
+    stream.write_all(&len.to_be_bytes()).unwrap();
+    stream.write_all(options.as_bytes()).unwrap();
+    send(&mut stream, &markdown).unwrap();
+    let chunks: Vec<Vec<u8>> = receive_chunks(&mut stream);
+
+    assert!(chunks.len() > 1, "{} chunks", chunks.len());
+    assert_eq!(chunks.concat(), daemon.convert(&markdown));
+
+    // The root tags need the whole document, so it comes in one chunk
+    let mut stream: UnixStream = daemon.connect();
+    let options: &str = "chunked = true\nstandalone = true";
+    let len: usize = options.len() | 1 << (usize::BITS - 1);
+    stream.write_all(&len.to_be_bytes()).unwrap();
+    stream.write_all(options.as_bytes()).unwrap();
+    send(&mut stream, DOC).unwrap();
+    let chunks: Vec<Vec<u8>> = receive_chunks(&mut stream);
+
+    assert_eq!(chunks.len(), 1);
+    assert!(chunks[0].starts_with(b"<!DOCTYPE html>\n"));
+}
+
+#[test]
+fn answers_datagrams() {
+    let dir: PathBuf = env::temp_dir();
+    let socket: PathBuf = dir.join(format!("md2htm-test-{}-datagram.sock", std::process::id()));
+    let own: PathBuf = dir.join(format!("md2htm-test-{}-client.sock", std::process::id()));
+    let _daemon: Daemon = Daemon::start_with_env(&[("MD2HTM_DATAGRAM", socket.to_str().unwrap())]);
+
+    let _ = fs::remove_file(&own);
+    let client: UnixDatagram = UnixDatagram::bind(&own).unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    let mut answer: Vec<u8> = vec![0; 1 << 20];
+
+    let request: Vec<u8> = [&DOC.len().to_be_bytes(), DOC].concat();
+    client.send_to(&request, &socket).unwrap();
+    let len: usize = client.recv(&mut answer).unwrap();
+    assert_eq!(&answer[..len], [&HTML.len().to_be_bytes(), HTML].concat());
+
+    let options: &[u8] = b"semantic = true";
+    let request: Vec<u8> = [
+        &(options.len() | 1 << (usize::BITS - 1)).to_be_bytes(),
+        options,
+        &DOC.len().to_be_bytes(),
+        DOC,
+    ]
+    .concat();
+    client.send_to(&request, &socket).unwrap();
+    let len: usize = client.recv(&mut answer).unwrap();
+    assert!(answer[PS..len].starts_with(b"<h1>Title</h1>\n\n<p>Some <em>italic</em>"));
+
+    // A length claiming more than the datagram holds gets an empty answer
+    client
+        .send_to(&(1usize << 20).to_be_bytes(), &socket)
+        .unwrap();
+    assert_eq!(client.recv(&mut answer).unwrap(), 0);
+
+    drop(client);
+    fs::remove_file(own).unwrap();
+    // The daemon is killed without a chance to clean up
+    let _ = fs::remove_file(socket);
+}
+
+#[test]
+fn logs_the_requests_and_rotates_the_log() {
+    let log: PathBuf =
+        env::temp_dir().join(format!("md2htm-test-{}-access.log", std::process::id()));
+    let rotated: PathBuf = log.with_extension("log.1");
+    let _ = fs::remove_file(&log);
+    let _ = fs::remove_file(&rotated);
+    let daemon: Daemon = Daemon::start_with_env(&[
+        ("MD2HTM_ACCESS_LOG", log.to_str().unwrap()),
+        ("MD2HTM_ACCESS_LOG_SIZE", "300"),
+    ]);
+
+    for _ in 0..4 {
+        assert_eq!(daemon.convert(DOC), HTML);
+    }
+
+    assert!(convert_with_options(&daemon, "safe = false", DOC).is_empty());
+
+    // The line is written after the answer
+    let mut lines: Vec<String> = Vec::new();
+
+    for _ in 0..100 {
+        lines = [&rotated, &log]
+            .iter()
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .flat_map(|text| text.lines().map(String::from).collect::<Vec<_>>())
+            .collect();
+
+        // The first one is the connection checking that the daemon listens
+        if lines.len() == 6 {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    assert_eq!(lines.len(), 6, "{lines:?}");
+    assert!(fs::metadata(&log).unwrap().len() <= 300);
+    assert!(lines[1].contains(&format!(" - in={} out={} ", DOC.len(), HTML.len())));
+    assert!(lines[1].ends_with("ms status=ok"), "{}", lines[1]);
+    assert!(lines[5].ends_with("ms status=dropped"), "{}", lines[5]);
+
+    let _ = fs::remove_file(log);
+    let _ = fs::remove_file(rotated);
+}
+
+#[test]
+fn refuses_options_loosening_its_limits() {
+    let daemon: Daemon = Daemon::start();
+
+    assert!(convert_with_options(&daemon, "max-output = 1000000", DOC).is_empty());
+    assert!(convert_with_options(&daemon, "safe = false", DOC).is_empty());
+    assert!(convert_with_options(&daemon, "dialect = \"unknown\"", DOC).is_empty());
+    assert_eq!(convert_with_options(&daemon, "safe = true", DOC), HTML);
+}
+
+#[test]
+fn refuses_raw_fences_in_safe_mode() {
+    let daemon: Daemon = Daemon::start_with_env(&[("MD2HTM_SAFE", "true")]);
+    let markdown: &[u8] = b"```html\n<img src=x onerror=alert(1)>\n```\n";
+
+    assert!(convert_with_options(&daemon, "enable = \"raw-fence\"", markdown).is_empty());
+    assert!(!daemon.convert(markdown).windows(4).any(|w| w == b"<img"));
+}
+
+#[test]
+fn reports_the_throughput_of_a_daemon() {
+    let daemon: Daemon = Daemon::start();
+    let sample: PathBuf =
+        env::temp_dir().join(format!("md2htm-loadtest-{}.md", std::process::id()));
+    fs::write(&sample, "# Title\n\nSome *words*.\n").unwrap();
+
+    let output: std::process::Output = Command::new(env!("CARGO_BIN_EXE_md2htm"))
+        .arg("loadtest")
+        .arg("--socket")
+        .arg(&daemon.sock)
+        .args(["--file", sample.to_str().unwrap()])
+        .args(["--clients", "2", "--duration", "0.5"])
+        .env("LANG", "C")
+        .output()
+        .unwrap();
+    let _ = fs::remove_file(&sample);
+    let printed: String = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success(), "{printed}");
+    assert!(printed.contains(" clients.\n"), "{printed}");
+    assert!(
+        printed.contains(" requests per second, 0 failed.\n"),
+        "{printed}"
+    );
+    assert!(printed.contains("Latency: p50 "), "{printed}");
+}
+
+#[test]
+fn fails_without_a_daemon() {
+    let output: std::process::Output = Command::new(env!("CARGO_BIN_EXE_md2htm"))
+        .args(["loadtest", "--socket", "/nonexistent/md2htm.sock"])
+        .args(["--file", "readme.md"])
+        .env("LANG", "C")
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Couldn't connect to "));
+}
+
+/// Answer of a connection that is read before sending the request
+fn busy(daemon: &Daemon) -> Vec<u8> {
+    let mut stream: UnixStream = daemon.connect();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let mut answer: Vec<u8> = Vec::new();
+    let _ = stream.read_to_end(&mut answer);
+    answer
+}
+
+#[test]
+fn answers_busy_over_the_connection_limit() {
+    let mut frame: Vec<u8> = (4usize | 1 << (usize::BITS - 1)).to_be_bytes().to_vec();
+    frame.extend_from_slice(b"busy");
+
+    for env in [
+        &[("MD2HTM_MAX_CONNECTIONS", "1")][..],
+        &[("MD2HTM_WORKERS", "1"), ("MD2HTM_MAX_CONNECTIONS", "1")],
+    ] {
+        let daemon: Daemon = Daemon::start_with_env(env);
+        sleep(Duration::from_millis(100));
+
+        // Waits for the request, holding the only place
+        let held: UnixStream = daemon.connect();
+        sleep(Duration::from_millis(100));
+        assert_eq!(busy(&daemon), frame, "{env:?}");
+
+        drop(held);
+        sleep(Duration::from_millis(100));
+        assert_eq!(daemon.convert(DOC), HTML, "{env:?}");
+    }
+}
+
+#[test]
+fn refuses_binary_markdown_unless_forced() {
+    let mut frame: Vec<u8> = (6usize | 1 << (usize::BITS - 1)).to_be_bytes().to_vec();
+    frame.extend_from_slice(b"binary");
+
+    let daemon: Daemon = Daemon::start();
+    let mut stream: UnixStream = daemon.connect();
+    send(&mut stream, b"text\0with a NUL\n").unwrap();
+    let mut answer: Vec<u8> = Vec::new();
+    let _ = stream.read_to_end(&mut answer);
+    assert_eq!(answer, frame);
+
+    let html: Vec<u8> = convert_with_options(&daemon, "force-binary = true", b"a\0b\n");
+    assert_eq!(html, b"<p>a\0b</p>\n");
+}
+
+#[test]
+fn closes_idle_connections() {
+    let daemon: Daemon = Daemon::start_with_env(&[
+        ("MD2HTM_IDLE_TIMEOUT", "0.2"),
+        ("MD2HTM_MAX_CONNECTIONS", "1"),
+    ]);
+    sleep(Duration::from_millis(100));
+
+    // Half a request, and then nothing
+    let mut stream: UnixStream = daemon.connect();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    stream.write_all(&DOC.len().to_be_bytes()).unwrap();
+
+    let mut response: Vec<u8> = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    assert!(response.is_empty());
+
+    // The place of the closed connection is free again
+    sleep(Duration::from_millis(50));
+    assert_eq!(daemon.convert(DOC), HTML);
+}
+
+#[test]
+fn tells_its_status() {
+    let daemon: Daemon = Daemon::start();
+    assert_eq!(daemon.convert(DOC), HTML);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_md2htm"))
+        .args(["status", "--socket"])
+        .arg(&daemon.sock)
+        .output()
+        .unwrap();
+    let status: String = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success(), "{status}");
+    assert!(status.contains("\nanswered-requests 1\n"), "{status}");
+    assert!(status.contains("\nopen-fds "), "{status}");
+    assert!(status.contains("\nrss-bytes "), "{status}");
+}
+
+#[test]
+fn survives_claimed_lengths_it_could_not_allocate() {
+    let daemon: Daemon = Daemon::start();
+
+    let mut stream: UnixStream = daemon.connect();
+    stream.write_all(&(1usize << 48).to_be_bytes()).unwrap();
+    stream.write_all(DOC).unwrap();
+    drop(stream);
+
+    assert_eq!(daemon.convert(DOC), HTML);
+}
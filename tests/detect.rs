@@ -0,0 +1,27 @@
+use md2htm::{detect_dialect, Dialect};
+
+#[test]
+fn detects_the_dialect_from_its_own_syntax() {
+    assert_eq!(
+        detect_dialect(b"| a | b |\n|---|:-:|\n| 1 | 2 |\n"),
+        Dialect::Gfm
+    );
+    assert_eq!(detect_dialect(b"Some ~~old~~ text\n"), Dialect::Gfm);
+    assert_eq!(detect_dialect(b"- [x] done\n- [ ] not\n"), Dialect::Gfm);
+    assert_eq!(
+        detect_dialect(b"Title\n=====\n\nText\n"),
+        Dialect::CommonMark
+    );
+    assert_eq!(
+        detect_dialect(b"# Title\n\n*text* and - a\n\n---\n"),
+        Dialect::Legacy
+    );
+}
+
+#[test]
+fn ignores_the_syntax_in_code_blocks() {
+    assert_eq!(
+        detect_dialect(b"```\n| a |\n|---|\n~~x~~\n```\n"),
+        Dialect::Legacy
+    );
+}
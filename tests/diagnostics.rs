@@ -0,0 +1,129 @@
+use md2htm::{Code, Diagnostic, Level, Output, Parser, ParserBuilder, Position, Severity, Utf8};
+
+fn diagnostics(builder: ParserBuilder, markdown: &str) -> Vec<Diagnostic> {
+    Parser::new(builder.build())
+        .parse(markdown.as_bytes())
+        .diagnostics
+}
+
+#[test]
+fn codes_are_reported() {
+    let found: Vec<Diagnostic> = diagnostics(ParserBuilder::new(), "#\n\ntext\n");
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].code, Code::EmptyHeader);
+    assert_eq!(found[0].severity, Severity::Warning);
+    assert!(found[0].to_string().ends_with("[empty-header]"));
+}
+
+#[test]
+fn allowed_codes_are_left_out() {
+    let builder: ParserBuilder = ParserBuilder::new().level(Code::EmptyHeader, Level::Allow);
+    assert!(diagnostics(builder, "#\n\ntext\n").is_empty());
+}
+
+#[test]
+fn denied_codes_are_errors() {
+    let builder: ParserBuilder = ParserBuilder::new()
+        .a11y(true)
+        .level(Code::MissingAlt, Level::Deny);
+    let found: Vec<Diagnostic> = diagnostics(builder, "#\n\n![](cat.png)\n");
+
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].severity, Severity::Warning);
+    assert_eq!(found[1].code, Code::MissingAlt);
+    assert_eq!(found[1].severity, Severity::Error);
+}
+
+#[test]
+fn the_last_level_wins() {
+    let builder: ParserBuilder = ParserBuilder::new()
+        .level(Code::EmptyHeader, Level::Deny)
+        .level(Code::EmptyHeader, Level::Warn);
+    let found: Vec<Diagnostic> = diagnostics(builder, "#\n");
+
+    assert_eq!(found[0].severity, Severity::Warning);
+}
+
+#[test]
+fn names_round_trip() {
+    for &code in Code::ALL {
+        assert_eq!(Code::from_name(code.name()), Some(code));
+    }
+}
+
+#[test]
+fn invalid_utf8_is_replaced_or_rejected() {
+    let markdown: &[u8] = b"# a\xff b\n";
+    let parse =
+        |utf8: Utf8| Parser::new(ParserBuilder::new().invalid_utf8(utf8).build()).parse(markdown);
+
+    let replaced: Output = parse(Utf8::Replace);
+    assert_eq!(replaced.html, "<h1>a\u{FFFD} b</h1>\n".as_bytes());
+    assert_eq!(replaced.diagnostics[0].code, Code::InvalidUtf8);
+    assert_eq!(replaced.diagnostics[0].severity, Severity::Warning);
+    assert_eq!(replaced.diagnostics[0].position.offset, 3);
+
+    let rejected: Output = parse(Utf8::Reject);
+    assert!(rejected.html.is_empty());
+    assert_eq!(rejected.diagnostics[0].severity, Severity::Error);
+
+    assert_eq!(parse(Utf8::Keep).html, b"<h1>a\xff b</h1>\n");
+}
+
+#[test]
+fn long_links_are_cut_short() {
+    let parser: Parser = Parser::new(ParserBuilder::new().max_link_length(8).build());
+    let output: Output = parser.parse(b"[long text here](https://example.com)\n");
+
+    assert_eq!(output.html, b"<a href=\"https://\">long tex</a>\n");
+    assert_eq!(output.diagnostics.len(), 1);
+    assert_eq!(output.diagnostics[0].code, Code::LinkTruncated);
+    assert_eq!(output.diagnostics[0].position.offset, 9);
+
+    // Control characters never get into the attributes
+    let output: Output = Parser::default().parse(b"![a\x00b](c\x1bd.png)\n");
+    assert_eq!(output.html, b"<img src=\"cd.png\" alt=\"ab\">\n");
+    assert!(output.diagnostics.is_empty());
+}
+
+#[test]
+fn duplicate_ids_get_the_next_free_number() {
+    let builder = || {
+        ParserBuilder::new()
+            .heading_ids(true)
+            .warn_duplicate_ids(true)
+    };
+    let markdown: &str = "# Setup\n\n# Setup\n\n# Setup 1\n\n# Setup\n";
+    let output: Output = Parser::new(builder().build()).parse(markdown.as_bytes());
+    let html: String = String::from_utf8(output.html).unwrap();
+
+    for id in ["setup", "setup-1", "setup-1-1", "setup-2"] {
+        assert!(html.contains(&format!("id=\"{id}\"")), "{id} in {html}");
+    }
+    assert_eq!(output.diagnostics.len(), 3);
+    assert!(output
+        .diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.code == Code::DuplicateId));
+
+    let many: String = "# a\n\n".repeat(20_000);
+    let html: Vec<u8> = Parser::new(builder().build()).parse(many.as_bytes()).html;
+    assert!(String::from_utf8(html).unwrap().contains("id=\"a-19999\""));
+}
+
+#[test]
+fn columns_count_characters_and_tab_stops() {
+    let parser: Parser = Parser::new(ParserBuilder::new().max_link_length(8).build());
+    let output: Output = parser.parse("a\r\né\t[long text here](https://example.com)\n".as_bytes());
+
+    // `é` is one column of two bytes and the tab goes to the column 5
+    assert_eq!(
+        output.diagnostics[0].position,
+        Position {
+            line: 2,
+            column: 14,
+            offset: 15,
+        }
+    );
+}
@@ -0,0 +1,88 @@
+use md2htm::{CodeBlocks, Dialect, Indented, Parser, ParserBuilder};
+
+/// The characters escaped one at a time
+fn escaped(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[test]
+fn code_is_escaped_wherever_the_characters_are() {
+    let parser: Parser = Parser::new(
+        ParserBuilder::new()
+            .code_blocks(CodeBlocks::Pre)
+            .indented(Indented::Code)
+            .build(),
+    );
+
+    // Lines of many lengths with the characters at the starts, in the
+    // middles and at the ends of the blocks of 16 bytes
+    for len in 0..48 {
+        for at in (0..len).step_by(5) {
+            let mut line: Vec<u8> = b"x".repeat(len);
+            line[at] = b"<>&\""[at % 4];
+            let line: String = String::from_utf8(line).unwrap();
+
+            let fenced: Vec<u8> = parser.parse(format!("```\n{line}\n```\n").as_bytes()).html;
+            assert_eq!(
+                String::from_utf8(fenced).unwrap(),
+                format!("<pre><code>{}</code></pre>\n", escaped(&line))
+            );
+
+            let indented: Vec<u8> = parser.parse(format!("    {line}\n").as_bytes()).html;
+            assert_eq!(
+                String::from_utf8(indented).unwrap(),
+                format!("<pre><code>{}</code></pre>\n", escaped(&line))
+            );
+        }
+    }
+}
+
+#[test]
+fn urls_are_percent_encoded_once() {
+    let markdown: &[u8] =
+        "See [a](my file.html?q=ä%20&r=%zz \"x\") ![my cat](p q.png)\n".as_bytes();
+
+    let parser: Parser = Parser::new(ParserBuilder::new().encode_urls(true).build());
+    assert_eq!(
+        String::from_utf8(parser.parse(markdown).html).unwrap(),
+        "<p>See <a href=\"my%20file.html?q=%C3%A4%20&r=%25zz\" title=\"x\">a</a> \
+         <img src=\"p%20q.png\" alt=\"my cat\"></p>\n"
+    );
+
+    let parser: Parser = Parser::new(ParserBuilder::new().dialect(Dialect::Gfm).build());
+    assert!(String::from_utf8(parser.parse(markdown).html)
+        .unwrap()
+        .contains("<img src=\"p%20q.png\""));
+
+    // The legacy output writes them as they were
+    assert!(String::from_utf8(Parser::default().parse(markdown).html)
+        .unwrap()
+        .contains("<img src=\"p q.png\""));
+}
+
+#[test]
+fn hosts_can_be_written_in_punycode() {
+    let markdown: &[u8] =
+        "See [a](https://user@Bücher.example:8080/straße) [b](http://例え.テスト/)\n".as_bytes();
+    let parser: Parser = Parser::new(
+        ParserBuilder::new()
+            .encode_urls(true)
+            .punycode_hosts(true)
+            .build(),
+    );
+
+    assert_eq!(
+        String::from_utf8(parser.parse(markdown).html).unwrap(),
+        "<p>See <a href=\"https://user@xn--bcher-kva.example:8080/stra%C3%9Fe\">a</a> \
+         <a href=\"http://xn--r8jz45g.xn--zckzah/\">b</a></p>\n"
+    );
+
+    // Without it the host is percent-encoded like the rest
+    let parser: Parser = Parser::new(ParserBuilder::new().encode_urls(true).build());
+    assert!(String::from_utf8(parser.parse(markdown).html)
+        .unwrap()
+        .contains("https://user@B%C3%BCcher.example:8080/"));
+}
@@ -0,0 +1,19 @@
+use md2htm::{estimate_output, Parser};
+
+#[test]
+fn estimate_covers_link_heavy_documents() {
+    let parser: Parser = Parser::default();
+
+    for markdown in [
+        b"[a](b) [c](d) `x` `y`\n\n".repeat(1000),
+        b"Plain text only here.\n".repeat(1000),
+        b"# Title\n\nSome *words* and [a link](x.html).\n\n- one\n\n```\ncode\n```\n\n"
+            .repeat(1000),
+    ] {
+        let html: usize = parser.parse(&markdown).html.len();
+        let estimate: usize = estimate_output(&markdown);
+
+        assert!(estimate >= html, "{estimate} is less than {html}");
+        assert!(estimate < html * 2, "{estimate} is far more than {html}");
+    }
+}
@@ -0,0 +1,164 @@
+use md2htm::{push_html, Dialect, Event, Ext, Kind, Output, Parser, ParserBuilder, Permalink};
+use std::fs;
+
+fn round_trip(output: &Output) -> Vec<u8> {
+    let mut written: Vec<u8> = Vec::new();
+    push_html(&mut written, output.html_events());
+    written
+}
+
+#[test]
+fn writes_back_the_same_html() {
+    for entry in fs::read_dir("tests/legacy").unwrap() {
+        let path = entry.unwrap().path();
+
+        if path.extension().is_some_and(|extension| extension == "md") {
+            let markdown: Vec<u8> = fs::read(&path).unwrap();
+
+            for dialect in [Dialect::Legacy, Dialect::Gfm] {
+                let parser: Parser = Parser::new(
+                    ParserBuilder::new()
+                        .dialect(dialect)
+                        .heading_ids(true)
+                        .sourcepos(true)
+                        .build(),
+                );
+                let output: Output = parser.parse(&markdown);
+                assert_eq!(
+                    String::from_utf8_lossy(&round_trip(&output)),
+                    String::from_utf8_lossy(&output.html),
+                    "{}",
+                    path.display()
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn marks_every_tag_the_parser_writes() {
+    let markdown: &[u8] = b"[TOC]\n\n# Title\n\nSee [a](http://x.y) <b>\n\n## Second\n\n| a | b |\n|:--|--:|\n| *x* | `y` |\n| [l](m) | ![i](j) |\n\n3. one\n5. two\n\n> quote *x*\n> - item\n>   > nested [z](q)\n\n---\n\n- item\n  continued\n\n  more\n\n```\ncode\n```\n";
+    let builders: [ParserBuilder; 3] = [
+        ParserBuilder::new()
+            .heading_ids(true)
+            .permalink(Permalink::Wrap)
+            .toc(true)
+            .sourcepos(true),
+        ParserBuilder::new()
+            .heading_ids(true)
+            .permalink(Permalink::Anchor)
+            .print(true)
+            .slides(true)
+            .xhtml(true),
+        ParserBuilder::new()
+            .wrap_paragraphs(false)
+            .csp(true)
+            .number_headings(true),
+    ];
+
+    for builder in builders {
+        let parser: Parser = Parser::new(builder.dialect(Dialect::Gfm).safe(true).build());
+        let output: Output = parser.parse(markdown);
+        assert_eq!(round_trip(&output), output.html);
+
+        // Safe mode escapes every `<` of the text
+        for event in output.html_events() {
+            if let Event::Text(text) = event {
+                assert!(!text.contains(&b'<'), "{:?}", String::from_utf8_lossy(text));
+            }
+        }
+    }
+}
+
+#[test]
+fn pairs_the_ends_with_their_starts() {
+    let output = Parser::default().parse(b"# Title\n\nA [link](a.html \"t\") and<br>\n");
+    let events: Vec<Event> = output.html_events().collect();
+
+    let link = events
+        .iter()
+        .find_map(|event| match event {
+            Event::Start(tag) if tag.kind() == Kind::Link => Some(tag),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(link.attribute("href"), Some("a.html"));
+    assert!(events.contains(&Event::End(link.clone())));
+
+    let starts: usize = events
+        .iter()
+        .filter(|e| matches!(e, Event::Start(_)))
+        .count();
+    let ends: usize = events.iter().filter(|e| matches!(e, Event::End(_))).count();
+    assert_eq!(starts, ends, "{events:?}");
+}
+
+#[test]
+fn collects_headings_from_the_events() {
+    let output = Parser::default().parse(b"# One\n\ntext\n\n## Two *x*\n");
+    let mut headings: Vec<(u8, String)> = Vec::new();
+    let mut level: Option<u8> = None;
+
+    for event in output.html_events() {
+        match event {
+            Event::Start(tag) => {
+                if let Kind::Heading(n) = tag.kind() {
+                    level = Some(n);
+                    headings.push((n, String::new()));
+                }
+            }
+            Event::End(tag) if matches!(tag.kind(), Kind::Heading(_)) => level = None,
+            Event::Text(text) if level.is_some() => headings
+                .last_mut()
+                .unwrap()
+                .1
+                .push_str(&String::from_utf8_lossy(text)),
+            _ => {}
+        }
+    }
+
+    assert_eq!(
+        headings,
+        [(1, String::from("One")), (2, String::from("Two x"))]
+    );
+}
+
+#[test]
+fn keeps_the_html_of_the_markdown_as_text() {
+    let output = Parser::default().parse(b"a < b <i>c</i>\n");
+    assert_eq!(round_trip(&output), output.html);
+
+    let events: Vec<Event> = output.html_events().collect();
+    assert_eq!(
+        events,
+        [
+            Event::Start(md2htm::Tag::new("p")),
+            Event::Text(b"a < b <i>c</i>"),
+            Event::End(md2htm::Tag::new("p")),
+            Event::Text(b"\n"),
+        ]
+    );
+
+    // The text is borrowed from the HTML
+    let Event::Text(text) = events[1] else {
+        panic!("{events:?}");
+    };
+    assert!(output.html.as_ptr_range().contains(&text.as_ptr()));
+}
+
+#[test]
+fn emits_raw_fences_as_html() {
+    let parser: Parser = Parser::new(ParserBuilder::new().extension(Ext::RawFence).build());
+    let output = parser.parse(b"```html\n<video src=\"a.mp4\"></video>\n```\n");
+    assert_eq!(round_trip(&output), output.html);
+
+    let events: Vec<Event> = output.html_events().collect();
+    assert!(
+        events.contains(&Event::Html(b"<video src=\"a.mp4\"></video>")),
+        "{events:?}"
+    );
+    assert!(
+        !events.iter().any(|e| matches!(e, Event::Start(_))),
+        "{events:?}"
+    );
+}
@@ -0,0 +1,232 @@
+use md2htm::{
+    BlockContext, Code, Diagnostic, Incremental, Level, Limits, MissingAlt, Output, Parser,
+    ParserBuilder,
+};
+
+const DOCUMENT: &str = "# Title\n\
+\n\
+First *paragraph* with a [link](https://example.com).\n\
+\n\
+- one\n\
+- two\n\
+\n\
+```\n\
+code\n\
+\n\
+more code\n\
+```\n\
+\n\
+![](image.png) without alt\n\
+\n\
+    indented\n\
+\n\
+Last **words**.\n";
+
+/// Applies the edits one after another and checks that every version comes
+/// out the same as when parsed from scratch
+fn check(parser: Parser, edits: &[(&str, &str)]) {
+    let mut document: Incremental = Incremental::new(parser.clone());
+    let mut text: String = DOCUMENT.to_string();
+    document.update(text.as_bytes());
+
+    for (from, to) in edits {
+        text = text.replacen(from, to, 1);
+        let output: &Output = document.update(text.as_bytes());
+        let expected: Output = parser.parse(text.as_bytes());
+
+        assert_eq!(
+            String::from_utf8_lossy(&output.html),
+            String::from_utf8_lossy(&expected.html),
+            "after replacing {from:?} with {to:?}"
+        );
+        assert_eq!(output.diagnostics, expected.diagnostics);
+    }
+}
+
+#[test]
+fn edits_in_one_block() {
+    check(
+        Parser::default(),
+        &[
+            ("First", "Very first"),
+            ("two", "two and a half"),
+            ("Last", "The last"),
+            ("Title", "New title"),
+        ],
+    );
+}
+
+#[test]
+fn edits_joining_and_splitting_blocks() {
+    check(
+        Parser::default(),
+        &[
+            ("link).\n\n", "link).\n"),
+            ("link).\n", "link).\n\n\n"),
+            ("- two\n\n", "- two\n"),
+            ("\n\nLast", "\nLast"),
+        ],
+    );
+}
+
+#[test]
+fn edits_opening_blocks() {
+    check(
+        Parser::default(),
+        &[
+            ("First *paragraph*", "First *paragraph"),
+            ("First *paragraph", "First *paragraph*"),
+            ("# Title\n", "# Title\n```\n"),
+            ("# Title\n```\n", "# Title\n"),
+            ("more code\n```", "more code\n"),
+        ],
+    );
+}
+
+#[test]
+fn diagnostics_move_with_the_text() {
+    let parser: Parser = Parser::new(ParserBuilder::new().missing_alt(MissingAlt::Warn).build());
+
+    check(
+        parser,
+        &[
+            ("First", "First\n\nAnother line\n\n"),
+            ("Another line\n\n", ""),
+            ("without alt", "still without alt"),
+        ],
+    );
+}
+
+#[test]
+fn allowed_diagnostics_stay_out() {
+    let parser: Parser = Parser::new(
+        ParserBuilder::new()
+            .missing_alt(MissingAlt::Warn)
+            .level(Code::LinkNewline, Level::Allow)
+            .build(),
+    );
+
+    check(
+        parser,
+        &[
+            ("First *paragraph*", "First [broken\nlink]"),
+            ("without alt", "still without alt"),
+            ("Last", "[Another\n](x) last"),
+        ],
+    );
+}
+
+#[test]
+fn chunks_add_up_to_the_whole_document() {
+    for piece in [1, 7, 20, 64, 1000] {
+        let mut html: Vec<u8> = Vec::new();
+        let mut chunks: usize = 0;
+
+        Parser::default()
+            .parse_chunked(
+                DOCUMENT.as_bytes(),
+                piece,
+                &Limits::default(),
+                &mut |chunk: &[u8]| {
+                    assert!(!chunk.is_empty());
+                    html.extend_from_slice(chunk);
+                    chunks += 1;
+                    true
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8_lossy(&html),
+            String::from_utf8_lossy(&Parser::default().parse(DOCUMENT.as_bytes()).html),
+            "in pieces of {piece} bytes"
+        );
+        assert!(chunks > 1 || piece >= DOCUMENT.len(), "{chunks} chunks");
+    }
+}
+
+/// Reader giving a few bytes at a time, like a slow socket
+struct Trickle<'a>(&'a [u8]);
+
+impl std::io::Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len: usize = buf.len().min(self.0.len()).min(1000);
+        buf[..len].copy_from_slice(&self.0[..len]);
+        self.0 = &self.0[len..];
+        Ok(len)
+    }
+}
+
+#[test]
+fn streams_add_up_to_the_whole_document() {
+    let markdown: Vec<u8> = DOCUMENT.repeat(1000).into_bytes();
+    let mut html: Vec<u8> = Vec::new();
+
+    Parser::default()
+        .parse_stream(Trickle(&markdown), &mut html, &Limits::default())
+        .unwrap();
+    assert_eq!(html, Parser::default().parse(&markdown).html);
+
+    let limits: Limits = Limits {
+        max_output: Some(1000),
+        ..Limits::default()
+    };
+    let error = Parser::default()
+        .parse_stream(Trickle(&markdown), std::io::sink(), &limits)
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::Other);
+}
+
+#[test]
+fn fragments_render_like_the_whole_document() {
+    let parser: Parser = Parser::new(ParserBuilder::new().missing_alt(MissingAlt::Warn).build());
+    let markdown: &[u8] = DOCUMENT.as_bytes();
+    let whole: Output = parser.parse(markdown);
+    let contexts: Vec<BlockContext> = parser.block_contexts(markdown);
+    assert!(contexts.len() > 2);
+
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+    for (i, context) in contexts.iter().enumerate() {
+        let (source_end, html_end) = contexts
+            .get(i + 1)
+            .map_or((markdown.len(), whole.html.len()), |next| {
+                (next.position.offset, next.html)
+            });
+
+        let fragment: Output =
+            parser.parse_fragment(&markdown[context.position.offset..source_end], *context);
+        assert_eq!(
+            String::from_utf8_lossy(&fragment.html),
+            String::from_utf8_lossy(&whole.html[context.html..html_end])
+        );
+        diagnostics.extend(fragment.diagnostics);
+    }
+
+    // The diagnostics are where they are in the whole document
+    assert!(!diagnostics.is_empty());
+    assert_eq!(diagnostics, whole.diagnostics);
+}
+
+#[test]
+fn parallel_parsing_gives_the_same_output() {
+    let parser: Parser = Parser::new(ParserBuilder::new().missing_alt(MissingAlt::Warn).build());
+
+    // Blocks that span where the parts would start without looking at them
+    let code: String = format!("```\n{}```\n\n", "code\n\n".repeat(30_000));
+    let list: String = "- item\n".repeat(30_000) + "\n";
+    let markdown: String = [DOCUMENT.repeat(2000), code, list, DOCUMENT.repeat(2000)].concat();
+
+    for threads in [1, 2, 3, 8] {
+        let output: Output = parser
+            .parse_parallel(markdown.as_bytes(), threads, &Limits::default())
+            .unwrap();
+        let expected: Output = parser.parse(markdown.as_bytes());
+
+        assert!(
+            output.html == expected.html,
+            "the HTML differs on {threads} threads"
+        );
+        assert_eq!(output.diagnostics, expected.diagnostics);
+    }
+}
@@ -0,0 +1,41 @@
+use md2htm::{Indented, Parser, ParserBuilder};
+
+const MARKDOWN: &str = "text\n    more\n\n    code <a>\n      deeper\n\nafter\n";
+
+fn parse(indented: Indented, markdown: &str) -> String {
+    let parser: Parser = Parser::new(ParserBuilder::new().indented(indented).build());
+    String::from_utf8(parser.parse(markdown.as_bytes()).html).unwrap()
+}
+
+#[test]
+fn wraps_indented_lines_in_intend_divs_by_default() {
+    assert_eq!(
+        String::from_utf8(Parser::default().parse(MARKDOWN.as_bytes()).html).unwrap(),
+        parse(Indented::Div, MARKDOWN)
+    );
+    assert_eq!(
+        parse(Indented::Div, MARKDOWN),
+        "<p>text</p>\n<div class=\"intend\"><p>more</p>\n\n<p>code <a></p>\n<p>deeper</p></div>\n\n<p>after</p>\n"
+    );
+}
+
+#[test]
+fn makes_code_blocks_of_indented_lines() {
+    assert_eq!(
+        parse(Indented::Code, MARKDOWN),
+        "<p>text\nmore</p>\n\n<div class=\"code\"><code class=\"code\">code &lt;a&gt;\n  deeper</code></div>\n\n<p>after</p>\n"
+    );
+    assert_eq!(
+        parse(Indented::Code, "    first\n"),
+        "<div class=\"code\"><code class=\"code\">first</code></div>\n"
+    );
+}
+
+#[test]
+fn joins_indented_lines_into_paragraphs() {
+    assert_eq!(
+        parse(Indented::Join, MARKDOWN),
+        "<p>text\nmore</p>\n\n<p>code <a>\ndeeper</p>\n\n<p>after</p>\n"
+    );
+    assert_eq!(parse(Indented::Join, "    first\n"), "<p>first</p>\n");
+}
@@ -0,0 +1,124 @@
+use md2htm::{Dialect, Ext, Parser, ParserBuilder, MDS};
+use std::time::{Duration, Instant};
+
+#[test]
+fn converts_inline_syntax_without_the_paragraph() {
+    assert_eq!(
+        MDS::convert_inline("See [docs](docs.html) for *more*"),
+        "See <a href=\"docs.html\">docs</a> for <i>more</i>"
+    );
+    assert_eq!(MDS::convert_inline(""), "");
+}
+
+#[test]
+fn starts_no_blocks() {
+    let parser: Parser = Parser::new(ParserBuilder::new().dialect(Dialect::Gfm).build());
+
+    assert_eq!(parser.convert_inline("# one"), "# one");
+    assert_eq!(
+        parser.convert_inline("- two\n- ~~three~~"),
+        "- two - <del>three</del>"
+    );
+    assert_eq!(parser.convert_inline("    four"), "four");
+}
+
+#[test]
+fn keeps_the_text_and_urls_of_nested_links_and_images() {
+    assert_eq!(
+        MDS::convert_inline("[![cat](cat.png \"Cat\") photos](cats.html) and [wow!](w!.html)"),
+        "<a href=\"cats.html\"><img src=\"cat.png\" alt=\"cat\" title=\"Cat\"> photos</a> and <a href=\"w!.html\">wow!</a>"
+    );
+    assert_eq!(
+        MDS::convert_inline("**[a](b.html)** [![c](d.png)![e](f.png)](g.html) ![h](i.png)"),
+        "<b><a href=\"b.html\">a</a></b> <a href=\"g.html\"><img src=\"d.png\" alt=\"c\"><img src=\"f.png\" alt=\"e\"></a> <img src=\"i.png\" alt=\"h\">"
+    );
+    // An image without its url is text of the link
+    assert_eq!(
+        MDS::convert_inline("[a ![b] c](d.html)"),
+        "<a href=\"d.html\">a ![b] c</a>"
+    );
+    // The text of a link that never ends is written as it was
+    assert_eq!(
+        MDS::convert_inline("[unclosed ![a](b.png) link"),
+        "[unclosed ![a](b.png) link"
+    );
+
+    let parser: Parser = Parser::new(ParserBuilder::new().asset_prefix("/static/").build());
+    let output = parser.parse(b"[![a](img/a.png)](a.html)\n");
+    assert_eq!(output.assets, ["img/a.png"]);
+}
+
+#[test]
+fn starts_every_document_with_an_empty_arena() {
+    let parser: Parser = Parser::new(ParserBuilder::new().build());
+    let markdown: &[u8] = b"See [x](y.html) and [![a](b.png)](c.html)\n";
+    let html: Vec<u8> = parser.parse(markdown).html;
+
+    assert_eq!(
+        html,
+        b"<p>See <a href=\"y.html\">x</a> and <a href=\"c.html\"><img src=\"b.png\" alt=\"a\"></a></p>\n"
+    );
+
+    // Links left open at the end of the documents before
+    parser.parse(b"[abc](def");
+    parser.parse(b"[![long text](long.png");
+    parser.parse(b"[x](y.html) [abc");
+
+    assert_eq!(parser.parse(markdown).html, html);
+}
+
+#[test]
+fn writes_the_size_hints_of_images() {
+    let parser: Parser = Parser::new(ParserBuilder::new().extension(Ext::Attributes).build());
+
+    assert_eq!(
+        parser.convert_inline(
+            "![a](b.png){sizes=\"(max-width: 600px) 100vw, 50vw\" srcset=\"b-1x.png 1x, b-2x.png 2x\" height=480 width=640 loading=lazy}"
+        ),
+        "<img src=\"b.png\" alt=\"a\" width=\"640\" height=\"480\" srcset=\"b-1x.png 1x, b-2x.png 2x\" sizes=\"(max-width: 600px) 100vw, 50vw\">"
+    );
+    // Sizes that aren't numbers are left out and the values are escaped
+    assert_eq!(
+        parser.convert_inline("![a](b.png){width=64px sizes=\"a<b\"}"),
+        "<img src=\"b.png\" alt=\"a\" sizes=\"a&lt;b\">"
+    );
+    // Only images take the attributes
+    assert_eq!(
+        parser.convert_inline("[a](b.html){width=64}"),
+        "<a href=\"b.html\">a</a>{width=64}"
+    );
+
+    let safe: Parser = Parser::new(
+        ParserBuilder::new()
+            .extension(Ext::Attributes)
+            .safe(true)
+            .build(),
+    );
+    assert_eq!(
+        safe.convert_inline("![a](b.png){srcset=\"b.png 1x, javascript:x 2x\"}"),
+        "<img src=\"b.png\" alt=\"a\">"
+    );
+    assert_eq!(
+        Parser::default().convert_inline("![a](b.png){width=64}"),
+        "<img src=\"b.png\" alt=\"a\">{width=64}"
+    );
+}
+
+#[test]
+fn gives_up_on_unclosed_attribute_blocks_early() {
+    let parser: Parser = Parser::new(ParserBuilder::new().extension(Ext::Attributes).build());
+
+    assert_eq!(
+        parser.convert_inline("![a](b.png){width=640 height=480}"),
+        "<img src=\"b.png\" alt=\"a\" width=\"640\" height=\"480\">"
+    );
+    assert_eq!(
+        parser.convert_inline("![a](b.png){width=640 ![c](d.png)"),
+        "<img src=\"b.png\" alt=\"a\">{width=640 <img src=\"d.png\" alt=\"c\">"
+    );
+
+    let markdown: String = "![a](b){ ".repeat(50_000);
+    let start: Instant = Instant::now();
+    parser.parse(markdown.as_bytes());
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
@@ -0,0 +1,86 @@
+//! Snapshots of the legacy output. Every `tests/legacy/*.md` must convert
+//! into the `.html` next to it with `--dialect legacy`, so sites rendered
+//! with it don't change when the defaults do. After an intended change the
+//! snapshots are rewritten by running the tests with `MD2HTM_BLESS=1`.
+
+use md2htm::{Dialect, Parser, ParserBuilder};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+fn snapshots() -> Vec<PathBuf> {
+    let dir: PathBuf = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/legacy");
+    let mut documents: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+        .collect();
+    documents.sort();
+    documents
+}
+
+#[test]
+fn legacy_output_matches_the_snapshots() {
+    let parser: Parser = Parser::new(ParserBuilder::new().dialect(Dialect::Legacy).build());
+    let bless: bool = env::var_os("MD2HTM_BLESS").is_some();
+    let mut changed: Vec<String> = Vec::new();
+
+    for document in snapshots() {
+        let html: Vec<u8> = parser.parse(&fs::read(&document).unwrap()).html;
+        let snapshot: PathBuf = document.with_extension("html");
+
+        if bless {
+            fs::write(&snapshot, &html).unwrap();
+        } else if fs::read(&snapshot).ok().as_ref() != Some(&html) {
+            changed.push(format!(
+                "{}:\n{}",
+                document.display(),
+                String::from_utf8_lossy(&html)
+            ));
+        }
+    }
+
+    assert!(changed.is_empty(), "{}", changed.join("\n"));
+}
+
+#[test]
+fn legacy_dialect_is_the_default() {
+    for document in snapshots() {
+        let markdown: Vec<u8> = fs::read(&document).unwrap();
+        assert_eq!(
+            Parser::default().parse(&markdown).html,
+            Parser::new(ParserBuilder::new().dialect(Dialect::Legacy).build())
+                .parse(&markdown)
+                .html,
+            "{}",
+            document.display()
+        );
+    }
+}
+
+#[test]
+fn dialect_flag_gives_the_snapshot() {
+    let document: &PathBuf = &snapshots()[0];
+    let output: PathBuf =
+        env::temp_dir().join(format!("md2htm-legacy-{}.html", std::process::id()));
+
+    let status = Command::new(env!("CARGO_BIN_EXE_md2htm"))
+        .args(["--dialect", "legacy"])
+        .arg(document)
+        .arg(&output)
+        .env(
+            "XDG_CONFIG_HOME",
+            env::temp_dir().join("md2htm-legacy-no-config"),
+        )
+        .status()
+        .unwrap();
+
+    assert!(status.success());
+    assert_eq!(
+        fs::read(&output).unwrap(),
+        fs::read(document.with_extension("html")).unwrap()
+    );
+    let _ = fs::remove_file(output);
+}
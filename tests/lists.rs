@@ -0,0 +1,156 @@
+use md2htm::{CodeBlocks, Dialect, Em, Ext, Numbering, Parser, ParserBuilder};
+
+/// Converts a single list item and returns the `<li>` element
+fn item(markdown: &str) -> String {
+    item_with(&Parser::default(), markdown)
+}
+
+fn item_with(parser: &Parser, markdown: &str) -> String {
+    let html: String = String::from_utf8(parser.parse(markdown.as_bytes()).html).unwrap();
+    let start: usize = html.find("<li>").expect("no list item in the output");
+    let end: usize = html.find("</li>").expect("the list item wasn't closed") + 5;
+    html[start..end].to_string()
+}
+
+#[test]
+fn italic() {
+    assert_eq!(item("- *italic* item\n"), "<li><i>italic</i> item</li>");
+    assert_eq!(item("- text *mid* end\n"), "<li>text <i>mid</i> end</li>");
+}
+
+#[test]
+fn bold() {
+    assert_eq!(item("- **bold** item\n"), "<li><b>bold</b> item</li>");
+    assert_eq!(item("- text **mid** end\n"), "<li>text <b>mid</b> end</li>");
+}
+
+#[test]
+fn semantic_emphasis() {
+    let parser: Parser = Parser::new(ParserBuilder::new().emphasis_style(Em::Semantic).build());
+
+    assert_eq!(
+        item_with(&parser, "- *a* and **b**\n"),
+        "<li><em>a</em> and <strong>b</strong></li>"
+    );
+}
+
+#[test]
+fn underline() {
+    assert_eq!(item("- _u_ item\n"), "<li><u>u</u> item</li>");
+}
+
+#[test]
+fn code_span() {
+    assert_eq!(
+        item("- `code` item\n"),
+        "<li><span class=\"code\"><code class=\"code\">code</code></span> item</li>"
+    );
+}
+
+#[test]
+fn link_and_image() {
+    assert_eq!(
+        item("- [link](http://x) item\n"),
+        "<li><a href=\"http://x\">link</a> item</li>"
+    );
+    assert_eq!(
+        item("- ![img](a.png) item\n"),
+        "<li><img src=\"a.png\" alt=\"img\"> item</li>"
+    );
+}
+
+#[test]
+fn escapes() {
+    assert_eq!(item("- \\*not italic\\*\n"), "<li>*not italic*</li>");
+}
+
+#[test]
+fn mixed() {
+    assert_eq!(
+        item("- *a* _b_ `c` **d** [e](f)\n"),
+        "<li><i>a</i> <u>b</u> <span class=\"code\"><code class=\"code\">c</code></span> \
+         <b>d</b> <a href=\"f\">e</a></li>"
+    );
+}
+
+#[test]
+fn formatting_keeps_the_list_going() {
+    let html: Vec<u8> = Parser::default()
+        .parse(b"- *one*\n- **two**\n- `three`\n")
+        .html;
+    let html: String = String::from_utf8(html).unwrap();
+
+    assert_eq!(html.matches("<ul>").count(), 1);
+    assert_eq!(html.matches("<li>").count(), 3);
+    assert_eq!(html.matches("</li>").count(), 3);
+}
+
+#[test]
+fn indented_paragraphs_continue_the_item() {
+    assert_eq!(
+        item("- first\n\n  second *para*\n- next\n"),
+        "<li><p>first</p>\n\n<p>second <i>para</i></p></li>"
+    );
+    assert_eq!(item("- one\n  two\n"), "<li><p>one</p>\n<p>two</p></li>");
+}
+
+#[test]
+fn ordered_lists() {
+    let parser: Parser = Parser::new(ParserBuilder::new().extension(Ext::OrderedLists).build());
+    let html: Vec<u8> = parser
+        .parse(b"1. *one*\n2. two\n   more\n\n3) three\n")
+        .html;
+
+    assert_eq!(
+        String::from_utf8_lossy(&html),
+        "<ol><li><i>one</i></li>\n<li>two more</li></ol>\n\n<ol start=\"3\"><li>three</li></ol>\n"
+    );
+}
+
+#[test]
+fn ordered_lists_respect_or_normalize_the_numbers() {
+    let markdown: &[u8] = b"3. a\n3. b\n7. c\n8. d\n";
+    let builder = || ParserBuilder::new().extension(Ext::OrderedLists);
+
+    assert_eq!(
+        Parser::new(builder().build()).parse(markdown).html,
+        b"<ol start=\"3\"><li>a</li>\n<li>b</li>\n<li>c</li>\n<li>d</li></ol>\n"
+    );
+    assert_eq!(
+        Parser::new(builder().list_numbering(Numbering::Respect).build())
+            .parse(markdown)
+            .html,
+        b"<ol start=\"3\"><li>a</li>\n<li value=\"3\">b</li>\n<li value=\"7\">c</li>\n<li>d</li></ol>\n"
+    );
+}
+
+#[test]
+fn ordered_lists_need_a_space_after_the_number() {
+    let parser: Parser = Parser::new(ParserBuilder::new().dialect(Dialect::CommonMark).build());
+
+    assert_eq!(parser.parse(b"1.5 litres\n").html, b"<p>1.5 litres</p>\n");
+    assert_eq!(
+        Parser::default().parse(b"1. one\n").html,
+        b"<p>1. one</p>\n"
+    );
+}
+
+#[test]
+fn dedents_the_code_fenced_in_a_list_item() {
+    let parser: Parser = Parser::new(ParserBuilder::new().dedent_code(true).build());
+
+    assert_eq!(
+        item_with(&parser, "- x\n  ```\n      a\n    b\n  ```\n"),
+        "<li><p>x</p>\n<div class=\"code\"><code class=\"code\">\n  a\nb\n</code></div></li>"
+    );
+}
+
+#[test]
+fn writes_the_code_fenced_in_a_list_item_in_pre() {
+    let parser: Parser = Parser::new(ParserBuilder::new().code_blocks(CodeBlocks::Pre).build());
+
+    assert_eq!(
+        item_with(&parser, "- x\n\n  ```rust\n  let a = 1;\n  ```\n- y\n"),
+        "<li><p>x</p>\n\n<pre><code class=\"language-rust\">  let a = 1;</code></pre></li>"
+    );
+}
@@ -0,0 +1,29 @@
+use md2htm::{Dialect, Parser, ParserBuilder};
+
+fn parse(builder: ParserBuilder, markdown: &str) -> String {
+    let parser: Parser = Parser::new(builder.wrap_paragraphs(false).build());
+    String::from_utf8(parser.parse(markdown.as_bytes()).html).unwrap()
+}
+
+#[test]
+fn leaves_out_the_paragraph_tags() {
+    assert_eq!(
+        parse(
+            ParserBuilder::new().dialect(Dialect::Gfm),
+            "one *two*\n\nthree\n"
+        ),
+        "one <em>two</em>\n\nthree\n"
+    );
+    assert_eq!(
+        parse(ParserBuilder::new().sourcepos(true), "one\n"),
+        "one\n"
+    );
+}
+
+#[test]
+fn keeps_the_code_as_it_is() {
+    assert_eq!(
+        parse(ParserBuilder::new(), "```\n<p>x</p>\n```\n"),
+        "\n<div class=\"code\"><code class=\"code\"><p>x</p>\n</code></div>\n"
+    );
+}
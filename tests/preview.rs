@@ -0,0 +1,128 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Frames the message like the language server protocol does
+fn frame(body: &str) -> String {
+    format!("Content-Length: {}\r\n\r\n{body}", body.len())
+}
+
+/// Sends the messages to a preview server and returns the bodies of its
+/// answers in order
+fn preview(messages: &[&str]) -> Vec<String> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_md2htm"))
+        .arg("preview")
+        .env("XDG_CONFIG_HOME", "/nonexistent")
+        .env("LANG", "C")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let input: String = messages.iter().map(|message| frame(message)).collect();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let mut stdout: &str = std::str::from_utf8(&output.stdout).unwrap();
+    let mut bodies: Vec<String> = Vec::new();
+
+    while let Some(rest) = stdout.strip_prefix("Content-Length: ") {
+        let (len, rest) = rest.split_once("\r\n\r\n").unwrap();
+        let len: usize = len.parse().unwrap();
+        bodies.push(rest[..len].to_string());
+        stdout = &rest[len..];
+    }
+
+    assert!(stdout.is_empty(), "left over: {stdout:?}");
+    bodies
+}
+
+const INITIALIZE: &str = r##"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"##;
+const SHUTDOWN: &str = r##"{"jsonrpc":"2.0","id":9,"method":"shutdown"}"##;
+const EXIT: &str = r##"{"jsonrpc":"2.0","method":"exit"}"##;
+
+#[test]
+fn asks_for_incremental_changes() {
+    let bodies: Vec<String> = preview(&[INITIALIZE, SHUTDOWN, EXIT]);
+
+    assert_eq!(bodies.len(), 2);
+    assert!(
+        bodies[0].starts_with(r##"{"jsonrpc":"2.0","id":1,"result":{"capabilities":{"textDocumentSync":{"openClose":true,"change":2}}"##),
+        "{}",
+        bodies[0]
+    );
+    assert_eq!(bodies[1], r##"{"jsonrpc":"2.0","id":9,"result":null}"##);
+}
+
+#[test]
+fn previews_opened_and_changed_documents() {
+    let bodies: Vec<String> = preview(&[
+        INITIALIZE,
+        r##"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.md","text":"# Title\n\n😀 *one*\n"}}}"##,
+        // The character counts UTF-16 code units, two for the emoji
+        r##"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///a.md"},"contentChanges":[{"range":{"start":{"line":2,"character":4},"end":{"line":2,"character":7}},"text":"two"}]}}"##,
+        r##"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///a.md"},"contentChanges":[{"text":"new\n"}]}}"##,
+        SHUTDOWN,
+        EXIT,
+    ]);
+
+    assert_eq!(bodies.len(), 8, "{bodies:?}");
+    assert_eq!(
+        bodies[1],
+        r##"{"jsonrpc":"2.0","method":"md2htm/preview","params":{"uri":"file:///a.md","html":"<h1>Title</h1>\n\n<p>😀 <i>one</i></p>\n"}}"##
+    );
+    assert_eq!(
+        bodies[2],
+        r##"{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///a.md","diagnostics":[]}}"##
+    );
+    assert!(
+        bodies[3].contains(r##""html":"<h1>Title</h1>\n\n<p>😀 <i>two</i></p>\n""##),
+        "{}",
+        bodies[3]
+    );
+    assert!(
+        bodies[5].contains(r##""html":"<p>new</p>\n""##),
+        "{}",
+        bodies[5]
+    );
+}
+
+#[test]
+fn publishes_the_diagnostics_at_their_lines() {
+    let bodies: Vec<String> = preview(&[
+        r##"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///b.md","text":"text\n\n#\n"}}}"##,
+        EXIT,
+    ]);
+
+    assert_eq!(bodies.len(), 2, "{bodies:?}");
+    assert!(
+        bodies[1].contains(r##""range":{"start":{"line":2,"character":1},"end":{"line":2,"character":2}},"severity":2,"source":"md2htm","code":"empty-header""##),
+        "{}",
+        bodies[1]
+    );
+}
+
+#[test]
+fn answers_unknown_requests_with_an_error() {
+    let bodies: Vec<String> = preview(&[
+        r##"{"jsonrpc":"2.0","id":"x","method":"textDocument/hover","params":{}}"##,
+        r##"{"jsonrpc":"2.0","method":"$/cancelRequest","params":{"id":1}}"##,
+        EXIT,
+    ]);
+
+    assert_eq!(bodies.len(), 1, "{bodies:?}");
+    assert!(
+        bodies[0].starts_with(r##"{"jsonrpc":"2.0","id":"x","error":{"code":-32601,"##),
+        "{}",
+        bodies[0]
+    );
+}
@@ -0,0 +1,42 @@
+use md2htm::{Dialect, Parser, ParserBuilder};
+
+fn print(builder: ParserBuilder, markdown: &str) -> String {
+    let parser: Parser = Parser::new(builder.print(true).build());
+    String::from_utf8(parser.parse(markdown.as_bytes()).html).unwrap()
+}
+
+#[test]
+fn breaks_pages_before_the_top_level_headings() {
+    assert_eq!(
+        print(ParserBuilder::new().dialect(Dialect::Gfm), "# One\n\ntext\n\n## Two\n\n### Three\n"),
+        "<h1>One</h1>\n\n<p>text</p>\n\n<div class=\"page-break\"></div>\n<h2>Two</h2>\n\n<h3>Three</h3>\n"
+    );
+}
+
+#[test]
+fn lists_the_addresses_of_the_links() {
+    assert_eq!(
+        print(
+            ParserBuilder::new().dialect(Dialect::Gfm),
+            "see [a](https://a.example/?x=1&y=2), [b](b.html) and [a](https://a.example/?x=1&y=2)\n"
+        ),
+        "<p>see <a href=\"https://a.example/?x=1&y=2\">a</a><sup class=\"link-note\">[1]</sup>, \
+         <a href=\"b.html\">b</a><sup class=\"link-note\">[2]</sup> and \
+         <a href=\"https://a.example/?x=1&y=2\">a</a><sup class=\"link-note\">[1]</sup></p>\n\
+         <ol class=\"link-notes\">\n<li>https://a.example/?x=1&y=2</li>\n<li>b.html</li>\n</ol>\n"
+    );
+}
+
+#[test]
+fn skips_links_within_the_page_and_opens_details() {
+    let html: String = print(
+        ParserBuilder::new().heading_ids(true).class_prefix("md-"),
+        "<details><summary>More</summary>\n\n[up](#top)\n\n</details>\n\n# Title\n",
+    );
+    assert!(html.contains("<details open>"), "{html}");
+    assert!(!html.contains("link-note"), "{html}");
+    assert!(
+        html.contains("<div class=\"md-page-break\"></div>\n<h1"),
+        "{html}"
+    );
+}
@@ -0,0 +1,18 @@
+use std::process::{Command, Output};
+
+#[test]
+fn selftest_passes() {
+    let output: Output = Command::new(env!("CARGO_BIN_EXE_md2htm"))
+        .arg("selftest")
+        .env("LANG", "C")
+        .output()
+        .unwrap();
+    let printed: String = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success(), "{printed}");
+    assert!(
+        printed.contains("ok      daemon chunked answer\n"),
+        "{printed}"
+    );
+    assert!(printed.ends_with(" passed, 0 failed.\n"), "{printed}");
+}
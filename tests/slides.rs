@@ -0,0 +1,36 @@
+use md2htm::{Dialect, Parser, ParserBuilder};
+
+fn slides(markdown: &str) -> String {
+    let parser: Parser = Parser::new(
+        ParserBuilder::new()
+            .dialect(Dialect::Gfm)
+            .slides(true)
+            .build(),
+    );
+    String::from_utf8(parser.parse(markdown.as_bytes()).html).unwrap()
+}
+
+#[test]
+fn splits_at_rules_and_second_level_headings() {
+    assert_eq!(
+        slides("# Talk\n\nintro\n\n## One\n\na\n\n---\n\nb\n\n### Still b\n"),
+        "<section class=\"slide\">\n<h1>Talk</h1>\n\n<p>intro</p>\n\n</section>\n\
+         <section class=\"slide\">\n<h2>One</h2>\n\n<p>a</p>\n\n</section>\n\
+         <section class=\"slide\">\n\n<p>b</p>\n\n<h3>Still b</h3>\n</section>\n"
+    );
+}
+
+#[test]
+fn leaves_no_empty_slides() {
+    let html: String = slides("---\n\n## One\n\na\n\n---\n\n## Two\n\n---\n");
+    assert_eq!(html.matches("<section").count(), 2, "{html}");
+    assert_eq!(html.matches("</section>").count(), 2, "{html}");
+    assert!(!html.contains("<hr"), "{html}");
+}
+
+#[test]
+fn ignores_rules_and_headings_of_raw_html_blocks() {
+    let html: String = slides("a\n\n<div><h2>x</h2><hr></div>\n");
+    assert_eq!(html.matches("<section").count(), 1, "{html}");
+    assert!(html.contains("<hr>"), "{html}");
+}
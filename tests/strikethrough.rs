@@ -0,0 +1,56 @@
+use md2htm::{Dialect, Ext, Parser, ParserBuilder};
+use std::time::{Duration, Instant};
+
+fn parse(builder: ParserBuilder, markdown: &str) -> String {
+    let parser: Parser = Parser::new(builder.build());
+    String::from_utf8(parser.parse(markdown.as_bytes()).html).unwrap()
+}
+
+#[test]
+fn strikes_through_text_between_tildes() {
+    assert_eq!(
+        parse(
+            ParserBuilder::new().extension(Ext::Strikethrough),
+            "~~old~~ new, ~~ not~~ and ~one~\n"
+        ),
+        "<p><del>old</del> new, ~~ not~~ and ~one~</p>\n"
+    );
+}
+
+#[test]
+fn nests_strikethrough_with_the_other_inline_states() {
+    assert_eq!(
+        parse(
+            ParserBuilder::new().extension(Ext::Strikethrough),
+            "~~a **b~~ c**\n\n~~a *b* c~~ d\n"
+        ),
+        "<p><del>a <b>b~~ c</b></del></p>\n\n<p><del>a <i>b</i> c</del> d</p>\n"
+    );
+}
+
+#[test]
+fn finds_the_closing_tildes_in_linear_time() {
+    let markdown: String = "~~ ".repeat(100_000);
+    let start: Instant = Instant::now();
+
+    parse(
+        ParserBuilder::new().extension(Ext::Strikethrough),
+        &markdown,
+    );
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn leaves_the_tildes_alone_when_disabled() {
+    let builder: ParserBuilder = ParserBuilder::new()
+        .dialect(Dialect::Gfm)
+        .disable(Ext::Strikethrough);
+
+    assert_eq!(
+        parse(builder, "~~old~~\n"),
+        parse(
+            ParserBuilder::new().dialect(Dialect::CommonMark),
+            "~~old~~\n"
+        )
+    );
+}
@@ -0,0 +1,111 @@
+use md2htm::{Align, Cancel, Dialect, Error, Ext, Limits, Parser, ParserBuilder};
+use std::cell::Cell;
+
+fn parse(builder: ParserBuilder, markdown: &str) -> String {
+    let parser: Parser = Parser::new(builder.build());
+    String::from_utf8(parser.parse(markdown.as_bytes()).html).unwrap()
+}
+
+#[test]
+fn writes_pipe_tables_with_aligned_columns() {
+    assert_eq!(
+        parse(
+            ParserBuilder::new().dialect(Dialect::Gfm),
+            "| a | *b* |\n|:--|--:|\n| `x|y` | 2 |\n| 3 |\n"
+        ),
+        "<table>\n<thead>\n<tr>\n<th align=\"left\">a</th>\n<th align=\"right\"><em>b</em></th>\n</tr>\n</thead>\n\
+         <tbody>\n<tr>\n<td align=\"left\"><span class=\"code\"><code class=\"code\">x|y</code></span></td>\n\
+         <td align=\"right\">2</td>\n</tr>\n<tr>\n<td align=\"left\">3</td>\n<td align=\"right\"></td>\n</tr>\n\
+         </tbody>\n</table>\n"
+    );
+}
+
+#[test]
+fn needs_a_delimiter_row_under_the_header() {
+    assert_eq!(
+        parse(ParserBuilder::new().dialect(Dialect::Gfm), "a | b\n"),
+        "<p>a | b</p>\n"
+    );
+}
+
+#[test]
+fn leaves_the_pipes_alone_when_disabled() {
+    let builder: ParserBuilder = ParserBuilder::new()
+        .dialect(Dialect::Gfm)
+        .disable(Ext::Tables);
+
+    assert_eq!(
+        parse(builder, "| a |\n|---|\n"),
+        parse(
+            ParserBuilder::new().dialect(Dialect::CommonMark),
+            "| a |\n|---|\n"
+        )
+    );
+}
+
+#[test]
+fn text_without_pipes_continues_the_table_until_another_block() {
+    let gfm = || ParserBuilder::new().dialect(Dialect::Gfm);
+
+    assert_eq!(
+        parse(gfm(), "| a | b |\n| - | - |\n| 1 | 2 |\nbar\n\nbar\n"),
+        "<table>\n<thead>\n<tr>\n<th>a</th>\n<th>b</th>\n</tr>\n</thead>\n<tbody>\n\
+         <tr>\n<td>1</td>\n<td>2</td>\n</tr>\n<tr>\n<td>bar</td>\n<td></td>\n</tr>\n\
+         </tbody>\n</table>\n\n<p>bar</p>\n"
+    );
+    assert!(parse(gfm(), "| a |\n| - |\n# Title\n").ends_with("</table>\n<h1>Title</h1>\n"));
+    assert!(parse(gfm(), "| a |\n| - |\n1. one\n").ends_with("</table>\n<ol><li>one</li></ol>\n"));
+}
+
+#[test]
+fn writes_the_alignment_as_chosen() {
+    let markdown: &str = "| a | b |\n|:-:|---|\n| x \\| y | z |\n";
+    let gfm = || ParserBuilder::new().dialect(Dialect::Gfm);
+
+    let html: String = parse(gfm(), markdown);
+    assert!(
+        html.contains("<th align=\"center\">a</th>\n<th>b</th>"),
+        "{html}"
+    );
+    assert!(html.contains("<td align=\"center\">x | y</td>"), "{html}");
+
+    let html: String = parse(gfm().table_align(Align::Style), markdown);
+    assert!(
+        html.contains("<th style=\"text-align: center\">a</th>"),
+        "{html}"
+    );
+
+    let html: String = parse(
+        gfm().table_align(Align::Class).class_prefix("md-"),
+        markdown,
+    );
+    assert!(
+        html.contains("<td class=\"md-align-center\">x | y</td>"),
+        "{html}"
+    );
+}
+
+/// Cancels the parse after the first check
+struct Second(Cell<usize>);
+
+impl Cancel for Second {
+    fn is_cancelled(&self) -> bool {
+        self.0.set(self.0.get() + 1);
+        self.0.get() > 1
+    }
+}
+
+#[test]
+fn parses_the_cells_within_the_limits() {
+    let parser: Parser = Parser::new(ParserBuilder::new().dialect(Dialect::Gfm).build());
+    let cancel: Second = Second(Cell::new(0));
+    let limits: Limits = Limits {
+        cancel: Some(&cancel),
+        ..Limits::default()
+    };
+
+    assert!(matches!(
+        parser.parse_limited(b"| a |\n| - |\n| b |\n", &limits),
+        Err(Error::Cancelled)
+    ));
+}
@@ -0,0 +1,32 @@
+use md2htm::{Parser, ParserBuilder};
+
+const MARKDOWN: &str = "```\n\tif x {\n\t\ty();\nab\tc\n```\n";
+
+fn parse(builder: ParserBuilder, markdown: &str) -> String {
+    String::from_utf8(Parser::new(builder.build()).parse(markdown.as_bytes()).html).unwrap()
+}
+
+#[test]
+fn expands_tabs_to_the_next_stop() {
+    assert_eq!(
+        parse(ParserBuilder::new().expand_tabs(4), MARKDOWN),
+        "<div class=\"code\"><code class=\"code\">\n    if x {\n        y();\nab  c\n</code></div>\n"
+    );
+    assert_eq!(
+        parse(ParserBuilder::new().expand_tabs(2), MARKDOWN),
+        "<div class=\"code\"><code class=\"code\">\n  if x {\n    y();\nab  c\n</code></div>\n"
+    );
+}
+
+#[test]
+fn leaves_the_tabs_by_default() {
+    assert!(parse(ParserBuilder::new(), MARKDOWN).contains("\tif x {\n\t\ty();\nab\tc\n"));
+}
+
+#[test]
+fn expands_only_the_tabs_of_code_blocks() {
+    assert_eq!(
+        parse(ParserBuilder::new().expand_tabs(4), "a\tb `c\td`\n"),
+        "<p>a\tb <span class=\"code\"><code class=\"code\">c\td</code></span></p>\n"
+    );
+}
@@ -0,0 +1,78 @@
+use md2htm::{Output, Parser, ParserBuilder, Position, Transition};
+use std::{env, fs, path::PathBuf, process::Command};
+
+fn transition(offset: usize, byte: u8, states: &[&str]) -> Transition {
+    Transition {
+        position: Position {
+            line: 1,
+            column: offset + 1,
+            offset,
+        },
+        byte,
+        states: states.iter().map(|state| state.to_string()).collect(),
+    }
+}
+
+#[test]
+fn logs_the_bytes_changing_the_states() {
+    let output: Output = Parser::new(ParserBuilder::new().trace(true).build()).parse(b"a *b*\n");
+
+    assert_eq!(
+        output.trace,
+        [
+            transition(0, b'a', &["None", "Paragraph"]),
+            transition(2, b'*', &["None", "Paragraph", "Italic"]),
+            transition(4, b'*', &["None", "Paragraph"]),
+            transition(5, b'\n', &["None"]),
+        ]
+    );
+    assert!(Parser::default().parse(b"a *b*\n").trace.is_empty());
+}
+
+/// Fresh directory with the markdown in `doc.md`
+fn dir(name: &str) -> PathBuf {
+    let dir: PathBuf = env::temp_dir().join(format!("md2htm-trace-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("doc.md"), "a *b*\n").unwrap();
+    dir
+}
+
+fn md2htm(dir: &PathBuf, env: &[(&str, &str)], args: &[&str]) {
+    let status = Command::new(env!("CARGO_BIN_EXE_md2htm"))
+        .args(args)
+        .env("XDG_CONFIG_HOME", dir)
+        .env("LANG", "C")
+        .envs(env.iter().copied())
+        .current_dir(dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+const LOG: &str = "{\"line\": 1, \"column\": 1, \"offset\": 0, \"byte\": \"a\", \"states\": [\"None\", \"Paragraph\"]}\n\
+                   {\"line\": 1, \"column\": 3, \"offset\": 2, \"byte\": \"*\", \"states\": [\"None\", \"Paragraph\", \"Italic\"]}\n\
+                   {\"line\": 1, \"column\": 5, \"offset\": 4, \"byte\": \"*\", \"states\": [\"None\", \"Paragraph\"]}\n\
+                   {\"line\": 1, \"column\": 6, \"offset\": 5, \"byte\": \"\\n\", \"states\": [\"None\"]}\n";
+
+#[test]
+fn writes_the_log_into_the_file_asked_for() {
+    let dir: PathBuf = dir("flag");
+    md2htm(&dir, &[], &["--trace-parser", "states.jsonl", "doc.md"]);
+
+    assert_eq!(fs::read_to_string(dir.join("states.jsonl")).unwrap(), LOG);
+    assert!(!dir.join("doc.trace.jsonl").exists());
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn writes_the_log_next_to_the_html_with_the_variable() {
+    let dir: PathBuf = dir("env");
+    md2htm(&dir, &[("MD2HTM_TRACE", "1")], &["doc.md"]);
+
+    assert_eq!(
+        fs::read_to_string(dir.join("doc.trace.jsonl")).unwrap(),
+        LOG
+    );
+    let _ = fs::remove_dir_all(dir);
+}
@@ -0,0 +1,149 @@
+use md2htm::{validate, CodeBlocks, Ext, Parser, ParserBuilder, Permalink};
+
+/// Documents using the features the way they are meant to be used
+const DOCUMENTS: [&str; 8] = [
+    "# Title\n\nFirst *paragraph* with a [link](https://example.com).\n\nLast **words**.\n",
+    "## Setup\n\n- one\n- two with `code`\n- three\n\nAfter the list.\n",
+    "Text with _underline_ and an ![image](cat.png) in it.\n\n---\n\nMore text.\n",
+    "```rust\nfn main() {\n    println!(\"<hi>\");\n}\n```\n\nDone.\n",
+    "    indented code\n    more code\n\nA paragraph.\n",
+    "# One\n\n## Two\n\n### Three\n\nPress [[Ctrl]] + [[C]].\n",
+    "Escaped \\*stars\\* and <tags> & \"quotes\".\n",
+    "[mail](mailto:me@example.com) and [web](https://example.com \"title\").\n",
+];
+
+/// Raw HTML passes through without safe mode, so only the safe output is
+/// expected to be well-formed
+fn parsers() -> Vec<Parser> {
+    vec![
+        Parser::new(ParserBuilder::new().safe(true).build()),
+        Parser::new(
+            ParserBuilder::new()
+                .safe(true)
+                .heading_ids(true)
+                .permalink(Permalink::Wrap)
+                .code_blocks(CodeBlocks::Pre)
+                .extension(Ext::Kbd)
+                .toc(true)
+                .build(),
+        ),
+        Parser::new(
+            ParserBuilder::new()
+                .safe(true)
+                .sourcepos(true)
+                .a11y(true)
+                .build(),
+        ),
+    ]
+}
+
+#[test]
+fn documents() {
+    for parser in parsers() {
+        for markdown in DOCUMENTS {
+            let html: Vec<u8> = parser.parse(markdown.as_bytes()).html;
+
+            if let Err(e) = validate(&html) {
+                panic!(
+                    "{markdown:?} became {:?}\n{e}",
+                    String::from_utf8_lossy(&html)
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn checker() {
+    assert!(validate(b"<p>a<br>b<img src=\"x\" alt=''/></p>\n<!-- c -->").is_ok());
+    assert!(validate(b"<script>if (a<b) {}</script>").is_ok());
+    assert!(validate(b"<p><b>a</p></b>").is_err());
+    assert!(validate(b"<p>a").is_err());
+    assert!(validate(b"a</p>").is_err());
+    assert!(validate(b"<a href=x>a</a>").is_err());
+    assert!(validate(b"<a href=\"x>a</a>").is_err());
+    assert!(validate(b"<a href=\"x\"class=\"y\">a</a>").is_err());
+    assert!(validate(b"a < b").is_err());
+    assert!(validate(b"<!-- a").is_err());
+}
+
+#[test]
+fn broken_markdown_closes_its_tags() {
+    let parser: Parser = Parser::new(ParserBuilder::new().safe(true).build());
+    let html = |markdown: &str| String::from_utf8(parser.parse(markdown.as_bytes()).html).unwrap();
+
+    assert_eq!(html("## ## "), "<h2>## </h2>");
+    assert_eq!(html("***    "), "<p><b>*    </b></p>");
+    assert_eq!(html("\\---\n"), "<p>---</p>\n");
+    assert_eq!(html("- ["), "<p><ul><li>[</li></ul></p>");
+    assert_eq!(html("#\n"), "<p>#</p>\n");
+    assert_eq!(html("#foo"), "<p>#foo</p>");
+    assert_eq!(html("!_"), "!<p><u></u></p>");
+    assert_eq!(
+        html("```\n`"),
+        "<p>\n<div class=\"code\"><code class=\"code\">`</code></div></p>"
+    );
+}
+
+/// Pieces the generated documents are made of
+const PIECES: [&str; 26] = [
+    "word ",
+    "*",
+    "**",
+    "_",
+    "`",
+    "```\n",
+    "\n",
+    "\n\n",
+    "#",
+    "# ",
+    "## ",
+    "- ",
+    "  - ",
+    "    ",
+    "[",
+    "](",
+    ")",
+    "![",
+    "!",
+    "\\",
+    "<",
+    "&",
+    "\"",
+    "---\n",
+    "https://example.com",
+    "[[Ctrl]]",
+];
+
+/// Xorshift, so failures can be reproduced from the seed
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 as usize
+    }
+}
+
+/// Fuzzes the parser with documents glued together from the pieces
+#[test]
+fn generated_documents() {
+    let parser: Parser = Parser::new(ParserBuilder::new().safe(true).build());
+    let mut rng: Rng = Rng(0x2545_f491_4f6c_dd1d);
+
+    for _ in 0..10_000 {
+        let markdown: String = (0..rng.next() % 40)
+            .map(|_| PIECES[rng.next() % PIECES.len()])
+            .collect();
+        let html: Vec<u8> = parser.parse(markdown.as_bytes()).html;
+
+        if let Err(e) = validate(&html) {
+            panic!(
+                "{markdown:?} became {:?}\n{e}",
+                String::from_utf8_lossy(&html)
+            );
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use md2htm::{to_xhtml, validate, Dialect, Parser, ParserBuilder};
+
+fn xhtml(markdown: &str) -> String {
+    let parser: Parser = Parser::new(
+        ParserBuilder::new()
+            .dialect(Dialect::Gfm)
+            .xhtml(true)
+            .build(),
+    );
+    String::from_utf8(parser.parse(markdown.as_bytes()).html).unwrap()
+}
+
+#[test]
+fn closes_void_elements() {
+    let html: String = xhtml("a<br>b\n\n---\n\nsee ![alt](a.png)\n");
+    assert!(html.contains("a<br />b"), "{html}");
+    assert!(html.contains("<hr />"), "{html}");
+    assert!(html.contains("<img src=\"a.png\" alt=\"alt\" />"), "{html}");
+}
+
+#[test]
+fn escapes_what_xml_does_not_allow() {
+    assert_eq!(
+        to_xhtml(b"<p>&copy; &unknown; a &amp; b &#x41; < c</p>"),
+        b"<p>&#169; &amp;unknown; a &amp; b &#x41; &lt; c</p>"
+    );
+    assert_eq!(
+        to_xhtml(b"<input type=checkbox checked disabled><a href='?a=1&b=2' title=\"x<y\">"),
+        b"<input type=\"checkbox\" checked=\"checked\" disabled=\"disabled\" /><a href='?a=1&amp;b=2' title=\"x&lt;y\">"
+    );
+}
+
+#[test]
+fn leaves_scripts_and_comments_alone() {
+    let html: &[u8] = b"<!-- a < b --><script>if (a < b && c) {}</script><br/>";
+    assert_eq!(to_xhtml(html), html);
+}
+
+#[test]
+fn serializing_twice_changes_nothing() {
+    let once: String = xhtml("# T&amp;C\n\n- [x] done\n\nx &nbsp; y <b>z</b>\n");
+    assert!(validate(once.as_bytes()).is_ok(), "{once}");
+    assert_eq!(to_xhtml(once.as_bytes()), once.as_bytes());
+}
@@ -0,0 +1,74 @@
+use std::{
+    env, fs,
+    os::unix::fs::PermissionsExt,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+fn xtest(corpus: &PathBuf, reference: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_md2htm"))
+        .args(["--reference", reference, "xtest"])
+        .arg(corpus)
+        .env("LANG", "C")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn reports_where_the_reference_differs() {
+    let dir: PathBuf = env::temp_dir().join(format!("md2htm-xtest-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("corpus/sub")).unwrap();
+    fs::write(dir.join("corpus/a.md"), "# Title\n").unwrap();
+    fs::write(dir.join("corpus/sub/b.md"), "Some *text*.\n").unwrap();
+
+    // Answers every document with the same heading, formatted differently
+    let reference: PathBuf = dir.join("reference");
+    fs::write(
+        &reference,
+        "#!/bin/sh\ncat > /dev/null\nprintf '<H1>\\n  Title\\n</H1>'\n",
+    )
+    .unwrap();
+    fs::set_permissions(&reference, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output: Output = xtest(&dir.join("corpus"), reference.to_str().unwrap());
+    let printed: String = String::from_utf8(output.stdout).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!printed.contains("a.md"), "{printed}");
+    assert!(printed.contains("b.md differs at token 1:\n"), "{printed}");
+    assert!(
+        printed.contains("    md2htm:    <p> \"Some\" <i>\n"),
+        "{printed}"
+    );
+    assert!(
+        printed.contains("    reference: <h1> \"Title\" </h1>\n"),
+        "{printed}"
+    );
+    assert!(printed.contains("1 of 2 files match.\n"), "{printed}");
+    assert!(
+        printed.ends_with("        1  md2htm <p> / reference <h1>\n"),
+        "{printed}"
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn tells_when_the_reference_is_missing() {
+    let dir: PathBuf = env::temp_dir().join(format!("md2htm-xtest-{}-missing", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("a.md"), "# Title\n").unwrap();
+
+    let output: Output = xtest(&dir, "md2htm-no-such-reference");
+    let error: String = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        error.contains("The reference md2htm-no-such-reference wasn't found"),
+        "{error}"
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}